@@ -1,6 +1,8 @@
 -- =====================================================
 -- MedBill Database Schema
 -- GST-Compliant Medical Billing & Inventory System
+-- Reference copy; the app builds medbill.db from the
+-- numbered migrations in src-tauri/migrations
 -- =====================================================
 
 -- Enable foreign keys
@@ -301,7 +303,7 @@ CREATE TABLE IF NOT EXISTS purchase_returns (
     total_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
     total_gst DECIMAL(12,2) NOT NULL DEFAULT 0,
     
-    status TEXT DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'COMPLETED')),
+    status TEXT DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'COMPLETED', 'REJECTED')),
     notes TEXT,
     
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
@@ -497,6 +499,151 @@ CREATE TABLE IF NOT EXISTS running_bills (
 CREATE INDEX IF NOT EXISTS idx_running_bills_bill ON running_bills(bill_id);
 CREATE INDEX IF NOT EXISTS idx_running_bills_status ON running_bills(status);
 
+-- =====================================================
+-- 20. PRINT_JOBS - Print Spool
+-- =====================================================
+CREATE TABLE IF NOT EXISTS print_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bill_id INTEGER,
+    document TEXT NOT NULL DEFAULT 'bill',
+    printer TEXT NOT NULL,
+    title TEXT NOT NULL,
+    raw INTEGER NOT NULL DEFAULT 1,
+    data BLOB NOT NULL,
+    copies INTEGER NOT NULL DEFAULT 1,
+    copies_printed INTEGER NOT NULL DEFAULT 0, -- sent so far; retries send the rest
+    status TEXT NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'printing', 'done', 'failed', 'cancelled')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    reprint_of INTEGER REFERENCES print_jobs(id),
+    next_attempt_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    printed_at DATETIME,
+    audit INTEGER NOT NULL DEFAULT 0,          -- write the print to audit_log once done
+    user_id INTEGER REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_print_jobs_status ON print_jobs(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_print_jobs_bill ON print_jobs(bill_id);
+
+-- =====================================================
+-- 21. RECEIPT_TEMPLATES - Editable Receipt Layouts
+-- =====================================================
+CREATE TABLE IF NOT EXISTS receipt_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    body TEXT NOT NULL,
+    width INTEGER NOT NULL DEFAULT 42,
+    is_active INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- =====================================================
+-- 22. MEDICINE_BUNDLE_BASE - Values Last Taken From the Bundled Medicine Master
+-- =====================================================
+CREATE TABLE IF NOT EXISTS medicine_bundle_base (
+    medicine_id INTEGER PRIMARY KEY REFERENCES medicines(id),
+    generic_name TEXT,
+    hsn_code TEXT,
+    category TEXT,
+    drug_type TEXT,
+    unit TEXT,
+    reorder_level INTEGER,
+    is_active INTEGER
+);
+
+-- =====================================================
+-- 23. MEDICINES_FTS - Full-Text Index for Medicine Search
+-- =====================================================
+CREATE VIRTUAL TABLE IF NOT EXISTS medicines_fts USING fts5(
+    name,
+    generic_name,
+    manufacturer,
+    content = 'medicines',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS medicines_fts_insert AFTER INSERT ON medicines BEGIN
+    INSERT INTO medicines_fts (rowid, name, generic_name, manufacturer)
+    VALUES (new.id, new.name, new.generic_name, new.manufacturer);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicines_fts_delete AFTER DELETE ON medicines BEGIN
+    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, manufacturer)
+    VALUES ('delete', old.id, old.name, old.generic_name, old.manufacturer);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicines_fts_update
+AFTER UPDATE OF name, generic_name, manufacturer ON medicines BEGIN
+    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, manufacturer)
+    VALUES ('delete', old.id, old.name, old.generic_name, old.manufacturer);
+    INSERT INTO medicines_fts (rowid, name, generic_name, manufacturer)
+    VALUES (new.id, new.name, new.generic_name, new.manufacturer);
+END;
+
+-- =====================================================
+-- 24. MEDICINE_CHANGES - Latest Name or Status Change per Medicine
+-- =====================================================
+CREATE TABLE IF NOT EXISTS medicine_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    medicine_id INTEGER NOT NULL UNIQUE
+);
+
+CREATE TRIGGER IF NOT EXISTS medicine_changes_insert AFTER INSERT ON medicines BEGIN
+    DELETE FROM medicine_changes WHERE medicine_id = new.id;
+    INSERT INTO medicine_changes (medicine_id) VALUES (new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicine_changes_delete AFTER DELETE ON medicines BEGIN
+    DELETE FROM medicine_changes WHERE medicine_id = old.id;
+    INSERT INTO medicine_changes (medicine_id) VALUES (old.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicine_changes_update
+AFTER UPDATE OF name, is_active ON medicines BEGIN
+    DELETE FROM medicine_changes WHERE medicine_id = new.id;
+    INSERT INTO medicine_changes (medicine_id) VALUES (new.id);
+END;
+
+-- =====================================================
+-- 25. STOCK_CHANGES - Latest Batch or Detail Change per Medicine
+-- =====================================================
+CREATE TABLE IF NOT EXISTS stock_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    medicine_id INTEGER NOT NULL UNIQUE
+);
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_batch_insert AFTER INSERT ON batches BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = new.medicine_id;
+    INSERT INTO stock_changes (medicine_id) VALUES (new.medicine_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_batch_delete AFTER DELETE ON batches BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = old.medicine_id;
+    INSERT INTO stock_changes (medicine_id) VALUES (old.medicine_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_batch_update
+AFTER UPDATE OF medicine_id, quantity, expiry_date, last_sold_date, is_active ON batches BEGIN
+    DELETE FROM stock_changes WHERE medicine_id IN (old.medicine_id, new.medicine_id);
+    INSERT INTO stock_changes (medicine_id) VALUES (new.medicine_id);
+    INSERT INTO stock_changes (medicine_id)
+    SELECT old.medicine_id WHERE old.medicine_id <> new.medicine_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_medicine_update AFTER UPDATE ON medicines BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = new.id;
+    INSERT INTO stock_changes (medicine_id) VALUES (new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_medicine_delete AFTER DELETE ON medicines BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = old.id;
+    INSERT INTO stock_changes (medicine_id) VALUES (old.id);
+END;
+
 -- =====================================================
 -- DEFAULT DATA
 -- =====================================================
@@ -515,10 +662,33 @@ INSERT OR IGNORE INTO settings (key, value, category, description) VALUES
 ('shop_address', '', 'shop', 'Shop address'),
 ('shop_phone', '', 'shop', 'Shop phone number'),
 ('shop_gstin', '', 'shop', 'GST Number'),
+('shop_upi_id', '', 'shop', 'UPI ID (VPA) printed as a payment QR code'),
 ('shop_drug_license', '', 'shop', 'Drug License Number'),
 ('shop_state', 'Tamil Nadu', 'shop', 'State for CGST/SGST'),
 ('bill_prefix', 'INV', 'billing', 'Bill number prefix'),
 ('thermal_printer_width', '80', 'printing', 'Thermal printer width in mm'),
+('dot_matrix_form_length', '0', 'printing', 'Dot matrix form length in lines (0 = continuous paper)'),
+('dot_matrix_page_lines', '0', 'printing', 'Lines printed per page before a bill continues on the next form (0 = form length)'),
+('dot_matrix_tear_off_lines', '6', 'printing', 'Lines fed after a bill on continuous paper'),
+('dot_matrix_lines_per_inch', '6', 'printing', 'Dot matrix line spacing (6 or 8 lines per inch)'),
+('thermal_printer_columns', '0', 'printing', 'Thermal characters per line (0 = from paper width)'),
+('thermal_cut_mode', 'partial', 'printing', 'Thermal paper cut: partial, full or none'),
+('thermal_qr_mode', 'native', 'printing', 'Thermal QR codes: native (printer-drawn) or raster'),
+('label_dpi', '203', 'printing', 'Label printer resolution in dots per inch (203 or 300)'),
+('label_gap_mm', '2', 'printing', 'Gap between labels on the roll in mm'),
+('label_barcode', 'code128', 'printing', 'Label barcode: code128 or datamatrix'),
+('printer_backend', 'system', 'printing', 'Printer connection: system, socket, file or mock'),
+('printer_address', '', 'printing', 'Network printer host:port for the socket backend'),
+('printer_output_dir', '', 'printing', 'Folder for print files when using the file backend'),
+('printer_status_interval', '15', 'printing', 'Seconds between bill printer status checks (0 = off)'),
+('cash_drawer_enabled', 'false', 'printing', 'Open the cash drawer after cash bills print'),
+('cash_drawer_pin', '2', 'printing', 'Cash drawer kick pin on the receipt printer (2 or 5)'),
+('cash_drawer_on_ms', '100', 'printing', 'Cash drawer pulse on time in ms'),
+('cash_drawer_off_ms', '200', 'printing', 'Cash drawer pulse off time in ms'),
+('unicode_font_path', '', 'printing', 'TrueType font for Tamil and other Unicode text on receipts (empty = system font)'),
+('customer_display_port', '', 'printing', 'Serial port of the customer pole display, e.g. serial:COM3?baud=9600 (empty = none)'),
+('customer_display_protocol', 'epson', 'printing', 'Customer display command set (epson or cd5220)'),
+('shop_logo_path', '', 'shop', 'Logo image printed on thermal receipts'),
 ('backup_path', './backups', 'system', 'Backup directory path'),
 ('last_backup_date', '', 'system', 'Last backup timestamp'),
 ('expiry_alert_days', '30', 'alerts', 'Days before expiry to alert'),
@@ -31,7 +31,11 @@ function parseCSVLine(line) {
     
     for (let i = 0; i < line.length; i++) {
         const char = line[i];
-        if (char === '"') {
+        if (char === '"' && inQuotes && line[i + 1] === '"') {
+            // "" inside a quoted field is a literal quote
+            current += '"';
+            i++;
+        } else if (char === '"') {
             inQuotes = !inQuotes;
         } else if (char === ',' && !inQuotes) {
             result.push(current.trim());
@@ -0,0 +1,384 @@
+-- =====================================================
+-- 0001 Baseline
+-- The schema the app created from the frontend before
+-- migrations were versioned. Unversioned databases of
+-- any age are brought up to it; columns added later are
+-- filled in by the migration itself.
+-- =====================================================
+
+-- Users Table
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    full_name TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('admin', 'staff')),
+    is_active INTEGER DEFAULT 1,
+    last_login DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Medicines Table - Master data only (GST/schedule set per batch)
+CREATE TABLE IF NOT EXISTS medicines (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    generic_name TEXT,
+    manufacturer TEXT,
+    hsn_code TEXT NOT NULL DEFAULT '3004',
+    category TEXT,
+    drug_type TEXT,
+    pack_size TEXT,
+    unit TEXT DEFAULT 'PCS',
+    reorder_level INTEGER DEFAULT 10,
+    is_active INTEGER DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Batches Table - GST rate and schedule stored per batch
+CREATE TABLE IF NOT EXISTS batches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+    batch_number TEXT NOT NULL,
+    expiry_date DATE NOT NULL,
+    purchase_price DECIMAL(10,2) NOT NULL,
+    mrp DECIMAL(10,2) NOT NULL,
+    selling_price DECIMAL(10,2) NOT NULL,
+    price_type TEXT NOT NULL DEFAULT 'INCLUSIVE' CHECK (price_type IN ('INCLUSIVE', 'EXCLUSIVE')),
+    gst_rate DECIMAL(5,2) NOT NULL DEFAULT 12 CHECK (gst_rate IN (0, 5, 12, 18)),
+    is_schedule INTEGER DEFAULT 0,
+    quantity INTEGER NOT NULL DEFAULT 0,
+    tablets_per_strip INTEGER DEFAULT 10,
+    rack TEXT,
+    box TEXT,
+    last_sold_date DATE,
+    purchase_id INTEGER,
+    supplier_id INTEGER REFERENCES suppliers(id),
+    is_active INTEGER DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(medicine_id, batch_number)
+);
+
+-- Suppliers Table
+CREATE TABLE IF NOT EXISTS suppliers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    contact_person TEXT,
+    phone TEXT,
+    email TEXT,
+    gstin TEXT,
+    address TEXT,
+    city TEXT,
+    state TEXT DEFAULT 'Tamil Nadu',
+    pincode TEXT,
+    payment_terms INTEGER DEFAULT 30,
+    is_active INTEGER DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Customers Table
+CREATE TABLE IF NOT EXISTS customers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    phone TEXT,
+    email TEXT,
+    gstin TEXT,
+    address TEXT,
+    credit_limit DECIMAL(12,2) DEFAULT 0,
+    current_balance DECIMAL(12,2) DEFAULT 0,
+    is_active INTEGER DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Bills Table
+CREATE TABLE IF NOT EXISTS bills (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bill_number TEXT NOT NULL UNIQUE,
+    bill_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+    customer_id INTEGER REFERENCES customers(id),
+    customer_name TEXT,
+    doctor_name TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    subtotal DECIMAL(12,2) NOT NULL DEFAULT 0,
+    discount_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    discount_percent DECIMAL(5,2) DEFAULT 0,
+    taxable_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    cgst_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    sgst_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    total_gst DECIMAL(12,2) NOT NULL DEFAULT 0,
+    round_off DECIMAL(5,2) DEFAULT 0,
+    grand_total DECIMAL(12,2) NOT NULL DEFAULT 0,
+    payment_mode TEXT NOT NULL DEFAULT 'CASH' CHECK (payment_mode IN ('CASH', 'ONLINE', 'CREDIT', 'SPLIT')),
+    payment_status TEXT NOT NULL DEFAULT 'PAID' CHECK (payment_status IN ('PAID', 'PARTIAL', 'PENDING')),
+    cash_amount DECIMAL(12,2) DEFAULT 0,
+    online_amount DECIMAL(12,2) DEFAULT 0,
+    credit_amount DECIMAL(12,2) DEFAULT 0,
+    notes TEXT,
+    total_items INTEGER DEFAULT 0,
+    is_cancelled INTEGER DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Bill Items Table
+CREATE TABLE IF NOT EXISTS bill_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bill_id INTEGER NOT NULL REFERENCES bills(id),
+    batch_id INTEGER NOT NULL REFERENCES batches(id),
+    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+    medicine_name TEXT NOT NULL,
+    batch_number TEXT NOT NULL,
+    hsn_code TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    quantity_strips INTEGER DEFAULT 0,
+    quantity_pieces INTEGER DEFAULT 0,
+    tablets_per_strip INTEGER DEFAULT 10,
+    unit TEXT NOT NULL DEFAULT 'PCS',
+    mrp DECIMAL(10,2) NOT NULL,
+    selling_price DECIMAL(10,2) NOT NULL,
+    discount_percent DECIMAL(5,2) DEFAULT 0,
+    discount_amount DECIMAL(10,2) DEFAULT 0,
+    taxable_amount DECIMAL(12,2) NOT NULL,
+    gst_rate DECIMAL(5,2) NOT NULL,
+    cgst_amount DECIMAL(10,2) NOT NULL,
+    sgst_amount DECIMAL(10,2) NOT NULL,
+    total_amount DECIMAL(12,2) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Purchases Table
+CREATE TABLE IF NOT EXISTS purchases (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    supplier_id INTEGER REFERENCES suppliers(id),
+    invoice_number TEXT NOT NULL,
+    invoice_date DATE NOT NULL,
+    subtotal DECIMAL(12,2) NOT NULL DEFAULT 0,
+    discount_amount DECIMAL(12,2) DEFAULT 0,
+    taxable_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    cgst_amount DECIMAL(12,2) DEFAULT 0,
+    sgst_amount DECIMAL(12,2) DEFAULT 0,
+    igst_amount DECIMAL(12,2) DEFAULT 0,
+    total_gst DECIMAL(12,2) DEFAULT 0,
+    grand_total DECIMAL(12,2) NOT NULL DEFAULT 0,
+    payment_status TEXT DEFAULT 'PENDING' CHECK (payment_status IN ('PAID', 'PARTIAL', 'PENDING')),
+    paid_amount DECIMAL(12,2) DEFAULT 0,
+    notes TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Purchase Items Table
+CREATE TABLE IF NOT EXISTS purchase_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    purchase_id INTEGER NOT NULL REFERENCES purchases(id),
+    batch_id INTEGER REFERENCES batches(id),
+    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+    medicine_name TEXT NOT NULL,
+    batch_number TEXT NOT NULL,
+    expiry_date DATE NOT NULL,
+    quantity INTEGER NOT NULL,
+    free_quantity INTEGER DEFAULT 0,
+    pack_size INTEGER DEFAULT 1,
+    purchase_price DECIMAL(10,2) NOT NULL,
+    mrp DECIMAL(10,2) NOT NULL,
+    discount_percent DECIMAL(5,2) DEFAULT 0,
+    gst_rate DECIMAL(5,2) NOT NULL,
+    cgst_amount DECIMAL(10,2) DEFAULT 0,
+    sgst_amount DECIMAL(10,2) DEFAULT 0,
+    total_amount DECIMAL(12,2) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Credits Table (Udhar)
+CREATE TABLE IF NOT EXISTS credits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    customer_id INTEGER NOT NULL REFERENCES customers(id),
+    bill_id INTEGER REFERENCES bills(id),
+    transaction_type TEXT NOT NULL CHECK (transaction_type IN ('CREDIT', 'PAYMENT', 'SALE', 'ADJUSTMENT')),
+    amount DECIMAL(12,2) NOT NULL,
+    balance_after DECIMAL(12,2) NOT NULL,
+    payment_mode TEXT CHECK (payment_mode IN ('CASH', 'ONLINE', 'ADJUSTMENT')),
+    reference_number TEXT,
+    notes TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Audit Log Table
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    action TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id INTEGER,
+    old_value TEXT,
+    new_value TEXT,
+    description TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Settings Table
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    category TEXT DEFAULT 'general',
+    description TEXT,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Bill Sequence Table
+CREATE TABLE IF NOT EXISTS bill_sequence (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    prefix TEXT NOT NULL DEFAULT 'INV',
+    current_number INTEGER NOT NULL DEFAULT 0,
+    financial_year TEXT NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Scheduled Medicine Records Table - Patient details for scheduled drug sales
+CREATE TABLE IF NOT EXISTS scheduled_medicine_records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bill_id INTEGER NOT NULL REFERENCES bills(id),
+    bill_item_id INTEGER NOT NULL REFERENCES bill_items(id),
+    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+    batch_id INTEGER NOT NULL REFERENCES batches(id),
+    patient_name TEXT NOT NULL,
+    patient_age INTEGER,
+    patient_gender TEXT CHECK (patient_gender IN ('M', 'F', 'O')),
+    patient_phone TEXT,
+    patient_address TEXT,
+    doctor_name TEXT,
+    doctor_registration_number TEXT,
+    clinic_hospital_name TEXT,
+    prescription_number TEXT,
+    prescription_date TEXT,
+    doctor_prescription TEXT,
+    quantity INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Running Bills Table - For medicines sold without stock (to be reconciled later)
+-- Creates an actual bill for the customer, but tracks the pending stock reconciliation
+CREATE TABLE IF NOT EXISTS running_bills (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bill_id INTEGER NOT NULL REFERENCES bills(id),
+    bill_item_id INTEGER REFERENCES bill_items(id),
+    medicine_name TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    unit_price DECIMAL(10,2) NOT NULL,
+    total_amount DECIMAL(12,2) NOT NULL,
+    gst_rate DECIMAL(5,2) DEFAULT 0,
+    hsn_code TEXT DEFAULT '3004',
+    notes TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'STOCKED', 'CANCELLED')),
+    linked_batch_id INTEGER REFERENCES batches(id),
+    linked_medicine_id INTEGER REFERENCES medicines(id),
+    stocked_at DATETIME,
+    stocked_by INTEGER REFERENCES users(id),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Sales Returns Table - Customer returns to pharmacy
+CREATE TABLE IF NOT EXISTS sales_returns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_number TEXT NOT NULL UNIQUE,
+    return_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+    bill_id INTEGER NOT NULL REFERENCES bills(id),
+    customer_id INTEGER REFERENCES customers(id),
+    reason TEXT,
+    refund_mode TEXT CHECK (refund_mode IN ('CASH', 'CREDIT_NOTE', 'ADJUSTMENT')),
+    total_amount DECIMAL(12,2) NOT NULL,
+    total_gst DECIMAL(12,2) DEFAULT 0,
+    status TEXT DEFAULT 'COMPLETED',
+    notes TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Sales Return Items Table
+CREATE TABLE IF NOT EXISTS sales_return_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_id INTEGER NOT NULL REFERENCES sales_returns(id),
+    bill_item_id INTEGER NOT NULL REFERENCES bill_items(id),
+    batch_id INTEGER NOT NULL REFERENCES batches(id),
+    quantity INTEGER NOT NULL,
+    unit_price DECIMAL(10,2) NOT NULL,
+    gst_rate DECIMAL(5,2) NOT NULL,
+    cgst DECIMAL(10,2) DEFAULT 0,
+    sgst DECIMAL(10,2) DEFAULT 0,
+    total DECIMAL(12,2) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Purchase Returns Table - Pharmacy returns to supplier
+CREATE TABLE IF NOT EXISTS purchase_returns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_number TEXT NOT NULL UNIQUE,
+    return_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+    purchase_id INTEGER REFERENCES purchases(id),
+    supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
+    reason TEXT CHECK (reason IN ('EXPIRY', 'DAMAGE', 'OVERSTOCK', 'OTHER')),
+    total_amount DECIMAL(12,2) NOT NULL,
+    total_gst DECIMAL(12,2) DEFAULT 0,
+    status TEXT DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'COMPLETED')),
+    notes TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Purchase Return Items Table
+CREATE TABLE IF NOT EXISTS purchase_return_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_id INTEGER NOT NULL REFERENCES purchase_returns(id),
+    batch_id INTEGER NOT NULL REFERENCES batches(id),
+    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+    quantity INTEGER NOT NULL,
+    unit_price DECIMAL(10,2) NOT NULL,
+    gst_rate DECIMAL(5,2) NOT NULL,
+    cgst DECIMAL(10,2) DEFAULT 0,
+    sgst DECIMAL(10,2) DEFAULT 0,
+    total DECIMAL(12,2) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_users_username ON users(username);
+CREATE INDEX IF NOT EXISTS idx_medicines_name ON medicines(name);
+CREATE INDEX IF NOT EXISTS idx_medicines_hsn ON medicines(hsn_code);
+CREATE INDEX IF NOT EXISTS idx_medicines_manufacturer ON medicines(manufacturer);
+CREATE INDEX IF NOT EXISTS idx_batches_medicine ON batches(medicine_id);
+CREATE INDEX IF NOT EXISTS idx_batches_expiry ON batches(expiry_date);
+CREATE INDEX IF NOT EXISTS idx_batches_location ON batches(rack, box);
+CREATE INDEX IF NOT EXISTS idx_batches_quantity ON batches(quantity);
+CREATE INDEX IF NOT EXISTS idx_batches_gst ON batches(gst_rate);
+CREATE INDEX IF NOT EXISTS idx_batches_schedule ON batches(is_schedule);
+CREATE INDEX IF NOT EXISTS idx_bills_number ON bills(bill_number);
+CREATE INDEX IF NOT EXISTS idx_bills_date ON bills(bill_date);
+CREATE INDEX IF NOT EXISTS idx_bills_customer ON bills(customer_id);
+CREATE INDEX IF NOT EXISTS idx_bills_cancelled ON bills(is_cancelled);
+CREATE INDEX IF NOT EXISTS idx_bill_items_bill ON bill_items(bill_id);
+CREATE INDEX IF NOT EXISTS idx_bill_items_batch ON bill_items(batch_id);
+CREATE INDEX IF NOT EXISTS idx_bill_items_medicine ON bill_items(medicine_id);
+CREATE INDEX IF NOT EXISTS idx_purchases_supplier ON purchases(supplier_id);
+CREATE INDEX IF NOT EXISTS idx_purchases_date ON purchases(invoice_date);
+CREATE INDEX IF NOT EXISTS idx_credits_customer ON credits(customer_id);
+CREATE INDEX IF NOT EXISTS idx_credits_bill ON credits(bill_id);
+CREATE INDEX IF NOT EXISTS idx_audit_entity ON audit_log(entity_type, entity_id);
+CREATE INDEX IF NOT EXISTS idx_scheduled_medicine_bill ON scheduled_medicine_records(bill_id);
+CREATE INDEX IF NOT EXISTS idx_scheduled_medicine_medicine ON scheduled_medicine_records(medicine_id);
+CREATE INDEX IF NOT EXISTS idx_running_bills_status ON running_bills(status);
+CREATE INDEX IF NOT EXISTS idx_running_bills_bill ON running_bills(bill_id);
+CREATE INDEX IF NOT EXISTS idx_batches_supplier ON batches(supplier_id);
+CREATE INDEX IF NOT EXISTS idx_sales_returns_bill ON sales_returns(bill_id);
+CREATE INDEX IF NOT EXISTS idx_sales_returns_customer ON sales_returns(customer_id);
+CREATE INDEX IF NOT EXISTS idx_sales_return_items_return ON sales_return_items(return_id);
+CREATE INDEX IF NOT EXISTS idx_purchase_returns_supplier ON purchase_returns(supplier_id);
+CREATE INDEX IF NOT EXISTS idx_purchase_return_items_return ON purchase_return_items(return_id);
@@ -0,0 +1,32 @@
+-- =====================================================
+-- 0002 Rejected Purchase Returns
+-- Suppliers can reject a return; SQLite cannot change a
+-- CHECK constraint in place, so the table is rebuilt
+-- =====================================================
+
+CREATE TABLE purchase_returns_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    return_number TEXT NOT NULL UNIQUE,
+    return_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+    purchase_id INTEGER REFERENCES purchases(id),
+    supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
+    reason TEXT CHECK (reason IN ('EXPIRY', 'DAMAGE', 'OVERSTOCK', 'OTHER')),
+    total_amount DECIMAL(12,2) NOT NULL,
+    total_gst DECIMAL(12,2) DEFAULT 0,
+    status TEXT DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'COMPLETED', 'REJECTED')),
+    notes TEXT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO purchase_returns_new (id, return_number, return_date, purchase_id, supplier_id,
+    reason, total_amount, total_gst, status, notes, user_id, created_at, updated_at)
+SELECT id, return_number, return_date, purchase_id, supplier_id,
+    reason, total_amount, total_gst, status, notes, user_id, created_at, updated_at
+FROM purchase_returns;
+
+DROP TABLE purchase_returns;
+ALTER TABLE purchase_returns_new RENAME TO purchase_returns;
+
+CREATE INDEX IF NOT EXISTS idx_purchase_returns_supplier ON purchase_returns(supplier_id);
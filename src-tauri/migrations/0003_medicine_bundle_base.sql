@@ -0,0 +1,17 @@
+-- =====================================================
+-- 0003 Medicine Bundle Base
+-- The values each medicine last took from the bundled
+-- medicine master, so a newer bundle can tell fields
+-- the shop edited from fields it never touched
+-- =====================================================
+
+CREATE TABLE IF NOT EXISTS medicine_bundle_base (
+    medicine_id INTEGER PRIMARY KEY REFERENCES medicines(id),
+    generic_name TEXT,
+    hsn_code TEXT,
+    category TEXT,
+    drug_type TEXT,
+    unit TEXT,
+    reorder_level INTEGER,
+    is_active INTEGER
+);
@@ -0,0 +1,37 @@
+-- =====================================================
+-- 0004 Medicine Search Index
+-- Full-text index over the medicine master for prefix
+-- search at the counter. The catalog's composition is
+-- stored in generic_name. Triggers keep it in step with
+-- every insert, edit and delete, whichever side writes.
+-- =====================================================
+
+CREATE VIRTUAL TABLE IF NOT EXISTS medicines_fts USING fts5(
+    name,
+    generic_name,
+    manufacturer,
+    content = 'medicines',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS medicines_fts_insert AFTER INSERT ON medicines BEGIN
+    INSERT INTO medicines_fts (rowid, name, generic_name, manufacturer)
+    VALUES (new.id, new.name, new.generic_name, new.manufacturer);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicines_fts_delete AFTER DELETE ON medicines BEGIN
+    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, manufacturer)
+    VALUES ('delete', old.id, old.name, old.generic_name, old.manufacturer);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicines_fts_update
+AFTER UPDATE OF name, generic_name, manufacturer ON medicines BEGIN
+    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, manufacturer)
+    VALUES ('delete', old.id, old.name, old.generic_name, old.manufacturer);
+    INSERT INTO medicines_fts (rowid, name, generic_name, manufacturer)
+    VALUES (new.id, new.name, new.generic_name, new.manufacturer);
+END;
+
+INSERT INTO medicines_fts (medicines_fts) VALUES ('rebuild');
@@ -0,0 +1,28 @@
+-- =====================================================
+-- 0005 Medicine Changes
+-- The latest change to each medicine's name or status,
+-- numbered in order, so the in-memory name index can
+-- catch up with edits made by any connection. Each
+-- medicine keeps only its latest entry.
+-- =====================================================
+
+CREATE TABLE IF NOT EXISTS medicine_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    medicine_id INTEGER NOT NULL UNIQUE
+);
+
+CREATE TRIGGER IF NOT EXISTS medicine_changes_insert AFTER INSERT ON medicines BEGIN
+    DELETE FROM medicine_changes WHERE medicine_id = new.id;
+    INSERT INTO medicine_changes (medicine_id) VALUES (new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicine_changes_delete AFTER DELETE ON medicines BEGIN
+    DELETE FROM medicine_changes WHERE medicine_id = old.id;
+    INSERT INTO medicine_changes (medicine_id) VALUES (old.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS medicine_changes_update
+AFTER UPDATE OF name, is_active ON medicines BEGIN
+    DELETE FROM medicine_changes WHERE medicine_id = new.id;
+    INSERT INTO medicine_changes (medicine_id) VALUES (new.id);
+END;
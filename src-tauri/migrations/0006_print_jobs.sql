@@ -0,0 +1,26 @@
+-- =====================================================
+-- 0006 Print Jobs
+-- The print spool's queue. Earlier builds created it
+-- when the spool started, so it may already exist.
+-- =====================================================
+
+CREATE TABLE IF NOT EXISTS print_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bill_id INTEGER,
+    document TEXT NOT NULL DEFAULT 'bill',
+    printer TEXT NOT NULL,
+    title TEXT NOT NULL,
+    raw INTEGER NOT NULL DEFAULT 1,
+    data BLOB NOT NULL,
+    copies INTEGER NOT NULL DEFAULT 1,
+    status TEXT NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'printing', 'done', 'failed', 'cancelled')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    reprint_of INTEGER REFERENCES print_jobs(id),
+    next_attempt_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    printed_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_print_jobs_status ON print_jobs(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_print_jobs_bill ON print_jobs(bill_id);
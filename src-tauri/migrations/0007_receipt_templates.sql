@@ -0,0 +1,16 @@
+-- =====================================================
+-- 0007 Receipt Templates
+-- Shop-edited receipt layouts. Earlier builds created
+-- the table at startup, so it may already exist; the
+-- standard template is seeded only into an empty table.
+-- =====================================================
+
+CREATE TABLE IF NOT EXISTS receipt_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    body TEXT NOT NULL,
+    width INTEGER NOT NULL DEFAULT 42,
+    is_active INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+-- =====================================================
+-- 0008 Print Job Audit
+-- Bills printed through the spool are written to
+-- audit_log once the job prints, against the user who
+-- asked for them
+-- =====================================================
+
+ALTER TABLE print_jobs ADD COLUMN audit INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE print_jobs ADD COLUMN user_id INTEGER REFERENCES users(id);
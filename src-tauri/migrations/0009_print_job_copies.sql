@@ -0,0 +1,7 @@
+-- =====================================================
+-- 0009 Print Job Copies
+-- Copies of a job already sent to the printer, so a
+-- retry sends only the rest
+-- =====================================================
+
+ALTER TABLE print_jobs ADD COLUMN copies_printed INTEGER NOT NULL DEFAULT 0;
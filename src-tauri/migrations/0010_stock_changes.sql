@@ -0,0 +1,40 @@
+-- =====================================================
+-- 0010 Stock Changes
+-- The latest change to each medicine's batches or
+-- details, numbered in order, so medicine search can
+-- re-read only the medicines a sale or purchase touched.
+-- Each medicine keeps only its latest entry.
+-- =====================================================
+
+CREATE TABLE IF NOT EXISTS stock_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    medicine_id INTEGER NOT NULL UNIQUE
+);
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_batch_insert AFTER INSERT ON batches BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = new.medicine_id;
+    INSERT INTO stock_changes (medicine_id) VALUES (new.medicine_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_batch_delete AFTER DELETE ON batches BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = old.medicine_id;
+    INSERT INTO stock_changes (medicine_id) VALUES (old.medicine_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_batch_update
+AFTER UPDATE OF medicine_id, quantity, expiry_date, last_sold_date, is_active ON batches BEGIN
+    DELETE FROM stock_changes WHERE medicine_id IN (old.medicine_id, new.medicine_id);
+    INSERT INTO stock_changes (medicine_id) VALUES (new.medicine_id);
+    INSERT INTO stock_changes (medicine_id)
+    SELECT old.medicine_id WHERE old.medicine_id <> new.medicine_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_medicine_update AFTER UPDATE ON medicines BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = new.id;
+    INSERT INTO stock_changes (medicine_id) VALUES (new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS stock_changes_medicine_delete AFTER DELETE ON medicines BEGIN
+    DELETE FROM stock_changes WHERE medicine_id = old.id;
+    INSERT INTO stock_changes (medicine_id) VALUES (old.id);
+END;
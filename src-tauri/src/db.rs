@@ -0,0 +1,45 @@
+use rusqlite::{Connection, OptionalExtension};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::Manager;
+
+/// Get the main database path (matches Tauri SQL plugin location - ~/.config/)
+pub fn get_db_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|p| p.join("medbill.db"))
+        .map_err(|e| format!("Failed to get config directory: {}", e))
+}
+
+/// Open the main database with the same busy timeout the frontend uses
+pub fn open(app: &tauri::AppHandle) -> Result<Connection, String> {
+    let db_path = get_db_path(app)?;
+    if let Some(dir) = db_path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let conn = Connection::open(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.busy_timeout(Duration::from_millis(5000))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    Ok(conn)
+}
+
+/// Read a single value from the settings table.
+/// Missing keys (or a database that has not been initialized yet) return None.
+pub fn get_setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        rusqlite::params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Read a numeric setting, falling back to `default` when missing or malformed
+pub fn get_setting_or<T: std::str::FromStr>(conn: &Connection, key: &str, default: T) -> T {
+    get_setting(conn, key)
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
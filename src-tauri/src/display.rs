@@ -0,0 +1,345 @@
+// =====================================================
+// Customer Pole Display
+// 2x20 VFD facing the customer at the billing counter,
+// driven over serial with the Epson or CD5220 commands
+// =====================================================
+
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::Connection;
+use tauri::{command, State};
+
+use crate::print::device::SerialConfig;
+
+/// Characters per line
+pub const COLUMNS: usize = 20;
+
+const ESC: u8 = 0x1B;
+const US: u8 = 0x1F;
+const CR: u8 = 0x0D;
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Command set the display is switched to (usually by DIP switch)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// Epson ESC/POS customer display commands; `US $ x y` places the cursor
+    #[default]
+    Epson,
+    /// CD5220 commands; `ESC Q A` and `ESC Q B` write the upper and lower line
+    Cd5220,
+}
+
+impl Protocol {
+    pub fn parse(value: &str) -> Protocol {
+        match value.trim().to_lowercase().as_str() {
+            "cd5220" => Protocol::Cd5220,
+            _ => Protocol::Epson,
+        }
+    }
+
+    /// Reset the display and hide the cursor. Epson displays are also put
+    /// in overwrite mode so a full line does not scroll.
+    fn init(self) -> Vec<u8> {
+        match self {
+            Protocol::Epson => vec![ESC, b'@', US, 0x01, US, b'C', 0],
+            Protocol::Cd5220 => vec![ESC, b'@', ESC, b'_', 0],
+        }
+    }
+
+    /// Write both lines in full, so nothing of the previous screen is left
+    fn lines(self, screen: &Screen) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (row, text) in [(1u8, &screen.top), (2, &screen.bottom)] {
+            match self {
+                Protocol::Epson => {
+                    out.extend_from_slice(&[US, b'$', 1, row]);
+                    out.extend_from_slice(text.as_bytes());
+                }
+                Protocol::Cd5220 => {
+                    out.extend_from_slice(&[ESC, b'Q', b'@' + row]);
+                    out.extend_from_slice(text.as_bytes());
+                    out.push(CR);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// What the customer sees: two lines of exactly `COLUMNS` ASCII characters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+    pub top: String,
+    pub bottom: String,
+}
+
+impl Screen {
+    pub fn new(top: &str, bottom: &str) -> Self {
+        Screen {
+            top: fit(top),
+            bottom: fit(bottom),
+        }
+    }
+
+    /// Shop name and a greeting while no bill is in progress
+    pub fn welcome(shop_name: &str) -> Self {
+        Screen::new(&center(shop_name), &center("WELCOME"))
+    }
+
+    /// The item just scanned with its price, and the running total
+    pub fn item(name: &str, price: f64, total: f64) -> Self {
+        Screen::new(
+            &spread(name, &amount(price)),
+            &spread("TOTAL", &amount(total)),
+        )
+    }
+
+    /// The running total, after an item is changed or removed
+    pub fn total(total: f64, items: usize) -> Self {
+        let count = match items {
+            1 => "1 ITEM".to_string(),
+            n => format!("{} ITEMS", n),
+        };
+        Screen::new(&spread("TOTAL", &amount(total)), &count)
+    }
+
+    /// Shown when the bill is saved
+    pub fn thank_you(change: f64) -> Self {
+        Screen::new(&center("THANK YOU"), &spread("CHANGE", &amount(change)))
+    }
+}
+
+fn amount(value: f64) -> String {
+    format!("Rs {:.2}", value.max(0.0))
+}
+
+/// ASCII only (anything else shows as '?'), cut or padded to `COLUMNS`
+fn fit(text: &str) -> String {
+    let mut line: String = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '?'
+            }
+        })
+        .take(COLUMNS)
+        .collect();
+    while line.len() < COLUMNS {
+        line.push(' ');
+    }
+    line
+}
+
+fn center(text: &str) -> String {
+    let text = text.trim();
+    let pad = COLUMNS.saturating_sub(text.chars().count()) / 2;
+    format!("{}{}", " ".repeat(pad), text)
+}
+
+/// `left` and `right` at either end of the line; `left` is cut short so
+/// the amount always shows in full
+fn spread(left: &str, right: &str) -> String {
+    let right_width = right.chars().count();
+    let room = COLUMNS.saturating_sub(right_width + 1);
+    let left: String = left.trim().chars().take(room).collect();
+    format!("{:<width$}{}", left, right, width = COLUMNS - right_width)
+}
+
+/// An open pole display
+pub struct PoleDisplay {
+    port: Box<dyn Write + Send>,
+    protocol: Protocol,
+}
+
+impl PoleDisplay {
+    /// Take over `port` and reset the display
+    pub fn new(port: Box<dyn Write + Send>, protocol: Protocol) -> Result<Self, String> {
+        let mut display = PoleDisplay { port, protocol };
+        display.write(&protocol.init())?;
+        Ok(display)
+    }
+
+    /// Open a `serial:` target such as `serial:COM4?baud=9600`
+    pub fn open(target: &str, protocol: Protocol) -> Result<Self, String> {
+        let port = SerialConfig::parse(target)?.open(WRITE_TIMEOUT)?;
+        PoleDisplay::new(Box::new(port), protocol)
+    }
+
+    pub fn show(&mut self, screen: &Screen) -> Result<(), String> {
+        let bytes = self.protocol.lines(screen);
+        self.write(&bytes)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.port
+            .write_all(bytes)
+            .and_then(|_| self.port.flush())
+            .map_err(|e| format!("Failed to write to customer display: {}", e))
+    }
+}
+
+/// `customer_display_port` and `customer_display_protocol`
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct DisplaySettings {
+    target: String,
+    protocol: Protocol,
+}
+
+impl DisplaySettings {
+    /// `None` when no display port is set
+    fn load(conn: &Connection) -> Option<Self> {
+        let target = crate::db::get_setting(conn, "customer_display_port")?
+            .trim()
+            .to_string();
+        if target.is_empty() {
+            return None;
+        }
+        Some(DisplaySettings {
+            target,
+            protocol: crate::db::get_setting(conn, "customer_display_protocol")
+                .map(|p| Protocol::parse(&p))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// The display the billing screen writes to, kept open between updates
+#[derive(Default)]
+pub struct CustomerDisplay(Mutex<Option<(DisplaySettings, PoleDisplay)>>);
+
+impl CustomerDisplay {
+    /// Show `screen` on the display in settings. The port is reopened when
+    /// the settings change or a write fails; without a display this does
+    /// nothing.
+    fn show(&self, app: &tauri::AppHandle, screen: &Screen) -> Result<(), String> {
+        let conn = crate::db::open(app)?;
+        let settings = DisplaySettings::load(&conn);
+        let mut open = self
+            .0
+            .lock()
+            .map_err(|_| "Customer display is unavailable".to_string())?;
+
+        let Some(settings) = settings else {
+            *open = None;
+            return Ok(());
+        };
+        if let Some((current, display)) = open.as_mut() {
+            if *current == settings {
+                match display.show(screen) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => log::warn!("{}, reopening {}", e, settings.target),
+                }
+            }
+        }
+
+        *open = None;
+        let mut display = PoleDisplay::open(&settings.target, settings.protocol)?;
+        display.show(screen)?;
+        log::info!("Customer display on {}", settings.target);
+        *open = Some((settings, display));
+        Ok(())
+    }
+}
+
+/// Show the item just added and the running bill total
+#[command]
+pub async fn customer_display_item(
+    app: tauri::AppHandle,
+    display: State<'_, CustomerDisplay>,
+    name: String,
+    price: f64,
+    total: f64,
+) -> Result<(), String> {
+    display.show(&app, &Screen::item(&name, price, total))
+}
+
+/// Show the running total of `items` items
+#[command]
+pub async fn customer_display_total(
+    app: tauri::AppHandle,
+    display: State<'_, CustomerDisplay>,
+    total: f64,
+    items: usize,
+) -> Result<(), String> {
+    display.show(&app, &Screen::total(total, items))
+}
+
+/// Thank the customer and show the change due once the bill is saved
+#[command]
+pub async fn customer_display_thank_you(
+    app: tauri::AppHandle,
+    display: State<'_, CustomerDisplay>,
+    change: f64,
+) -> Result<(), String> {
+    display.show(&app, &Screen::thank_you(change))
+}
+
+/// Show the shop name and a greeting
+#[command]
+pub async fn customer_display_welcome(
+    app: tauri::AppHandle,
+    display: State<'_, CustomerDisplay>,
+) -> Result<(), String> {
+    let shop = crate::db::open(&app)
+        .ok()
+        .and_then(|conn| crate::db::get_setting(&conn, "shop_name"))
+        .unwrap_or_default();
+    display.show(&app, &Screen::welcome(&shop))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_screen_keeps_the_price_in_full() {
+        let screen = Screen::item("Azithromycin 500mg Tablets IP", 35.0, 1249.5);
+        assert_eq!(screen.top, "Azithromyci Rs 35.00");
+        assert_eq!(screen.bottom, "TOTAL     Rs 1249.50");
+
+        let screen = Screen::thank_you(48.0);
+        assert_eq!(screen.top, "     THANK YOU      ");
+        assert_eq!(screen.bottom, "CHANGE      Rs 48.00");
+        assert_eq!(Screen::new("₹ 10", "").top, "? 10                ");
+    }
+
+    #[test]
+    fn protocols_place_each_line() {
+        let screen = Screen::new("A", "B");
+        let mut epson = vec![0x1F, b'$', 1, 1];
+        epson.extend_from_slice(screen.top.as_bytes());
+        epson.extend_from_slice(&[0x1F, b'$', 1, 2]);
+        epson.extend_from_slice(screen.bottom.as_bytes());
+        assert_eq!(Protocol::Epson.lines(&screen), epson);
+
+        let cd5220 = Protocol::Cd5220.lines(&screen);
+        assert_eq!(&cd5220[..4], b"\x1BQAA");
+        assert_eq!(&cd5220[23..28], b"\r\x1BQBB");
+        assert_eq!(cd5220.len(), 2 * (3 + COLUMNS + 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_to_a_pseudo_terminal() {
+        use serialport::TTYPort;
+        use std::io::Read;
+
+        let (mut master, slave) = TTYPort::pair().unwrap();
+        let mut display = PoleDisplay::new(Box::new(slave), Protocol::Epson).unwrap();
+        display
+            .show(&Screen::item("Paracetamol", 25.0, 25.0))
+            .unwrap();
+
+        let mut received = vec![0u8; 7 + 2 * (4 + COLUMNS)];
+        master.read_exact(&mut received).unwrap();
+        assert_eq!(&received[..7], [0x1B, b'@', 0x1F, 0x01, 0x1F, b'C', 0]);
+        assert_eq!(&received[7..11], [0x1F, b'$', 1, 1]);
+        assert_eq!(&received[11..31], b"Paracetamol Rs 25.00");
+        assert_eq!(&received[35..55], b"TOTAL       Rs 25.00");
+    }
+}
@@ -1,7 +1,10 @@
 use tauri::Manager;
 
+mod db;
+mod display;
 mod medicines;
-mod print;
+mod migrations;
+pub mod print;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -12,12 +15,42 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             print::silent_print,
+            print::print_bill,
+            print::save_bill_pdf,
+            print::save_report_pdf,
+            print::get_reprinted_bills,
+            print::print_batch_labels,
+            print::print_purchase_labels,
             print::print_raw_text,
+            print::encode_receipt,
+            print::preview_text,
+            print::preview_bill_text,
             print::check_printer_available,
+            print::get_printer_status,
+            print::open_cash_drawer,
             print::get_default_printer,
             print::list_printers,
+            print::get_printer_profiles,
+            print::save_printer_profiles,
+            print::list_receipt_templates,
+            print::save_receipt_template,
+            print::delete_receipt_template,
+            print::preview_receipt_template,
+            print::list_print_jobs,
+            print::cancel_print_job,
+            print::resend_print_job,
+            display::customer_display_item,
+            display::customer_display_total,
+            display::customer_display_thank_you,
+            display::customer_display_welcome,
             medicines::import_bundled_medicines,
-            medicines::get_medicines_count
+            medicines::merge_bundled_medicines,
+            medicines::csv_import::import_medicines_csv,
+            medicines::csv_import::cancel_medicine_import,
+            medicines::search::search_medicines,
+            medicines::fuzzy::match_medicine_names,
+            medicines::get_medicines_count,
+            migrations::migrate_database
         ])
         .setup(|app| {
             // Initialize logging in debug mode
@@ -34,6 +67,42 @@ pub fn run() {
             std::fs::create_dir_all(&app_data_dir)?;
 
             log::info!("MedBill initialized. Data directory: {:?}", app_data_dir);
+            print::locate_resources(app.handle());
+
+            // The SQL plugin preloads no database, so this is the first
+            // connection to medbill.db: the frontend only loads it once
+            // migrate_database succeeds. If migrating failed, nothing here
+            // that opens the database starts either, so a database from a
+            // newer MedBill is never written to.
+            let migrated = match migrations::run(app.handle()) {
+                Ok(version) => {
+                    log::info!("Database schema version {}", version);
+                    true
+                }
+                Err(e) => {
+                    log::error!("{}", e);
+                    false
+                }
+            };
+
+            app.manage(display::CustomerDisplay::default());
+            app.manage(medicines::csv_import::CsvImport::default());
+            app.manage(medicines::search::MedicineSearch::default());
+            app.manage(medicines::fuzzy::MedicineNames::default());
+            if !migrated {
+                let printer = print::backend::system();
+                app.manage(print::spool::Spool::stopped());
+                app.manage(print::backend::PrinterState(printer));
+                return Ok(());
+            }
+
+            // Printer backend is chosen once from the printing settings
+            let printer = print::backend::from_settings(app.handle());
+            log::info!("Printer backend: {}", printer.name());
+            app.manage(print::spool::start(app.handle().clone(), printer.clone()));
+            print::status::start(app.handle().clone(), printer.clone());
+            app.manage(print::backend::PrinterState(printer));
+            medicines::fuzzy::start(app.handle().clone());
 
             Ok(())
         })
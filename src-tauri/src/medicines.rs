@@ -1,7 +1,16 @@
-use rusqlite::Connection;
-use std::path::PathBuf;
+pub mod csv_import;
+pub mod fuzzy;
+pub mod search;
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Transaction};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tauri::Manager;
 
+use crate::db::get_db_path;
+
 /// Get the path to a bundled resource
 fn get_resource_path(app: &tauri::AppHandle, resource: &str) -> Result<PathBuf, String> {
     app.path()
@@ -10,30 +19,41 @@ fn get_resource_path(app: &tauri::AppHandle, resource: &str) -> Result<PathBuf,
         .map_err(|e| format!("Failed to get resource directory: {}", e))
 }
 
-/// Get the main database path (matches Tauri SQL plugin location - ~/.config/)
-fn get_db_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    app.path()
-        .app_config_dir()
-        .map(|p| p.join("medbill.db"))
-        .map_err(|e| format!("Failed to get config directory: {}", e))
-}
-
-#[tauri::command]
-pub async fn import_bundled_medicines(app: tauri::AppHandle) -> Result<u32, String> {
-    // Get paths
-    let bundle_path = get_resource_path(&app, "resources/medicines-bundle.db")?;
-    let db_path = get_db_path(&app)?;
-
-    // Check if bundle exists
+/// Path to medicines-bundle.db, which must exist
+fn bundle_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let bundle_path = get_resource_path(app, "resources/medicines-bundle.db")?;
     if !bundle_path.exists() {
         return Err(format!(
             "Bundled medicines database not found at {:?}",
             bundle_path
         ));
     }
+    Ok(bundle_path)
+}
+
+fn attach_bundle(conn: &Connection, bundle_path: &Path) -> Result<(), String> {
+    conn.execute(
+        "ATTACH DATABASE ?1 AS bundle",
+        rusqlite::params![bundle_path.to_string_lossy()],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to attach bundle database: {}", e))
+}
+
+fn detach_bundle(conn: &Connection) -> Result<(), String> {
+    conn.execute("DETACH DATABASE bundle", [])
+        .map(|_| ())
+        .map_err(|e| format!("Failed to detach bundle: {}", e))
+}
+
+#[tauri::command]
+pub async fn import_bundled_medicines(app: tauri::AppHandle) -> Result<u32, String> {
+    // Get paths
+    let bundle_path = bundle_path(&app)?;
+    let db_path = get_db_path(&app)?;
 
     // Open main database
-    let main_db =
+    let mut main_db =
         Connection::open(&db_path).map_err(|e| format!("Failed to open main database: {}", e))?;
 
     // Check current medicine count
@@ -47,7 +67,7 @@ pub async fn import_bundled_medicines(app: tauri::AppHandle) -> Result<u32, Stri
         bundle_path
     );
 
-    // Only import if no medicines exist
+    // Only import if no medicines exist; later bundles are merged instead
     if current_count > 0 {
         log::info!("Medicines already exist, skipping import");
         return Ok(current_count);
@@ -55,16 +75,23 @@ pub async fn import_bundled_medicines(app: tauri::AppHandle) -> Result<u32, Stri
 
     log::info!("Importing medicines from bundled database...");
 
-    // Attach bundled database
-    main_db
-        .execute(
-            "ATTACH DATABASE ?1 AS bundle",
-            rusqlite::params![bundle_path.to_string_lossy()],
-        )
-        .map_err(|e| format!("Failed to attach bundle database: {}", e))?;
+    attach_bundle(&main_db, &bundle_path)?;
+    let imported = import(&mut main_db);
+    detach_bundle(&main_db)?;
+    let imported = imported?;
+
+    log::info!("Successfully imported {} medicines", imported);
+
+    Ok(imported)
+}
 
-    // Copy medicines from bundle to main database
-    let imported = main_db
+/// Copy `bundle.medicines` into an empty medicine master, recording what
+/// each medicine was imported with
+fn import(conn: &mut Connection) -> Result<u32, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start import: {}", e))?;
+    let imported = tx
         .execute(
             "INSERT INTO medicines (name, generic_name, manufacturer, hsn_code, category, drug_type, pack_size, unit, reorder_level, is_active)
              SELECT name, generic_name, manufacturer, hsn_code, category, drug_type, pack_size, unit, reorder_level, is_active
@@ -72,15 +99,354 @@ pub async fn import_bundled_medicines(app: tauri::AppHandle) -> Result<u32, Stri
             [],
         )
         .map_err(|e| format!("Failed to import medicines: {}", e))?;
+    tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO medicine_bundle_base (medicine_id, {0})
+             SELECT id, {0} FROM medicines",
+            FIELDS.join(", ")
+        ),
+        [],
+    )
+    .map_err(|e| format!("Failed to record imported medicines: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to save imported medicines: {}", e))?;
+    Ok(imported as u32)
+}
 
-    // Detach bundle
-    main_db
-        .execute("DETACH DATABASE bundle", [])
-        .map_err(|e| format!("Failed to detach bundle: {}", e))?;
+/// Medicine fields a newer bundle may update. Name, manufacturer and pack
+/// identify the product and are never changed.
+const FIELDS: [&str; 7] = [
+    "generic_name",
+    "hsn_code",
+    "category",
+    "drug_type",
+    "unit",
+    "reorder_level",
+    "is_active",
+];
 
-    log::info!("Successfully imported {} medicines", imported);
+/// Conflicts listed in a merge summary; the rest are only counted
+const LISTED_CONFLICTS: usize = 200;
 
-    Ok(imported as u32)
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MergeSummary {
+    pub dry_run: bool,
+    pub added: u32,
+    pub updated: u32,
+    /// Unchanged, changed only here, or repeated in the bundle
+    pub skipped: u32,
+    pub conflicting: u32,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MergeConflict {
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub pack_size: Option<String>,
+    pub reason: String,
+}
+
+/// Bundle rows match medicines on name, manufacturer and pack, ignoring
+/// case and spacing
+fn merge_key(name: &str, manufacturer: Option<&str>, pack_size: Option<&str>) -> String {
+    [Some(name), manufacturer, pack_size]
+        .iter()
+        .map(|part| {
+            part.unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn columns(prefix: &str) -> String {
+    FIELDS
+        .iter()
+        .map(|field| format!("{}{}", prefix, field))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn placeholders(from: usize) -> String {
+    (from..from + FIELDS.len())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn values(row: &rusqlite::Row, from: usize) -> rusqlite::Result<Vec<Value>> {
+    (from..from + FIELDS.len()).map(|i| row.get(i)).collect()
+}
+
+/// Merge the attached `bundle.medicines` into the medicine master.
+/// Products the shop lacks are added. For one it has, each field follows
+/// the bundle unless it was edited here; a field edited here and also
+/// changed in the bundle is a conflict and keeps the shop's value.
+/// A medicine with no values recorded from the bundle (one the shop added
+/// itself) counts as edited throughout: only its empty fields are filled
+/// in, and any other difference is a conflict.
+/// With `dry_run` the summary is worked out and nothing is saved.
+pub fn merge(conn: &mut Connection, dry_run: bool) -> Result<MergeSummary, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start merge: {}", e))?;
+    let summary =
+        merge_rows(&tx, dry_run, &mut |_| true)?.ok_or_else(|| "Merge stopped".to_string())?;
+    if !dry_run {
+        tx.commit()
+            .map_err(|e| format!("Failed to save merged medicines: {}", e))?;
+    }
+
+    log::info!(
+        "Merged bundled medicines{}: {} added, {} updated, {} skipped, {} conflicting",
+        if dry_run { " (dry run)" } else { "" },
+        summary.added,
+        summary.updated,
+        summary.skipped,
+        summary.conflicting
+    );
+    Ok(summary)
+}
+
+/// Bundle rows merged between calls to `keep_going`
+const MERGE_STEP: u32 = 5000;
+
+/// The work of [`merge`] inside the caller's transaction, which is left
+/// for the caller to commit. `keep_going` gets the number of bundle rows
+/// merged so far every [`MERGE_STEP`] rows; if it returns false the merge
+/// stops and gives None.
+pub(crate) fn merge_rows(
+    tx: &Transaction,
+    dry_run: bool,
+    keep_going: &mut dyn FnMut(u32) -> bool,
+) -> Result<Option<MergeSummary>, String> {
+    let mut summary = MergeSummary {
+        dry_run,
+        ..Default::default()
+    };
+    let mut local: HashMap<String, Vec<i64>> = HashMap::new();
+    let mut stmt = tx
+        .prepare("SELECT id, name, manufacturer, pack_size FROM main.medicines")
+        .map_err(|e| format!("Failed to read medicines: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let name: String = row.get(1)?;
+            let manufacturer: Option<String> = row.get(2)?;
+            let pack_size: Option<String> = row.get(3)?;
+            Ok((
+                row.get::<_, i64>(0)?,
+                merge_key(&name, manufacturer.as_deref(), pack_size.as_deref()),
+            ))
+        })
+        .map_err(|e| format!("Failed to read medicines: {}", e))?;
+    for row in rows {
+        let (id, key) = row.map_err(|e| format!("Failed to read medicines: {}", e))?;
+        local.entry(key).or_default().push(id);
+    }
+
+    let mut read_bundle = tx
+        .prepare(&format!(
+            "SELECT name, manufacturer, pack_size, {} FROM bundle.medicines ORDER BY id",
+            columns("")
+        ))
+        .map_err(|e| format!("Failed to read bundle medicines: {}", e))?;
+    let mut read_local = tx
+        .prepare(&format!(
+            "SELECT {}, b.medicine_id IS NOT NULL, {}
+                 FROM main.medicines m
+                 LEFT JOIN main.medicine_bundle_base b ON b.medicine_id = m.id
+                 WHERE m.id = ?1",
+            columns("m."),
+            columns("b.")
+        ))
+        .map_err(|e| format!("Failed to read medicines: {}", e))?;
+    let mut insert = tx
+            .prepare(&format!(
+                "INSERT INTO main.medicines (name, manufacturer, pack_size, {}) VALUES (?1, ?2, ?3, {})",
+                columns(""),
+                placeholders(4)
+            ))
+            .map_err(|e| format!("Failed to add medicines: {}", e))?;
+    let mut update = tx
+        .prepare(&format!(
+            "UPDATE main.medicines SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            FIELDS
+                .iter()
+                .enumerate()
+                .map(|(i, field)| format!("{} = ?{}", field, i + 2))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .map_err(|e| format!("Failed to update medicines: {}", e))?;
+    let mut record = tx
+        .prepare(&format!(
+            "INSERT OR REPLACE INTO main.medicine_bundle_base (medicine_id, {}) VALUES (?1, {})",
+            columns(""),
+            placeholders(2)
+        ))
+        .map_err(|e| format!("Failed to record bundle values: {}", e))?;
+
+    let mut seen = HashSet::new();
+    let mut done = 0;
+    let mut rows = read_bundle
+        .query([])
+        .map_err(|e| format!("Failed to read bundle medicines: {}", e))?;
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("Failed to read bundle medicines: {}", e))?
+    {
+        done += 1;
+        if done % MERGE_STEP == 0 && !keep_going(done) {
+            return Ok(None);
+        }
+        let read = || -> rusqlite::Result<_> {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                values(row, 3)?,
+            ))
+        };
+        let (name, manufacturer, pack_size, theirs) =
+            read().map_err(|e| format!("Failed to read bundle medicines: {}", e))?;
+        let key = merge_key(&name, manufacturer.as_deref(), pack_size.as_deref());
+        if !seen.insert(key.clone()) {
+            summary.skipped += 1;
+            continue;
+        }
+        let conflict = |reason: String, summary: &mut MergeSummary| {
+            summary.conflicting += 1;
+            if summary.conflicts.len() < LISTED_CONFLICTS {
+                summary.conflicts.push(MergeConflict {
+                    name: name.clone(),
+                    manufacturer: manufacturer.clone(),
+                    pack_size: pack_size.clone(),
+                    reason,
+                });
+            }
+        };
+
+        let id = match local.get(&key).map(Vec::as_slice) {
+            None => {
+                let mut new_row = vec![
+                    Value::from(name.clone()),
+                    Value::from(manufacturer.clone()),
+                    Value::from(pack_size.clone()),
+                ];
+                new_row.extend(theirs.iter().cloned());
+                insert
+                    .execute(params_from_iter(new_row))
+                    .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+                let id = tx.last_insert_rowid();
+                record
+                    .execute(params_from_iter(
+                        std::iter::once(Value::from(id)).chain(theirs),
+                    ))
+                    .map_err(|e| format!("Failed to record {}: {}", name, e))?;
+                summary.added += 1;
+                continue;
+            }
+            Some([id]) => *id,
+            Some(ids) => {
+                conflict(
+                    format!("Matches {} medicines here", ids.len()),
+                    &mut summary,
+                );
+                continue;
+            }
+        };
+
+        let (ours, stored) = read_local
+            .query_row(params![id], |row| {
+                let has_base: bool = row.get(FIELDS.len())?;
+                Ok((
+                    values(row, 0)?,
+                    if has_base {
+                        Some(values(row, FIELDS.len() + 1)?)
+                    } else {
+                        None
+                    },
+                ))
+            })
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+
+        let mut merged = ours.clone();
+        let mut new_base = theirs.clone();
+        let mut edited_both = Vec::new();
+        for (i, field) in FIELDS.iter().enumerate() {
+            if ours[i] == theirs[i] {
+                continue;
+            }
+            match stored.as_ref().map(|base| &base[i]) {
+                // Never edited here
+                Some(base) if *base == ours[i] => merged[i] = theirs[i].clone(),
+                // Edited here only
+                Some(base) if *base == theirs[i] => {}
+                // Added here and never filled in
+                None if ours[i] == Value::Null => merged[i] = theirs[i].clone(),
+                base => {
+                    edited_both.push(*field);
+                    if let Some(base) = base {
+                        new_base[i] = base.clone();
+                    }
+                }
+            }
+        }
+
+        let changed = merged != ours;
+        if changed {
+            update
+                .execute(params_from_iter(
+                    std::iter::once(Value::from(id)).chain(merged),
+                ))
+                .map_err(|e| format!("Failed to update {}: {}", name, e))?;
+        }
+        // Without a base, a conflicting field's original value is unknown
+        let known = stored.is_some() || edited_both.is_empty();
+        if known && stored.as_ref() != Some(&new_base) {
+            record
+                .execute(params_from_iter(
+                    std::iter::once(Value::from(id)).chain(new_base),
+                ))
+                .map_err(|e| format!("Failed to record {}: {}", name, e))?;
+        }
+
+        if !edited_both.is_empty() {
+            let reason = if stored.is_some() {
+                "Edited here and changed in the bundle"
+            } else {
+                "Added here and different in the bundle"
+            };
+            conflict(
+                format!("{}: {}", reason, edited_both.join(", ")),
+                &mut summary,
+            );
+        } else if changed {
+            summary.updated += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+    Ok(Some(summary))
+}
+
+/// Merge the bundled medicine master into a shop that already has
+/// medicines; `dry_run` reports what would change without saving it
+#[tauri::command]
+pub async fn merge_bundled_medicines(
+    app: tauri::AppHandle,
+    dry_run: bool,
+) -> Result<MergeSummary, String> {
+    let bundle_path = bundle_path(&app)?;
+    let mut conn = crate::db::open(&app)?;
+    attach_bundle(&conn, &bundle_path)?;
+    let summary = merge(&mut conn, dry_run);
+    detach_bundle(&conn)?;
+    summary
 }
 
 #[tauri::command]
@@ -103,3 +469,164 @@ pub fn get_medicines_count(app: tauri::AppHandle) -> Result<u32, String> {
 
     Ok(count)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = "
+        CREATE TABLE bundle.medicines (id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL, generic_name TEXT, manufacturer TEXT,
+            hsn_code TEXT NOT NULL DEFAULT '3004', category TEXT, drug_type TEXT,
+            pack_size TEXT, unit TEXT DEFAULT 'PCS', reorder_level INTEGER DEFAULT 10,
+            is_active INTEGER DEFAULT 1);
+    ";
+
+    fn shop() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join("medbill-medicines-unused");
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        conn.execute("ATTACH DATABASE ':memory:' AS bundle", [])
+            .unwrap();
+        conn.execute_batch(BUNDLE).unwrap();
+        conn
+    }
+
+    fn release(conn: &Connection, rows: &str) {
+        conn.execute_batch(&format!(
+            "DELETE FROM bundle.medicines;
+             INSERT INTO bundle.medicines (name, manufacturer, pack_size, generic_name,
+                 category, hsn_code, reorder_level) VALUES {};",
+            rows
+        ))
+        .unwrap();
+    }
+
+    fn medicine(conn: &Connection, name: &str) -> (String, String, String, i64) {
+        conn.query_row(
+            "SELECT generic_name, category, hsn_code, reorder_level FROM medicines
+             WHERE name = ?1",
+            params![name],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap()
+    }
+
+    fn medicines(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM medicines", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn newer_bundles_update_only_what_the_shop_never_edited() {
+        let mut conn = shop();
+        release(
+            &conn,
+            "('Dolo 650', 'Micro Labs', 'strip of 15', 'Paracetamol', 'Analgesic', '3004', 10),
+             ('Azee 500', 'Cipla', 'strip of 3', 'Azithromycin', 'Antibiotic', '3004', 10),
+             ('Pan 40', 'Alkem', 'strip of 15', 'Pantoprazole', 'Antacid', '3004', 10)",
+        );
+        assert_eq!(import(&mut conn).unwrap(), 3);
+
+        // Edits at the counter, and medicines the shop added itself
+        conn.execute_batch(
+            "UPDATE medicines SET category = 'Fever', updated_at = datetime('now', '+1 minute')
+                 WHERE name = 'Dolo 650';
+             UPDATE medicines SET reorder_level = 25, updated_at = datetime('now', '+1 minute')
+                 WHERE name = 'Azee 500';
+             INSERT INTO medicines (name, manufacturer, pack_size, category)
+                 VALUES ('Becosules', 'Pfizer', 'strip of 20', 'Vitamin');
+             INSERT INTO medicines (name, manufacturer, pack_size, generic_name, category)
+                 VALUES ('Limcee', 'Abbott', 'strip of 15', 'Ascorbic Acid', 'Vitamin C');
+             INSERT INTO medicines (name, manufacturer, pack_size) VALUES
+                 ('Shelcal 500', 'Torrent', 'strip of 15'), ('Shelcal 500', 'Torrent', 'strip of 15');",
+        )
+        .unwrap();
+
+        release(
+            &conn,
+            "('Dolo 650', 'Micro Labs', 'strip of 15', 'Paracetamol 650mg', 'Antipyretic', '3004', 10),
+             ('Azee 500', 'Cipla', 'strip of 3', 'Azithromycin', 'Antibiotic', '30042019', 10),
+             ('Pan 40', 'Alkem', 'strip of 15', 'Pantoprazole', 'Antacid', '3004', 10),
+             ('DOLO  650', 'Micro Labs', 'Strip of 15', 'Paracetamol', 'Analgesic', '3004', 10),
+             ('Montair LC', 'Cipla', 'strip of 10', 'Montelukast', 'Antiallergic', '3004', 10),
+             ('Becosules', 'Pfizer', 'strip of 20', 'B-Complex', 'Multivitamin', '3004', 10),
+             ('Limcee', 'Abbott', 'strip of 15', 'Vitamin C', 'Supplement', '3004', 10),
+             ('Shelcal 500', 'Torrent', 'strip of 15', 'Calcium', 'Supplement', '3004', 10)",
+        );
+
+        let preview = merge(&mut conn, true).unwrap();
+        assert_eq!(
+            (
+                preview.added,
+                preview.updated,
+                preview.skipped,
+                preview.conflicting
+            ),
+            (1, 1, 2, 4)
+        );
+        assert_eq!(medicines(&conn), 7);
+        assert_eq!(medicine(&conn, "Azee 500").2, "3004");
+
+        let summary = merge(&mut conn, false).unwrap();
+        assert_eq!(
+            (
+                summary.added,
+                summary.updated,
+                summary.skipped,
+                summary.conflicting
+            ),
+            (1, 1, 2, 4)
+        );
+        let reasons: Vec<_> = summary
+            .conflicts
+            .iter()
+            .map(|c| c.reason.as_str())
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "Edited here and changed in the bundle: category",
+                "Added here and different in the bundle: category",
+                "Added here and different in the bundle: generic_name, category",
+                "Matches 2 medicines here",
+            ]
+        );
+
+        // The shop's category stays, the untouched generic name follows the bundle
+        assert_eq!(
+            medicine(&conn, "Dolo 650"),
+            (
+                "Paracetamol 650mg".into(),
+                "Fever".into(),
+                "3004".into(),
+                10
+            )
+        );
+        assert_eq!(
+            medicine(&conn, "Azee 500"),
+            (
+                "Azithromycin".into(),
+                "Antibiotic".into(),
+                "30042019".into(),
+                25
+            )
+        );
+        // Medicines the shop added keep what it typed; only empty fields fill
+        let becosules = medicine(&conn, "Becosules");
+        assert_eq!(
+            (becosules.0.as_str(), becosules.1.as_str()),
+            ("B-Complex", "Vitamin")
+        );
+        assert_eq!(medicine(&conn, "Limcee").1, "Vitamin C");
+        assert_eq!(medicine(&conn, "Montair LC").0, "Montelukast");
+        assert_eq!(medicines(&conn), 8);
+
+        // Merging the same bundle again changes nothing; conflicts remain
+        let again = merge(&mut conn, false).unwrap();
+        assert_eq!(
+            (again.added, again.updated, again.skipped, again.conflicting),
+            (0, 0, 4, 4)
+        );
+    }
+}
@@ -0,0 +1,676 @@
+// =====================================================
+// Medicine Catalog CSV Import
+// Streams a catalog such as dataset/indian_medicine_data.csv
+// into a staging table, then merges it into the medicine
+// master the same way a newer bundle is merged
+// =====================================================
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use csv::{ByteRecord, ReaderBuilder, StringRecord, Trim, Writer, WriterBuilder};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, State};
+
+use super::{merge_rows, MergeSummary};
+
+/// Tauri event emitted while an import runs
+pub const EVENT: &str = "medicine-import-progress";
+
+const CANCELLED: &str = "Import cancelled";
+
+/// Rows read between progress events and cancellation checks
+const STEP: u64 = 2000;
+
+/// Shaped like the bundle's medicines table, so the merge reads either
+const STAGING: &str = "
+CREATE TABLE bundle.medicines (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    generic_name TEXT,
+    manufacturer TEXT,
+    hsn_code TEXT NOT NULL DEFAULT '3004',
+    category TEXT,
+    drug_type TEXT,
+    pack_size TEXT,
+    unit TEXT DEFAULT 'PCS',
+    reorder_level INTEGER DEFAULT 10,
+    is_active INTEGER DEFAULT 1
+);
+";
+
+/// Pack labels naming one of these keep the part before any bracket as
+/// the category, as the bundle does
+const FORMS: &[&str] = &[
+    "tablet",
+    "capsule",
+    "syrup",
+    "injection",
+    "cream",
+    "gel",
+    "drops",
+    "ointment",
+    "suspension",
+    "powder",
+    "solution",
+    "lotion",
+    "inhaler",
+    "spray",
+    "respules",
+    "sachets",
+    "granules",
+    "patch",
+    "suppository",
+    "vial",
+    "ampoule",
+    "strip",
+    "bottle",
+    "tube",
+    "jar",
+];
+
+/// The CSV column feeding each medicine field, matched ignoring case.
+/// Defaults suit dataset/indian_medicine_data.csv.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CsvMapping {
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub drug_type: Option<String>,
+    /// Pack label; also gives the category
+    pub pack_size: Option<String>,
+    /// Composition columns, joined with " + " into the generic name
+    pub generic_name: Vec<String>,
+    /// TRUE or FALSE; discontinued medicines are imported inactive
+    pub discontinued: Option<String>,
+    /// Checked to be a price and otherwise unused, as prices are per batch
+    pub price: Option<String>,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self {
+            name: "name".into(),
+            manufacturer: Some("manufacturer_name".into()),
+            drug_type: Some("type".into()),
+            pack_size: Some("pack_size_label".into()),
+            generic_name: vec!["short_composition1".into(), "short_composition2".into()],
+            discontinued: Some("Is_discontinued".into()),
+            price: Some("price(₹)".into()),
+        }
+    }
+}
+
+/// Sent as [`EVENT`] every few thousand rows
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportProgress {
+    /// "reading" the file, counted in bytes, then "merging", counted in rows
+    pub stage: &'static str,
+    pub done: u64,
+    pub total: u64,
+    pub rejected: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CsvImportSummary {
+    pub rows: u64,
+    pub rejected: u64,
+    /// CSV of the rejected rows with the reason for each, if any were
+    pub report: Option<String>,
+    pub merge: MergeSummary,
+}
+
+/// A row ready for the staging table
+struct Medicine {
+    name: String,
+    generic_name: Option<String>,
+    manufacturer: Option<String>,
+    category: Option<String>,
+    drug_type: Option<String>,
+    pack_size: Option<String>,
+    is_active: bool,
+}
+
+/// Positions of the mapped columns in the file
+struct Columns {
+    width: usize,
+    name: usize,
+    manufacturer: Option<usize>,
+    drug_type: Option<usize>,
+    pack_size: Option<usize>,
+    generic_name: Vec<usize>,
+    discontinued: Option<usize>,
+    price: Option<usize>,
+}
+
+impl Columns {
+    fn find(headers: &StringRecord, mapping: &CsvMapping) -> Result<Self, String> {
+        let find = |column: &str| {
+            headers
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(column.trim()))
+                .ok_or_else(|| format!("Column \"{}\" is not in the file", column))
+        };
+        let find_optional = |column: &Option<String>| column.as_deref().map(find).transpose();
+        Ok(Self {
+            width: headers.len(),
+            name: find(&mapping.name)?,
+            manufacturer: find_optional(&mapping.manufacturer)?,
+            drug_type: find_optional(&mapping.drug_type)?,
+            pack_size: find_optional(&mapping.pack_size)?,
+            generic_name: mapping
+                .generic_name
+                .iter()
+                .map(|column| find(column))
+                .collect::<Result<_, _>>()?,
+            discontinued: find_optional(&mapping.discontinued)?,
+            price: find_optional(&mapping.price)?,
+        })
+    }
+
+    /// Validate a row, giving the reason it was rejected on failure
+    fn medicine(&self, record: &StringRecord) -> Result<Medicine, String> {
+        if record.len() != self.width {
+            return Err(format!(
+                "Expected {} fields, found {}",
+                self.width,
+                record.len()
+            ));
+        }
+        let field = |index: Option<usize>| {
+            index
+                .map(|i| record[i].to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let name = record[self.name].to_string();
+        if name.is_empty() {
+            return Err("Name is empty".into());
+        }
+        if let Some(price) = field(self.price) {
+            let valid = price
+                .trim_start_matches('₹')
+                .replace(',', "")
+                .trim()
+                .parse::<f64>()
+                .is_ok_and(|p| p.is_finite() && p >= 0.0);
+            if !valid {
+                return Err(format!("Price \"{}\" is not a number", price));
+            }
+        }
+        let discontinued = match field(self.discontinued)
+            .map(|value| value.to_lowercase())
+            .as_deref()
+        {
+            None | Some("false" | "no" | "0") => false,
+            Some("true" | "yes" | "1") => true,
+            Some(_) => {
+                return Err(format!(
+                    "Discontinued \"{}\" is not TRUE or FALSE",
+                    field(self.discontinued).unwrap_or_default()
+                ))
+            }
+        };
+        let compositions: Vec<String> = self
+            .generic_name
+            .iter()
+            .filter_map(|&i| field(Some(i)))
+            .collect();
+        let pack_size = field(self.pack_size);
+
+        Ok(Medicine {
+            name,
+            generic_name: (!compositions.is_empty()).then(|| compositions.join(" + ")),
+            manufacturer: field(self.manufacturer),
+            category: pack_size.as_deref().and_then(category),
+            drug_type: field(self.drug_type),
+            pack_size,
+            is_active: !discontinued,
+        })
+    }
+}
+
+/// The bundle's category for a pack label: the label up to any bracket when
+/// that names a dosage form or container, otherwise its first word
+fn category(pack_size: &str) -> Option<String> {
+    let before = pack_size.split('(').next().unwrap_or("").trim();
+    let lower = before.to_lowercase();
+    if FORMS.iter().any(|form| lower.contains(form)) {
+        return Some(before.to_string());
+    }
+    pack_size
+        .split(' ')
+        .next()
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+}
+
+/// Read `source` into a staging table and merge it into the medicine
+/// master in one transaction. Rejected rows go to `report` with their line
+/// and reason. `progress` is called every [`STEP`] rows; when it returns
+/// false the import stops with None and nothing is saved.
+fn import<R: Read, W: Write>(
+    conn: &mut Connection,
+    source: R,
+    total_bytes: u64,
+    mapping: &CsvMapping,
+    report: &mut Writer<W>,
+    progress: &mut dyn FnMut(ImportProgress) -> bool,
+) -> Result<Option<CsvImportSummary>, String> {
+    conn.execute("ATTACH DATABASE '' AS bundle", [])
+        .map_err(|e| format!("Failed to create the staging table: {}", e))?;
+    let result = conn
+        .execute_batch(STAGING)
+        .map_err(|e| format!("Failed to create the staging table: {}", e))
+        .and_then(|_| stage_and_merge(conn, source, total_bytes, mapping, report, progress));
+    super::detach_bundle(conn)?;
+    result
+}
+
+fn stage_and_merge<R: Read, W: Write>(
+    conn: &mut Connection,
+    source: R,
+    total_bytes: u64,
+    mapping: &CsvMapping,
+    report: &mut Writer<W>,
+    progress: &mut dyn FnMut(ImportProgress) -> bool,
+) -> Result<Option<CsvImportSummary>, String> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(source);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read the CSV header: {}", e))?
+        .clone();
+    let columns = Columns::find(&headers, mapping)?;
+    let report_error = |e: csv::Error| format!("Failed to write the rejected rows: {}", e);
+    report
+        .write_record(["line", "reason"].into_iter().chain(headers.iter()))
+        .map_err(report_error)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start import: {}", e))?;
+    let (mut rows, mut rejected) = (0, 0);
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO bundle.medicines (name, generic_name, manufacturer, category,
+                     drug_type, pack_size, is_active)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .map_err(|e| format!("Failed to stage medicines: {}", e))?;
+        let mut record = ByteRecord::new();
+        while reader
+            .read_byte_record(&mut record)
+            .map_err(|e| format!("Failed to read the CSV: {}", e))?
+        {
+            rows += 1;
+            let line = record.position().map_or(0, |p| p.line());
+            let medicine = StringRecord::from_byte_record(record.clone())
+                .map_err(|_| "Not valid UTF-8".to_string())
+                .and_then(|fields| columns.medicine(&fields));
+            match medicine {
+                Ok(m) => {
+                    insert
+                        .execute(params![
+                            m.name,
+                            m.generic_name,
+                            m.manufacturer,
+                            m.category,
+                            m.drug_type,
+                            m.pack_size,
+                            m.is_active
+                        ])
+                        .map_err(|e| format!("Failed to stage line {}: {}", line, e))?;
+                }
+                Err(reason) => {
+                    rejected += 1;
+                    let line = line.to_string();
+                    report
+                        .write_record(
+                            [line.as_bytes(), reason.as_bytes()]
+                                .into_iter()
+                                .chain(record.iter()),
+                        )
+                        .map_err(report_error)?;
+                }
+            }
+
+            if rows % STEP == 0
+                && !progress(ImportProgress {
+                    stage: "reading",
+                    done: reader.position().byte(),
+                    total: total_bytes,
+                    rejected,
+                })
+            {
+                return Ok(None);
+            }
+        }
+    }
+
+    let staged = rows - rejected;
+    let merge = merge_rows(&tx, false, &mut |done| {
+        progress(ImportProgress {
+            stage: "merging",
+            done: done.into(),
+            total: staged,
+            rejected,
+        })
+    })?;
+    let Some(merge) = merge else {
+        return Ok(None);
+    };
+    tx.commit()
+        .map_err(|e| format!("Failed to save imported medicines: {}", e))?;
+    Ok(Some(CsvImportSummary {
+        rows,
+        rejected,
+        report: None,
+        merge,
+    }))
+}
+
+/// Lets a running import be cancelled from another command
+#[derive(Default)]
+pub struct CsvImport {
+    running: AtomicBool,
+    cancel: AtomicBool,
+}
+
+/// Where the rejected rows of importing `source` are written
+fn report_path(app: &tauri::AppHandle, source: &Path) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map(|p| p.join("imports"))
+        .map_err(|e| format!("Failed to get config directory: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create the imports folder: {}", e))?;
+    let stem = source
+        .file_stem()
+        .map_or("medicines".into(), |s| s.to_string_lossy());
+    Ok(dir.join(format!(
+        "Rejected_{}_{}.csv",
+        stem,
+        chrono::Local::now().format("%d%m%Y_%H%M%S")
+    )))
+}
+
+fn run(
+    app: &tauri::AppHandle,
+    state: &CsvImport,
+    path: &Path,
+    mapping: &CsvMapping,
+) -> Result<CsvImportSummary, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let total_bytes = file.metadata().map_or(0, |m| m.len());
+    let report_path = report_path(app, path)?;
+    // Rejected rows are copied as they were, whatever their length
+    let mut report = WriterBuilder::new()
+        .flexible(true)
+        .from_path(&report_path)
+        .map_err(|e| format!("Failed to create the rejected rows file: {}", e))?;
+    let mut conn = crate::db::open(app)?;
+
+    let outcome = import(
+        &mut conn,
+        file,
+        total_bytes,
+        mapping,
+        &mut report,
+        &mut |progress| {
+            let _ = app.emit(EVENT, progress);
+            !state.cancel.load(Ordering::SeqCst)
+        },
+    )
+    .and_then(|summary| {
+        report
+            .flush()
+            .map_err(|e| format!("Failed to write the rejected rows: {}", e))?;
+        summary.ok_or_else(|| CANCELLED.to_string())
+    });
+    drop(report);
+
+    match outcome {
+        Ok(mut summary) if summary.rejected > 0 => {
+            summary.report = Some(report_path.to_string_lossy().into_owned());
+            Ok(summary)
+        }
+        outcome => {
+            let _ = std::fs::remove_file(&report_path);
+            outcome
+        }
+    }
+}
+
+/// Import a medicine catalog CSV, adding new products and updating ones
+/// the shop never edited. Emits [`EVENT`] while it runs.
+#[tauri::command]
+pub async fn import_medicines_csv(
+    app: tauri::AppHandle,
+    state: State<'_, CsvImport>,
+    path: String,
+    mapping: Option<CsvMapping>,
+) -> Result<CsvImportSummary, String> {
+    if state.running.swap(true, Ordering::SeqCst) {
+        return Err("A medicine import is already running".into());
+    }
+    state.cancel.store(false, Ordering::SeqCst);
+    // Reading and merging a full catalog takes a while; keep it off the
+    // async runtime's worker threads
+    let source = path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<CsvImport>();
+        run(
+            &app,
+            &state,
+            Path::new(&source),
+            &mapping.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Medicine import failed: {}", e))
+    .and_then(|result| result);
+    state.running.store(false, Ordering::SeqCst);
+
+    match &result {
+        Ok(summary) => log::info!(
+            "Imported {:?}: {} rows, {} rejected, {} added, {} updated, {} conflicting",
+            path,
+            summary.rows,
+            summary.rejected,
+            summary.merge.added,
+            summary.merge.updated,
+            summary.merge.conflicting
+        ),
+        Err(e) => log::warn!("Medicine import from {:?} stopped: {}", path, e),
+    }
+    result
+}
+
+/// Stop the running import; nothing it read is saved
+#[tauri::command]
+pub fn cancel_medicine_import(state: State<'_, CsvImport>) {
+    state.cancel.store(true, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"id,name,price(₹),Is_discontinued,manufacturer_name,type,pack_size_label,short_composition1,short_composition2
+1,Augmentin 625 Duo Tablet,223.42,FALSE,Glaxo SmithKline Pharmaceuticals Ltd,allopathy,strip of 10 tablets,Amoxycillin  (500mg) ,  Clavulanic Acid (125mg)
+2,"Accu-Chek ""Active"" Strips, 50",899,FALSE,Roche Diabetes Care India Pvt Ltd,allopathy,box of 50 test strips,,
+3,,12,FALSE,Cipla Ltd,allopathy,strip of 10 tablets,Paracetamol (500mg),
+4,Crocin Advance Tablet,abc,FALSE,GSK,allopathy,strip of 15 tablets,Paracetamol (500mg),
+5,Avil 25 Tablet,10.96,maybe,Sanofi India  Ltd,allopathy,strip of 15 tablets,Pheniramine (25mg),
+
+6,Zinetac 150mg Tablet,30,TRUE,GSK,allopathy,strip of 30 tablets,Ranitidine (150mg),
+7,Short row,1
+"#;
+
+    fn report() -> Writer<Vec<u8>> {
+        WriterBuilder::new().flexible(true).from_writer(Vec::new())
+    }
+
+    fn shop() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join("medbill-csv-import-unused");
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        conn
+    }
+
+    fn medicine(conn: &Connection, name: &str) -> (Option<String>, String, String, bool) {
+        conn.query_row(
+            "SELECT generic_name, category, manufacturer, is_active FROM medicines
+             WHERE name = ?1",
+            params![name],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rows_are_validated_mapped_and_merged() {
+        let mut conn = shop();
+        let mut rejected_rows = report();
+        let summary = import(
+            &mut conn,
+            CATALOG.as_bytes(),
+            CATALOG.len() as u64,
+            &CsvMapping::default(),
+            &mut rejected_rows,
+            &mut |_| true,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!((summary.rows, summary.rejected), (7, 4));
+        assert_eq!(summary.merge.added, 3);
+
+        // Escaped quotes and commas survive; fields are trimmed as the bundle's are
+        assert_eq!(
+            medicine(&conn, "Augmentin 625 Duo Tablet"),
+            (
+                Some("Amoxycillin  (500mg) + Clavulanic Acid (125mg)".into()),
+                "strip of 10 tablets".into(),
+                "Glaxo SmithKline Pharmaceuticals Ltd".into(),
+                true
+            )
+        );
+        assert_eq!(
+            medicine(&conn, "Accu-Chek \"Active\" Strips, 50"),
+            (
+                None,
+                "box of 50 test strips".into(),
+                "Roche Diabetes Care India Pvt Ltd".into(),
+                true
+            )
+        );
+        assert!(!medicine(&conn, "Zinetac 150mg Tablet").3);
+
+        let rejected_rows = rejected_rows.into_inner().unwrap();
+        let reasons: Vec<(String, String)> = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(rejected_rows.as_slice())
+            .records()
+            .map(|r| {
+                let r = r.unwrap();
+                (r[0].to_string(), r[1].to_string())
+            })
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                ("4".to_string(), "Name is empty".to_string()),
+                ("5".into(), "Price \"abc\" is not a number".into()),
+                (
+                    "6".into(),
+                    "Discontinued \"maybe\" is not TRUE or FALSE".into()
+                ),
+                ("9".into(), "Expected 9 fields, found 3".into()),
+            ]
+        );
+
+        // The same file again finds nothing new
+        let again = import(
+            &mut conn,
+            CATALOG.as_bytes(),
+            0,
+            &CsvMapping::default(),
+            &mut report(),
+            &mut |_| true,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!((again.merge.added, again.merge.updated), (0, 0));
+    }
+
+    #[test]
+    fn cancelled_imports_save_nothing() {
+        let mut conn = shop();
+        let mut catalog = String::from("Product,Maker\n");
+        for i in 0..STEP * 2 {
+            catalog.push_str(&format!("Medicine {},Maker {}\n", i, i % 7));
+        }
+        let mapping = CsvMapping {
+            name: "product".into(),
+            manufacturer: Some("MAKER".into()),
+            drug_type: None,
+            pack_size: None,
+            generic_name: Vec::new(),
+            discontinued: None,
+            price: None,
+        };
+
+        let mut calls = 0;
+        let outcome = import(
+            &mut conn,
+            catalog.as_bytes(),
+            catalog.len() as u64,
+            &mapping,
+            &mut report(),
+            &mut |progress| {
+                calls += 1;
+                assert_eq!(progress.stage, "reading");
+                false
+            },
+        )
+        .unwrap();
+        assert!(outcome.is_none());
+        assert_eq!(calls, 1);
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM medicines", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let missing = CsvMapping::default();
+        let err = import(
+            &mut conn,
+            catalog.as_bytes(),
+            0,
+            &missing,
+            &mut report(),
+            &mut |_| true,
+        )
+        .unwrap_err();
+        assert_eq!(err, "Column \"name\" is not in the file");
+
+        let summary = import(
+            &mut conn,
+            catalog.as_bytes(),
+            0,
+            &mapping,
+            &mut report(),
+            &mut |_| true,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(summary.merge.added as u64, STEP * 2);
+    }
+}
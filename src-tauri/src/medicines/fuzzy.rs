@@ -0,0 +1,660 @@
+// =====================================================
+// Medicine Name Matching
+// Finds medicines by what the counter heard rather than
+// how the name is spelt: typos, letters that sound the
+// same and numbers run into words. Active names are held
+// in memory, split into words, and kept current from the
+// medicine_changes log.
+// =====================================================
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::Manager;
+
+/// How alike a word typed is to a word of a name
+const EXACT: f64 = 1.0;
+const SOUNDS_ALIKE: f64 = 0.9;
+/// Taken off for each letter wrong, missing, extra or swapped
+const PER_EDIT: f64 = 0.15;
+/// Factor for matching only the start of a name's word, as while typing
+const PREFIX: f64 = 0.9;
+
+/// Share of the score from the first word typed matching the first word of
+/// the name, where the brand is
+const FIRST_WORD: f64 = 0.2;
+/// Weakest match returned
+const MIN_SCORE: f64 = 0.5;
+
+/// Words typed beyond this many are ignored
+const MAX_WORDS: usize = 8;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 50;
+
+/// Past this many changes reading every name again is quicker
+const RELOAD_AFTER: usize = 5000;
+
+/// A medicine whose name matches what was typed; a score of 1 is exact
+#[derive(Clone, Debug, Serialize)]
+pub struct NameMatch {
+    pub id: i64,
+    pub name: String,
+    pub score: f64,
+}
+
+/// Lowercased runs of letters and runs of digits, so "dolo650" and
+/// "Dolo 650" are the same two words
+fn words(text: &str) -> Vec<Vec<char>> {
+    let mut words = Vec::new();
+    let mut word: Vec<char> = Vec::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if word
+            .last()
+            .is_some_and(|last| last.is_ascii_digit() != c.is_ascii_digit())
+        {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// The key words that sound alike share, as Indian brand names are heard:
+/// ph/f, c/k/s, z/j, w/v, y/i and x/ks are merged, an h after a consonant
+/// and the vowels after the first letter are dropped, and a repeated sound
+/// counts once. "Paracitamol" and "Paracetamol" are both "prstml".
+fn sound(word: &[char]) -> Vec<char> {
+    let mut key: Vec<char> = Vec::with_capacity(word.len());
+    let mut i = 0;
+    while i < word.len() {
+        let next = word.get(i + 1).copied();
+        let heard: &[char] = match (word[i], next) {
+            ('p', Some('h')) => {
+                i += 1;
+                &['f']
+            }
+            ('c', Some('k')) => {
+                i += 1;
+                &['k']
+            }
+            ('c', Some('h')) => {
+                i += 1;
+                &['c']
+            }
+            ('c', Some('e' | 'i' | 'y')) => &['s'],
+            ('c' | 'q', _) => &['k'],
+            ('x', _) => &['k', 's'],
+            ('z', _) => &['j'],
+            ('w', _) => &['v'],
+            ('h', _) if !key.is_empty() => &[],
+            ('a' | 'e' | 'i' | 'o' | 'u' | 'y', _) if !key.is_empty() => &[],
+            ('a' | 'e' | 'i' | 'o' | 'u' | 'y', _) => &['a'],
+            (c, _) => {
+                if key.last() != Some(&c) {
+                    key.push(c);
+                }
+                i += 1;
+                continue;
+            }
+        };
+        for &c in heard {
+            if key.last() != Some(&c) {
+                key.push(c);
+            }
+        }
+        i += 1;
+    }
+    key
+}
+
+/// Edits (a letter wrong, missing, extra, or two swapped) turning `a` into
+/// `b`, or None if more than `max`
+fn edits(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut before = vec![0; b.len() + 1];
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+    let mut row = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        row[0] = i;
+        let mut least = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+            least = least.min(row[j]);
+        }
+        if least > max {
+            return None;
+        }
+        std::mem::swap(&mut before, &mut last);
+        std::mem::swap(&mut last, &mut row);
+    }
+    Some(last[b.len()]).filter(|&d| d <= max)
+}
+
+/// Typos forgiven in a word of this length; none in short words, where one
+/// letter makes another word
+fn forgiven(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    }
+}
+
+/// A word typed or a word of the names
+struct Word {
+    text: Vec<char>,
+    sound: Vec<char>,
+}
+
+impl Word {
+    fn new(text: Vec<char>) -> Self {
+        let sound = if is_number(&text) {
+            Vec::new()
+        } else {
+            sound(&text)
+        };
+        Self { text, sound }
+    }
+
+    /// Whether the sound is distinct enough to match on
+    fn heard(&self) -> bool {
+        self.text.len() >= 4 && self.sound.len() >= 3
+    }
+
+    /// How alike a word typed is to `word`, 0 if not at all
+    fn likeness(&self, word: &Word) -> f64 {
+        let (typed, text) = (&self.text, &word.text);
+        if typed == text {
+            return EXACT;
+        }
+        if self.sound.is_empty() {
+            return if text.starts_with(typed) { PREFIX } else { 0.0 };
+        }
+        let max = forgiven(typed.len());
+        let mut likeness: f64 = 0.0;
+        if let Some(d) = edits(typed, text, max) {
+            likeness = likeness.max(EXACT - PER_EDIT * d as f64);
+        }
+        if self.heard() && self.sound == word.sound {
+            likeness = likeness.max(SOUNDS_ALIKE);
+        }
+        if text.len() > typed.len() {
+            for len in [typed.len(), typed.len() + 1] {
+                if let Some(d) = text.get(..len).and_then(|start| edits(typed, start, max)) {
+                    likeness = likeness.max(PREFIX * (EXACT - PER_EDIT * d as f64));
+                }
+            }
+            if self.heard() && word.sound.starts_with(&self.sound) {
+                likeness = likeness.max(PREFIX * SOUNDS_ALIKE);
+            }
+        }
+        likeness
+    }
+}
+
+fn is_number(word: &[char]) -> bool {
+    word.first().is_some_and(char::is_ascii_digit)
+}
+
+/// A word of the names and the names it appears in
+struct Entry {
+    word: Word,
+    /// Slots of the names; some may have been emptied since
+    names: Vec<u32>,
+}
+
+struct Name {
+    id: i64,
+    text: String,
+    /// Words in order, as indexes into [`Names::entries`]
+    words: Vec<u32>,
+}
+
+/// Active medicine names split into words
+#[derive(Default)]
+struct Names {
+    slots: Vec<Option<Name>>,
+    emptied: usize,
+    slot_of: HashMap<i64, u32>,
+    entries: Vec<Entry>,
+    entry_of: HashMap<Vec<char>, u32>,
+    /// Words of letters by the first letter of their sound, and numbers
+    by_sound: HashMap<char, Vec<u32>>,
+    numbers: Vec<u32>,
+}
+
+impl Names {
+    fn insert(&mut self, id: i64, text: String) {
+        let slot = self.slots.len() as u32;
+        let mut ids: Vec<u32> = Vec::new();
+        for word in words(&text) {
+            let entry = match self.entry_of.get(&word) {
+                Some(&entry) => entry,
+                None => {
+                    let entry = self.entries.len() as u32;
+                    let word = Word::new(word);
+                    match word.sound.first() {
+                        Some(&first) => self.by_sound.entry(first).or_default().push(entry),
+                        None => self.numbers.push(entry),
+                    }
+                    self.entry_of.insert(word.text.clone(), entry);
+                    self.entries.push(Entry {
+                        word,
+                        names: Vec::new(),
+                    });
+                    entry
+                }
+            };
+            if !ids.contains(&entry) {
+                self.entries[entry as usize].names.push(slot);
+            }
+            ids.push(entry);
+        }
+        self.slots.push(Some(Name {
+            id,
+            text,
+            words: ids,
+        }));
+        if let Some(old) = self.slot_of.insert(id, slot) {
+            self.slots[old as usize] = None;
+            self.emptied += 1;
+        }
+    }
+
+    fn remove(&mut self, id: i64) {
+        if let Some(slot) = self.slot_of.remove(&id) {
+            self.slots[slot as usize] = None;
+            self.emptied += 1;
+        }
+    }
+
+    /// Words of the names like a word typed, most alike first. Only words
+    /// sharing its first sound are compared, so a typo in the first letter
+    /// is not forgiven.
+    fn alike(&self, typed: &Word) -> Vec<(u32, f64)> {
+        let candidates = match typed.sound.first() {
+            Some(first) => self.by_sound.get(first),
+            None => Some(&self.numbers),
+        };
+        let mut alike: Vec<(u32, f64)> = candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|&entry| {
+                let likeness = typed.likeness(&self.entries[entry as usize].word);
+                (likeness > 0.0).then_some((entry, likeness))
+            })
+            .collect();
+        alike.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        alike
+    }
+
+    /// Names most like `query`, best first. Each word typed scores its most
+    /// alike word of the name, and the first word typed matching the first
+    /// word of the name counts extra.
+    fn find(&self, query: &str, limit: usize) -> Vec<NameMatch> {
+        let typed: Vec<Word> = words(query)
+            .into_iter()
+            .take(MAX_WORDS)
+            .map(Word::new)
+            .collect();
+        if typed.is_empty() {
+            return Vec::new();
+        }
+
+        let mut total = vec![0.0; self.slots.len()];
+        // 1 + the last word typed counted for each name
+        let mut counted = vec![0u8; self.slots.len()];
+        let mut touched: Vec<u32> = Vec::new();
+        let mut first_word: HashMap<u32, f64> = HashMap::new();
+        for (i, word) in typed.iter().enumerate() {
+            let alike = self.alike(word);
+            if i == 0 {
+                first_word = alike.iter().copied().collect();
+            }
+            for (entry, likeness) in alike {
+                for &slot in &self.entries[entry as usize].names {
+                    let slot = slot as usize;
+                    if counted[slot] == i as u8 + 1 {
+                        continue;
+                    }
+                    if counted[slot] == 0 {
+                        touched.push(slot as u32);
+                    }
+                    counted[slot] = i as u8 + 1;
+                    total[slot] += likeness;
+                }
+            }
+        }
+
+        let mut found: Vec<(f64, &Name)> = touched
+            .into_iter()
+            .filter_map(|slot| {
+                let name = self.slots[slot as usize].as_ref()?;
+                let first = name
+                    .words
+                    .first()
+                    .and_then(|entry| first_word.get(entry))
+                    .copied()
+                    .unwrap_or(0.0);
+                let score = (1.0 - FIRST_WORD) * total[slot as usize] / typed.len() as f64
+                    + FIRST_WORD * first;
+                (score >= MIN_SCORE).then_some((score, name))
+            })
+            .collect();
+        let best_first = |(a, x): &(f64, &Name), (b, y): &(f64, &Name)| {
+            b.total_cmp(a)
+                .then_with(|| x.words.len().cmp(&y.words.len()))
+                .then_with(|| x.text.cmp(&y.text))
+        };
+        if found.len() > limit {
+            found.select_nth_unstable_by(limit, best_first);
+            found.truncate(limit);
+        }
+        found.sort_by(best_first);
+        found
+            .into_iter()
+            .map(|(score, name)| NameMatch {
+                id: name.id,
+                name: name.text.clone(),
+                score: (score * 1000.0).round() / 1000.0,
+            })
+            .collect()
+    }
+}
+
+/// The names with the connection they are read from. The connection never
+/// writes, so `PRAGMA data_version` changes only when another commits.
+struct NameIndex {
+    conn: Connection,
+    version: i64,
+    /// The last entry of medicine_changes applied
+    seq: i64,
+    names: Names,
+}
+
+impl NameIndex {
+    fn open(conn: Connection) -> Result<Self, String> {
+        let mut index = Self {
+            conn,
+            version: 0,
+            seq: 0,
+            names: Names::default(),
+        };
+        index.reload()?;
+        Ok(index)
+    }
+
+    fn data_version(&self) -> Result<i64, String> {
+        self.conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to check for medicine changes: {}", e))
+    }
+
+    /// Read every active name afresh
+    fn reload(&mut self) -> Result<(), String> {
+        let error = |e: rusqlite::Error| format!("Failed to load medicine names: {}", e);
+        let version = self.data_version()?;
+        let tx = self.conn.unchecked_transaction().map_err(error)?;
+        let seq: i64 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(seq), 0) FROM medicine_changes",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(error)?;
+        let mut names = Names::default();
+        let mut stmt = tx
+            .prepare("SELECT id, name FROM medicines WHERE is_active = 1")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(error)?;
+        for row in rows {
+            let (id, name) = row.map_err(error)?;
+            names.insert(id, name);
+        }
+        drop(stmt);
+        drop(tx);
+
+        log::info!(
+            "Medicine name index: {} names, {} words",
+            names.slot_of.len(),
+            names.entries.len()
+        );
+        self.names = names;
+        self.version = version;
+        self.seq = seq;
+        Ok(())
+    }
+
+    /// Apply the changes other connections committed since the last look
+    fn refresh(&mut self) -> Result<(), String> {
+        let error = |e: rusqlite::Error| format!("Failed to read medicine changes: {}", e);
+        let version = self.data_version()?;
+        if version == self.version {
+            return Ok(());
+        }
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT c.seq, c.medicine_id, m.name
+                 FROM medicine_changes c
+                 LEFT JOIN medicines m ON m.id = c.medicine_id AND m.is_active = 1
+                 WHERE c.seq > ?1
+                 ORDER BY c.seq
+                 LIMIT ?2",
+            )
+            .map_err(error)?;
+        let changes = stmt
+            .query_map(params![self.seq, RELOAD_AFTER as i64 + 1], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(error)?
+            .collect::<Result<Vec<(i64, i64, Option<String>)>, _>>()
+            .map_err(error)?;
+        drop(stmt);
+
+        if changes.len() > RELOAD_AFTER || self.names.emptied > self.names.slot_of.len() {
+            return self.reload();
+        }
+        for (seq, id, name) in changes {
+            self.names.remove(id);
+            if let Some(name) = name {
+                self.names.insert(id, name);
+            }
+            self.seq = seq;
+        }
+        self.version = version;
+        Ok(())
+    }
+}
+
+/// The name index, built in the background when the app starts
+#[derive(Default)]
+pub struct MedicineNames {
+    index: Mutex<Option<NameIndex>>,
+    /// Set while an index is being built, outside the lock
+    loading: AtomicBool,
+}
+
+impl MedicineNames {
+    /// Build the index without holding the lock, so searches meanwhile get
+    /// an answer at once, then swap it in
+    fn load(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let result = crate::db::open(app)
+            .and_then(NameIndex::open)
+            .and_then(|opened| {
+                let mut index = self
+                    .index
+                    .lock()
+                    .map_err(|_| "Medicine name matching is unavailable".to_string())?;
+                index.get_or_insert(opened);
+                Ok(())
+            });
+        self.loading.store(false, Ordering::SeqCst);
+        result
+    }
+}
+
+/// Build the name index off the main thread so the first search is quick
+pub fn start(app: tauri::AppHandle) {
+    app.state::<MedicineNames>()
+        .loading
+        .store(true, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        if let Err(e) = app.state::<MedicineNames>().load(&app) {
+            log::warn!("Medicine name index unavailable: {}", e);
+        }
+    });
+}
+
+/// Medicines whose names look or sound like `query`, best first with their
+/// scores, for when prefix search finds nothing
+#[tauri::command]
+pub async fn match_medicine_names(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<NameMatch>, String> {
+    // Catching up on changes reads the database; keep it off the async
+    // runtime's worker threads
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<MedicineNames>();
+        let ready = state
+            .index
+            .lock()
+            .map_err(|_| "Medicine name matching is unavailable".to_string())?
+            .is_some();
+        if !ready {
+            // Only when building at startup failed; otherwise wait for it
+            if state.loading.swap(true, Ordering::SeqCst) {
+                return Err("Medicine names are still loading".to_string());
+            }
+            state.load(&app)?;
+        }
+        let mut index = state
+            .index
+            .lock()
+            .map_err(|_| "Medicine name matching is unavailable".to_string())?;
+        let index = index.as_mut().expect("loaded above");
+        index.refresh()?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+        Ok(index.names.find(&query, limit))
+    })
+    .await
+    .map_err(|e| format!("Medicine name matching failed: {}", e))
+    .and_then(|result| result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn names(list: &[&str]) -> Names {
+        let mut names = Names::default();
+        for (id, name) in list.iter().enumerate() {
+            names.insert(id as i64 + 1, name.to_string());
+        }
+        names
+    }
+
+    fn best(names: &Names, query: &str) -> Vec<String> {
+        names
+            .find(query, 3)
+            .into_iter()
+            .map(|found| found.name)
+            .collect()
+    }
+
+    #[test]
+    fn typos_sound_alikes_and_run_together_numbers_match() {
+        assert_eq!(sound(&chars("paracitamol")), chars("prstml"));
+        assert_eq!(sound(&chars("amoxyclav")), sound(&chars("amoxiclav")));
+        assert_eq!(sound(&chars("zincovit")), sound(&chars("jinkovit")));
+        assert_eq!(
+            edits(&chars("paracetamol"), &chars("pracetamol"), 2),
+            Some(1)
+        );
+        assert_eq!(edits(&chars("dolo"), &chars("odlo"), 1), Some(1));
+        assert_eq!(edits(&chars("dolo"), &chars("calpol"), 2), None);
+
+        let names = names(&[
+            "Dolo 650 Tablet",
+            "Dolo 500 Tablet",
+            "Dolonex DT 20 Tablet",
+            "Paracetamol 500mg Tablet",
+            "Amoxiclav 625 Tablet",
+            "Augmentin 625 Duo Tablet",
+            "Zincovit Tablet",
+            "Calpol 650 Tablet",
+        ]);
+        assert_eq!(best(&names, "paracitamol"), ["Paracetamol 500mg Tablet"]);
+        assert_eq!(best(&names, "amoxyclav")[0], "Amoxiclav 625 Tablet");
+        assert_eq!(best(&names, "jincovit")[0], "Zincovit Tablet");
+        assert_eq!(best(&names, "dolo 65")[0], "Dolo 650 Tablet");
+        assert_eq!(best(&names, "dolo650")[0], "Dolo 650 Tablet");
+        assert_eq!(best(&names, "calpol650")[0], "Calpol 650 Tablet");
+
+        let found = names.find("paracetamol 500mg tablet", 1);
+        assert_eq!(found[0].score, 1.0);
+        assert!(names.find("paracitamol", 1)[0].score < 1.0);
+        assert!(best(&names, "insulin").is_empty());
+    }
+
+    #[test]
+    fn the_index_catches_up_with_other_connections() {
+        let dir = std::env::temp_dir().join(format!("medbill-names-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("medbill.db");
+        let mut conn = Connection::open(&path).unwrap();
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        conn.execute_batch(
+            "INSERT INTO medicines (id, name) VALUES
+                 (1, 'Dolo 650 Tablet'), (2, 'Calpol 500mg Tablet'), (3, 'Crocin Advance Tablet');",
+        )
+        .unwrap();
+
+        let mut index = NameIndex::open(Connection::open(&path).unwrap()).unwrap();
+        assert_eq!(best(&index.names, "calpool")[0], "Calpol 500mg Tablet");
+        conn.execute_batch(
+            "UPDATE medicines SET name = 'Dolo 500 Tablet' WHERE id = 1;
+             UPDATE medicines SET is_active = 0 WHERE id = 2;
+             DELETE FROM medicines WHERE id = 3;
+             INSERT OR IGNORE INTO medicines (id, name) VALUES (4, 'Pacimol 650 Tablet');",
+        )
+        .unwrap();
+        index.refresh().unwrap();
+        assert_eq!(best(&index.names, "dolo 500"), ["Dolo 500 Tablet"]);
+        assert!(best(&index.names, "calpool").is_empty());
+        assert!(best(&index.names, "crocin").is_empty());
+        assert_eq!(best(&index.names, "pacimool")[0], "Pacimol 650 Tablet");
+
+        drop(index);
+        drop(conn);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
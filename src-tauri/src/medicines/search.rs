@@ -0,0 +1,656 @@
+// =====================================================
+// Medicine Search
+// Prefix search over the medicine master. Medicines in
+// stock or sold recently are few, so they are matched
+// and ranked here; the rest of the catalog comes from
+// the medicines_fts index, name matches first
+// =====================================================
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use tauri::State;
+
+/// Relevance of a word typed matching the start of a word in the name,
+/// the composition (generic_name) or the manufacturer
+const WEIGHTS: [f64; 3] = [10.0, 4.0, 1.0];
+/// Extra relevance when the name begins with the first word typed
+const NAME_START: f64 = 5.0;
+
+/// Ranking multipliers for medicines in stock and recent sellers
+const IN_STOCK_BOOST: f64 = 2.0;
+const RECENT_SALE_BOOST: f64 = 0.5;
+
+/// A sale within this many days makes a medicine a recent seller
+const RECENT_DAYS: u32 = 30;
+
+/// Changed medicines past which the search reloads all stock rather than
+/// re-reading each
+const RELOAD_AFTER: usize = 1000;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 200;
+
+const COLUMNS: &str = "m.id, m.name, m.generic_name, m.manufacturer, m.hsn_code, m.category,
+    m.drug_type, m.pack_size, m.unit, m.reorder_level, m.is_active, m.created_at, m.updated_at";
+
+/// A medicine found by [`search_medicines`], with its sellable stock
+#[derive(Clone, Debug, Serialize)]
+pub struct MedicineMatch {
+    pub id: i64,
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub hsn_code: String,
+    pub category: Option<String>,
+    pub drug_type: Option<String>,
+    pub pack_size: Option<String>,
+    pub unit: Option<String>,
+    pub reorder_level: Option<i64>,
+    pub is_active: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Tablets or units in active, unexpired batches
+    pub stock: i64,
+    pub last_sold_date: Option<String>,
+}
+
+impl MedicineMatch {
+    /// From [`COLUMNS`] followed by the stock and last sale
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            generic_name: row.get(2)?,
+            manufacturer: row.get(3)?,
+            hsn_code: row.get(4)?,
+            category: row.get(5)?,
+            drug_type: row.get(6)?,
+            pack_size: row.get(7)?,
+            unit: row.get(8)?,
+            reorder_level: row.get(9)?,
+            is_active: row.get(10)?,
+            created_at: row.get(11)?,
+            updated_at: row.get(12)?,
+            stock: row.get(13)?,
+            last_sold_date: row.get(14)?,
+        })
+    }
+}
+
+/// Lowercased words, split at anything but a letter or digit as the
+/// index's tokenizer does
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The FTS5 query for the words typed: each must begin a word of the
+/// medicine. Quoting every word keeps anything typed from being read as
+/// query syntax.
+fn match_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"*", term))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A medicine in stock or sold recently, with its words split for matching
+struct Stocked {
+    medicine: MedicineMatch,
+    /// Words of the name, composition and manufacturer
+    words: [Vec<String>; 3],
+    sold_recently: bool,
+}
+
+/// The first date a sale counts as recent, which also changes with the day
+/// stock expires on
+fn recent_since(conn: &Connection) -> Result<String, String> {
+    conn.query_row(
+        "SELECT date('now', ?1)",
+        params![format!("-{} days", RECENT_DAYS)],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to search medicines: {}", e))
+}
+
+/// Active medicines with stock in active, unexpired batches or a sale on or
+/// after `since`; only `medicine` when given
+fn stocked(conn: &Connection, since: &str, medicine: Option<i64>) -> Result<Vec<Stocked>, String> {
+    let error = |e: rusqlite::Error| format!("Failed to load medicines in stock: {}", e);
+    let mut stmt = conn
+        .prepare_cached(&format!(
+            "SELECT {}, s.quantity, s.last_sold_date
+             FROM (SELECT medicine_id,
+                          SUM(CASE WHEN quantity > 0 AND expiry_date > date('now')
+                                   THEN quantity ELSE 0 END) AS quantity,
+                          MAX(last_sold_date) AS last_sold_date
+                   FROM batches WHERE is_active = 1{} GROUP BY medicine_id) s
+             JOIN medicines m ON m.id = s.medicine_id
+             WHERE m.is_active = 1 AND (s.quantity > 0 OR s.last_sold_date >= ?1)",
+            COLUMNS,
+            if medicine.is_some() {
+                " AND medicine_id = ?2"
+            } else {
+                ""
+            }
+        ))
+        .map_err(error)?;
+    let rows = match medicine {
+        Some(id) => stmt.query_map(params![since, id], MedicineMatch::from_row),
+        None => stmt.query_map(params![since], MedicineMatch::from_row),
+    }
+    .map_err(error)?;
+    rows.map(|row| {
+        let medicine = row.map_err(error)?;
+        let split = |text: Option<&str>| text.map(|t| words(t).collect()).unwrap_or_default();
+        Ok(Stocked {
+            words: [
+                split(Some(&medicine.name)),
+                split(medicine.generic_name.as_deref()),
+                split(medicine.manufacturer.as_deref()),
+            ],
+            sold_recently: medicine
+                .last_sold_date
+                .as_deref()
+                .is_some_and(|date| date >= since),
+            medicine,
+        })
+    })
+    .collect()
+}
+
+/// How well `terms` match a medicine's words, or None unless each begins a
+/// word of its name, composition or manufacturer
+fn relevance(terms: &[String], columns: &[Vec<String>; 3]) -> Option<f64> {
+    let mut score = 0.0;
+    for term in terms {
+        score += columns
+            .iter()
+            .zip(WEIGHTS)
+            .filter(|(words, _)| words.iter().any(|word| word.starts_with(term.as_str())))
+            .map(|(_, weight)| weight)
+            .reduce(f64::max)?;
+    }
+    if columns[0]
+        .first()
+        .zip(terms.first())
+        .is_some_and(|(word, term)| word.starts_with(term.as_str()))
+    {
+        score += NAME_START;
+    }
+    Some(score)
+}
+
+/// Active medicines matching `query`, best first: those in stock or sold
+/// recently, by relevance with boosts for each; then names, then
+/// compositions and manufacturers matching, in catalog order. Every word
+/// typed must begin a word of the medicine.
+fn find(
+    conn: &Connection,
+    stocked: &[Stocked],
+    since: &str,
+    query: &str,
+    limit: u32,
+    in_stock_only: bool,
+) -> Result<Vec<MedicineMatch>, String> {
+    let terms: Vec<String> = words(query).collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.clamp(1, MAX_LIMIT) as usize;
+    let error = |e: rusqlite::Error| format!("Failed to search medicines: {}", e);
+
+    let mut ranked: Vec<(f64, &MedicineMatch)> = stocked
+        .iter()
+        .filter(|item| !in_stock_only || item.medicine.stock > 0)
+        .filter_map(|item| {
+            let boost =
+                1.0 + if item.medicine.stock > 0 {
+                    IN_STOCK_BOOST
+                } else {
+                    0.0
+                } + if item.sold_recently {
+                    RECENT_SALE_BOOST
+                } else {
+                    0.0
+                };
+            Some((relevance(&terms, &item.words)? * boost, &item.medicine))
+        })
+        .collect();
+    ranked.sort_by(|(a, x), (b, y)| b.total_cmp(a).then_with(|| x.name.cmp(&y.name)));
+    let mut found: Vec<MedicineMatch> = ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, medicine)| medicine.clone())
+        .collect();
+    if in_stock_only || found.len() == limit {
+        return Ok(found);
+    }
+
+    // The rest of the catalog. Index order is catalog order, so this stops
+    // after the first few matches however many there are.
+    let fts_query = match_query(&terms);
+    let mut stmt = conn
+        .prepare_cached(&format!(
+            "SELECT {}, 0, (SELECT MAX(last_sold_date) FROM batches WHERE medicine_id = m.id)
+             FROM medicines_fts f
+             CROSS JOIN medicines m ON m.id = f.rowid
+             WHERE medicines_fts MATCH ?1 AND m.is_active = 1
+               AND NOT EXISTS (
+                   SELECT 1 FROM batches b
+                   WHERE b.medicine_id = m.id AND b.is_active = 1
+                     AND (b.quantity > 0 AND b.expiry_date > date('now')
+                          OR b.last_sold_date >= ?2))
+             LIMIT ?3",
+            COLUMNS
+        ))
+        .map_err(error)?;
+    let mut seen: HashSet<i64> = found.iter().map(|m| m.id).collect();
+    for fts_query in [format!("name : ({})", fts_query), fts_query] {
+        let wanted = limit - found.len();
+        let rows = stmt
+            .query_map(
+                params![fts_query, since, (wanted + seen.len()) as i64],
+                MedicineMatch::from_row,
+            )
+            .map_err(error)?;
+        for row in rows {
+            let medicine = row.map_err(error)?;
+            if found.len() < limit && seen.insert(medicine.id) {
+                found.push(medicine);
+            }
+        }
+        if found.len() == limit {
+            break;
+        }
+    }
+    Ok(found)
+}
+
+/// A connection kept for searching, as the counter searches on every
+/// keystroke, with the medicines in stock loaded once and kept current from
+/// the stock_changes log. It never writes, so `PRAGMA data_version` changes
+/// only when another connection commits.
+struct Searcher {
+    conn: Connection,
+    /// `PRAGMA data_version` and the first day of recent sales when last
+    /// brought up to date
+    loaded: Option<(i64, String)>,
+    /// The last entry of stock_changes applied
+    seq: i64,
+    stocked: Vec<Stocked>,
+}
+
+impl Searcher {
+    fn new(conn: Connection) -> Self {
+        Self {
+            conn,
+            loaded: None,
+            seq: 0,
+            stocked: Vec::new(),
+        }
+    }
+
+    /// Read every medicine in stock afresh
+    fn reload(&mut self, version: i64, since: String) -> Result<(), String> {
+        let error = |e: rusqlite::Error| format!("Failed to load medicines in stock: {}", e);
+        let tx = self.conn.unchecked_transaction().map_err(error)?;
+        self.seq = tx
+            .query_row(
+                "SELECT COALESCE(MAX(seq), 0) FROM stock_changes",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(error)?;
+        self.stocked = stocked(&tx, &since, None)?;
+        self.loaded = Some((version, since));
+        Ok(())
+    }
+
+    /// Re-read the medicines other connections changed since the last look
+    fn refresh(&mut self, version: i64, since: String) -> Result<(), String> {
+        let error = |e: rusqlite::Error| format!("Failed to read stock changes: {}", e);
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT seq, medicine_id FROM stock_changes WHERE seq > ?1
+                 ORDER BY seq LIMIT ?2",
+            )
+            .map_err(error)?;
+        let changes = stmt
+            .query_map(params![self.seq, RELOAD_AFTER as i64 + 1], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(error)?
+            .collect::<Result<Vec<(i64, i64)>, _>>()
+            .map_err(error)?;
+        drop(stmt);
+
+        if changes.len() > RELOAD_AFTER {
+            return self.reload(version, since);
+        }
+        let changed: HashSet<i64> = changes.iter().map(|(_, id)| *id).collect();
+        self.stocked
+            .retain(|item| !changed.contains(&item.medicine.id));
+        for id in changed {
+            let found = stocked(&self.conn, &since, Some(id))?;
+            self.stocked.extend(found);
+        }
+        if let Some((seq, _)) = changes.last() {
+            self.seq = *seq;
+        }
+        self.loaded = Some((version, since));
+        Ok(())
+    }
+
+    /// [`find`], catching up with changes to stock since the last search
+    /// and reloading it when the date moves on
+    fn search(
+        &mut self,
+        query: &str,
+        limit: u32,
+        in_stock_only: bool,
+    ) -> Result<Vec<MedicineMatch>, String> {
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to search medicines: {}", e))?;
+        let since = recent_since(&self.conn)?;
+        match &self.loaded {
+            Some((loaded, day)) if *day == since => {
+                if *loaded != version {
+                    self.refresh(version, since)?;
+                }
+            }
+            _ => self.reload(version, since)?,
+        }
+        let (_, since) = self.loaded.as_ref().expect("loaded above");
+        find(
+            &self.conn,
+            &self.stocked,
+            since,
+            query,
+            limit,
+            in_stock_only,
+        )
+    }
+}
+
+/// The search connection, opened on the first search
+#[derive(Default)]
+pub struct MedicineSearch(Mutex<Option<Searcher>>);
+
+/// Search the medicine master by the start of any word of the name,
+/// composition or manufacturer
+#[tauri::command]
+pub async fn search_medicines(
+    app: tauri::AppHandle,
+    state: State<'_, MedicineSearch>,
+    query: String,
+    limit: Option<u32>,
+    in_stock_only: Option<bool>,
+) -> Result<Vec<MedicineMatch>, String> {
+    let mut searcher = state
+        .0
+        .lock()
+        .map_err(|_| "Medicine search is unavailable".to_string())?;
+    if searcher.is_none() {
+        *searcher = Some(Searcher::new(crate::db::open(&app)?));
+    }
+    searcher.as_mut().expect("opened above").search(
+        &query,
+        limit.unwrap_or(DEFAULT_LIMIT),
+        in_stock_only.unwrap_or(false),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(
+        conn: &Connection,
+        query: &str,
+        limit: u32,
+        in_stock_only: bool,
+    ) -> Result<Vec<MedicineMatch>, String> {
+        let since = recent_since(conn)?;
+        let stocked = stocked(conn, &since, None)?;
+        find(conn, &stocked, &since, query, limit, in_stock_only)
+    }
+
+    fn shop() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join("medbill-search-unused");
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        conn.execute_batch(
+            "INSERT INTO medicines (id, name, generic_name, manufacturer) VALUES
+                 (1, 'Dolo 650 Tablet', 'Paracetamol (650mg)', 'Micro Labs Ltd'),
+                 (2, 'Calpol 500mg Tablet', 'Paracetamol (500mg)', 'GSK'),
+                 (3, 'Pacimol 650 Tablet', 'Paracetamol (650mg)', 'Ipca Laboratories'),
+                 (4, 'Crocin Advance Tablet', 'Paracetamol (500mg)', 'GSK'),
+                 (5, 'Augmentin 625 Duo Tablet', 'Amoxycillin (500mg) + Clavulanic Acid (125mg)', 'GSK'),
+                 (6, 'Dolonex DT 20 Tablet', 'Piroxicam (20mg)', 'Pfizer Ltd');
+             INSERT INTO batches (medicine_id, batch_number, expiry_date, purchase_price, mrp,
+                 selling_price, quantity, last_sold_date) VALUES
+                 (3, 'P01', date('now', '+1 year'), 1, 2, 2, 100, NULL),
+                 (4, 'C01', date('now', '+1 year'), 1, 2, 2, 30, date('now', '-2 days')),
+                 (2, 'X01', date('now', '-1 day'), 1, 2, 2, 50, NULL);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn names(conn: &Connection, query: &str, in_stock_only: bool) -> Vec<String> {
+        search(conn, query, 10, in_stock_only)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.name)
+            .collect()
+    }
+
+    #[test]
+    fn words_match_by_prefix_and_stock_ranks_first() {
+        let conn = shop();
+        assert_eq!(
+            names(&conn, "dol", false),
+            ["Dolo 650 Tablet", "Dolonex DT 20 Tablet"]
+        );
+        assert_eq!(names(&conn, "DOLO 65", false), ["Dolo 650 Tablet"]);
+        assert_eq!(
+            names(&conn, "clav acid", false),
+            ["Augmentin 625 Duo Tablet"]
+        );
+
+        // The recent seller in stock first, then stock, then the rest;
+        // expired stock does not count
+        let paracetamol = names(&conn, "paracetamol", false);
+        assert_eq!(
+            &paracetamol[..2],
+            ["Crocin Advance Tablet", "Pacimol 650 Tablet"]
+        );
+        assert_eq!(paracetamol.len(), 4);
+        assert_eq!(
+            names(&conn, "paracetamol", true),
+            ["Crocin Advance Tablet", "Pacimol 650 Tablet"]
+        );
+        let crocin = &search(&conn, "crocin", 1, false).unwrap()[0];
+        assert_eq!(crocin.stock, 30);
+    }
+
+    #[test]
+    fn the_index_follows_edits_and_query_syntax_is_ignored() {
+        let conn = shop();
+        conn.execute_batch(
+            "UPDATE medicines SET name = 'Dolo 500 Tablet' WHERE id = 1;
+             DELETE FROM medicines WHERE id = 6;
+             INSERT INTO medicines (name, manufacturer) VALUES ('Dolokind Plus', 'Mankind');
+             UPDATE medicines SET is_active = 0 WHERE id = 2;",
+        )
+        .unwrap();
+        let mut dolo = names(&conn, "dolo", false);
+        dolo.sort();
+        assert_eq!(dolo, ["Dolo 500 Tablet", "Dolokind Plus"]);
+        assert_eq!(names(&conn, "dolo 500", false), ["Dolo 500 Tablet"]);
+        assert!(names(&conn, "calpol", false).is_empty());
+
+        assert_eq!(names(&conn, "\"dolo\" OR NEAR(", false).len(), 0);
+        assert_eq!(names(&conn, "dolo*", false).len(), 2);
+        assert!(names(&conn, " -*() ", false).is_empty());
+    }
+
+    #[test]
+    fn the_counter_sees_stock_received_elsewhere() {
+        let dir = std::env::temp_dir().join(format!("medbill-search-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("medbill.db");
+        let mut conn = Connection::open(&path).unwrap();
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        conn.execute_batch(
+            "INSERT INTO medicines (id, name) VALUES
+                 (1, 'Dolo 650 Tablet'), (2, 'Dolonex DT 20 Tablet');",
+        )
+        .unwrap();
+
+        let mut searcher = Searcher::new(Connection::open(&path).unwrap());
+        assert!(searcher.search("dolo", 10, true).unwrap().is_empty());
+        conn.execute(
+            "INSERT INTO batches (medicine_id, batch_number, expiry_date, purchase_price, mrp,
+                 selling_price, quantity) VALUES (2, 'D01', date('now', '+1 year'), 1, 2, 2, 10)",
+            [],
+        )
+        .unwrap();
+        let found = searcher.search("dolo", 10, false).unwrap();
+        assert_eq!(found[0].name, "Dolonex DT 20 Tablet");
+        assert_eq!(found[0].stock, 10);
+
+        // A sale and an edit are picked up without reloading all stock
+        conn.execute_batch(
+            "UPDATE batches SET quantity = 0, last_sold_date = date('now', '-40 days');
+             UPDATE medicines SET name = 'Dolo 650mg Tablet' WHERE id = 1;",
+        )
+        .unwrap();
+        let loaded = searcher.seq;
+        assert!(searcher.search("dolonex", 10, true).unwrap().is_empty());
+        assert_eq!(searcher.search("dolo 650mg", 10, false).unwrap().len(), 1);
+        assert!(searcher.seq > loaded);
+        assert!(searcher.stocked.is_empty());
+
+        drop(searcher);
+        drop(conn);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture`: a catalog
+    /// the size of the full master with a tenth of it in stock
+    #[test]
+    #[ignore]
+    fn searches_a_full_catalog_quickly() {
+        use std::time::{Duration, Instant};
+
+        const SYLLABLES: [&str; 40] = [
+            "do", "lo", "pa", "ra", "ce", "ta", "mol", "cro", "cin", "am", "ox", "cla", "vu", "az",
+            "ith", "ro", "my", "cal", "pol", "ni", "me", "su", "li", "de", "met", "for", "gli",
+            "pi", "zo", "pan", "tra", "zol", "ator", "va", "sta", "tin", "ce", "tri", "zi", "ne",
+        ];
+        const FORMS: [&str; 5] = ["Tablet", "Capsule", "Syrup", "Injection", "Cream"];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join("medbill-search-unused");
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        let tx = conn.transaction().unwrap();
+        {
+            let mut medicine = tx
+                .prepare(
+                    "INSERT INTO medicines (id, name, generic_name, manufacturer)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )
+                .unwrap();
+            let mut batch = tx
+                .prepare(
+                    "INSERT INTO batches (medicine_id, batch_number, expiry_date, purchase_price,
+                         mrp, selling_price, quantity, last_sold_date)
+                     VALUES (?1, ?2, date('now', ?3), 1, 2, 2, ?4,
+                             CASE WHEN ?5 THEN date('now', '-3 days') END)",
+                )
+                .unwrap();
+            for id in 1..=250_000usize {
+                let name = format!(
+                    "{}{}{} {} {}",
+                    SYLLABLES[id % 40],
+                    SYLLABLES[id / 40 % 40],
+                    SYLLABLES[id / 1600 % 40],
+                    id % 1000,
+                    FORMS[id % 5]
+                );
+                let generic = format!(
+                    "{}{} ({}mg)",
+                    SYLLABLES[id % 37],
+                    SYLLABLES[id / 37 % 40],
+                    id % 7 * 50 + 50
+                );
+                let maker = format!("{}{} Pharma", SYLLABLES[id % 23], SYLLABLES[id % 29]);
+                medicine
+                    .execute(params![id as i64, name, generic, maker])
+                    .unwrap();
+                if id % 10 == 0 {
+                    batch
+                        .execute(params![id as i64, "B01", "+1 year", 100, id % 50 == 0])
+                        .unwrap();
+                }
+                if id % 7 == 0 {
+                    batch
+                        .execute(params![id as i64, "B02", "-1 month", 20, false])
+                        .unwrap();
+                }
+            }
+        }
+        tx.commit().unwrap();
+
+        let mut searcher = Searcher::new(conn);
+        let since = recent_since(&searcher.conn).unwrap();
+        let started = Instant::now();
+        searcher.reload(0, since.clone()).unwrap();
+        let reload = started.elapsed();
+        assert_eq!(searcher.stocked.len(), 25_000);
+
+        // A bill of five lines, as another connection would commit it
+        searcher
+            .conn
+            .execute(
+                "UPDATE batches SET quantity = quantity - 1, last_sold_date = date('now')
+                 WHERE medicine_id IN (10, 20, 30, 40, 50) AND quantity = 100",
+                [],
+            )
+            .unwrap();
+        let started = Instant::now();
+        searcher.refresh(1, since.clone()).unwrap();
+        let refresh = started.elapsed();
+        assert_eq!(searcher.stocked.len(), 25_000);
+        let (conn, stocked) = (&searcher.conn, &searcher.stocked);
+
+        let mut slowest = Duration::ZERO;
+        for query in [
+            "d", "do", "dolo", "para 50", "ox ithro", "pharma", "zzz", "ne 999",
+        ] {
+            for in_stock_only in [false, true] {
+                for _ in 0..10 {
+                    let started = Instant::now();
+                    find(conn, stocked, &since, query, DEFAULT_LIMIT, in_stock_only).unwrap();
+                    slowest = slowest.max(started.elapsed());
+                }
+            }
+        }
+        println!(
+            "stock reload {:?}, refresh after a sale {:?}, slowest search {:?}",
+            reload, refresh, slowest
+        );
+        assert!(slowest < Duration::from_millis(20), "{:?}", slowest);
+    }
+}
@@ -0,0 +1,522 @@
+// =====================================================
+// Schema Migrations
+// Brings medbill.db up to this build's schema at startup,
+// one numbered step at a time, tracked in user_version
+// =====================================================
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, Transaction};
+
+/// One step of the schema. Steps are applied in order, each in its own
+/// transaction together with the new `user_version`.
+struct Migration {
+    version: u32,
+    name: &'static str,
+    /// Rebuilds or drops a table; the database is backed up first
+    destructive: bool,
+    apply: fn(&Transaction) -> Result<(), String>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        destructive: false,
+        apply: baseline,
+    },
+    Migration {
+        version: 2,
+        name: "purchase returns can be rejected",
+        destructive: true,
+        apply: purchase_returns_rejected,
+    },
+    Migration {
+        version: 3,
+        name: "medicine bundle base",
+        destructive: false,
+        apply: medicine_bundle_base,
+    },
+    Migration {
+        version: 4,
+        name: "medicine search index",
+        destructive: false,
+        apply: medicines_fts,
+    },
+    Migration {
+        version: 5,
+        name: "medicine changes",
+        destructive: false,
+        apply: medicine_changes,
+    },
+    Migration {
+        version: 6,
+        name: "print jobs",
+        destructive: false,
+        apply: print_jobs,
+    },
+    Migration {
+        version: 7,
+        name: "receipt templates",
+        destructive: false,
+        apply: receipt_templates,
+    },
+    Migration {
+        version: 8,
+        name: "print job audit",
+        destructive: false,
+        apply: print_job_audit,
+    },
+    Migration {
+        version: 9,
+        name: "print job copies",
+        destructive: false,
+        apply: print_job_copies,
+    },
+    Migration {
+        version: 10,
+        name: "stock changes",
+        destructive: false,
+        apply: stock_changes,
+    },
+];
+
+/// The schema version this build writes
+pub const LATEST: u32 = MIGRATIONS.len() as u32;
+
+/// Columns added to existing databases before migrations were versioned,
+/// in the order the frontend used to add them
+const BASELINE_COLUMNS: &[(&str, &str, &str)] = &[
+    ("batches", "tablets_per_strip", "INTEGER DEFAULT 10"),
+    ("bill_items", "quantity_strips", "INTEGER DEFAULT 0"),
+    ("bill_items", "quantity_pieces", "INTEGER DEFAULT 0"),
+    ("bill_items", "tablets_per_strip", "INTEGER DEFAULT 10"),
+    ("bills", "total_items", "INTEGER DEFAULT 0"),
+    ("bills", "doctor_name", "TEXT"),
+    (
+        "scheduled_medicine_records",
+        "doctor_registration_number",
+        "TEXT",
+    ),
+    ("scheduled_medicine_records", "clinic_hospital_name", "TEXT"),
+    ("scheduled_medicine_records", "prescription_date", "TEXT"),
+    ("scheduled_medicine_records", "doctor_prescription", "TEXT"),
+    ("batches", "supplier_id", "INTEGER REFERENCES suppliers(id)"),
+    ("batches", "gst_rate", "DECIMAL(5,2) DEFAULT 12"),
+    ("batches", "is_schedule", "INTEGER DEFAULT 0"),
+    ("medicines", "pack_size", "TEXT"),
+    ("batches", "free_quantity", "INTEGER DEFAULT 0"),
+];
+
+/// Every table the app created before versioning, with the columns added
+/// since; older databases get the missing tables and columns. Batch stock,
+/// once kept in strips, is converted to tablets if that never happened.
+fn baseline(tx: &Transaction) -> Result<(), String> {
+    for (table, column, declaration) in BASELINE_COLUMNS {
+        if has_table(tx, table)? && !has_column(tx, table, column)? {
+            sql(
+                tx,
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, declaration
+                ),
+            )?;
+        }
+    }
+    // Indexes on the added columns come after them
+    sql(tx, include_str!("../migrations/0001_baseline.sql"))?;
+
+    let converted: bool = tx
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM settings WHERE key = 'tablets_migration_done')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    if !converted {
+        sql(
+            tx,
+            "UPDATE batches SET quantity = quantity * COALESCE(tablets_per_strip, 10)
+             WHERE quantity > 0;
+             INSERT INTO settings (key, value, category, description) VALUES
+             ('tablets_migration_done', 'true', 'system', 'Quantity converted from strips to tablets');",
+        )?;
+    }
+    Ok(())
+}
+
+fn purchase_returns_rejected(tx: &Transaction) -> Result<(), String> {
+    sql(
+        tx,
+        include_str!("../migrations/0002_purchase_returns_rejected.sql"),
+    )
+}
+
+fn medicine_bundle_base(tx: &Transaction) -> Result<(), String> {
+    sql(
+        tx,
+        include_str!("../migrations/0003_medicine_bundle_base.sql"),
+    )
+}
+
+fn medicines_fts(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0004_medicines_fts.sql"))
+}
+
+fn medicine_changes(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0005_medicine_changes.sql"))
+}
+
+fn print_jobs(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0006_print_jobs.sql"))
+}
+
+fn receipt_templates(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0007_receipt_templates.sql"))?;
+    crate::print::receipt_templates::seed(tx)
+}
+
+fn print_job_audit(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0008_print_job_audit.sql"))
+}
+
+fn print_job_copies(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0009_print_job_copies.sql"))
+}
+
+fn stock_changes(tx: &Transaction) -> Result<(), String> {
+    sql(tx, include_str!("../migrations/0010_stock_changes.sql"))
+}
+
+fn sql(tx: &Transaction, statements: &str) -> Result<(), String> {
+    tx.execute_batch(statements).map_err(|e| e.to_string())
+}
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        params![table],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read schema: {}", e))
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        params![table, column],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read schema: {}", e))
+}
+
+/// Schema version of the database; 0 before migrations were versioned
+pub fn version(conn: &Connection) -> Result<u32, String> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))
+}
+
+/// Copy the database into `dir` as a backup the Settings page lists and can
+/// restore. Returns the backup's path.
+pub fn backup(conn: &Connection, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create backup folder: {}", e))?;
+    let name = chrono::Local::now()
+        .format("Backup_%d%m%Y_%H%M%S.db")
+        .to_string();
+    let path = dir.join(name);
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+        .map_err(|e| format!("Failed to back up the database: {}", e))?;
+    Ok(path)
+}
+
+/// Apply every migration after the database's version up to `target`.
+/// Destructive steps are preceded by one backup into `backup_dir` (none for
+/// a new, empty database). A database from a newer build is refused, as
+/// this build would not know what its tables mean. Returns the new version.
+pub fn migrate(conn: &mut Connection, target: u32, backup_dir: &Path) -> Result<u32, String> {
+    let current = version(conn)?;
+    if current > LATEST {
+        return Err(format!(
+            "This database was created by a newer version of MedBill (schema version {}, \
+             this version understands up to {}). Install the latest MedBill to open it.",
+            current, LATEST
+        ));
+    }
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+        .collect();
+    if pending.is_empty() {
+        return Ok(current);
+    }
+
+    let empty: bool = conn
+        .query_row(
+            "SELECT NOT EXISTS (SELECT 1 FROM sqlite_master)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read schema: {}", e))?;
+    if !empty && pending.iter().any(|m| m.destructive) {
+        let path = backup(conn, backup_dir)?;
+        log::info!("Backed up the database to {:?} before migrating", path);
+    }
+
+    // Rebuilt tables are dropped while other tables still refer to them;
+    // rows are copied with their ids, so the references hold again after
+    conn.execute_batch("PRAGMA foreign_keys = OFF")
+        .map_err(|e| format!("Failed to prepare migrations: {}", e))?;
+    for migration in pending {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start migration: {}", e))?;
+        (migration.apply)(&tx)
+            .and_then(|_| {
+                tx.pragma_update(None, "user_version", migration.version)
+                    .map_err(|e| e.to_string())
+            })
+            .map_err(|e| {
+                format!(
+                    "Database migration {} ({}) failed: {}",
+                    migration.version, migration.name, e
+                )
+            })?;
+        tx.commit().map_err(|e| {
+            format!(
+                "Failed to save migration {} ({}): {}",
+                migration.version, migration.name, e
+            )
+        })?;
+        log::info!(
+            "Migrated the database to version {} ({})",
+            migration.version,
+            migration.name
+        );
+    }
+    version(conn)
+}
+
+/// Bring medbill.db up to date; called at startup and by the frontend
+/// before it opens the database
+pub fn run(app: &tauri::AppHandle) -> Result<u32, String> {
+    let mut conn = crate::db::open(app)?;
+    let backups = crate::db::get_db_path(app)?
+        .parent()
+        .map(|dir| dir.join("backups"))
+        .ok_or_else(|| "Failed to find the backup folder".to_string())?;
+    migrate(&mut conn, LATEST, &backups)
+}
+
+/// Migrate the database and return its schema version. Fails for a
+/// database from a newer build, which must then not be opened.
+#[tauri::command]
+pub fn migrate_database(app: tauri::AppHandle) -> Result<u32, String> {
+    run(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tables as the first release created them, before the frontend's
+    /// ALTER TABLE migrations, with stock still counted in strips
+    const V0_FIRST_RELEASE: &str = "
+        CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, username TEXT NOT NULL UNIQUE,
+            password_hash TEXT NOT NULL, full_name TEXT NOT NULL, role TEXT NOT NULL);
+        CREATE TABLE medicines (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL,
+            generic_name TEXT, manufacturer TEXT, hsn_code TEXT NOT NULL DEFAULT '3004',
+            is_active INTEGER DEFAULT 1, created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP);
+        CREATE TABLE batches (id INTEGER PRIMARY KEY AUTOINCREMENT, medicine_id INTEGER NOT NULL,
+            batch_number TEXT NOT NULL, expiry_date DATE NOT NULL, quantity INTEGER NOT NULL,
+            rack TEXT, box TEXT);
+        CREATE TABLE bills (id INTEGER PRIMARY KEY AUTOINCREMENT, bill_number TEXT NOT NULL,
+            bill_date DATETIME, customer_id INTEGER, is_cancelled INTEGER DEFAULT 0);
+        CREATE TABLE bill_items (id INTEGER PRIMARY KEY AUTOINCREMENT, bill_id INTEGER NOT NULL,
+            batch_id INTEGER NOT NULL, medicine_id INTEGER NOT NULL, quantity INTEGER NOT NULL);
+        CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT, category TEXT,
+            description TEXT, updated_at DATETIME);
+        CREATE TABLE purchase_returns (id INTEGER PRIMARY KEY AUTOINCREMENT,
+            return_number TEXT NOT NULL UNIQUE, return_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+            purchase_id INTEGER, supplier_id INTEGER NOT NULL, reason TEXT,
+            total_amount DECIMAL(12,2) NOT NULL, total_gst DECIMAL(12,2) DEFAULT 0,
+            status TEXT DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'COMPLETED')),
+            notes TEXT, user_id INTEGER NOT NULL, created_at DATETIME, updated_at DATETIME);
+        INSERT INTO medicines (id, name) VALUES (1, 'Paracetamol 500mg');
+        INSERT INTO batches VALUES (1, 1, 'BT01', '2027-06-30', 5, 'A1', '1');
+        INSERT INTO settings (key, value) VALUES ('shop_name', 'City Medicals');
+        INSERT INTO purchase_returns (id, return_number, supplier_id, reason, total_amount,
+            user_id) VALUES (1, 'PR-0001', 1, 'EXPIRY', 120, 1);
+    ";
+
+    fn backup_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "medbill-migrations-{}-{}",
+            std::process::id(),
+            test
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn backups(dir: &Path) -> usize {
+        std::fs::read_dir(dir).map_or(0, |entries| entries.count())
+    }
+
+    fn reject_return(conn: &Connection) -> rusqlite::Result<usize> {
+        conn.execute(
+            "UPDATE purchase_returns SET status = 'REJECTED' WHERE return_number = 'PR-0001'",
+            [],
+        )
+    }
+
+    #[test]
+    fn first_release_databases_are_brought_up_to_date() {
+        let dir = backup_dir("first-release");
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(V0_FIRST_RELEASE).unwrap();
+        assert!(reject_return(&conn).is_err());
+
+        assert_eq!(migrate(&mut conn, LATEST, &dir).unwrap(), LATEST);
+        assert_eq!(version(&conn).unwrap(), LATEST);
+        for (table, column, _) in BASELINE_COLUMNS {
+            assert!(
+                has_column(&conn, table, column).unwrap(),
+                "{}.{}",
+                table,
+                column
+            );
+        }
+        assert!(has_table(&conn, "sales_returns").unwrap());
+        assert!(has_table(&conn, "print_jobs").unwrap());
+        assert!(has_column(&conn, "print_jobs", "audit").unwrap());
+        assert!(has_column(&conn, "print_jobs", "copies_printed").unwrap());
+        let templates: String = conn
+            .query_row(
+                "SELECT group_concat(name) FROM receipt_templates",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(templates, "Standard");
+
+        // Stock moves from strips to tablets once; data is kept
+        let (quantity, rack): (i64, String) = conn
+            .query_row(
+                "SELECT quantity, rack FROM batches WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((quantity, rack.as_str()), (50, "A1"));
+        assert_eq!(
+            crate::db::get_setting(&conn, "shop_name").as_deref(),
+            Some("City Medicals")
+        );
+
+        assert_eq!(reject_return(&conn).unwrap(), 1);
+        assert_eq!(backups(&dir), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn databases_from_the_last_unversioned_release_keep_their_stock() {
+        let dir = backup_dir("unversioned");
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/0001_baseline.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO settings (key, value) VALUES ('tablets_migration_done', 'true');
+             INSERT INTO medicines (id, name) VALUES (1, 'Paracetamol 500mg');
+             INSERT INTO batches (id, medicine_id, batch_number, expiry_date, purchase_price,
+                 mrp, selling_price, quantity) VALUES (1, 1, 'BT01', '2027-06-30', 1, 2, 2, 40);",
+        )
+        .unwrap();
+
+        migrate(&mut conn, LATEST, &dir).unwrap();
+        let quantity: i64 = conn
+            .query_row("SELECT quantity FROM batches WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(quantity, 40);
+        assert_eq!(version(&conn).unwrap(), LATEST);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn each_version_migrates_to_the_latest() {
+        for from in 1..LATEST {
+            let dir = backup_dir(&format!("from-{}", from));
+            let mut conn = Connection::open_in_memory().unwrap();
+            assert_eq!(migrate(&mut conn, from, &dir).unwrap(), from);
+            conn.execute(
+                "INSERT INTO purchase_returns (return_number, supplier_id, reason, total_amount,
+                     user_id) VALUES ('PR-0001', 1, 'DAMAGE', 80, 1)",
+                [],
+            )
+            .unwrap();
+
+            assert_eq!(migrate(&mut conn, LATEST, &dir).unwrap(), LATEST);
+            assert_eq!(reject_return(&conn).unwrap(), 1);
+            let destructive = MIGRATIONS.iter().any(|m| m.version > from && m.destructive);
+            assert_eq!(backups(&dir), usize::from(destructive));
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn print_tables_made_before_migrations_are_kept() {
+        let dir = backup_dir("print-tables");
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, 5, &dir).unwrap();
+        // As the spool and receipt templates created them at startup
+        conn.execute_batch(
+            "CREATE TABLE print_jobs (id INTEGER PRIMARY KEY AUTOINCREMENT, bill_id INTEGER,
+                 document TEXT NOT NULL DEFAULT 'bill', printer TEXT NOT NULL,
+                 title TEXT NOT NULL, raw INTEGER NOT NULL DEFAULT 1, data BLOB NOT NULL,
+                 copies INTEGER NOT NULL DEFAULT 1, status TEXT NOT NULL DEFAULT 'queued',
+                 attempts INTEGER NOT NULL DEFAULT 0, last_error TEXT,
+                 reprint_of INTEGER REFERENCES print_jobs(id),
+                 next_attempt_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP, printed_at DATETIME);
+             CREATE TABLE receipt_templates (id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 name TEXT NOT NULL UNIQUE, body TEXT NOT NULL,
+                 width INTEGER NOT NULL DEFAULT 42, is_active INTEGER NOT NULL DEFAULT 0,
+                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                 updated_at DATETIME DEFAULT CURRENT_TIMESTAMP);
+             INSERT INTO print_jobs (printer, title, data) VALUES ('Counter', 'Bill', x'1b40');
+             INSERT INTO receipt_templates (name, body, is_active) VALUES ('Ours', '{{ bill.total }}', 1);",
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&mut conn, LATEST, &dir).unwrap(), LATEST);
+        let jobs: i64 = conn
+            .query_row("SELECT COUNT(*) FROM print_jobs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(jobs, 1);
+        let templates: String = conn
+            .query_row(
+                "SELECT group_concat(name) FROM receipt_templates",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(templates, "Ours");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn new_databases_need_no_backup_and_newer_ones_are_refused() {
+        let dir = backup_dir("new");
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&mut conn, LATEST, &dir).unwrap(), LATEST);
+        assert_eq!(migrate(&mut conn, LATEST, &dir).unwrap(), LATEST);
+        assert!(!dir.exists());
+
+        conn.pragma_update(None, "user_version", LATEST + 1)
+            .unwrap();
+        let err = migrate(&mut conn, LATEST, &dir).unwrap_err();
+        assert!(err.contains("newer version of MedBill"), "{}", err);
+    }
+}
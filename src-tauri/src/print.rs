@@ -3,350 +3,802 @@
 // Optimized for TVS MSP 250 - Minimal Paper Usage
 // =====================================================
 
-use std::process::Command;
-use tauri::command;
+pub mod backend;
+mod bill;
+mod bitmap;
+#[cfg(not(windows))]
+mod cups;
+pub mod device;
+mod drawer;
+mod escp;
+mod escpos;
+#[cfg(test)]
+mod fixtures;
+mod html_text;
+mod invoice;
+mod label;
+mod paginate;
+mod pdf;
+pub mod profiles;
+mod receipt;
+pub mod receipt_templates;
+mod reports;
+mod reprint;
+mod socket;
+pub mod spool;
+pub mod status;
+mod template;
+mod unicode;
+mod upi;
+#[cfg(windows)]
+mod win32;
+#[cfg(windows)]
+mod winspool;
+
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use tauri::{command, Emitter, State};
+
+use backend::{PrintJob, PrinterBackend, PrinterState, PrinterStatus};
+use bill::BillTemplate;
+use bitmap::Bitmap;
+use drawer::DrawerOptions;
+use escp::EscpOptions;
+use escpos::EscposOptions;
+use label::{LabelData, LabelOptions};
+use profiles::{DocumentKind, PrintFormat, PrinterProfile, PrinterProfiles};
+use receipt::{Block, Receipt};
+use receipt_templates::ReceiptTemplate;
+use reports::ReportKind;
+use reprint::ReprintedBill;
+use spool::{NewJob, Spool, SpoolJob};
+use status::DeviceStatus;
+
+/// Line width for dot matrix and driver-rendered text (80 columns at 10 cpi)
+const PAGE_COLUMNS: usize = 80;
+
+/// Find the fonts bundled as Tauri resources
+pub fn locate_resources(app: &tauri::AppHandle) {
+    use tauri::Manager;
+    match app.path().resource_dir() {
+        Ok(dir) => unicode::set_resource_dir(dir),
+        Err(e) => log::warn!("Failed to get resource directory: {}", e),
+    }
+}
 
-/// Print plain text silently to the default printer.
+/// Print a document silently.
 /// Optimized for dot matrix printers like TVS MSP 250.
+///
+/// The printer, format and copies come from the printer profile for
+/// `document` (a bill by default); `format` and `printer_name` override it.
+/// With `escp` (dot matrix) or `escpos` (thermal) the receipt is encoded as
+/// printer commands and sent as a RAW job, keeping bold headers, condensed
+/// item rows, the form feed or the paper cut.
+///
+/// The job is queued in the print spool and printed in the background, with
+/// retries while the printer is offline. Progress is reported through
+/// `print-job-status` events.
 #[command]
-pub async fn silent_print(html_content: String) -> Result<String, String> {
-    #[cfg(windows)]
-    {
-        // Get default printer name first
-        let printer_output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-Command",
-                "(Get-CimInstance -Class Win32_Printer | Where-Object {$_.Default -eq $true}).Name",
-            ])
-            .output();
-
-        let printer_name = match printer_output {
-            Ok(result) => {
-                let name = String::from_utf8_lossy(&result.stdout).trim().to_string();
-                log::info!("Default printer: {}", name);
-                name
-            }
-            Err(_) => String::new(),
-        };
-
-        if printer_name.is_empty() {
-            return Err("No default printer. Set TVS MSP 250 as default.".to_string());
-        }
+#[allow(clippy::too_many_arguments)]
+pub async fn silent_print(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    spool: State<'_, Spool>,
+    html_content: String,
+    format: Option<PrintFormat>,
+    document: Option<DocumentKind>,
+    printer_name: Option<String>,
+    bill_id: Option<i64>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let mut new = prepare_job(
+        state.0.as_ref(),
+        Some(&conn),
+        document.unwrap_or(DocumentKind::Bill),
+        printer_name.as_deref(),
+        format,
+        &html_content,
+    )?;
+    new.bill_id = bill_id;
+    queue(&app, &conn, &spool, &new)
+}
 
-        // Extract just the receipt text from HTML (between <pre> tags if present)
-        let receipt_text = extract_receipt_text(&html_content);
-
-        log::info!("Printing {} chars to {}", receipt_text.len(), printer_name);
-
-        // Use Out-Printer cmdlet
-        let escaped_text = receipt_text.replace("'", "''").replace("`", "``");
-
-        let ps_script = format!(
-            r#"
-$content = @'
-{}
-'@
-$content | Out-Printer
-"#,
-            escaped_text
-        );
-
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-WindowStyle",
-                "Hidden",
-                "-ExecutionPolicy",
-                "Bypass",
-                "-Command",
-                &ps_script,
-            ])
-            .output();
-
-        match output {
-            Ok(result) => {
-                let stderr = String::from_utf8_lossy(&result.stderr);
-                if stderr.trim().is_empty() || !stderr.to_lowercase().contains("error") {
-                    Ok(format!("Printed to {}", printer_name))
-                } else {
-                    log::warn!("Print stderr: {}", stderr.trim());
-                    Err(stderr.trim().to_string())
-                }
-            }
-            Err(e) => Err(format!("Print failed: {}", e)),
+/// Render document HTML into a job for its printer profile.
+/// Together with `spool::enqueue` and `spool::process_next` this is the whole
+/// `silent_print` path minus Tauri, so it can be driven with a `MockBackend`.
+pub fn prepare_job(
+    backend: &dyn PrinterBackend,
+    conn: Option<&Connection>,
+    document: DocumentKind,
+    printer: Option<&str>,
+    format: Option<PrintFormat>,
+    html: &str,
+) -> Result<NewJob, String> {
+    let profile = profiles::resolve(conn, document);
+    let printer_name = resolve_printer(backend, printer.or(profile.printer_name()))?;
+    let format = format.unwrap_or(profile.format);
+
+    let job = match format {
+        PrintFormat::Escp | PrintFormat::Escpos => {
+            let bytes = render_raw(conn, &profile, html, format)?;
+            log::info!(
+                "Rendered {} {:?} bytes for {}",
+                bytes.len(),
+                format,
+                printer_name
+            );
+            PrintJob::raw("MedBill Receipt", bytes)
         }
-    }
+        PrintFormat::Text => {
+            let page_lines = conn.map(paginate::page_lines).unwrap_or(0);
+            let receipt_text = html_text_pages(html, page_lines);
+            log::info!("Rendered {} chars for {}", receipt_text.len(), printer_name);
+            PrintJob::text("MedBill Receipt", &receipt_text)
+        }
+        PrintFormat::Pdf => {
+            return Err("PDF cannot be sent to a printer. Use ESC/P, ESC/POS or text.".to_string())
+        }
+        PrintFormat::Zpl | PrintFormat::Tspl | PrintFormat::Epl => {
+            return Err(format!(
+                "{:?} is a label printer format. Use ESC/P, ESC/POS or text for this document.",
+                format
+            ))
+        }
+    };
+
+    Ok(NewJob {
+        bill_id: None,
+        document,
+        printer: printer_name,
+        job,
+        copies: profile.copies,
+        audit: false,
+        user_id: None,
+    })
+}
 
-    #[cfg(not(windows))]
-    {
-        let _ = html_content;
-        Err("Only supported on Windows".to_string())
+/// Document HTML as plain text, split into pages of `page_lines` lines
+/// (0 for one continuous page)
+fn html_text_pages(html: &str, page_lines: usize) -> String {
+    let text = html_text::render(html, PAGE_COLUMNS);
+    if page_lines == 0 {
+        return text;
     }
+    paged_text(&Receipt::from_text(&text), page_lines)
+}
+
+/// `receipt` as plain text with a form feed between pages
+fn paged_text(receipt: &Receipt, page_lines: usize) -> String {
+    paginate::paginate(receipt, page_lines, paginate::text_lines).to_text()
 }
 
-/// Extract receipt text from HTML - looks for <pre> content or strips to plain text
-fn extract_receipt_text(html: &str) -> String {
-    // Method 1: Look for content between <pre> tags (dot matrix template uses this)
-    if let Some(start) = html.find("<pre") {
-        if let Some(tag_end) = html[start..].find('>') {
-            let content_start = start + tag_end + 1;
-            if let Some(end) = html[content_start..].find("</pre>") {
-                let pre_content = &html[content_start..content_start + end];
-                return decode_entities(pre_content.trim());
-            }
+/// The plain text document HTML prints as, split into pages of `page_lines`
+/// lines (by default from the printing settings) with form feeds between them
+#[command]
+pub fn preview_text(
+    app: tauri::AppHandle,
+    html_content: String,
+    page_lines: Option<usize>,
+) -> Result<String, String> {
+    let page_lines = match page_lines {
+        Some(lines) => lines,
+        None => paginate::page_lines(&crate::db::open(&app)?),
+    };
+    Ok(html_text_pages(&html_content, page_lines))
+}
+
+/// The plain text the next print of a saved bill gives on a text or dot
+/// matrix printer, split into pages of `page_lines` lines (by default from
+/// the printing settings). Nothing is printed or logged.
+#[command]
+pub fn preview_bill_text(
+    app: tauri::AppHandle,
+    bill_id: i64,
+    template: Option<BillTemplate>,
+    page_lines: Option<usize>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let page_lines = page_lines.unwrap_or_else(|| paginate::page_lines(&conn));
+    let mut bill = bill::load(&conn, bill_id)?;
+    bill.reprint = reprint::print_count(&conn, bill_id)?;
+    let shop = bill::Shop::load(&conn);
+    let receipt = bill_receipt(&conn, &shop, bill_id, &bill, template, PAGE_COLUMNS)?;
+    Ok(paged_text(&receipt, page_lines))
+}
+
+/// Lay out a saved bill `width` characters wide: with the active receipt
+/// template unless a built-in `template` is asked for
+fn bill_receipt(
+    conn: &Connection,
+    shop: &bill::Shop,
+    bill_id: i64,
+    bill: &bill::Bill,
+    template: Option<BillTemplate>,
+    width: usize,
+) -> Result<Receipt, String> {
+    if template.is_none() {
+        if let Some(custom) = receipt_templates::active(conn) {
+            return receipt_templates::render_bill(conn, &custom, shop, bill_id, bill);
         }
     }
+    let template = template.unwrap_or_else(|| BillTemplate::for_width(width));
+    Ok(bill::layout(shop, bill, template, width))
+}
 
-    // Method 2: Look for <body> content
-    let body_content = if let Some(start) = html.find("<body") {
-        if let Some(tag_end) = html[start..].find('>') {
-            let content_start = start + tag_end + 1;
-            if let Some(end) = html[content_start..].find("</body>") {
-                &html[content_start..content_start + end]
-            } else {
-                html
-            }
-        } else {
-            html
-        }
-    } else {
-        html
-    };
+/// Add a job to the spool and wake the worker
+fn queue(
+    app: &tauri::AppHandle,
+    conn: &Connection,
+    spool: &Spool,
+    new: &NewJob,
+) -> Result<String, String> {
+    let id = spool::enqueue(conn, new)?;
+    let _ = app.emit(
+        spool::EVENT,
+        spool::JobEvent {
+            id,
+            bill_id: new.bill_id,
+            status: "queued".to_string(),
+            attempts: 0,
+            error: None,
+        },
+    );
+    spool.wake();
+    Ok(format!("Queued for {}", new.printer))
+}
+
+/// Encode a bill as raw printer bytes (`escp` or `escpos`) without printing it.
+/// The result can be passed unchanged to `print_raw_text`.
+#[command]
+pub fn encode_receipt(
+    app: tauri::AppHandle,
+    html_content: String,
+    format: Option<PrintFormat>,
+    document: Option<DocumentKind>,
+) -> Result<Vec<u8>, String> {
+    let conn = crate::db::open(&app).ok();
+    let profile = profiles::resolve(conn.as_ref(), document.unwrap_or(DocumentKind::Bill));
+    let format = format.unwrap_or(profile.format);
+    render_raw(conn.as_ref(), &profile, &html_content, format)
+}
 
-    // Convert HTML to plain text
-    html_to_text(body_content)
-}
-
-/// Convert HTML to plain text with minimal formatting
-fn html_to_text(html: &str) -> String {
-    let mut text = html.to_string();
-
-    // Remove script/style blocks using simple string matching
-    text = remove_block(&text, "<script", "</script>");
-    text = remove_block(&text, "<style", "</style>");
-    text = remove_block(&text, "<!--", "-->");
-
-    // Replace block elements with newlines
-    text = text.replace("</tr>", "\n");
-    text = text.replace("</p>", "\n");
-    text = text.replace("</div>", "\n");
-    text = text.replace("</h1>", "\n");
-    text = text.replace("</h2>", "\n");
-    text = text.replace("</h3>", "\n");
-    text = text.replace("<br>", "\n");
-    text = text.replace("<br/>", "\n");
-    text = text.replace("<br />", "\n");
-    text = text.replace("<hr>", "\n---\n");
-    text = text.replace("<hr/>", "\n---\n");
-
-    // Table cells with spacing
-    text = text.replace("</td>", "  ");
-    text = text.replace("</th>", "  ");
-
-    // Remove all HTML tags
-    let mut result = String::new();
-    let mut in_tag = false;
-    for c in text.chars() {
-        if c == '<' {
-            in_tag = true;
-        } else if c == '>' {
-            in_tag = false;
-        } else if !in_tag {
-            result.push(c);
+/// Convert bill HTML to printer commands using the printing settings
+fn render_raw(
+    conn: Option<&Connection>,
+    profile: &PrinterProfile,
+    html: &str,
+    format: PrintFormat,
+) -> Result<Vec<u8>, String> {
+    match format {
+        PrintFormat::Escp => {
+            let receipt = Receipt::from_text(&html_text::render(html, PAGE_COLUMNS));
+            let options = conn.map(EscpOptions::from_settings).unwrap_or_default();
+            escp::encode(&receipt, &options)
         }
+        PrintFormat::Escpos => {
+            let width_mm = profile.roll_width_mm();
+            let options = match conn {
+                Some(conn) => EscposOptions::from_settings(conn, width_mm),
+                None => EscposOptions::for_width_mm(width_mm.unwrap_or(80)),
+            };
+            let receipt = Receipt::from_text(&html_text::render(html, options.columns));
+            encode_escpos(receipt, &options)
+        }
+        other => Err(format!("{:?} is not a printer command format", other)),
     }
+}
 
-    // Decode entities and clean up
-    result = decode_entities(&result);
-
-    // Remove excessive whitespace
-    let mut lines: Vec<&str> = result
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .filter(|l| !l.starts_with('@')) // Remove CSS
-        .filter(|l| !l.contains('{')) // Remove CSS
-        .filter(|l| !l.contains('}')) // Remove CSS
-        .collect();
-
-    // Limit consecutive empty-ish lines
-    lines.dedup();
-
-    lines.join("\n") + "\n\n"
-}
-
-/// Remove a block of content between start and end markers
-fn remove_block(text: &str, start_marker: &str, end_marker: &str) -> String {
-    let mut result = text.to_string();
-    while let Some(start) = result.to_lowercase().find(&start_marker.to_lowercase()) {
-        if let Some(rel_end) = result[start..]
-            .to_lowercase()
-            .find(&end_marker.to_lowercase())
-        {
-            let end = start + rel_end + end_marker.len();
-            result = result[..start].to_string() + &result[end..];
-        } else {
-            break;
+/// ESC/POS bytes with the shop logo, if one is configured, above the header
+fn encode_escpos(mut receipt: Receipt, options: &EscposOptions) -> Result<Vec<u8>, String> {
+    if let Some(path) = &options.logo_path {
+        match Bitmap::load(path, options.dots) {
+            Ok(logo) => receipt.blocks.insert(0, Block::Image(logo)),
+            Err(e) => log::warn!("Skipping logo: {}", e),
         }
     }
-    result
+    escpos::encode(&receipt, options)
+}
+
+/// Print a saved bill laid out from the database rather than from page HTML,
+/// so every reprint of a bill produces the same bytes.
+///
+/// A bill that has been printed before is stamped "DUPLICATE COPY" with its
+/// reprint number. Every print is written to `audit_log` against `user_id`
+/// (the bill's user when not given) and the printer once it has printed.
+///
+/// The printer, format and copies come from the profile for `document`:
+/// a bill the first time, a duplicate after that. `template` picks the item
+/// columns; by default rolls narrower than 64 columns get the compact
+/// receipt and wider paper the invoice with HSN and GST columns.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn print_bill(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    spool: State<'_, Spool>,
+    bill_id: i64,
+    template: Option<BillTemplate>,
+    document: Option<DocumentKind>,
+    printer_name: Option<String>,
+    user_id: Option<i64>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let new = prepare_logged_bill_job(
+        state.0.as_ref(),
+        &conn,
+        bill_id,
+        document,
+        printer_name.as_deref(),
+        template,
+        user_id,
+    )?;
+    queue(&app, &conn, &spool, &new)
+}
+
+/// `prepare_bill_job` numbered from the bill's print history. The spool
+/// writes the print to `audit_log` once it is done. Without `document` the
+/// first print uses the bill profile and reprints the duplicate profile.
+fn prepare_logged_bill_job(
+    backend: &dyn PrinterBackend,
+    conn: &Connection,
+    bill_id: i64,
+    document: Option<DocumentKind>,
+    printer: Option<&str>,
+    template: Option<BillTemplate>,
+    user_id: Option<i64>,
+) -> Result<NewJob, String> {
+    let reprint = reprint::print_count(conn, bill_id)?;
+    let document = document.unwrap_or(if reprint > 0 {
+        DocumentKind::Duplicate
+    } else {
+        DocumentKind::Bill
+    });
+    let mut new = prepare_bill_job(backend, conn, bill_id, document, printer, template, reprint)?;
+    new.audit = true;
+    new.user_id = user_id;
+    Ok(new)
+}
+
+/// Bills dated `start_date` to `end_date` (`YYYY-MM-DD`, default the current
+/// month) that were reprinted more than `more_than` times (default 1)
+#[command]
+pub fn get_reprinted_bills(
+    app: tauri::AppHandle,
+    more_than: Option<u32>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<Vec<ReprintedBill>, String> {
+    let today = chrono::Local::now().date_naive();
+    let from = start_date.unwrap_or_else(|| {
+        today
+            .with_day(1)
+            .unwrap_or(today)
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+    let to = end_date.unwrap_or_else(|| today.format("%Y-%m-%d").to_string());
+    let conn = crate::db::open(&app)?;
+    reprint::reprinted_bills(&conn, more_than.unwrap_or(1), from.trim(), to.trim())
 }
 
-/// Decode HTML entities
-fn decode_entities(text: &str) -> String {
-    text.replace("&nbsp;", " ")
-        .replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&#39;", "'")
-        .replace("&#8377;", "Rs.")
-        .replace("₹", "Rs.")
-        .replace("&times;", "x")
-        .replace("×", "x")
-        .replace("  ", " ")
+/// Save a bill as an A4 tax invoice PDF at `path`, returning the path
+#[command]
+pub async fn save_bill_pdf(
+    app: tauri::AppHandle,
+    bill_id: i64,
+    path: String,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let bill = bill::load(&conn, bill_id)?;
+    let doc = invoice::render(&bill::Shop::load(&conn), &bill)?;
+    doc.save(&format!("Invoice {}", bill.bill_number), Path::new(&path))?;
+    log::info!("Saved invoice {} to {}", bill.bill_number, path);
+    Ok(path)
 }
 
-/// Check if a default printer is configured
+/// Save a report as an A4 PDF at `path`, returning the path.
+/// `start_date` and `end_date` (`YYYY-MM-DD`) default to the current month;
+/// the expiry and credit reports are as of today.
 #[command]
-pub fn check_printer_available() -> Result<bool, String> {
-    #[cfg(windows)]
-    {
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-Command",
-                "(Get-CimInstance -Class Win32_Printer | Where-Object {$_.Default -eq $true}).Name",
-            ])
-            .output();
-
-        match output {
-            Ok(result) => {
-                let stdout = String::from_utf8_lossy(&result.stdout);
-                Ok(!stdout.trim().is_empty())
-            }
-            Err(_) => Ok(false),
+pub async fn save_report_pdf(
+    app: tauri::AppHandle,
+    report: ReportKind,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    path: String,
+) -> Result<String, String> {
+    let today = chrono::Local::now().date_naive();
+    let parse = |value: Option<String>, default: NaiveDate| match value {
+        Some(v) => NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date '{}': {}", v, e)),
+        None => Ok(default),
+    };
+    let from = parse(start_date, today.with_day(1).unwrap_or(today))?;
+    let to = parse(end_date, today)?;
+
+    let conn = crate::db::open(&app)?;
+    let doc = reports::render(&conn, &bill::Shop::load(&conn), report, from, to, today)?;
+    doc.save(report.title(), Path::new(&path))?;
+    log::info!(
+        "Saved {} ({} pages) to {}",
+        report.title(),
+        doc.page_count(),
+        path
+    );
+    Ok(path)
+}
+
+/// Lay out a saved bill into a job for its printer profile.
+/// `reprint` is 0 for the first print, else the reprint number to stamp.
+pub fn prepare_bill_job(
+    backend: &dyn PrinterBackend,
+    conn: &Connection,
+    bill_id: i64,
+    document: DocumentKind,
+    printer: Option<&str>,
+    template: Option<BillTemplate>,
+    reprint: u32,
+) -> Result<NewJob, String> {
+    let profile = profiles::resolve(Some(conn), document);
+    let printer_name = resolve_printer(backend, printer.or(profile.printer_name()))?;
+    let mut bill = bill::load(conn, bill_id)?;
+    bill.reprint = reprint;
+    let shop = bill::Shop::load(conn);
+    let title = format!("Bill {}", bill.bill_number);
+    let layout = |width: usize| bill_receipt(conn, &shop, bill_id, &bill, template, width);
+
+    let job = match profile.format {
+        PrintFormat::Escp => {
+            let options = EscpOptions::from_settings(conn);
+            PrintJob::raw(&title, escp::encode(&layout(PAGE_COLUMNS)?, &options)?)
         }
-    }
+        PrintFormat::Escpos => {
+            let options = EscposOptions::from_settings(conn, profile.roll_width_mm());
+            let receipt = layout(options.columns)?;
+            PrintJob::raw(&title, encode_escpos(receipt, &options)?)
+        }
+        PrintFormat::Text => PrintJob::text(
+            &title,
+            &paged_text(&layout(PAGE_COLUMNS)?, paginate::page_lines(conn)),
+        ),
+        PrintFormat::Pdf => {
+            return Err("PDF cannot be sent to a printer. Use ESC/P, ESC/POS or text.".to_string())
+        }
+        PrintFormat::Zpl | PrintFormat::Tspl | PrintFormat::Epl => {
+            return Err(format!(
+                "{:?} is a label printer format. Use ESC/P, ESC/POS or text for bills.",
+                profile.format
+            ))
+        }
+    };
+    log::info!("Laid out {} for {}", title, printer_name);
+
+    Ok(NewJob {
+        bill_id: Some(bill_id),
+        document,
+        printer: printer_name,
+        job,
+        copies: profile.copies,
+        audit: false,
+        user_id: None,
+    })
+}
 
-    #[cfg(not(windows))]
-    {
-        Ok(false)
-    }
+/// Print shelf or strip labels for batches on the label printer,
+/// `copies` of each (one by default)
+#[command]
+pub async fn print_batch_labels(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    spool: State<'_, Spool>,
+    batch_ids: Vec<i64>,
+    copies: Option<u32>,
+    printer_name: Option<String>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let labels = label::load_batches(&conn, &batch_ids, copies.unwrap_or(1))?;
+    let new = prepare_label_job(
+        state.0.as_ref(),
+        &conn,
+        "Batch labels",
+        &labels,
+        printer_name.as_deref(),
+    )?;
+    queue(&app, &conn, &spool, &new)
 }
 
-/// Get the name of the default printer
+/// Print labels for every item of a purchase invoice as it is received.
+/// Each item gets one label per strip received unless `labels_per_item`
+/// is given.
 #[command]
-pub fn get_default_printer() -> Result<String, String> {
-    #[cfg(windows)]
-    {
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-Command",
-                "(Get-CimInstance -Class Win32_Printer | Where-Object {$_.Default -eq $true}).Name",
-            ])
-            .output();
-
-        match output {
-            Ok(result) => {
-                let name = String::from_utf8_lossy(&result.stdout).trim().to_string();
-                if name.is_empty() {
-                    Err("No default printer".to_string())
-                } else {
-                    Ok(name)
-                }
-            }
-            Err(e) => Err(format!("Failed: {}", e)),
+pub async fn print_purchase_labels(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    spool: State<'_, Spool>,
+    purchase_id: i64,
+    labels_per_item: Option<u32>,
+    printer_name: Option<String>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let labels = label::load_purchase(&conn, purchase_id, labels_per_item)?;
+    let new = prepare_label_job(
+        state.0.as_ref(),
+        &conn,
+        &format!("Purchase {} labels", purchase_id),
+        &labels,
+        printer_name.as_deref(),
+    )?;
+    queue(&app, &conn, &spool, &new)
+}
+
+/// Encode labels for the label printer profile
+pub fn prepare_label_job(
+    backend: &dyn PrinterBackend,
+    conn: &Connection,
+    title: &str,
+    labels: &[LabelData],
+    printer: Option<&str>,
+) -> Result<NewJob, String> {
+    let profile = profiles::resolve(Some(conn), DocumentKind::Label);
+    let printer_name = resolve_printer(backend, printer.or(profile.printer_name()))?;
+    let options = LabelOptions::from_settings(conn, &profile);
+    let data = label::encode(labels, &options, profile.format)?;
+    log::info!(
+        "Encoded {} labels as {:?} for {}",
+        labels.iter().map(|l| l.copies).sum::<u32>(),
+        profile.format,
+        printer_name
+    );
+
+    Ok(NewJob {
+        bill_id: None,
+        document: DocumentKind::Label,
+        printer: printer_name,
+        job: PrintJob::raw(title, data),
+        copies: profile.copies,
+        audit: false,
+        user_id: None,
+    })
+}
+
+/// The requested printer, or the backend's default
+fn resolve_printer(
+    backend: &dyn PrinterBackend,
+    requested: Option<&str>,
+) -> Result<String, String> {
+    match requested.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(name) => Ok(name.to_string()),
+        None => {
+            let name = backend
+                .default_printer()
+                .ok_or("No default printer. Set TVS MSP 250 as default.")?;
+            log::info!("Default printer: {}", name);
+            Ok(name)
         }
     }
+}
 
-    #[cfg(not(windows))]
-    {
-        Err("Windows only".to_string())
+/// Check if the printer for `document` (a bill by default) is configured
+/// and not offline
+#[command]
+pub fn check_printer_available(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    document: Option<DocumentKind>,
+) -> Result<bool, String> {
+    let conn = crate::db::open(&app).ok();
+    let profile = profiles::resolve(conn.as_ref(), document.unwrap_or(DocumentKind::Bill));
+    let backend = state.0.as_ref();
+    Ok(resolve_printer(backend, profile.printer_name())
+        .is_ok_and(|p| backend.status(&p) != PrinterStatus::Offline))
+}
+
+/// Paper, cover and error state of `printer_name`, else of the printer for
+/// `document` (a bill by default). ESC/POS printers reached over `tcp://` or
+/// `serial:` are asked directly; others report what the spooler knows.
+#[command]
+pub async fn get_printer_status(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    document: Option<DocumentKind>,
+    printer_name: Option<String>,
+) -> Result<DeviceStatus, String> {
+    let conn = crate::db::open(&app).ok();
+    let profile = profiles::resolve(conn.as_ref(), document.unwrap_or(DocumentKind::Bill));
+    let backend = state.0.as_ref();
+    let printer = resolve_printer(backend, printer_name.as_deref().or(profile.printer_name()))?;
+    Ok(backend.device_status(&printer))
+}
+
+/// Open the cash drawer on the bill printer without a sale. The drawer
+/// must be enabled in settings; every open is written to `audit_log`
+/// against `user_id`.
+#[command]
+pub async fn open_cash_drawer(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    user_id: i64,
+    reason: Option<String>,
+    printer_name: Option<String>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let options = DrawerOptions::from_settings(&conn);
+    if !options.enabled {
+        return Err("No cash drawer is set up. Enable it in Settings > Printing.".to_string());
     }
+    let profile = profiles::resolve(Some(&conn), DocumentKind::Bill);
+    drawer::check_format(profile.format)?;
+    let backend = state.0.as_ref();
+    let printer = resolve_printer(backend, printer_name.as_deref().or(profile.printer_name()))?;
+
+    drawer::kick(backend, &printer, &options)?;
+    drawer::log_no_sale(&conn, user_id, &printer, reason.as_deref())?;
+    log::info!("Cash drawer opened without a sale by user {}", user_id);
+    Ok(printer)
+}
+
+/// Get the name of the default printer
+#[command]
+pub fn get_default_printer(state: State<'_, PrinterState>) -> Result<String, String> {
+    state
+        .0
+        .default_printer()
+        .ok_or_else(|| "No default printer".to_string())
 }
 
 /// List all available printers
 #[command]
-pub fn list_printers() -> Result<Vec<String>, String> {
-    #[cfg(windows)]
-    {
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-Command",
-                "Get-CimInstance -Class Win32_Printer | Select-Object -ExpandProperty Name",
-            ])
-            .output();
-
-        match output {
-            Ok(result) => {
-                let stdout = String::from_utf8_lossy(&result.stdout);
-                let printers: Vec<String> = stdout
-                    .lines()
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect();
-                Ok(printers)
-            }
-            Err(e) => Err(format!("Failed: {}", e)),
-        }
-    }
+pub fn list_printers(state: State<'_, PrinterState>) -> Result<Vec<String>, String> {
+    state.0.list_printers()
+}
 
-    #[cfg(not(windows))]
-    {
-        Err("Windows only".to_string())
-    }
+/// Print raw text directly to printer.
+/// When `bytes` is given (e.g. from `encode_receipt`) it is sent unchanged as a
+/// RAW job. The printer is `printer_name`, else the one in the profile for
+/// `document`, else the default printer. The job goes through the print spool.
+///
+/// The printer may also be a device that is not installed in the OS:
+/// `serial:COM3?baud=9600&parity=none&flow=xonxoff`, `serial:/dev/ttyUSB0`
+/// or `lp:/dev/usb/lp0`.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn print_raw_text(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    spool: State<'_, Spool>,
+    text: String,
+    printer_name: Option<String>,
+    bytes: Option<Vec<u8>>,
+    document: Option<DocumentKind>,
+    bill_id: Option<i64>,
+) -> Result<String, String> {
+    let conn = crate::db::open(&app)?;
+    let document = document.unwrap_or(DocumentKind::Bill);
+    let profile = profiles::resolve(Some(&conn), document);
+    let printer_name = resolve_printer(
+        state.0.as_ref(),
+        printer_name.as_deref().or(profile.printer_name()),
+    )?;
+    let job = match bytes {
+        Some(bytes) => PrintJob::raw("MedBill Raw", bytes),
+        None => PrintJob::text("MedBill Raw", &text),
+    };
+    let new = NewJob {
+        bill_id,
+        document,
+        printer: printer_name,
+        job,
+        copies: profile.copies,
+        audit: false,
+        user_id: None,
+    };
+    queue(&app, &conn, &spool, &new)
 }
 
-/// Print raw text directly to printer
+/// Printer profiles for every document kind (saved values over defaults)
 #[command]
-pub async fn print_raw_text(text: String, _printer_name: Option<String>) -> Result<String, String> {
-    #[cfg(windows)]
-    {
-        let escaped = text.replace("'", "''").replace("`", "``");
-
-        let ps = format!(
-            r#"@'
-{}
-'@ | Out-Printer"#,
-            escaped
-        );
-
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-WindowStyle",
-                "Hidden",
-                "-Command",
-                &ps,
-            ])
-            .output();
-
-        match output {
-            Ok(r) => {
-                let err = String::from_utf8_lossy(&r.stderr);
-                if err.trim().is_empty() {
-                    Ok("Sent".to_string())
-                } else {
-                    Err(err.trim().to_string())
-                }
-            }
-            Err(e) => Err(format!("{}", e)),
-        }
-    }
+pub fn get_printer_profiles(app: tauri::AppHandle) -> Result<PrinterProfiles, String> {
+    let conn = crate::db::open(&app)?;
+    Ok(profiles::load(Some(&conn)))
+}
 
-    #[cfg(not(windows))]
-    {
-        let _ = text;
-        Err("Windows only".to_string())
-    }
+#[command]
+pub fn save_printer_profiles(
+    app: tauri::AppHandle,
+    profiles: PrinterProfiles,
+) -> Result<(), String> {
+    let conn = crate::db::open(&app)?;
+    profiles::save(&conn, &profiles)
+}
+
+#[command]
+pub fn list_receipt_templates(app: tauri::AppHandle) -> Result<Vec<ReceiptTemplate>, String> {
+    let conn = crate::db::open(&app)?;
+    receipt_templates::list(&conn)
+}
+
+/// Save a receipt template (a new one without `id`). The template is
+/// checked against a sample bill first; errors give the line number.
+/// An active template is used for every bill from then on.
+#[command]
+pub fn save_receipt_template(
+    app: tauri::AppHandle,
+    id: Option<i64>,
+    name: String,
+    body: String,
+    width: usize,
+    is_active: bool,
+) -> Result<i64, String> {
+    let mut conn = crate::db::open(&app)?;
+    receipt_templates::save(&mut conn, id, &name, &body, width, is_active)
+}
+
+#[command]
+pub fn delete_receipt_template(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    let conn = crate::db::open(&app)?;
+    receipt_templates::delete(&conn, id)
+}
+
+/// A template laid out with a sample bill, as plain text
+#[command]
+pub fn preview_receipt_template(body: String, width: usize) -> Result<String, String> {
+    receipt_templates::validate(&body, width)
+}
+
+/// Spooled jobs, newest first. Filter by `status` (queued, printing, done,
+/// failed, cancelled) or `bill_id` to see a bill's print and reprint history.
+#[command]
+pub fn list_print_jobs(
+    app: tauri::AppHandle,
+    status: Option<String>,
+    bill_id: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<SpoolJob>, String> {
+    let conn = crate::db::open(&app)?;
+    spool::list(&conn, status.as_deref(), bill_id, limit.unwrap_or(100))
+}
+
+/// Cancel a queued or failed job
+#[command]
+pub fn cancel_print_job(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    let conn = crate::db::open(&app)?;
+    let event = spool::cancel(&conn, id)?;
+    let _ = app.emit(spool::EVENT, event);
+    Ok(())
+}
+
+/// Retry a failed or cancelled job, or reprint a finished one.
+/// A printed bill is laid out again rather than copied, so the reprint is
+/// stamped as a duplicate and written to `audit_log` against `user_id`.
+/// Returns the id of the queued job.
+#[command]
+pub fn resend_print_job(
+    app: tauri::AppHandle,
+    state: State<'_, PrinterState>,
+    spool: State<'_, Spool>,
+    id: i64,
+    user_id: Option<i64>,
+) -> Result<i64, String> {
+    let conn = crate::db::open(&app)?;
+    let event = match spool::printed_bill(&conn, id)? {
+        Some((bill_id, printer)) => {
+            let new = prepare_logged_bill_job(
+                state.0.as_ref(),
+                &conn,
+                bill_id,
+                None,
+                Some(&printer),
+                None,
+                user_id,
+            )?;
+            spool::enqueue_reprint(&conn, &new, id)?
+        }
+        None => spool::resend(&conn, id)?,
+    };
+    let queued_id = event.id;
+    let _ = app.emit(spool::EVENT, event);
+    spool.wake();
+    Ok(queued_id)
 }
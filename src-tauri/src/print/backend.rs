@@ -0,0 +1,389 @@
+// =====================================================
+// Printer Backends
+// One implementation per way of reaching a printer; the
+// active one is picked from settings at startup
+// =====================================================
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::device;
+use super::socket::{self, SocketBackend};
+use super::status::{self, DeviceStatus, StatusSource};
+
+/// A document ready to be sent to a printer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrintJob {
+    pub title: String,
+    /// Printer command bytes (ESC/P, ESC/POS) when true, plain UTF-8 text
+    /// for the driver to lay out when false
+    pub raw: bool,
+    pub data: Vec<u8>,
+}
+
+impl PrintJob {
+    pub fn raw(title: &str, data: Vec<u8>) -> Self {
+        PrintJob {
+            title: title.to_string(),
+            raw: true,
+            data,
+        }
+    }
+
+    pub fn text(title: &str, text: &str) -> Self {
+        PrintJob {
+            title: title.to_string(),
+            raw: false,
+            data: text.as_bytes().to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrinterStatus {
+    Ready,
+    Offline,
+    /// The backend cannot tell without sending a job
+    Unknown,
+}
+
+pub trait PrinterBackend: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &'static str;
+    fn list_printers(&self) -> Result<Vec<String>, String>;
+    fn default_printer(&self) -> Option<String>;
+    fn status(&self, printer: &str) -> PrinterStatus;
+    /// Paper, cover and error state where the backend can tell; by default
+    /// only whether the printer is offline
+    fn device_status(&self, printer: &str) -> DeviceStatus {
+        DeviceStatus::from_status(printer, self.status(printer), StatusSource::Connection)
+    }
+    /// Send a job and return the spooler's job id (or a description)
+    fn submit(&self, printer: &str, job: &PrintJob) -> Result<String, String>;
+}
+
+/// Tauri managed state holding the backend chosen at startup
+pub struct PrinterState(pub Arc<dyn PrinterBackend>);
+
+/// The operating system spooler (Windows spooler or CUPS)
+pub fn system() -> Arc<dyn PrinterBackend> {
+    #[cfg(windows)]
+    {
+        Arc::new(super::win32::WindowsBackend)
+    }
+
+    #[cfg(not(windows))]
+    {
+        Arc::new(super::cups::CupsBackend)
+    }
+}
+
+/// Pick the backend from the `printer_backend` setting:
+/// `system` (default), `socket`, `file` or `mock`.
+/// Whichever is chosen, `tcp://`, `serial:` and `lp:` printers are reachable too.
+pub fn from_settings(app: &tauri::AppHandle) -> Arc<dyn PrinterBackend> {
+    Arc::new(DirectRouting(configured(app)))
+}
+
+fn configured(app: &tauri::AppHandle) -> Arc<dyn PrinterBackend> {
+    use tauri::Manager;
+
+    let conn = crate::db::open(app).ok();
+    let setting = |key: &str| {
+        conn.as_ref()
+            .and_then(|c| crate::db::get_setting(c, key))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    match setting("printer_backend").as_deref() {
+        Some("socket") => match setting("printer_address") {
+            Some(address) => Arc::new(SocketBackend::new(&address)),
+            None => {
+                log::warn!("printer_backend is socket but printer_address is empty");
+                system()
+            }
+        },
+        Some("file") => {
+            let dir = setting("printer_output_dir")
+                .map(PathBuf::from)
+                .or_else(|| {
+                    app.path()
+                        .app_data_dir()
+                        .ok()
+                        .map(|d| d.join("print-output"))
+                });
+            match dir {
+                Some(dir) => Arc::new(FileBackend::new(dir)),
+                None => system(),
+            }
+        }
+        Some("mock") => Arc::new(MockBackend::default()),
+        Some("system") | None => system(),
+        Some(other) => {
+            log::warn!("Unknown printer_backend '{}', using system", other);
+            system()
+        }
+    }
+}
+
+// =====================================================
+// Direct targets (network and attached devices)
+// =====================================================
+
+/// Wraps another backend so printer names like `tcp://10.0.0.5:9100`,
+/// `serial:COM3?baud=9600` or `lp:/dev/usb/lp0` (e.g. in a printer profile)
+/// bypass the spooler and go straight to the printer
+pub struct DirectRouting(pub Arc<dyn PrinterBackend>);
+
+impl PrinterBackend for DirectRouting {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn list_printers(&self) -> Result<Vec<String>, String> {
+        self.0.list_printers()
+    }
+
+    fn default_printer(&self) -> Option<String> {
+        self.0.default_printer()
+    }
+
+    fn status(&self, printer: &str) -> PrinterStatus {
+        if printer.starts_with(socket::SCHEME) {
+            SocketBackend::new(printer).status(printer)
+        } else if device::is_device(printer) {
+            device::status(printer)
+        } else {
+            self.0.status(printer)
+        }
+    }
+
+    fn device_status(&self, printer: &str) -> DeviceStatus {
+        if printer.starts_with(socket::SCHEME) || device::is_device(printer) {
+            status::direct(printer)
+        } else {
+            self.0.device_status(printer)
+        }
+    }
+
+    fn submit(&self, printer: &str, job: &PrintJob) -> Result<String, String> {
+        if printer.starts_with(socket::SCHEME) {
+            SocketBackend::new(printer).submit(printer, job)
+        } else if device::is_device(printer) {
+            device::submit(printer, job)
+        } else {
+            self.0.submit(printer, job)
+        }
+    }
+}
+
+// =====================================================
+// File output (one file per job)
+// =====================================================
+
+/// Writes each job to a file in `dir` instead of printing it
+pub struct FileBackend {
+    dir: PathBuf,
+}
+
+impl FileBackend {
+    pub const PRINTER_NAME: &'static str = "File";
+
+    pub fn new(dir: PathBuf) -> Self {
+        FileBackend { dir }
+    }
+}
+
+impl PrinterBackend for FileBackend {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn list_printers(&self) -> Result<Vec<String>, String> {
+        Ok(vec![Self::PRINTER_NAME.to_string()])
+    }
+
+    fn default_printer(&self) -> Option<String> {
+        Some(Self::PRINTER_NAME.to_string())
+    }
+
+    fn status(&self, _printer: &str) -> PrinterStatus {
+        if std::fs::create_dir_all(&self.dir).is_ok() {
+            PrinterStatus::Ready
+        } else {
+            PrinterStatus::Offline
+        }
+    }
+
+    fn submit(&self, _printer: &str, job: &PrintJob) -> Result<String, String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+        let title: String = job
+            .title
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let file_name = format!(
+            "{}-{}.{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S%3f"),
+            title,
+            if job.raw { "prn" } else { "txt" }
+        );
+        let path = self.dir.join(file_name);
+
+        std::fs::write(&path, &job.data)
+            .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        Ok(path.display().to_string())
+    }
+}
+
+// =====================================================
+// Mock (records jobs, prints nothing)
+// =====================================================
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedJob {
+    pub printer: String,
+    pub job: PrintJob,
+}
+
+/// In-memory backend that records every submitted job
+pub struct MockBackend {
+    printers: Vec<String>,
+    jobs: Mutex<Vec<RecordedJob>>,
+    /// Jobs accepted before every printer goes offline
+    offline_after: Mutex<Option<usize>>,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        MockBackend::new(vec!["Mock Printer".to_string()])
+    }
+}
+
+impl MockBackend {
+    /// The first printer is reported as the default
+    pub fn new(printers: Vec<String>) -> Self {
+        MockBackend {
+            printers,
+            jobs: Mutex::new(Vec::new()),
+            offline_after: Mutex::new(None),
+        }
+    }
+
+    /// Fail every job once `jobs` have been recorded in total; `None` puts
+    /// the printers back online
+    pub fn offline_after(&self, jobs: Option<usize>) {
+        if let Ok(mut limit) = self.offline_after.lock() {
+            *limit = jobs;
+        }
+    }
+
+    /// Jobs submitted so far, oldest first
+    pub fn jobs(&self) -> Vec<RecordedJob> {
+        self.jobs.lock().map(|j| j.clone()).unwrap_or_default()
+    }
+}
+
+impl PrinterBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn list_printers(&self) -> Result<Vec<String>, String> {
+        Ok(self.printers.clone())
+    }
+
+    fn default_printer(&self) -> Option<String> {
+        self.printers.first().cloned()
+    }
+
+    fn status(&self, printer: &str) -> PrinterStatus {
+        if self.printers.iter().any(|p| p == printer) {
+            PrinterStatus::Ready
+        } else {
+            PrinterStatus::Offline
+        }
+    }
+
+    fn submit(&self, printer: &str, job: &PrintJob) -> Result<String, String> {
+        if !self.printers.iter().any(|p| p == printer) {
+            return Err(format!("Unknown printer: {}", printer));
+        }
+        let mut jobs = self
+            .jobs
+            .lock()
+            .map_err(|_| "Mock printer lock poisoned".to_string())?;
+        let limit = self.offline_after.lock().ok().and_then(|limit| *limit);
+        if limit.is_some_and(|limit| jobs.len() >= limit) {
+            return Err(format!("{} is offline", printer));
+        }
+        jobs.push(RecordedJob {
+            printer: printer.to_string(),
+            job: job.clone(),
+        });
+        log::info!(
+            "Mock printer recorded job {} ({} bytes)",
+            jobs.len(),
+            job.data.len()
+        );
+        Ok(format!("mock-{}", jobs.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::fixtures;
+    use crate::print::profiles::DocumentKind;
+    use crate::print::spool;
+
+    /// Lay out bill 1 for `document`, spool it and print it on `backend`
+    fn print_bill(conn: &rusqlite::Connection, backend: &MockBackend, document: DocumentKind) {
+        let reprint = (document == DocumentKind::Duplicate) as u32;
+        let new = crate::print::prepare_bill_job(backend, conn, 1, document, None, None, reprint)
+            .unwrap();
+        spool::enqueue(conn, &new).unwrap();
+        assert!(spool::process_next(conn, backend, &|_| {}).unwrap());
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn bills_reach_the_printer_as_escpos_and_escp() {
+        let conn = fixtures::database();
+        fixtures::seed(&conn);
+        let backend = MockBackend::default();
+
+        // Thermal is the default printer type
+        print_bill(&conn, &backend, DocumentKind::Bill);
+        let jobs = backend.jobs();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].printer, "Mock Printer");
+        assert_eq!(jobs[0].job.title, "Bill INV-2425-00001");
+        assert!(jobs[0].job.raw);
+        let data = &jobs[0].job.data;
+        assert!(data.starts_with(b"\x1b@\x1bt\x00\x1ba\x01\x1bE\x01"));
+        assert!(contains(data, b"Test Medical Store\n"));
+        assert!(contains(data, b"INV-2425-00001"));
+        assert!(contains(data, b"Paracetamol 500mg"));
+        assert!(contains(data, b"952.00"));
+        assert!(data.ends_with(b"\x1dVB\x03"));
+
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES ('printer_type', 'dotmatrix')",
+            [],
+        )
+        .unwrap();
+        print_bill(&conn, &backend, DocumentKind::Duplicate);
+        let data = &backend.jobs()[1].job.data;
+        assert!(data.starts_with(b"\x1b@\x1bl\x00"));
+        assert!(contains(data, b"DUPLICATE COPY"));
+        assert!(contains(data, b"Azithromycin 500mg Tablets IP"));
+        assert!(!contains(data, b"\x1dV"));
+    }
+}
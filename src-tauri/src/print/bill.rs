@@ -0,0 +1,807 @@
+// =====================================================
+// Bill Layout
+// Lays out a saved bill straight from medbill.db, so a
+// reprint gives the same receipt whichever page asks
+// =====================================================
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::receipt::{align_to, wrap_words, Align, Receipt, Style};
+use super::upi;
+
+/// Receipts at least this wide get the invoice columns by default
+const INVOICE_MIN_WIDTH: usize = 64;
+
+/// Column set for the item table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BillTemplate {
+    /// Item, quantity and amount; fits a 58mm roll
+    Receipt,
+    /// Adds HSN, GST rate and rate columns and the "TAX INVOICE" title
+    Invoice,
+}
+
+impl BillTemplate {
+    pub fn for_width(width: usize) -> Self {
+        if width >= INVOICE_MIN_WIDTH {
+            BillTemplate::Invoice
+        } else {
+            BillTemplate::Receipt
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Shop {
+    pub name: String,
+    pub address: String,
+    pub phone: String,
+    pub gstin: String,
+    pub drug_license: String,
+    pub state: String,
+    /// VPA for the payment QR code on online bills
+    pub upi_id: String,
+}
+
+impl Shop {
+    pub fn load(conn: &Connection) -> Self {
+        let get = |key: &str| {
+            crate::db::get_setting(conn, key)
+                .map(|v| v.trim().to_string())
+                .unwrap_or_default()
+        };
+        let name = get("shop_name");
+        Shop {
+            name: if name.is_empty() {
+                "Medical Store".to_string()
+            } else {
+                name
+            },
+            address: get("shop_address"),
+            phone: get("shop_phone"),
+            gstin: get("shop_gstin"),
+            drug_license: get("shop_drug_license"),
+            state: get("shop_state"),
+            upi_id: get("shop_upi_id"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BillItem {
+    pub medicine_name: String,
+    pub hsn_code: String,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub rack: Option<String>,
+    pub box_label: Option<String>,
+    pub quantity: i64,
+    pub tablets_per_strip: i64,
+    pub unit_price: f64,
+    pub discount_amount: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+    pub cgst: f64,
+    pub sgst: f64,
+    pub total: f64,
+    /// Schedule H/H1 drug, sold against a prescription
+    pub scheduled: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Bill {
+    pub bill_number: String,
+    pub bill_date: String,
+    pub customer_name: Option<String>,
+    pub doctor_name: Option<String>,
+    pub subtotal: f64,
+    pub discount_amount: f64,
+    pub total_cgst: f64,
+    pub total_sgst: f64,
+    pub round_off: f64,
+    pub grand_total: f64,
+    pub payment_mode: String,
+    pub cash_amount: f64,
+    pub online_amount: f64,
+    pub credit_amount: f64,
+    pub status: String,
+    pub items: Vec<BillItem>,
+    /// 0 for the first print; later prints are stamped as a duplicate copy
+    /// with this reprint number
+    pub reprint: u32,
+}
+
+/// Load a bill and its items. Expiry, rack and box come from the batch
+/// each item was sold from.
+pub fn load(conn: &Connection, bill_id: i64) -> Result<Bill, String> {
+    let bill = conn
+        .query_row(
+            "SELECT bill_number, bill_date, customer_name, doctor_name,
+                    COALESCE(subtotal, 0), COALESCE(discount_amount, 0),
+                    COALESCE(cgst_amount, 0), COALESCE(sgst_amount, 0),
+                    COALESCE(round_off, 0), COALESCE(grand_total, 0), payment_mode,
+                    COALESCE(cash_amount, 0), COALESCE(online_amount, 0),
+                    COALESCE(credit_amount, 0),
+                    CASE WHEN is_cancelled = 1 THEN 'CANCELLED' ELSE 'COMPLETED' END
+             FROM bills WHERE id = ?1",
+            params![bill_id],
+            |row| {
+                Ok(Bill {
+                    bill_number: row.get(0)?,
+                    bill_date: row.get(1)?,
+                    customer_name: row.get(2)?,
+                    doctor_name: row.get(3)?,
+                    subtotal: row.get(4)?,
+                    discount_amount: row.get(5)?,
+                    total_cgst: row.get(6)?,
+                    total_sgst: row.get(7)?,
+                    round_off: row.get(8)?,
+                    grand_total: row.get(9)?,
+                    payment_mode: row.get(10)?,
+                    cash_amount: row.get(11)?,
+                    online_amount: row.get(12)?,
+                    credit_amount: row.get(13)?,
+                    status: row.get(14)?,
+                    items: Vec::new(),
+                    reprint: 0,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load bill: {}", e))?;
+    let mut bill = bill.ok_or_else(|| format!("Bill {} not found", bill_id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT bi.medicine_name, bi.hsn_code, bi.batch_number,
+                    COALESCE(bt.expiry_date, ''), bt.rack, bt.box,
+                    bi.quantity, COALESCE(bi.tablets_per_strip, bt.tablets_per_strip, 10),
+                    bi.selling_price, COALESCE(bi.discount_amount, 0), bi.taxable_amount,
+                    bi.gst_rate, bi.cgst_amount, bi.sgst_amount, bi.total_amount,
+                    COALESCE(bt.is_schedule, 0)
+             FROM bill_items bi
+             LEFT JOIN batches bt ON bt.id = bi.batch_id
+             WHERE bi.bill_id = ?1
+             ORDER BY bi.id",
+        )
+        .map_err(|e| format!("Failed to load bill items: {}", e))?;
+    bill.items = stmt
+        .query_map(params![bill_id], |row| {
+            Ok(BillItem {
+                medicine_name: row.get(0)?,
+                hsn_code: row.get(1)?,
+                batch_number: row.get(2)?,
+                expiry_date: row.get(3)?,
+                rack: row.get(4)?,
+                box_label: row.get(5)?,
+                quantity: row.get(6)?,
+                tablets_per_strip: row.get(7)?,
+                unit_price: row.get(8)?,
+                discount_amount: row.get(9)?,
+                taxable_value: row.get(10)?,
+                gst_rate: row.get(11)?,
+                cgst: row.get(12)?,
+                sgst: row.get(13)?,
+                total: row.get(14)?,
+                scheduled: row.get::<_, i64>(15)? != 0,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load bill items: {}", e))?;
+
+    Ok(bill)
+}
+
+/// Taxable value and tax for one HSN code and GST rate
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GstLine {
+    pub hsn_code: String,
+    pub gst_rate: f64,
+    pub taxable: f64,
+    pub cgst: f64,
+    pub sgst: f64,
+}
+
+/// HSN-wise GST breakup, ordered by HSN code then rate
+pub fn gst_breakup(items: &[BillItem]) -> Vec<GstLine> {
+    let mut lines: BTreeMap<(String, i64), GstLine> = BTreeMap::new();
+    for item in items {
+        // Rates such as 2.5% are keyed in hundredths so they sort numerically
+        let key = (
+            item.hsn_code.clone(),
+            (item.gst_rate * 100.0).round() as i64,
+        );
+        let line = lines.entry(key).or_insert_with(|| GstLine {
+            hsn_code: item.hsn_code.clone(),
+            gst_rate: item.gst_rate,
+            ..GstLine::default()
+        });
+        line.taxable += item.taxable_value;
+        line.cgst += item.cgst;
+        line.sgst += item.sgst;
+    }
+    lines.into_values().collect()
+}
+
+/// Lay out `bill` for a receipt `width` characters wide
+pub fn layout(shop: &Shop, bill: &Bill, template: BillTemplate, width: usize) -> Receipt {
+    let mut r = Receipt::new(width);
+    let center = Style {
+        align: Align::Center,
+        ..Style::default()
+    };
+
+    r.text(
+        shop.name.as_str(),
+        Style {
+            bold: true,
+            double_width: true,
+            align: Align::Center,
+            ..Style::default()
+        },
+    );
+    for line in wrap_words(&shop.address, width) {
+        if !line.is_empty() {
+            r.text(line, center);
+        }
+    }
+    for (label, value) in [
+        ("Ph: ", &shop.phone),
+        ("GSTIN: ", &shop.gstin),
+        ("D.L: ", &shop.drug_license),
+    ] {
+        if !value.is_empty() {
+            r.text(format!("{}{}", label, value), center);
+        }
+    }
+    if template == BillTemplate::Invoice {
+        r.text(
+            "TAX INVOICE",
+            Style {
+                bold: true,
+                align: Align::Center,
+                ..Style::default()
+            },
+        );
+    }
+    r.rule('=');
+
+    left_right(
+        &mut r,
+        &format!("Bill: {}", bill.bill_number),
+        &format_date_time(&bill.bill_date),
+        Style::default(),
+    );
+    let customer = bill
+        .customer_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .unwrap_or("Walk-in Customer");
+    left_right(&mut r, "Customer:", customer, Style::default());
+    if let Some(doctor) = bill.doctor_name.as_deref().filter(|d| !d.trim().is_empty()) {
+        left_right(&mut r, "Doctor:", doctor.trim(), Style::default());
+    }
+    if bill.status == "CANCELLED" {
+        r.text(
+            "*** CANCELLED ***",
+            Style {
+                bold: true,
+                align: Align::Center,
+                ..Style::default()
+            },
+        );
+    }
+    if bill.reprint > 0 {
+        r.text(
+            "*** DUPLICATE COPY ***",
+            Style {
+                bold: true,
+                align: Align::Center,
+                ..Style::default()
+            },
+        );
+        r.text(format!("Reprint #{}", bill.reprint), center);
+    }
+    r.rule('-');
+
+    items(&mut r, bill, template, width);
+    r.rule('-');
+    gst_summary(&mut r, bill, template, width);
+    r.rule('-');
+    totals(&mut r, bill);
+
+    r.text(payment_line(bill), center);
+    if let (Some(uri), Some(amount)) = (upi::for_bill(shop, bill), upi::amount_due(bill)) {
+        r.feed(1);
+        r.text(format!("Scan to pay {} by UPI", money(amount)), center);
+        r.qr(uri);
+        r.text(format!("UPI: {}", shop.upi_id), center);
+    }
+    r.feed(1);
+    r.text("Thank you!", center);
+    r.text("*** Get Well Soon ***", center);
+    r.feed(1);
+    r.text("Computer generated bill", center);
+    r
+}
+
+/// Trailing columns of the item table: heading, width and values per item
+fn item_columns(template: BillTemplate) -> Vec<(&'static str, usize)> {
+    match template {
+        BillTemplate::Receipt => vec![("Qty", 6), ("Amt", 8)],
+        BillTemplate::Invoice => vec![
+            ("HSN", 8),
+            ("GST", 4),
+            ("Qty", 8),
+            ("Rate", 9),
+            ("Amount", 10),
+        ],
+    }
+}
+
+fn items(r: &mut Receipt, bill: &Bill, template: BillTemplate, width: usize) {
+    let columns = item_columns(template);
+    let tail: usize = columns.iter().map(|(_, w)| w + 1).sum();
+    let name_width = width.saturating_sub(tail).max(8);
+    let item_style = Style {
+        condensed: true,
+        ..Style::default()
+    };
+
+    let mut heading = pad_right("Item", name_width);
+    for (title, w) in &columns {
+        heading.push(' ');
+        heading.push_str(&align_to(title, *w, Align::Right));
+    }
+    r.text(
+        heading,
+        Style {
+            bold: true,
+            condensed: true,
+            ..Style::default()
+        },
+    );
+    r.rule('-');
+
+    for (i, item) in bill.items.iter().enumerate() {
+        let values: Vec<String> = match template {
+            BillTemplate::Receipt => vec![qty_display(item), money(item.total)],
+            BillTemplate::Invoice => vec![
+                item.hsn_code.clone(),
+                format!("{}%", rate(item.gst_rate)),
+                qty_display(item),
+                money(item.unit_price),
+                money(item.total),
+            ],
+        };
+
+        let number = format!("{}. ", i + 1);
+        let indent = number.chars().count();
+        let name = wrap_words(
+            &item.medicine_name,
+            name_width.saturating_sub(indent).max(1),
+        );
+        let last = name.len() - 1;
+        for (n, part) in name.iter().enumerate() {
+            let prefix = if n == 0 {
+                number.clone()
+            } else {
+                " ".repeat(indent)
+            };
+            let mut line = pad_right(&format!("{}{}", prefix, part), name_width);
+            if n == last {
+                for ((_, w), value) in columns.iter().zip(&values) {
+                    line.push(' ');
+                    line.push_str(&align_to(value, *w, Align::Right));
+                }
+            }
+            r.text(line.trim_end().to_string(), item_style);
+        }
+
+        let mut details = vec![
+            format!("Batch {}", item.batch_number),
+            format!("Exp {}", format_expiry(&item.expiry_date)),
+        ];
+        if let Some(location) = location(item) {
+            details.push(location);
+        }
+        if template == BillTemplate::Receipt {
+            details.push(format!("@{}", money(item.unit_price)));
+        }
+        if item.discount_amount > 0.0 {
+            details.push(format!("Disc {}", money(item.discount_amount)));
+        }
+        for line in pack(&details, width, indent) {
+            r.text(line, item_style);
+        }
+    }
+}
+
+/// HSN-wise taxable value and tax
+fn gst_summary(r: &mut Receipt, bill: &Bill, template: BillTemplate, width: usize) {
+    let breakup = gst_breakup(&bill.items);
+    let style = Style {
+        condensed: true,
+        ..Style::default()
+    };
+
+    let wide = template == BillTemplate::Invoice && width >= 48;
+    let widths: &[usize] = if wide {
+        &[10, 6, 12, 10, 10]
+    } else {
+        &[8, 4, 10, 7]
+    };
+    let titles: &[&str] = if wide {
+        &["HSN", "GST%", "Taxable", "CGST", "SGST"]
+    } else {
+        &["HSN", "GST%", "Taxable", "Tax"]
+    };
+
+    r.text(
+        table_row(titles, widths),
+        Style {
+            bold: true,
+            condensed: true,
+            ..Style::default()
+        },
+    );
+    for line in &breakup {
+        let mut cells = vec![
+            line.hsn_code.clone(),
+            rate(line.gst_rate),
+            money(line.taxable),
+        ];
+        if wide {
+            cells.push(money(line.cgst));
+            cells.push(money(line.sgst));
+        } else {
+            cells.push(money(line.cgst + line.sgst));
+        }
+        let cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+        r.text(table_row(&cells, widths), style);
+    }
+}
+
+fn totals(r: &mut Receipt, bill: &Bill) {
+    let mut row = |label: &str, value: String| left_right(r, label, &value, Style::default());
+    row(
+        &format!("Sub Total ({} items):", bill.items.len()),
+        money(bill.subtotal),
+    );
+    if bill.discount_amount > 0.0 {
+        row("Discount:", format!("-{}", money(bill.discount_amount)));
+    }
+    row("CGST:", money(bill.total_cgst));
+    row("SGST:", money(bill.total_sgst));
+    if bill.round_off != 0.0 {
+        row("Round Off:", money(bill.round_off));
+    }
+
+    r.rule('=');
+    left_right(
+        r,
+        "TOTAL:",
+        &format!("Rs.{}", money(bill.grand_total)),
+        Style {
+            bold: true,
+            double_height: true,
+            ..Style::default()
+        },
+    );
+    r.rule('=');
+}
+
+pub fn payment_line(bill: &Bill) -> String {
+    let mode = bill.payment_mode.to_uppercase();
+    match mode.as_str() {
+        "CASH" if bill.cash_amount > 0.0 => format!("CASH | Cash: {}", money(bill.cash_amount)),
+        "SPLIT" => [
+            ("Cash", bill.cash_amount),
+            ("Online", bill.online_amount),
+            ("Credit", bill.credit_amount),
+        ]
+        .iter()
+        .filter(|(_, amount)| *amount > 0.0)
+        .map(|(label, amount)| format!("{}:{}", label, money(*amount)))
+        .collect::<Vec<_>>()
+        .join(" | "),
+        _ => mode,
+    }
+}
+
+/// `Rack A1/2`, `Rack A1` or `Box 2`
+pub fn location(item: &BillItem) -> Option<String> {
+    shelf(item.rack.as_deref(), item.box_label.as_deref())
+}
+
+/// Where a batch is kept, from its rack and box
+pub fn shelf(rack: Option<&str>, box_label: Option<&str>) -> Option<String> {
+    let rack = rack.map(str::trim).filter(|s| !s.is_empty());
+    let box_label = box_label.map(str::trim).filter(|s| !s.is_empty());
+    match (rack, box_label) {
+        (Some(rack), Some(b)) => Some(format!("Rack {}/{}", rack, b)),
+        (Some(rack), None) => Some(format!("Rack {}", rack)),
+        (None, Some(b)) => Some(format!("Box {}", b)),
+        (None, None) => None,
+    }
+}
+
+/// Strips and loose pieces, as the billing screen shows them (`1S + 5P`)
+pub fn qty_display(item: &BillItem) -> String {
+    let per_strip = item.tablets_per_strip.max(1);
+    let strips = item.quantity / per_strip;
+    let pieces = item.quantity % per_strip;
+    match (strips, pieces) {
+        (0, p) => format!("{}P", p),
+        (s, 0) => format!("{}S", s),
+        (s, p) => format!("{}S+{}P", s, p),
+    }
+}
+
+fn money(amount: f64) -> String {
+    format!("{:.2}", amount)
+}
+
+/// `12` for whole rates, `2.5` otherwise
+pub fn rate(rate: f64) -> String {
+    if rate.fract() == 0.0 {
+        format!("{}", rate as i64)
+    } else {
+        format!("{}", rate)
+    }
+}
+
+/// `dd/mm/yy HH:MM` for the stored `YYYY-MM-DD HH:MM:SS` or ISO timestamp
+pub fn format_date_time(value: &str) -> String {
+    let value = value.trim();
+    [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|f| NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), f).ok())
+    .map(|d| d.format("%d/%m/%y %H:%M").to_string())
+    .unwrap_or_else(|| value.to_string())
+}
+
+/// `MM/YY`, the way strips are printed
+pub fn format_expiry(value: &str) -> String {
+    let value = value.trim();
+    let date = value.get(..10).unwrap_or(value);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.format("%m/%y").to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+fn pad_right(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(len)))
+}
+
+/// `left` and `right` at the edges of the line. When they do not fit
+/// together `right` moves to a line of its own.
+fn left_right(r: &mut Receipt, left: &str, right: &str, style: Style) {
+    let used = left.chars().count() + right.chars().count();
+    if used < r.width {
+        let gap = " ".repeat(r.width - used);
+        r.text(format!("{}{}{}", left, gap, right), style);
+    } else {
+        r.text(left, style);
+        r.text(
+            right,
+            Style {
+                align: Align::Right,
+                ..style
+            },
+        );
+    }
+}
+
+/// First column left aligned, the rest right aligned
+fn table_row(cells: &[&str], widths: &[usize]) -> String {
+    cells
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(i, (cell, w))| {
+            if i == 0 {
+                pad_right(cell, *w)
+            } else {
+                align_to(cell, *w, Align::Right)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end()
+        .to_string()
+}
+
+/// Fill lines with `parts` two spaces apart, never splitting a part
+fn pack(parts: &[String], width: usize, indent: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = " ".repeat(indent);
+    for part in parts {
+        let len = line.chars().count();
+        if len > indent && len + 2 + part.chars().count() > width {
+            lines.push(std::mem::replace(&mut line, " ".repeat(indent)));
+        }
+        if line.chars().count() > indent {
+            line.push_str("  ");
+        }
+        line.push_str(part);
+    }
+    if line.chars().count() > indent {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::receipt::Block;
+
+    fn sample_db() -> Connection {
+        let conn = crate::print::fixtures::database();
+        crate::print::fixtures::seed(&conn);
+        conn
+    }
+
+    fn text_lines(receipt: &Receipt) -> Vec<String> {
+        receipt.to_text().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn loads_items_with_batch_location() {
+        let conn = sample_db();
+        let bill = load(&conn, 1).unwrap();
+        assert_eq!(bill.items.len(), 3);
+        assert_eq!(bill.items[0].rack.as_deref(), Some("A1"));
+        assert_eq!(bill.items[1].rack.as_deref(), Some("C3"));
+        assert_eq!(bill.items[1].box_label.as_deref(), Some("2"));
+        assert_eq!(bill.items[1].tablets_per_strip, 10);
+        assert_eq!(bill.items[2].expiry_date, "2027-01-31");
+        assert!(bill.items[1].scheduled && !bill.items[0].scheduled);
+        assert_eq!(bill.status, "COMPLETED");
+        assert!(load(&conn, 2).is_err());
+    }
+
+    #[test]
+    fn gst_breakup_groups_by_hsn_and_rate() {
+        let bill = load(&sample_db(), 1).unwrap();
+        let breakup = gst_breakup(&bill.items);
+        assert_eq!(breakup.len(), 2);
+        assert_eq!(breakup[0].gst_rate, 5.0);
+        assert_eq!(breakup[1].gst_rate, 12.0);
+        assert_eq!(money(breakup[1].taxable), "850.00");
+        assert_eq!(money(breakup[1].cgst), "51.00");
+    }
+
+    #[test]
+    fn receipt_layout_fits_the_roll() {
+        let conn = sample_db();
+        let bill = load(&conn, 1).unwrap();
+        let receipt = layout(&Shop::load(&conn), &bill, BillTemplate::for_width(32), 32);
+        let lines = text_lines(&receipt);
+        assert!(
+            lines.iter().all(|l| l.chars().count() <= 32),
+            "{:#?}",
+            lines
+        );
+        assert!(lines.contains(&"   500mg             2S   476.00".to_string()));
+        assert!(lines.contains(&"   Batch BT2024001  Exp 06/27".to_string()));
+        assert!(lines.contains(&"   Rack A1/1  @25.00  Disc 25.00".to_string()));
+        assert!(lines.contains(&"3004        5      95.24    4.76".to_string()));
+    }
+
+    #[test]
+    fn invoice_layout_has_hsn_columns() {
+        let conn = sample_db();
+        let bill = load(&conn, 1).unwrap();
+        let receipt = layout(&Shop::load(&conn), &bill, BillTemplate::for_width(80), 80);
+        let lines = text_lines(&receipt);
+        assert!(
+            lines.iter().all(|l| l.chars().count() <= 80),
+            "{:#?}",
+            lines
+        );
+        assert!(lines.iter().any(|l| l.trim() == "TAX INVOICE"));
+        assert!(lines.contains(
+            &"2. Azithromycin 500mg Tablets IP         3004  12%    1S+5P     35.00     476.00"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn online_bill_gets_a_upi_qr_after_the_payment_line() {
+        let conn = sample_db();
+        conn.execute_batch(
+            "UPDATE bills SET payment_mode = 'SPLIT', cash_amount = 452, online_amount = 500;
+             INSERT INTO settings (key, value) VALUES ('shop_upi_id', 'testmedical@okaxis');",
+        )
+        .unwrap();
+        let bill = load(&conn, 1).unwrap();
+        let receipt = layout(&Shop::load(&conn), &bill, BillTemplate::Receipt, 32);
+
+        let qr = receipt
+            .blocks
+            .iter()
+            .position(|b| matches!(b, Block::Qr(_)))
+            .unwrap();
+        assert_eq!(
+            receipt.blocks[qr],
+            Block::Qr(
+                "upi://pay?pa=testmedical@okaxis&pn=Test%20Medical%20Store&am=500.00\
+                 &cu=INR&tn=Bill%20INV-2425-00001"
+                    .to_string()
+            )
+        );
+        let lines = text_lines(&receipt);
+        assert!(lines.contains(&"   Scan to pay 500.00 by UPI".to_string()));
+        assert!(lines.contains(&"    UPI: testmedical@okaxis".to_string()));
+    }
+
+    #[test]
+    fn reprints_are_stamped_as_duplicates() {
+        let conn = sample_db();
+        let mut bill = load(&conn, 1).unwrap();
+        let original = text_lines(&layout(
+            &Shop::load(&conn),
+            &bill,
+            BillTemplate::Receipt,
+            32,
+        ));
+        assert!(!original.iter().any(|l| l.contains("DUPLICATE")));
+
+        bill.reprint = 2;
+        let lines = text_lines(&layout(
+            &Shop::load(&conn),
+            &bill,
+            BillTemplate::Receipt,
+            32,
+        ));
+        assert!(
+            lines.contains(&"     *** DUPLICATE COPY ***".to_string()),
+            "{:#?}",
+            lines
+        );
+        assert!(lines.contains(&"           Reprint #2".to_string()));
+    }
+
+    #[test]
+    fn long_bills_carry_item_totals_across_pages() {
+        use crate::print::paginate::{paginate, text_lines as lines_of};
+
+        let conn = sample_db();
+        let mut bill = load(&conn, 1).unwrap();
+        bill.items = (0..8).flat_map(|_| bill.items.clone()).collect();
+        let receipt = layout(&Shop::load(&conn), &bill, BillTemplate::Receipt, 42);
+        let text = paginate(&receipt, 36, lines_of).to_text();
+        let pages: Vec<&str> = text.split('\x0C').collect();
+        assert!(pages.len() > 2, "{}", text);
+
+        let mut carried = 0.0;
+        for page in &pages {
+            assert!(page.lines().count() <= 36, "{}", page);
+            assert!(page.contains("Bill: INV-2425-00001"));
+            // Wrapped names stay with their amount and batch lines
+            let numbers: Vec<usize> = page
+                .lines()
+                .filter_map(|l| l.split_once(". ")?.0.parse().ok())
+                .collect();
+            carried += numbers.iter().map(|n| bill.items[n - 1].total).sum::<f64>();
+            if let Some(line) = page.lines().find(|l| l.starts_with("Carried forward:")) {
+                assert!(line.ends_with(&format!(" {}", money(carried))), "{}", page);
+            }
+        }
+        assert!(pages.last().unwrap().contains("Sub Total (24 items):"));
+    }
+}
@@ -0,0 +1,113 @@
+// =====================================================
+// Monochrome Bitmaps for Printer Graphics
+// =====================================================
+
+use std::path::Path;
+
+use qrcode::{Color, EcLevel, QrCode};
+
+/// Blank modules around a QR symbol that scanners need to find it
+pub const QUIET_ZONE: usize = 4;
+
+/// A 1-bit image; `true` pixels are printed (black)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Bitmap {
+            width,
+            height,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, black: bool) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = black;
+        }
+    }
+
+    /// Load a PNG/JPEG and convert it to black and white, scaling it down
+    /// to at most `max_width` dots. Transparent areas print as paper.
+    pub fn load(path: &Path, max_width: usize) -> Result<Bitmap, String> {
+        let img =
+            image::open(path).map_err(|e| format!("Failed to load image {:?}: {}", path, e))?;
+
+        let img = if img.width() as usize > max_width {
+            let height = (img.height() as u64 * max_width as u64 / img.width() as u64).max(1);
+            img.resize_exact(
+                max_width as u32,
+                height as u32,
+                image::imageops::FilterType::Triangle,
+            )
+        } else {
+            img
+        };
+
+        let rgba = img.to_rgba8();
+        let mut bitmap = Bitmap::new(rgba.width() as usize, rgba.height() as usize);
+        for (x, y, pixel) in rgba.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+            // Composite over white paper
+            let value = (luma * a as u32 + 255 * (255 - a as u32)) / 255;
+            bitmap.set(x as usize, y as usize, value < 128);
+        }
+        Ok(bitmap)
+    }
+
+    /// A QR symbol for `data` with one pixel per module, quiet zone included
+    pub fn qr(data: &str) -> Result<Bitmap, String> {
+        let code = QrCode::with_error_correction_level(data, EcLevel::M)
+            .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+        let size = code.width();
+        let mut bitmap = Bitmap::new(size + 2 * QUIET_ZONE, size + 2 * QUIET_ZONE);
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            bitmap.set(
+                QUIET_ZONE + i % size,
+                QUIET_ZONE + i / size,
+                color == Color::Dark,
+            );
+        }
+        Ok(bitmap)
+    }
+
+    /// Enlarge by whole dots, each pixel becoming an `sx` by `sy` block
+    pub fn scaled(&self, sx: usize, sy: usize) -> Bitmap {
+        let mut out = Bitmap::new(self.width * sx, self.height * sy);
+        for y in 0..out.height {
+            for x in 0..out.width {
+                out.set(x, y, self.get(x / sx, y / sy));
+            }
+        }
+        out
+    }
+
+    /// Bytes per packed row
+    pub fn row_bytes(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    /// Row-major packing, most significant bit = leftmost dot
+    pub fn packed_rows(&self) -> Vec<u8> {
+        let row_bytes = self.row_bytes();
+        let mut data = vec![0u8; row_bytes * self.height];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get(x, y) {
+                    data[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+        }
+        data
+    }
+}
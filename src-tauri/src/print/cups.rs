@@ -0,0 +1,326 @@
+// =====================================================
+// CUPS Backend (Linux / macOS)
+// Uses the lpstat and lp command line clients, so the
+// CUPS_SERVER environment variable and lpoptions apply
+// =====================================================
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::backend::{PrintJob, PrinterBackend, PrinterStatus};
+use super::status::{DeviceStatus, StatusSource};
+
+pub struct CupsBackend;
+
+impl PrinterBackend for CupsBackend {
+    fn name(&self) -> &'static str {
+        "cups"
+    }
+
+    fn list_printers(&self) -> Result<Vec<String>, String> {
+        list_printers()
+    }
+
+    fn default_printer(&self) -> Option<String> {
+        default_printer()
+    }
+
+    fn status(&self, printer: &str) -> PrinterStatus {
+        status(printer)
+    }
+
+    fn device_status(&self, printer: &str) -> DeviceStatus {
+        device_status(printer)
+    }
+
+    fn submit(&self, printer: &str, job: &PrintJob) -> Result<String, String> {
+        submit(printer, &job.title, &job.data, job.raw)
+    }
+}
+
+/// `program` from PATH; tests put stub scripts first on it for one thread.
+/// The C locale keeps its messages in the English the parsers expect.
+fn command(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.env("LC_ALL", "C").env("LANG", "C");
+    #[cfg(test)]
+    if let Some(path) = tests::STUB_PATH.with(|path| path.borrow().clone()) {
+        cmd.env("PATH", path);
+    }
+    cmd
+}
+
+/// Names of all queues accepting jobs (`lpstat -e`)
+pub fn list_printers() -> Result<Vec<String>, String> {
+    let output = command("lpstat")
+        .arg("-e")
+        .output()
+        .map_err(|e| format!("Failed to run lpstat: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // lpstat exits non-zero when CUPS has no queues at all
+        if stderr.to_lowercase().contains("no destinations") {
+            return Ok(Vec::new());
+        }
+        return Err(format!("lpstat failed: {}", stderr.trim()));
+    }
+
+    Ok(parse_destinations(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The system default destination (`lpstat -d`), if one is set
+pub fn default_printer() -> Option<String> {
+    let output = command("lpstat").arg("-d").output().ok()?;
+    parse_default(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Queue state from `lpstat -p`; disabled or missing queues are offline
+pub fn status(printer: &str) -> PrinterStatus {
+    match command("lpstat").args(["-p", printer]).output() {
+        Ok(output) if output.status.success() => {
+            parse_status(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) => PrinterStatus::Offline,
+        Err(_) => PrinterStatus::Unknown,
+    }
+}
+
+/// Queue state plus the printer-state-reasons that `lpstat -l -p` lists
+/// under `Alerts:`, where the driver reports them
+pub fn device_status(printer: &str) -> DeviceStatus {
+    match command("lpstat").args(["-l", "-p", printer]).output() {
+        Ok(output) if output.status.success() => {
+            parse_device_status(printer, &String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) => DeviceStatus::from_status(printer, PrinterStatus::Offline, StatusSource::Spooler),
+        Err(_) => DeviceStatus::from_status(printer, PrinterStatus::Unknown, StatusSource::Spooler),
+    }
+}
+
+/// Submit a job with `lp`, feeding `data` on stdin.
+/// `raw` jobs bypass the CUPS filters so printer commands reach the device
+/// unchanged; otherwise the data is printed as plain text.
+/// Returns the CUPS job id (e.g. `TVS_MSP_250-42`).
+pub fn submit(printer: &str, title: &str, data: &[u8], raw: bool) -> Result<String, String> {
+    let mut cmd = command("lp");
+    cmd.args(["-d", printer]);
+    cmd.args(["-t", title]);
+    if raw {
+        cmd.args(["-o", "raw"]);
+    }
+
+    let mut child = cmd
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run lp: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(data)
+            .map_err(|e| format!("Failed to send job to lp: {}", e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("lp failed: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "lp failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_job_id(&stdout).unwrap_or_else(|| stdout.trim().to_string()))
+}
+
+/// One queue name per line
+fn parse_destinations(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// "system default destination: NAME" or "no system default destination"
+fn parse_default(stdout: &str) -> Option<String> {
+    stdout
+        .lines()
+        .find_map(|l| l.split_once("default destination:"))
+        .map(|(_, name)| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// "printer NAME is idle." / "printer NAME disabled since ..."
+fn parse_status(stdout: &str) -> PrinterStatus {
+    if stdout.contains(" disabled") {
+        PrinterStatus::Offline
+    } else if stdout.contains(" is idle") || stdout.contains(" now printing") {
+        PrinterStatus::Ready
+    } else {
+        PrinterStatus::Unknown
+    }
+}
+
+/// "\tAlerts: media-empty-error door-open-report" (or "none")
+fn parse_device_status(printer: &str, stdout: &str) -> DeviceStatus {
+    let mut status = DeviceStatus::new(printer, StatusSource::Spooler);
+    status.online = parse_status(stdout) != PrinterStatus::Offline;
+
+    let alerts = stdout
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Alerts:"))
+        .unwrap_or("");
+    for reason in alerts.split_whitespace() {
+        let reason = reason
+            .trim_end_matches("-error")
+            .trim_end_matches("-warning")
+            .trim_end_matches("-report");
+        match reason {
+            "media-empty" | "media-needed" => status.paper_out = true,
+            "media-low" => status.paper_low = true,
+            "door-open" | "cover-open" | "interlock-open" => status.cover_open = true,
+            "offline" | "shutdown" | "connecting-to-device" => status.online = false,
+            "media-jam" | "cutter-failure" | "fuser-over-temp" => status.error = true,
+            _ => {}
+        }
+    }
+    status.describe()
+}
+
+/// "request id is NAME-42 (1 file(s))"
+fn parse_job_id(stdout: &str) -> Option<String> {
+    stdout
+        .split_once("request id is ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .map(|id| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    thread_local! {
+        pub static STUB_PATH: RefCell<Option<OsString>> = const { RefCell::new(None) };
+    }
+
+    /// Captured from CUPS 2.4
+    const LPSTAT_E: &str = "EPSON_TM_T82\nTVS_MSP_250\n";
+    const LPSTAT_D: &str = "system default destination: TVS_MSP_250\n";
+    const LPSTAT_P_IDLE: &str =
+        "printer TVS_MSP_250 is idle.  enabled since Sat 17 Oct 2026 09:12:40 AM IST\n";
+    const LPSTAT_P_DISABLED: &str = "printer EPSON_TM_T82 disabled since Sat 17 Oct 2026 \
+        11:02:05 AM IST -\n\tPaused\n";
+    const LPSTAT_L_P: &str = "printer EPSON_TM_T82 now printing EPSON_TM_T82-57.  \
+        enabled since Sat 17 Oct 2026 11:04:51 AM IST\n\
+        \tWaiting for printer to finish.\n\
+        \tAlerts: media-low-report door-open-error\n\
+        \tDescription: Counter 2\n\
+        \tLocation: \n\
+        \tConnection: direct\n";
+    const LP: &str = "request id is TVS_MSP_250-42 (1 file(s))\n";
+
+    #[test]
+    fn lpstat_output_is_parsed() {
+        assert_eq!(
+            parse_destinations(LPSTAT_E),
+            ["EPSON_TM_T82", "TVS_MSP_250"]
+        );
+        assert!(parse_destinations("").is_empty());
+        assert_eq!(parse_default(LPSTAT_D).as_deref(), Some("TVS_MSP_250"));
+        assert_eq!(parse_default("no system default destination\n"), None);
+        assert_eq!(parse_status(LPSTAT_P_IDLE), PrinterStatus::Ready);
+        assert_eq!(parse_status(LPSTAT_P_DISABLED), PrinterStatus::Offline);
+        assert_eq!(parse_status(""), PrinterStatus::Unknown);
+        assert_eq!(parse_job_id(LP).as_deref(), Some("TVS_MSP_250-42"));
+        assert_eq!(parse_job_id("lp: Error - no default destination"), None);
+
+        let status = parse_device_status("EPSON_TM_T82", LPSTAT_L_P);
+        assert!(status.online && status.paper_low && status.cover_open);
+        assert!(!status.paper_out && !status.error);
+    }
+
+    /// Write executable stubs for `lp` and `lpstat` in a fresh folder.
+    /// `lp` saves its arguments and stdin next to itself; `lpstat` only
+    /// answers in the C locale.
+    fn stubs(name: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir =
+            std::env::temp_dir().join(format!("medbill-cups-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let scripts = [
+            (
+                "lp",
+                format!(
+                    "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$(dirname \"$0\")/args\"\n\
+                     cat > \"$(dirname \"$0\")/data\"\nprintf '{}'\n",
+                    LP.trim_end()
+                ),
+            ),
+            (
+                "lpstat",
+                format!(
+                    "#!/bin/sh\n[ \"$LC_ALL\" = C ] && [ \"$LANG\" = C ] || exit 2\n\
+                     case \"$1\" in\n\
+                     -e) printf '{}' ;;\n\
+                     -d) printf '{}' ;;\n\
+                     -p) [ \"$2\" = TVS_MSP_250 ] || exit 1; printf '{}' ;;\n\
+                     esac\n",
+                    LPSTAT_E.replace('\n', "\\n"),
+                    LPSTAT_D.replace('\n', "\\n"),
+                    LPSTAT_P_IDLE.replace('\n', "\\n")
+                ),
+            ),
+        ];
+        for (program, script) in scripts {
+            let path = dir.join(program);
+            std::fs::write(&path, script).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn jobs_and_queues_go_through_lp_and_lpstat() {
+        let dir = stubs("submit");
+        let system = std::env::var_os("PATH").unwrap_or_default();
+        let path = std::env::join_paths(
+            std::iter::once(dir.clone()).chain(std::env::split_paths(&system)),
+        )
+        .unwrap();
+        STUB_PATH.with(|stub| *stub.borrow_mut() = Some(path));
+
+        assert_eq!(list_printers().unwrap(), ["EPSON_TM_T82", "TVS_MSP_250"]);
+        assert_eq!(default_printer().as_deref(), Some("TVS_MSP_250"));
+        assert_eq!(status("TVS_MSP_250"), PrinterStatus::Ready);
+        assert_eq!(status("Missing"), PrinterStatus::Offline);
+
+        let id = submit("TVS_MSP_250", "MedBill Bill", b"\x1b@BILL\x0c", true).unwrap();
+        assert_eq!(id, "TVS_MSP_250-42");
+        let args = std::fs::read_to_string(dir.join("args")).unwrap();
+        assert_eq!(
+            args.lines().collect::<Vec<_>>(),
+            ["-d", "TVS_MSP_250", "-t", "MedBill Bill", "-o", "raw", "-"]
+        );
+        assert_eq!(std::fs::read(dir.join("data")).unwrap(), b"\x1b@BILL\x0c");
+
+        // Text jobs go through the CUPS filters
+        submit("TVS_MSP_250", "Report", b"text", false).unwrap();
+        let args = std::fs::read_to_string(dir.join("args")).unwrap();
+        assert!(!args.lines().any(|a| a == "raw"), "{}", args);
+
+        STUB_PATH.with(|path| *path.borrow_mut() = None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
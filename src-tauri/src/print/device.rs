@@ -0,0 +1,283 @@
+// =====================================================
+// Direct Device Output
+// Serial printers (USB-serial adapters, COM ports) and
+// USB line-printer device files, bypassing the spooler
+// =====================================================
+
+use std::io::Write;
+use std::time::Duration;
+
+use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+use super::backend::{PrintJob, PrinterStatus};
+
+/// `serial:/dev/ttyUSB0?baud=9600&parity=none&flow=xonxoff`,
+/// `serial:COM3?9600,8N1` or `serial:COM3`
+pub const SERIAL_SCHEME: &str = "serial:";
+/// `lp:/dev/usb/lp0` - a device file that accepts raw bytes
+pub const LP_SCHEME: &str = "lp:";
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub path: String,
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl SerialConfig {
+    /// 9600 8N1 without flow control unless the target says otherwise.
+    /// Query keys: `baud`, `data_bits` (5-8), `parity` (none/odd/even),
+    /// `stop_bits` (1/2), `flow` (none/xonxoff/rtscts). The usual shorthand
+    /// from printer manuals, `9600,8N1`, sets the baud rate and framing.
+    pub fn parse(target: &str) -> Result<SerialConfig, String> {
+        let target = target.trim();
+        let target = target.strip_prefix(SERIAL_SCHEME).unwrap_or(target);
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if path.is_empty() {
+            return Err("Serial printer has no device path".to_string());
+        }
+
+        let mut config = SerialConfig {
+            path: path.to_string(),
+            baud_rate: 9600,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        };
+
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let Some((key, value)) = pair.split_once('=') else {
+                config.apply_shorthand(pair)?;
+                continue;
+            };
+            let value = value.to_lowercase();
+            match key {
+                "baud" => {
+                    config.baud_rate = value
+                        .parse()
+                        .map_err(|_| format!("Invalid baud rate: {}", value))?
+                }
+                "data_bits" => {
+                    config.data_bits = match value.as_str() {
+                        "5" => DataBits::Five,
+                        "6" => DataBits::Six,
+                        "7" => DataBits::Seven,
+                        "8" => DataBits::Eight,
+                        _ => return Err(format!("Invalid data bits: {}", value)),
+                    }
+                }
+                "parity" => {
+                    config.parity = match value.as_str() {
+                        "none" | "n" => Parity::None,
+                        "odd" | "o" => Parity::Odd,
+                        "even" | "e" => Parity::Even,
+                        _ => return Err(format!("Invalid parity: {}", value)),
+                    }
+                }
+                "stop_bits" => {
+                    config.stop_bits = match value.as_str() {
+                        "1" => StopBits::One,
+                        "2" => StopBits::Two,
+                        _ => return Err(format!("Invalid stop bits: {}", value)),
+                    }
+                }
+                "flow" => {
+                    config.flow_control = match value.as_str() {
+                        "none" => FlowControl::None,
+                        "xonxoff" | "software" => FlowControl::Software,
+                        "rtscts" | "hardware" => FlowControl::Hardware,
+                        _ => return Err(format!("Invalid flow control: {}", value)),
+                    }
+                }
+                other => log::warn!("Ignoring unknown serial option '{}'", other),
+            }
+        }
+        Ok(config)
+    }
+
+    /// `9600,8N1`, `19200` or `7E2`: baud rate, then data bits, parity
+    /// (N/O/E) and stop bits
+    fn apply_shorthand(&mut self, shorthand: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid serial settings: {}", shorthand);
+        for part in shorthand.split(',').map(str::trim) {
+            if let Ok(baud) = part.parse() {
+                self.baud_rate = baud;
+                continue;
+            }
+            let frame: Vec<char> = part.to_uppercase().chars().collect();
+            let [data, parity, stop] = frame[..] else {
+                return Err(invalid());
+            };
+            self.data_bits = match data {
+                '5' => DataBits::Five,
+                '6' => DataBits::Six,
+                '7' => DataBits::Seven,
+                '8' => DataBits::Eight,
+                _ => return Err(invalid()),
+            };
+            self.parity = match parity {
+                'N' => Parity::None,
+                'O' => Parity::Odd,
+                'E' => Parity::Even,
+                _ => return Err(invalid()),
+            };
+            self.stop_bits = match stop {
+                '1' => StopBits::One,
+                '2' => StopBits::Two,
+                _ => return Err(invalid()),
+            };
+        }
+        Ok(())
+    }
+
+    /// Open the port; `timeout` applies to each read and write
+    pub fn open(&self, timeout: Duration) -> Result<Box<dyn serialport::SerialPort>, String> {
+        serialport::new(&self.path, self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control)
+            .timeout(timeout)
+            .open()
+            .map_err(|e| format!("Cannot open {}: {}", self.path, e))
+    }
+}
+
+/// Whether `printer` names a directly attached device
+pub fn is_device(printer: &str) -> bool {
+    printer.starts_with(SERIAL_SCHEME) || printer.starts_with(LP_SCHEME)
+}
+
+fn lp_path(printer: &str) -> &str {
+    printer.strip_prefix(LP_SCHEME).unwrap_or(printer).trim()
+}
+
+pub fn status(printer: &str) -> PrinterStatus {
+    if printer.starts_with(SERIAL_SCHEME) {
+        match SerialConfig::parse(printer).and_then(|c| c.open(WRITE_TIMEOUT)) {
+            Ok(_) => PrinterStatus::Ready,
+            Err(e) => {
+                log::info!("Printer probe: {}", e);
+                PrinterStatus::Offline
+            }
+        }
+    } else {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .open(lp_path(printer))
+        {
+            Ok(_) => PrinterStatus::Ready,
+            Err(_) => PrinterStatus::Offline,
+        }
+    }
+}
+
+/// Write the job straight to the device
+pub fn submit(printer: &str, job: &PrintJob) -> Result<String, String> {
+    if printer.starts_with(SERIAL_SCHEME) {
+        let config = SerialConfig::parse(printer)?;
+        let mut port = config.open(WRITE_TIMEOUT)?;
+        port.write_all(&job.data)
+            .and_then(|_| port.flush())
+            .map_err(|e| format!("Failed to write to {}: {}", config.path, e))?;
+        Ok(config.path)
+    } else {
+        let path = lp_path(printer);
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|e| format!("Cannot open {}: {}", path, e))?;
+        file.write_all(&job.data)
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Failed to write to {}: {}", path, e))?;
+        Ok(path.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_targets_are_parsed() {
+        let config = SerialConfig::parse("serial:/dev/ttyUSB0").unwrap();
+        assert_eq!(config.path, "/dev/ttyUSB0");
+        assert_eq!(config.baud_rate, 9600);
+        assert_eq!(
+            (
+                config.data_bits,
+                config.parity,
+                config.stop_bits,
+                config.flow_control
+            ),
+            (
+                DataBits::Eight,
+                Parity::None,
+                StopBits::One,
+                FlowControl::None
+            )
+        );
+
+        let config = SerialConfig::parse("serial:COM3?19200,7E2").unwrap();
+        assert_eq!(config.path, "COM3");
+        assert_eq!(config.baud_rate, 19200);
+        assert_eq!(
+            (config.data_bits, config.parity, config.stop_bits),
+            (DataBits::Seven, Parity::Even, StopBits::Two)
+        );
+
+        let config = SerialConfig::parse("serial:COM3?9600,8n1&flow=xonxoff").unwrap();
+        assert_eq!((config.baud_rate, config.parity), (9600, Parity::None));
+        assert_eq!(config.flow_control, FlowControl::Software);
+
+        let config =
+            SerialConfig::parse("serial:/dev/ttyS0?baud=38400&data_bits=7&parity=odd&stop_bits=2")
+                .unwrap();
+        assert_eq!(config.baud_rate, 38400);
+        assert_eq!(
+            (config.data_bits, config.parity, config.stop_bits),
+            (DataBits::Seven, Parity::Odd, StopBits::Two)
+        );
+    }
+
+    #[test]
+    fn bad_serial_targets_are_rejected() {
+        for target in [
+            "serial:",
+            "serial:?9600",
+            "serial:COM3?9600,8X1",
+            "serial:COM3?9600,9N1",
+            "serial:COM3?9600,8N3",
+            "serial:COM3?fast",
+            "serial:COM3?baud=fast",
+            "serial:COM3?parity=mark",
+            "serial:COM3?flow=dtr",
+        ] {
+            assert!(SerialConfig::parse(target).is_err(), "{}", target);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn serial_jobs_are_written_to_the_port() {
+        use serialport::{SerialPort, TTYPort};
+        use std::io::Read;
+
+        let (mut master, slave) = TTYPort::pair().unwrap();
+        let path = slave.name().unwrap();
+        let printer = format!("serial:{}?9600,8N1", path);
+        assert!(is_device(&printer));
+
+        let job = PrintJob::raw("MedBill Receipt", b"\x1b@BILL\n\x1dVB\x03".to_vec());
+        assert_eq!(submit(&printer, &job).unwrap(), path);
+        let mut received = vec![0u8; job.data.len()];
+        master.read_exact(&mut received).unwrap();
+        assert_eq!(received, job.data);
+    }
+}
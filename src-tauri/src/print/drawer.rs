@@ -0,0 +1,294 @@
+// =====================================================
+// Cash Drawer
+// Drawers wired to the receipt printer's RJ11 port are
+// opened by an ESC/POS pulse (ESC p m t1 t2) sent to
+// the printer
+// =====================================================
+
+use rusqlite::{Connection, OptionalExtension};
+
+use super::backend::{PrintJob, PrinterBackend};
+use super::profiles::{self, DocumentKind, PrintFormat};
+
+const ESC: u8 = 0x1B;
+
+/// Drawer kick connector pin the solenoid is wired to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawerPin {
+    Pin2,
+    Pin5,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrawerOptions {
+    /// Whether a drawer is connected to the bill printer at all
+    pub enabled: bool,
+    pub pin: DrawerPin,
+    pub on_ms: u32,
+    pub off_ms: u32,
+}
+
+impl Default for DrawerOptions {
+    fn default() -> Self {
+        DrawerOptions {
+            enabled: false,
+            pin: DrawerPin::Pin2,
+            on_ms: 100,
+            off_ms: 200,
+        }
+    }
+}
+
+impl DrawerOptions {
+    /// Read `cash_drawer_enabled`, `cash_drawer_pin` (2 or 5) and the
+    /// `cash_drawer_on_ms` / `cash_drawer_off_ms` pulse times
+    pub fn from_settings(conn: &Connection) -> Self {
+        let defaults = DrawerOptions::default();
+        DrawerOptions {
+            enabled: matches!(
+                crate::db::get_setting(conn, "cash_drawer_enabled").as_deref(),
+                Some("1") | Some("true")
+            ),
+            pin: match crate::db::get_setting(conn, "cash_drawer_pin").as_deref() {
+                Some("5") => DrawerPin::Pin5,
+                _ => DrawerPin::Pin2,
+            },
+            on_ms: crate::db::get_setting_or(conn, "cash_drawer_on_ms", defaults.on_ms),
+            off_ms: crate::db::get_setting_or(conn, "cash_drawer_off_ms", defaults.off_ms),
+        }
+    }
+}
+
+/// `ESC p m t1 t2`: pulse `pin` for t1 x 2ms, then rest for t2 x 2ms.
+/// No ESC @ first, since that would clear a receipt still in the buffer.
+pub fn pulse(options: &DrawerOptions) -> Vec<u8> {
+    let units = |ms: u32| (ms / 2).clamp(1, 255) as u8;
+    let m = match options.pin {
+        DrawerPin::Pin2 => 0,
+        DrawerPin::Pin5 => 1,
+    };
+    vec![ESC, b'p', m, units(options.on_ms), units(options.off_ms)]
+}
+
+/// Only ESC/POS printers take the pulse. To an ESC/P dot matrix `ESC p`
+/// turns on proportional spacing, which would garble every later bill.
+pub fn check_format(format: PrintFormat) -> Result<(), String> {
+    if format == PrintFormat::Escpos {
+        Ok(())
+    } else {
+        Err(format!(
+            "A cash drawer needs an ESC/POS bill printer; the bill profile prints {:?}",
+            format
+        ))
+    }
+}
+
+/// Send the drawer pulse to `printer` right away (not through the spool)
+pub fn kick(
+    backend: &dyn PrinterBackend,
+    printer: &str,
+    options: &DrawerOptions,
+) -> Result<String, String> {
+    backend.submit(
+        printer,
+        &PrintJob::raw("MedBill Cash Drawer", pulse(options)),
+    )
+}
+
+/// Whether finishing spool job `job_id` should open the drawer: the first
+/// print of a bill that is not cancelled and took cash. Reprints and
+/// duplicates do not.
+pub fn opens_after(conn: &Connection, job_id: i64) -> Result<bool, String> {
+    let cash_amount: Option<f64> = conn
+        .query_row(
+            "SELECT b.cash_amount FROM print_jobs j
+             JOIN bills b ON b.id = j.bill_id
+             WHERE j.id = ?1 AND j.document = 'bill' AND j.reprint_of IS NULL
+               AND COALESCE(b.is_cancelled, 0) = 0
+               AND NOT EXISTS (
+                   SELECT 1 FROM print_jobs p
+                   WHERE p.bill_id = j.bill_id AND p.document = 'bill'
+                     AND p.status = 'done' AND p.id <> j.id
+               )",
+            rusqlite::params![job_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read bill for cash drawer: {}", e))?;
+    Ok(cash_amount.is_some_and(|amount| amount > 0.0))
+}
+
+/// Open the drawer after a cash bill prints. Called by the spool once the
+/// job is done; a failure here is logged and does not fail the job.
+pub fn after_print(conn: &Connection, backend: &dyn PrinterBackend, job_id: i64, printer: &str) {
+    let options = DrawerOptions::from_settings(conn);
+    if !options.enabled {
+        return;
+    }
+    let format = profiles::resolve(Some(conn), DocumentKind::Bill).format;
+    match opens_after(conn, job_id).and_then(|open| {
+        if open {
+            check_format(format)?;
+            kick(backend, printer, &options).map(Some)
+        } else {
+            Ok(None)
+        }
+    }) {
+        Ok(Some(_)) => log::info!("Opened cash drawer on {} after job {}", printer, job_id),
+        Ok(None) => {}
+        Err(e) => log::warn!("Cash drawer: {}", e),
+    }
+}
+
+/// Record a drawer opened without a sale
+pub fn log_no_sale(
+    conn: &Connection,
+    user_id: i64,
+    printer: &str,
+    reason: Option<&str>,
+) -> Result<i64, String> {
+    let reason = reason.map(str::trim).filter(|r| !r.is_empty());
+    conn.execute(
+        "INSERT INTO audit_log (user_id, action, entity_type, entity_id, new_value, description)
+         VALUES (?1, 'NO_SALE', 'cash_drawer', NULL, ?2, ?3)",
+        rusqlite::params![
+            user_id,
+            printer,
+            reason.unwrap_or("Cash drawer opened without a sale")
+        ],
+    )
+    .map_err(|e| format!("Failed to write audit log: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::backend::MockBackend;
+    use crate::print::fixtures;
+    use crate::print::spool::{self, NewJob};
+
+    /// Bill 1 took cash, bill 2 went on credit and bill 3 was cancelled
+    fn fixture() -> Connection {
+        let conn = fixtures::database();
+        fixtures::seed(&conn);
+        conn.execute_batch(
+            "INSERT INTO settings (key, value) VALUES ('cash_drawer_enabled', 'true'),
+                 ('cash_drawer_pin', '5');
+             INSERT INTO bills (id, bill_number, user_id, payment_mode, cash_amount, credit_amount)
+                 VALUES (2, 'INV-2', 1, 'CREDIT', 0, 100);
+             INSERT INTO bills (id, bill_number, user_id, cash_amount, is_cancelled)
+                 VALUES (3, 'INV-3', 1, 500, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn print(conn: &Connection, backend: &MockBackend, bill_id: i64) {
+        let new = NewJob {
+            bill_id: Some(bill_id),
+            document: DocumentKind::Bill,
+            printer: "Mock Printer".to_string(),
+            job: PrintJob::raw("MedBill Receipt", b"receipt".to_vec()),
+            copies: 1,
+            audit: false,
+            user_id: None,
+        };
+        spool::enqueue(conn, &new).unwrap();
+        assert!(spool::process_next(conn, backend, &|_| {}).unwrap());
+    }
+
+    fn kicks(backend: &MockBackend) -> usize {
+        backend
+            .jobs()
+            .iter()
+            .filter(|j| j.job.data.starts_with(&[ESC, b'p']))
+            .count()
+    }
+
+    #[test]
+    fn pulse_times_are_in_two_millisecond_units() {
+        let options = DrawerOptions {
+            enabled: true,
+            pin: DrawerPin::Pin5,
+            on_ms: 50,
+            off_ms: 1000,
+        };
+        assert_eq!(pulse(&options), [0x1B, b'p', 1, 25, 255]);
+        assert_eq!(pulse(&DrawerOptions::default()), [0x1B, b'p', 0, 50, 100]);
+    }
+
+    #[test]
+    fn drawer_opens_once_after_a_cash_bill_prints() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+
+        print(&conn, &backend, 1);
+        let jobs = backend.jobs();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[1].job.data, [0x1B, b'p', 1, 50, 100]);
+
+        // Printing the same bill again, or resending the job, does not
+        print(&conn, &backend, 1);
+        spool::resend(&conn, 1).unwrap();
+        assert!(spool::process_next(&conn, &backend, &|_| {}).unwrap());
+        assert_eq!(kicks(&backend), 1);
+    }
+
+    #[test]
+    fn no_kick_without_cash_or_when_disabled() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+        print(&conn, &backend, 2);
+        print(&conn, &backend, 3);
+        assert_eq!(kicks(&backend), 0);
+
+        conn.execute(
+            "UPDATE settings SET value = 'false' WHERE key = 'cash_drawer_enabled'",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO bills (id, bill_number, user_id, cash_amount) VALUES (4, 'INV-4', 1, 100)",
+            [],
+        )
+        .unwrap();
+        print(&conn, &backend, 4);
+        assert_eq!(kicks(&backend), 0);
+    }
+
+    #[test]
+    fn no_kick_when_bills_print_escp() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+        let mut saved = profiles::load(Some(&conn));
+        saved.get_mut(&DocumentKind::Bill).unwrap().format = PrintFormat::Escp;
+        profiles::save(&conn, &saved).unwrap();
+
+        print(&conn, &backend, 1);
+        assert_eq!(backend.jobs().len(), 1);
+        assert_eq!(kicks(&backend), 0);
+    }
+
+    #[test]
+    fn no_sale_is_audited() {
+        let conn = fixture();
+        log_no_sale(&conn, 2, "Mock Printer", Some("  ")).unwrap();
+        let row: (i64, String, String, String) = conn
+            .query_row(
+                "SELECT user_id, action, new_value, description FROM audit_log",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                2,
+                "NO_SALE".to_string(),
+                "Mock Printer".to_string(),
+                "Cash drawer opened without a sale".to_string()
+            )
+        );
+    }
+}
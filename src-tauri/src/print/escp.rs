@@ -0,0 +1,508 @@
+// =====================================================
+// ESC/P Encoder for Dot Matrix Printers
+// Produces raw printer bytes for the TVS MSP 250 and
+// other Epson-compatible 9/24-pin printers
+// =====================================================
+
+use std::path::PathBuf;
+
+use super::bitmap::Bitmap;
+use super::paginate;
+use super::receipt::{align_to, transliterate, Align, Block, Receipt, Style};
+use super::unicode::{self, Cell, LazyFonts, Piece};
+
+const ESC: u8 = 0x1B;
+const SI: u8 = 0x0F; // condensed (17 cpi) on
+const DC2: u8 = 0x12; // condensed off
+const FF: u8 = 0x0C;
+
+/// Horizontal dots per character column at 10 cpi in 120 dpi graphics
+const DOTS_PER_COLUMN: usize = 12;
+/// Dots per QR module: 5/120" across and 3/72" down make it square
+const QR_MODULE: (usize, usize) = (5, 3);
+/// Lines drawn as graphics: two 8-dot bands, 1/120" dots across and 1/72"
+/// down; condensed (17 cpi) columns are 7 dots wide
+const TEXT_CELL: Cell = Cell {
+    width: DOTS_PER_COLUMN,
+    height: 16,
+    aspect: 120.0 / 72.0,
+};
+const CONDENSED_DOTS_PER_COLUMN: usize = 7;
+
+/// Vertical line pitch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineSpacing {
+    /// 1/6 inch - the printer default
+    SixLpi,
+    /// 1/8 inch - fits more lines on pre-cut stationery
+    EightLpi,
+    /// n/216 inch
+    Custom(u8),
+}
+
+/// How the paper is advanced once the receipt is printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaperFeed {
+    /// Pre-cut or continuous forms of a fixed length (in lines); ends with a form feed
+    FormLength(u8),
+    /// Continuous paper torn off at the bar; feeds the given number of lines
+    TearOff(u8),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EscpOptions {
+    pub line_spacing: LineSpacing,
+    pub feed: PaperFeed,
+    /// Font for lines the printer cannot show as text (Tamil names);
+    /// a system font is looked for when not set
+    pub unicode_font: Option<PathBuf>,
+    /// Lines per page of pre-cut stationery; 0 prints one continuous page
+    pub page_lines: usize,
+}
+
+impl Default for EscpOptions {
+    fn default() -> Self {
+        EscpOptions {
+            line_spacing: LineSpacing::SixLpi,
+            feed: PaperFeed::TearOff(6),
+            unicode_font: None,
+            page_lines: 0,
+        }
+    }
+}
+
+impl EscpOptions {
+    /// Read the dot matrix settings (`dot_matrix_*` keys) and `unicode_font_path`
+    pub fn from_settings(conn: &rusqlite::Connection) -> Self {
+        let lpi: u8 = crate::db::get_setting_or(conn, "dot_matrix_lines_per_inch", 6);
+        let form_length: u8 = crate::db::get_setting_or(conn, "dot_matrix_form_length", 0);
+        let tear_off: u8 = crate::db::get_setting_or(conn, "dot_matrix_tear_off_lines", 6);
+
+        EscpOptions {
+            line_spacing: match lpi {
+                0 | 6 => LineSpacing::SixLpi,
+                8 => LineSpacing::EightLpi,
+                // Finer than 1/216 inch would be ESC 3 0: no feed at all
+                n => LineSpacing::Custom((216 / n).max(1)),
+            },
+            feed: if form_length > 0 {
+                PaperFeed::FormLength(form_length)
+            } else {
+                PaperFeed::TearOff(tear_off)
+            },
+            unicode_font: unicode::font_setting(conn),
+            page_lines: paginate::page_lines(conn),
+        }
+    }
+}
+
+/// Low-level ESC/P command writer
+pub struct EscpWriter {
+    buf: Vec<u8>,
+}
+
+impl Default for EscpWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EscpWriter {
+    /// Start a new job with ESC @ so no state leaks from the previous one
+    pub fn new() -> Self {
+        EscpWriter {
+            buf: vec![ESC, b'@'],
+        }
+    }
+
+    pub fn bold(&mut self, on: bool) -> &mut Self {
+        self.buf
+            .extend_from_slice(&[ESC, if on { b'E' } else { b'F' }]);
+        self
+    }
+
+    pub fn double_width(&mut self, on: bool) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'W', on as u8]);
+        self
+    }
+
+    pub fn condensed(&mut self, on: bool) -> &mut Self {
+        self.buf.push(if on { SI } else { DC2 });
+        self
+    }
+
+    pub fn line_spacing(&mut self, spacing: LineSpacing) -> &mut Self {
+        match spacing {
+            LineSpacing::SixLpi => self.buf.extend_from_slice(&[ESC, b'2']),
+            LineSpacing::EightLpi => self.buf.extend_from_slice(&[ESC, b'0']),
+            LineSpacing::Custom(n) => self.buf.extend_from_slice(&[ESC, b'3', n]),
+        }
+        self
+    }
+
+    /// Page length in lines at the current line spacing (ESC C n)
+    pub fn form_length(&mut self, lines: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'C', lines.max(1)]);
+        self
+    }
+
+    /// Cancel skip-over-perforation so continuous paper is not wasted (ESC O)
+    pub fn no_perforation_skip(&mut self) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'O']);
+        self
+    }
+
+    /// Left margin in columns (ESC l n)
+    pub fn left_margin(&mut self, columns: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'l', columns]);
+        self
+    }
+
+    /// Write text, replacing anything outside the printer code page
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.buf.extend_from_slice(transliterate(text).as_bytes());
+        self
+    }
+
+    pub fn newline(&mut self) -> &mut Self {
+        self.buf.extend_from_slice(b"\r\n");
+        self
+    }
+
+    pub fn feed_lines(&mut self, lines: u8) -> &mut Self {
+        for _ in 0..lines {
+            self.newline();
+        }
+        self
+    }
+
+    /// Print a bitmap in 8-dot bands (ESC * 1: 120 dpi across, 72 dpi down
+    /// on 9-pin heads), `indent` columns from the left margin. Leaves the
+    /// line spacing at 24/216" so the bands touch; callers restore theirs.
+    pub fn bit_image(&mut self, bitmap: &Bitmap, indent: usize) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'3', 24]);
+        for top in (0..bitmap.height).step_by(8) {
+            self.buf.extend_from_slice(" ".repeat(indent).as_bytes());
+            self.band(bitmap, top);
+            self.newline();
+        }
+        self
+    }
+
+    /// The 8 rows of `bitmap` from `top` as one ESC * 1 band, printed where
+    /// the head is so text can go either side of it
+    pub fn band(&mut self, bitmap: &Bitmap, top: usize) -> &mut Self {
+        let width = bitmap.width;
+        self.buf
+            .extend_from_slice(&[ESC, b'*', 1, (width & 0xFF) as u8, (width >> 8) as u8]);
+        for x in 0..width {
+            let column = (0..8)
+                .filter(|dot| bitmap.get(x, top + dot))
+                .fold(0u8, |column, dot| column | (0x80 >> dot));
+            self.buf.push(column);
+        }
+        self
+    }
+
+    pub fn form_feed(&mut self) -> &mut Self {
+        self.buf.push(FF);
+        self
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Encode a receipt as an ESC/P byte stream, split into pages of
+/// `options.page_lines` lines when set. Fails when a line has a character
+/// no font can draw.
+pub fn encode(receipt: &Receipt, options: &EscpOptions) -> Result<Vec<u8>, String> {
+    let receipt = &paginate::paginate(receipt, options.page_lines, |block| {
+        block_lines(block, options.line_spacing)
+    });
+    let fonts = LazyFonts::new(options.unicode_font.clone());
+    let mut w = EscpWriter::new();
+    w.left_margin(0).line_spacing(options.line_spacing);
+    match options.feed {
+        PaperFeed::FormLength(lines) => {
+            w.form_length(lines);
+        }
+        PaperFeed::TearOff(_) => {
+            w.no_perforation_skip();
+        }
+    }
+
+    for block in &receipt.blocks {
+        match block {
+            Block::Text { text, style } if unicode::needs_graphics(text) => {
+                write_graphic_line(&mut w, text, style, receipt.width, &fonts)?
+                    .line_spacing(options.line_spacing);
+            }
+            Block::Text { text, style } => write_line(&mut w, text, style, receipt.width),
+            Block::Rule(c) => {
+                w.text(&c.to_string().repeat(receipt.width)).newline();
+            }
+            Block::Feed(lines) => {
+                w.feed_lines(*lines);
+            }
+            // Logos are only printed on thermal receipts
+            Block::Image(_) => {}
+            Block::Qr(data) => match Bitmap::qr(data) {
+                Ok(symbol) => {
+                    let symbol = symbol.scaled(QR_MODULE.0, QR_MODULE.1);
+                    let indent = (receipt.width * DOTS_PER_COLUMN).saturating_sub(symbol.width) / 2;
+                    w.bit_image(&symbol, indent / DOTS_PER_COLUMN)
+                        .line_spacing(options.line_spacing);
+                }
+                Err(e) => log::warn!("Skipping QR code: {}", e),
+            },
+            Block::PageBreak => {
+                w.form_feed();
+            }
+        }
+    }
+
+    match options.feed {
+        PaperFeed::FormLength(_) => {
+            w.form_feed();
+        }
+        PaperFeed::TearOff(lines) => {
+            w.feed_lines(lines);
+        }
+    }
+
+    Ok(w.into_bytes())
+}
+
+/// Text lines a block takes at `spacing`; graphics are rounded up
+pub fn block_lines(block: &Block, spacing: LineSpacing) -> usize {
+    let pitch = match spacing {
+        LineSpacing::SixLpi => 36,
+        LineSpacing::EightLpi => 27,
+        LineSpacing::Custom(n) => n.max(1) as usize,
+    };
+    // Bit images advance 24/216" per 8-dot band
+    let image = |height: usize| (height.div_ceil(8) * 24).div_ceil(pitch);
+    match block {
+        Block::Text { text, .. } if unicode::needs_graphics(text) => image(TEXT_CELL.height),
+        Block::Qr(data) => Bitmap::qr(data)
+            .map(|symbol| image(symbol.height * QR_MODULE.1))
+            .unwrap_or(0),
+        other => paginate::text_lines(other),
+    }
+}
+
+fn write_line(w: &mut EscpWriter, text: &str, style: &Style, width: usize) {
+    // Double width halves the columns available; fall back to normal width
+    // rather than wrapping a long shop name
+    let double_width = style.double_width && text.chars().count() * 2 <= width;
+    let columns = if double_width { width / 2 } else { width };
+    let line = match style.align {
+        Align::Left => text.to_string(),
+        align => align_to(text, columns, align),
+    };
+
+    if style.condensed {
+        w.condensed(true);
+    }
+    if style.bold {
+        w.bold(true);
+    }
+    if double_width {
+        w.double_width(true);
+    }
+
+    w.text(&line);
+
+    if double_width {
+        w.double_width(false);
+    }
+    if style.bold {
+        w.bold(false);
+    }
+    if style.condensed {
+        w.condensed(false);
+    }
+    w.newline();
+}
+
+/// Print a line with characters outside the code page on the same column
+/// grid as text lines: the words the printer can show as text, the others
+/// as bit images between them, in two bands. A line with no text words is
+/// drawn whole. Leaves the graphics line spacing set.
+fn write_graphic_line<'a>(
+    w: &'a mut EscpWriter,
+    text: &str,
+    style: &Style,
+    width: usize,
+    fonts: &LazyFonts,
+) -> Result<&'a mut EscpWriter, String> {
+    let double_width = style.double_width && text.chars().count() * 2 <= width;
+    let (columns, cell) = if double_width {
+        (width / 2, TEXT_CELL.with_width(2 * DOTS_PER_COLUMN))
+    } else if style.condensed {
+        (width, TEXT_CELL.with_width(CONDENSED_DOTS_PER_COLUMN))
+    } else {
+        (width, TEXT_CELL)
+    };
+    let line = match style.align {
+        Align::Left => text.to_string(),
+        align => align_to(text, columns, align),
+    };
+    let fonts = fonts.get();
+    let Some(pieces) = fonts.mixed_line(&line, columns, cell, style.bold)? else {
+        let bitmap = fonts.render_line(text, columns, cell, style.align, style.bold)?;
+        return Ok(w.bit_image(&bitmap, 0));
+    };
+
+    w.line_spacing(LineSpacing::Custom(24));
+    for top in (0..cell.height).step_by(8) {
+        // Spaces and text take the line's pitch so the images land on the grid
+        if style.condensed && !double_width {
+            w.condensed(true);
+        }
+        if double_width {
+            w.double_width(true);
+        }
+        if style.bold {
+            w.bold(true);
+        }
+        let mut at = 0;
+        for (column, piece) in &pieces {
+            w.text(&" ".repeat(column.saturating_sub(at)));
+            at = at.max(*column);
+            match piece {
+                // The text sits in the top band, level with the drawn words
+                Piece::Text(text) if top == 0 => {
+                    w.text(text);
+                    at += text.chars().count();
+                }
+                Piece::Text(text) => {
+                    w.text(&" ".repeat(text.chars().count()));
+                    at += text.chars().count();
+                }
+                Piece::Image(bitmap) => {
+                    w.band(bitmap, top);
+                    at += bitmap.width / cell.width;
+                }
+            }
+        }
+        if style.bold {
+            w.bold(false);
+        }
+        if double_width {
+            w.double_width(false);
+        }
+        if style.condensed && !double_width {
+            w.condensed(false);
+        }
+        w.newline();
+    }
+    Ok(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str, actual: &[u8], expected: &[u8]) {
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let path = format!(
+                "{}/tests/fixtures/escp/{}.bin",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            );
+            std::fs::write(path, actual).unwrap();
+            return;
+        }
+        assert_eq!(actual, expected, "{} does not match its golden file", name);
+    }
+
+    fn bill() -> Receipt {
+        let heading = Style {
+            bold: true,
+            double_width: true,
+            align: Align::Center,
+            ..Style::default()
+        };
+        let items = Style {
+            condensed: true,
+            ..Style::default()
+        };
+        let mut receipt = Receipt::new(40);
+        receipt.text("CITY MEDICALS", heading);
+        receipt.rule('-');
+        receipt.text("Dolo 650 Tablet        2 x 30.00   60.00", items);
+        receipt.feed(1);
+        receipt.text(
+            "TOTAL: 60.00",
+            Style {
+                bold: true,
+                align: Align::Right,
+                ..Style::default()
+            },
+        );
+        receipt
+    }
+
+    #[test]
+    fn tear_off_bill() {
+        let bytes = encode(&bill(), &EscpOptions::default()).unwrap();
+        // Init, left margin 0, 1/6" spacing, no perforation skip
+        assert!(bytes.starts_with(b"\x1b@\x1bl\x00\x1b2\x1bO"));
+        assert!(bytes.ends_with(&b"TOTAL: 60.00\x1bF\r\n\r\n\r\n\r\n\r\n\r\n\r\n"[..]));
+        golden(
+            "tear_off",
+            &bytes,
+            include_bytes!("../../tests/fixtures/escp/tear_off.bin"),
+        );
+    }
+
+    #[test]
+    fn form_length_bill() {
+        let mut receipt = bill();
+        receipt.blocks.push(Block::PageBreak);
+        receipt.text("Page 2", Style::default());
+        let options = EscpOptions {
+            line_spacing: LineSpacing::EightLpi,
+            feed: PaperFeed::FormLength(44),
+            ..EscpOptions::default()
+        };
+        let bytes = encode(&receipt, &options).unwrap();
+        // Init, left margin 0, 1/8" spacing, 44 line form
+        assert!(bytes.starts_with(b"\x1b@\x1bl\x00\x1b0\x1bC\x2c"));
+        assert!(bytes.ends_with(b"\x0cPage 2\r\n\x0c"));
+        golden(
+            "form_length",
+            &bytes,
+            include_bytes!("../../tests/fixtures/escp/form_length.bin"),
+        );
+    }
+
+    #[test]
+    fn custom_line_spacing() {
+        let mut w = EscpWriter::new();
+        w.line_spacing(LineSpacing::Custom(30))
+            .condensed(true)
+            .text("₹5")
+            .condensed(false);
+        assert_eq!(w.into_bytes(), b"\x1b@\x1b3\x1e\x0fRs.5\x12");
+    }
+    #[test]
+    fn line_spacing_setting_never_stops_the_feed() {
+        let conn = crate::print::fixtures::database();
+        let spacing = |lpi: &str| {
+            conn.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES ('dot_matrix_lines_per_inch', ?1)",
+                [lpi],
+            )
+            .unwrap();
+            EscpOptions::from_settings(&conn).line_spacing
+        };
+        assert_eq!(spacing("6"), LineSpacing::SixLpi);
+        assert_eq!(spacing("9"), LineSpacing::Custom(24));
+        assert_eq!(spacing("216"), LineSpacing::Custom(1));
+        assert_eq!(spacing("250"), LineSpacing::Custom(1));
+    }
+}
@@ -0,0 +1,534 @@
+// =====================================================
+// ESC/POS Encoder for Thermal Receipt Printers
+// Epson TM series and compatibles (58mm / 80mm)
+// =====================================================
+
+use std::path::PathBuf;
+
+use super::bitmap::Bitmap;
+use super::receipt::{align_to, transliterate, Align, Block, Receipt, Style};
+use super::unicode::{self, Cell, LazyFonts, Piece};
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+const LF: u8 = 0x0A;
+
+/// Font A cells are 12 x 24 dots; bit images on a text line are one band
+/// of that height
+const FONT_A_WIDTH: usize = 12;
+const BAND_HEIGHT: usize = 24;
+
+/// Paper cut issued at the end of the receipt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CutMode {
+    Partial,
+    Full,
+    None,
+}
+
+/// How QR codes are sent to the printer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QrMode {
+    /// The printer encodes and draws the symbol itself (GS ( k)
+    Native,
+    /// The symbol is drawn here and sent as a raster image, for clones
+    /// whose firmware lacks the QR commands
+    Raster,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EscposOptions {
+    /// Characters per line in font A
+    pub columns: usize,
+    /// Printable width in dots (for graphics)
+    pub dots: usize,
+    pub cut: CutMode,
+    pub qr_mode: QrMode,
+    /// Shop logo printed above the header, if configured
+    pub logo_path: Option<PathBuf>,
+    /// Font for lines the printer cannot show as text (Tamil names);
+    /// a system font is looked for when not set
+    pub unicode_font: Option<PathBuf>,
+}
+
+impl Default for EscposOptions {
+    fn default() -> Self {
+        EscposOptions::for_width_mm(80)
+    }
+}
+
+impl EscposOptions {
+    /// Columns and dot width for a paper roll width.
+    /// 58mm rolls print 32 columns, 76mm rolls 42 and 80mm rolls 48.
+    pub fn for_width_mm(width_mm: u32) -> Self {
+        let (columns, dots) = match width_mm {
+            0..=58 => (32, 384),
+            59..=76 => (42, 512),
+            _ => (48, 576),
+        };
+        EscposOptions {
+            columns,
+            dots,
+            cut: CutMode::Partial,
+            qr_mode: QrMode::Native,
+            logo_path: None,
+            unicode_font: None,
+        }
+    }
+
+    /// Read `thermal_printer_width` and the other `thermal_*` settings.
+    /// `width_mm` (from a printer profile) takes precedence over the setting.
+    pub fn from_settings(conn: &rusqlite::Connection, width_mm: Option<u32>) -> Self {
+        let width_mm = width_mm
+            .unwrap_or_else(|| crate::db::get_setting_or(conn, "thermal_printer_width", 80));
+        let mut options = EscposOptions::for_width_mm(width_mm);
+
+        let columns: usize = crate::db::get_setting_or(conn, "thermal_printer_columns", 0);
+        if columns > 0 {
+            options.columns = columns;
+        }
+
+        options.cut = match crate::db::get_setting(conn, "thermal_cut_mode").as_deref() {
+            Some("full") => CutMode::Full,
+            Some("none") => CutMode::None,
+            _ => CutMode::Partial,
+        };
+
+        if crate::db::get_setting(conn, "thermal_qr_mode").as_deref() == Some("raster") {
+            options.qr_mode = QrMode::Raster;
+        }
+
+        options.logo_path = crate::db::get_setting(conn, "shop_logo_path")
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+        options.unicode_font = unicode::font_setting(conn);
+
+        options
+    }
+}
+
+/// Low-level ESC/POS command writer
+pub struct EscposWriter {
+    buf: Vec<u8>,
+}
+
+impl Default for EscposWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EscposWriter {
+    /// Start a new job with ESC @ (initialize printer) and PC437, the code
+    /// page `transliterate` writes for. ESC @ alone restores the page set
+    /// by the printer's switches, which is not PC437 on every clone.
+    pub fn new() -> Self {
+        let mut w = EscposWriter {
+            buf: vec![ESC, b'@'],
+        };
+        w.code_page(0);
+        w
+    }
+
+    /// Character code table (ESC t n); 0 is PC437
+    pub fn code_page(&mut self, n: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b't', n]);
+        self
+    }
+
+    pub fn align(&mut self, align: Align) -> &mut Self {
+        let n = match align {
+            Align::Left => 0,
+            Align::Center => 1,
+            Align::Right => 2,
+        };
+        self.buf.extend_from_slice(&[ESC, b'a', n]);
+        self
+    }
+
+    pub fn bold(&mut self, on: bool) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'E', on as u8]);
+        self
+    }
+
+    /// Character size (GS ! n)
+    pub fn size(&mut self, double_width: bool, double_height: bool) -> &mut Self {
+        let n = (if double_width { 0x10 } else { 0 }) | (if double_height { 0x01 } else { 0 });
+        self.buf.extend_from_slice(&[GS, b'!', n]);
+        self
+    }
+
+    /// Write text, replacing anything outside the printer code page
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.buf.extend_from_slice(transliterate(text).as_bytes());
+        self
+    }
+
+    pub fn newline(&mut self) -> &mut Self {
+        self.buf.push(LF);
+        self
+    }
+
+    /// Print and feed n lines (ESC d n)
+    pub fn feed(&mut self, lines: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'd', lines]);
+        self
+    }
+
+    /// Print a raster bit image (GS v 0)
+    pub fn raster(&mut self, bitmap: &Bitmap) -> &mut Self {
+        let row_bytes = bitmap.row_bytes();
+        let height = bitmap.height;
+        self.buf.extend_from_slice(&[
+            GS,
+            b'v',
+            b'0',
+            0,
+            (row_bytes & 0xFF) as u8,
+            (row_bytes >> 8) as u8,
+            (height & 0xFF) as u8,
+            (height >> 8) as u8,
+        ]);
+        self.buf.extend_from_slice(&bitmap.packed_rows());
+        self
+    }
+
+    /// A bit image up to 24 dots tall on the current line, where text can
+    /// go either side of it (ESC * 33: 24-dot double density)
+    pub fn bit_image(&mut self, bitmap: &Bitmap) -> &mut Self {
+        let width = bitmap.width;
+        self.buf
+            .extend_from_slice(&[ESC, b'*', 33, (width & 0xFF) as u8, (width >> 8) as u8]);
+        for x in 0..width {
+            for top in (0..BAND_HEIGHT).step_by(8) {
+                let column = (0..8)
+                    .filter(|dot| bitmap.get(x, top + dot))
+                    .fold(0u8, |column, dot| column | (0x80 >> dot));
+                self.buf.push(column);
+            }
+        }
+        self
+    }
+
+    /// Print a QR code with the printer's own encoder (GS ( k): model 2,
+    /// error correction level M, `module` dots per module
+    pub fn qr(&mut self, data: &str, module: u8) -> &mut Self {
+        let stored = data.len() + 3;
+        self.buf
+            .extend_from_slice(&[GS, b'(', b'k', 4, 0, 49, 65, 50, 0]);
+        self.buf
+            .extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 67, module.clamp(1, 16)]);
+        self.buf
+            .extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 69, 49]);
+        self.buf.extend_from_slice(&[
+            GS,
+            b'(',
+            b'k',
+            (stored & 0xFF) as u8,
+            (stored >> 8) as u8,
+            49,
+            80,
+            48,
+        ]);
+        self.buf.extend_from_slice(data.as_bytes());
+        self.buf
+            .extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 81, 48]);
+        self
+    }
+
+    /// Feed to the cutter and cut (GS V m n)
+    pub fn cut(&mut self, mode: CutMode) -> &mut Self {
+        match mode {
+            CutMode::Partial => self.buf.extend_from_slice(&[GS, b'V', 66, 3]),
+            CutMode::Full => self.buf.extend_from_slice(&[GS, b'V', 65, 3]),
+            CutMode::None => self.buf.extend_from_slice(&[ESC, b'd', 4]),
+        }
+        self
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Encode a receipt as an ESC/POS byte stream, wrapped to the paper width.
+/// Fails when a line has a character no font can draw.
+pub fn encode(receipt: &Receipt, options: &EscposOptions) -> Result<Vec<u8>, String> {
+    let receipt = receipt.reflow(options.columns);
+    let fonts = LazyFonts::new(options.unicode_font.clone());
+    let mut w = EscposWriter::new();
+
+    for block in &receipt.blocks {
+        match block {
+            Block::Text { text, style } if unicode::needs_graphics(text) => {
+                write_graphic_line(&mut w, text, style, options, &fonts)?
+            }
+            Block::Text { text, style } => write_line(&mut w, text, style),
+            Block::Rule(c) => {
+                w.text(&c.to_string().repeat(options.columns)).newline();
+            }
+            Block::Feed(lines) => {
+                w.feed(*lines);
+            }
+            Block::Image(bitmap) => {
+                w.align(Align::Center).raster(bitmap).align(Align::Left);
+            }
+            Block::Qr(data) => write_qr(&mut w, data, options),
+            // Rolls have no pages
+            Block::PageBreak => {}
+        }
+    }
+
+    w.cut(options.cut);
+    Ok(w.into_bytes())
+}
+
+/// QR codes take up to two thirds of the roll, in whole dots per module
+fn write_qr(w: &mut EscposWriter, data: &str, options: &EscposOptions) {
+    let symbol = match Bitmap::qr(data) {
+        Ok(symbol) => symbol,
+        Err(e) => {
+            log::warn!("Skipping QR code: {}", e);
+            return;
+        }
+    };
+    let module = (options.dots * 2 / 3 / symbol.width).clamp(1, 8);
+
+    w.align(Align::Center);
+    match options.qr_mode {
+        QrMode::Native => w.qr(data, module as u8),
+        QrMode::Raster => w.raster(&symbol.scaled(module, module)),
+    };
+    w.align(Align::Left);
+}
+
+fn write_line(w: &mut EscposWriter, text: &str, style: &Style) {
+    // Thermal heads have a single font size per line; condensed rows are
+    // already wrapped to the roll width so they print in font A
+    let sized = style.double_width || style.double_height;
+
+    if style.align != Align::Left {
+        w.align(style.align);
+    }
+    if style.bold {
+        w.bold(true);
+    }
+    if sized {
+        w.size(style.double_width, style.double_height);
+    }
+
+    w.text(text);
+    w.newline();
+
+    if sized {
+        w.size(false, false);
+    }
+    if style.bold {
+        w.bold(false);
+    }
+    if style.align != Align::Left {
+        w.align(Align::Left);
+    }
+}
+
+/// Print a line with characters outside the code page, one font A cell
+/// (12 x 24 dots on 80mm) per column: the words the printer can show as
+/// text, the others as bit images between them. Lines with no text words,
+/// double-height lines and rolls whose columns are not font A cells are
+/// drawn whole as a raster image.
+fn write_graphic_line(
+    w: &mut EscposWriter,
+    text: &str,
+    style: &Style,
+    options: &EscposOptions,
+    fonts: &LazyFonts,
+) -> Result<(), String> {
+    let base = options.dots / options.columns.max(1);
+    let mut cell = Cell {
+        width: base,
+        height: 2 * base,
+        aspect: 1.0,
+    };
+    let mut columns = options.columns;
+    if style.double_width {
+        cell = cell.with_width(2 * base);
+        columns /= 2;
+    }
+    if style.double_height {
+        cell = cell.with_height(2 * cell.height);
+    }
+    let fonts = fonts.get();
+    let pieces = if base == FONT_A_WIDTH && cell.height == BAND_HEIGHT {
+        let line = match style.align {
+            Align::Left => text.to_string(),
+            align => align_to(text, columns, align),
+        };
+        fonts.mixed_line(&line, columns, cell, style.bold)?
+    } else {
+        None
+    };
+    let Some(pieces) = pieces else {
+        w.raster(&fonts.render_line(text, columns, cell, style.align, style.bold)?);
+        return Ok(());
+    };
+
+    // Spaces and text take the line's size so the images land on the grid
+    if style.bold {
+        w.bold(true);
+    }
+    if style.double_width {
+        w.size(true, false);
+    }
+    let mut at = 0;
+    for (column, piece) in &pieces {
+        w.text(&" ".repeat(column.saturating_sub(at)));
+        at = at.max(*column);
+        match piece {
+            Piece::Text(text) => {
+                w.text(text);
+                at += text.chars().count();
+            }
+            Piece::Image(bitmap) => {
+                w.bit_image(bitmap);
+                at += bitmap.width / cell.width;
+            }
+        }
+    }
+    w.newline();
+    if style.double_width {
+        w.size(false, false);
+    }
+    if style.bold {
+        w.bold(false);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str, actual: &[u8], expected: &[u8]) {
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let path = format!(
+                "{}/tests/fixtures/escpos/{}.bin",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            );
+            std::fs::write(path, actual).unwrap();
+            return;
+        }
+        assert_eq!(actual, expected, "{} does not match its golden file", name);
+    }
+
+    fn bill() -> Receipt {
+        let mut receipt = Receipt::new(40);
+        receipt.text(
+            "CITY MEDICALS",
+            Style {
+                bold: true,
+                double_width: true,
+                align: Align::Center,
+                ..Style::default()
+            },
+        );
+        receipt.rule('-');
+        receipt.text("Paracetamol 500mg Tablet  2   60.00", Style::default());
+        receipt.text(
+            "TOTAL: ₹60.00",
+            Style {
+                bold: true,
+                double_height: true,
+                align: Align::Right,
+                ..Style::default()
+            },
+        );
+        receipt
+    }
+
+    /// Text of the printed lines. Every command before the text ends in a
+    /// parameter byte below 0x20, so the text starts after the last one.
+    fn lines(bytes: &[u8]) -> Vec<String> {
+        bytes
+            .split(|&b| b == LF)
+            .map(|line| {
+                let start = line
+                    .iter()
+                    .rposition(u8::is_ascii_control)
+                    .map_or(0, |i| i + 1);
+                String::from_utf8_lossy(&line[start..]).to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn receipt_on_58mm_roll() {
+        let bytes = encode(&bill(), &EscposOptions::for_width_mm(58)).unwrap();
+        // Init, PC437, centered bold double-width heading
+        assert!(bytes.starts_with(b"\x1b@\x1bt\x00\x1ba\x01\x1bE\x01\x1d!\x10CITY MEDICALS\n"));
+        assert!(bytes.ends_with(b"\x1d!\x01TOTAL: Rs.60.00\n\x1d!\x00\x1bE\x00\x1ba\x00\x1dVB\x03"));
+        golden(
+            "receipt_58mm",
+            &bytes,
+            include_bytes!("../../tests/fixtures/escpos/receipt_58mm.bin"),
+        );
+    }
+
+    #[test]
+    fn rows_are_reflowed_to_the_roll_width() {
+        let bytes = encode(&bill(), &EscposOptions::for_width_mm(58)).unwrap();
+        let narrow = lines(&bytes);
+        assert!(narrow.contains(&"-".repeat(32)), "{:?}", narrow);
+        // The amount keeps its distance from the right edge; the name wraps
+        assert!(
+            narrow.iter().all(|l| l.chars().count() <= 32),
+            "{:?}",
+            narrow
+        );
+        assert!(
+            narrow.iter().any(|l| l.ends_with("  2   60.00")),
+            "{:?}",
+            narrow
+        );
+
+        let wide = lines(&encode(&bill(), &EscposOptions::for_width_mm(80)).unwrap());
+        assert!(wide.contains(&"-".repeat(48)), "{:?}", wide);
+    }
+
+    #[test]
+    fn raster_header_gives_width_in_bytes_and_height_in_dots() {
+        let mut bitmap = Bitmap::new(20, 3);
+        bitmap.set(0, 0, true);
+        bitmap.set(19, 2, true);
+        let mut w = EscposWriter::new();
+        w.raster(&bitmap);
+        assert_eq!(
+            w.into_bytes(),
+            [
+                b"\x1b@\x1bt\x00\x1dv0\x00\x03\x00\x03\x00".as_slice(),
+                &[0x80, 0, 0, 0, 0, 0, 0, 0, 0x10]
+            ]
+            .concat()
+        );
+
+        // Sizes over 255 are split into low and high bytes
+        let mut w = EscposWriter::new();
+        w.raster(&Bitmap::new(576, 300));
+        let bytes = w.into_bytes();
+        assert_eq!(&bytes[5..13], b"\x1dv0\x00\x48\x00\x2c\x01");
+        assert_eq!(bytes.len(), 13 + 72 * 300);
+    }
+
+    #[test]
+    fn cut_modes() {
+        let cut = |mode| {
+            let mut w = EscposWriter::new();
+            w.cut(mode);
+            w.into_bytes()[5..].to_vec()
+        };
+        assert_eq!(cut(CutMode::Partial), b"\x1dVB\x03");
+        assert_eq!(cut(CutMode::Full), b"\x1dVA\x03");
+        assert_eq!(cut(CutMode::None), b"\x1bd\x04");
+    }
+}
@@ -0,0 +1,52 @@
+// =====================================================
+// Test Fixtures
+// In-memory databases built by the numbered migrations,
+// so tests see the same schema as medbill.db
+// =====================================================
+
+use rusqlite::Connection;
+
+/// An empty database at the latest schema version
+pub fn database() -> Connection {
+    let mut conn = Connection::open_in_memory().unwrap();
+    let unused = std::env::temp_dir().join("medbill-fixture-backups-unused");
+    crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &unused).unwrap();
+    conn
+}
+
+/// The shop, two users, three batches and bill 1: a cash bill by user 1
+/// for Paracetamol (2 strips), Azithromycin (1 strip 5 tablets, Schedule H)
+/// and a cough syrup
+pub fn seed(conn: &Connection) {
+    conn.execute_batch(
+        "INSERT INTO settings (key, value) VALUES ('shop_name', 'Test Medical Store'),
+             ('shop_phone', '9876543210'), ('shop_gstin', '33AABCU9603R1ZM');
+         INSERT INTO users (id, username, password_hash, full_name, role) VALUES
+             (1, 'admin', '-', 'Admin', 'admin'),
+             (2, 'counter', '-', 'Counter Staff', 'staff');
+         INSERT INTO medicines (id, name) VALUES
+             (1, 'Paracetamol 500mg'), (2, 'Azithromycin 500mg Tablets IP'), (3, 'Cough Syrup');
+         INSERT INTO batches (id, medicine_id, batch_number, expiry_date, purchase_price, mrp,
+                 selling_price, gst_rate, is_schedule, quantity, tablets_per_strip, rack, box)
+             VALUES
+             (1, 1, 'BT2024001', '2027-06-30', 18, 30, 25, 12, 0, 500, 10, 'A1', '1'),
+             (2, 2, 'BT2024002', '2027-12-31', 25, 40, 35, 12, 1, 200, 10, 'C3', '2'),
+             (3, 3, 'CS01', '2027-01-31', 70, 110, 100, 5, 0, 20, 1, NULL, NULL);
+         INSERT INTO bills (id, bill_number, bill_date, customer_name, user_id, subtotal,
+                 discount_amount, taxable_amount, cgst_amount, sgst_amount, total_gst, round_off,
+                 grand_total, payment_mode, cash_amount)
+             VALUES (1, 'INV-2425-00001', '2026-01-02 10:30:00', 'John Doe', 1, 1000, 50,
+                 945.24, 56.1, 56.1, 112.2, 0.48, 952, 'CASH', 952);
+         INSERT INTO bill_items (id, bill_id, batch_id, medicine_id, medicine_name, batch_number,
+                 hsn_code, quantity, tablets_per_strip, mrp, selling_price, discount_amount,
+                 taxable_amount, gst_rate, cgst_amount, sgst_amount, total_amount)
+             VALUES
+             (1, 1, 1, 1, 'Paracetamol 500mg', 'BT2024001', '3004', 20, 10, 30, 25, 25, 425,
+                 12, 25.5, 25.5, 476),
+             (2, 1, 2, 2, 'Azithromycin 500mg Tablets IP', 'BT2024002', '3004', 15, NULL, 40,
+                 35, 26.25, 425, 12, 25.5, 25.5, 476),
+             (3, 1, 3, 3, 'Cough Syrup', 'CS01', '3004', 1, 1, 110, 100, 0, 95.24, 5, 2.38,
+                 2.38, 100);",
+    )
+    .unwrap();
+}
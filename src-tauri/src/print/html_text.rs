@@ -0,0 +1,572 @@
+// =====================================================
+// HTML to Text
+// Lays out bill HTML as fixed-width text for printers
+// that cannot render it: tables become aligned columns,
+// <pre> blocks are kept exactly as written
+// =====================================================
+
+use scraper::{CaseSensitivity, ElementRef, Html, Node};
+
+use super::receipt::{align_to, wrap_words, Align};
+
+/// Spaces between table columns
+const COLUMN_GAP: usize = 2;
+/// Columns are not narrowed below this unless nothing else fits
+const MIN_COLUMN: usize = 3;
+
+/// Elements with nothing printable in them
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "title", "meta", "link", "noscript", "template", "button", "input",
+    "select", "textarea",
+];
+
+/// Elements that start on a new line
+const BLOCKS: &[&str] = &[
+    "html",
+    "body",
+    "div",
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "footer",
+    "section",
+    "article",
+    "main",
+    "nav",
+    "aside",
+    "address",
+    "blockquote",
+    "center",
+    "form",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "tr",
+    "caption",
+    "figure",
+    "figcaption",
+];
+
+/// Render `html` as plain text lines at most `width` characters wide.
+/// Entities are decoded by the parser; lines inside `<pre>` are never wrapped.
+pub fn render(html: &str, width: usize) -> String {
+    let document = Html::parse_document(html);
+    let mut writer = Writer::new(Some(width.max(1)));
+    writer.children(document.root_element());
+    writer.finish()
+}
+
+/// Accumulates output lines while walking the DOM
+struct Writer {
+    /// `None` lays out a table cell, which is wrapped later to its column
+    width: Option<usize>,
+    lines: Vec<String>,
+    inline: String,
+    space: bool,
+    align: Vec<Align>,
+    /// Item numbers of the enclosing lists; `None` for `<ul>`
+    lists: Vec<Option<usize>>,
+}
+
+impl Writer {
+    fn new(width: Option<usize>) -> Self {
+        Writer {
+            width,
+            lines: Vec::new(),
+            inline: String::new(),
+            space: false,
+            align: Vec::new(),
+            lists: Vec::new(),
+        }
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if SKIPPED.contains(&name) || has_class(element, "no-print") {
+            return;
+        }
+
+        match name {
+            "br" => self.break_line(),
+            "hr" => self.rule('-'),
+            "pre" => self.pre(element),
+            "table" => self.table(element),
+            "td" | "th" => {
+                // A cell outside a table; keep its text apart from the next one
+                self.children(element);
+                self.space = true;
+            }
+            _ if BLOCKS.contains(&name) => self.block(element),
+            _ => self.children(element),
+        }
+    }
+
+    fn block(&mut self, element: ElementRef) {
+        self.flush();
+
+        if is_separator(element) {
+            let double = element
+                .value()
+                .classes()
+                .any(|c| c.to_lowercase().contains("double"));
+            self.rule(if double { '=' } else { '-' });
+            return;
+        }
+
+        if let Some((left, right)) = self.split_row(element) {
+            self.justify(&left, &right);
+            return;
+        }
+
+        let align = text_align(element);
+        if let Some(align) = align {
+            self.align.push(align);
+        }
+
+        match element.value().name() {
+            "ul" => self.lists.push(None),
+            "ol" => self.lists.push(Some(0)),
+            "li" => {
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", n)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.inline.push_str(&marker);
+            }
+            _ => {}
+        }
+
+        self.children(element);
+        self.flush();
+
+        if matches!(element.value().name(), "ul" | "ol") {
+            self.lists.pop();
+        }
+        if align.is_some() {
+            self.align.pop();
+        }
+    }
+
+    /// Collapse whitespace the way a browser does
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c') {
+                self.space = !self.inline.is_empty();
+            } else {
+                if self.space && !self.inline.ends_with(' ') {
+                    self.inline.push(' ');
+                }
+                self.space = false;
+                push_char(&mut self.inline, c);
+            }
+        }
+    }
+
+    /// End the current line if it has any text
+    fn flush(&mut self) {
+        if !self.inline.trim().is_empty() {
+            self.break_line();
+        }
+        self.inline.clear();
+        self.space = false;
+    }
+
+    /// End the current line, even when it is empty
+    fn break_line(&mut self) {
+        let line = std::mem::take(&mut self.inline);
+        let line = line.trim();
+        self.space = false;
+
+        let Some(width) = self.width else {
+            self.lines.push(line.to_string());
+            return;
+        };
+        let align = self.align.last().copied().unwrap_or_default();
+        let wrapped = if line.chars().count() <= width {
+            vec![line.to_string()]
+        } else {
+            wrap_words(line, width)
+        };
+        for part in wrapped {
+            self.lines.push(align_to(&part, width, align));
+        }
+    }
+
+    fn rule(&mut self, c: char) {
+        self.flush();
+        // Inside a table cell the column width is not known yet
+        let width = self.width.unwrap_or(MIN_COLUMN);
+        self.lines.push(c.to_string().repeat(width));
+    }
+
+    fn pre(&mut self, element: ElementRef) {
+        self.flush();
+        // The parser already drops the newline right after <pre>
+        let text: String = element.text().collect();
+        for line in text.lines() {
+            let mut out = String::with_capacity(line.len());
+            line.chars().for_each(|c| push_char(&mut out, c));
+            self.lines.push(out);
+        }
+    }
+
+    /// A label and a value laid out as `<div><span>..</span> <span>..</span></div>`,
+    /// printed at the left and right edges of the line
+    fn split_row(&self, element: ElementRef) -> Option<(String, String)> {
+        self.width?;
+        let mut spans = Vec::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) if text.trim().is_empty() => {}
+                Node::Comment(_) => {}
+                Node::Element(e) if e.name() == "span" => spans.push(ElementRef::wrap(child)?),
+                _ => return None,
+            }
+        }
+        match spans.as_slice() {
+            [left, right] => Some((inline_text(*left), inline_text(*right))),
+            _ => None,
+        }
+    }
+
+    fn justify(&mut self, left: &str, right: &str) {
+        let width = self.width.unwrap_or(0);
+        let used = left.chars().count() + 1 + right.chars().count();
+        if used <= width {
+            let gap = width - used + 1;
+            self.lines
+                .push(format!("{}{}{}", left, " ".repeat(gap), right));
+        } else {
+            self.inline = left.to_string();
+            self.break_line();
+            self.lines.push(align_to(right, width, Align::Right));
+        }
+    }
+
+    fn table(&mut self, element: ElementRef) {
+        self.flush();
+
+        let rows = table_rows(element);
+        if rows.is_empty() {
+            return;
+        }
+        let count = rows.iter().map(|r| r.cells.len()).max().unwrap_or(0);
+        if count == 0 {
+            return;
+        }
+
+        let natural: Vec<usize> = (0..count)
+            .map(|c| {
+                rows.iter()
+                    .filter_map(|r| r.cells.get(c))
+                    .flat_map(|cell| cell.lines.iter())
+                    .map(|l| l.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let longest_word: Vec<usize> = (0..count)
+            .map(|c| {
+                rows.iter()
+                    .filter_map(|r| r.cells.get(c))
+                    .flat_map(|cell| cell.lines.iter())
+                    .flat_map(|l| l.split_whitespace())
+                    .map(|w| w.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let (widths, gap) = match self.width {
+            Some(width) => fit_columns(&natural, &longest_word, width),
+            None => (natural, COLUMN_GAP),
+        };
+        let total = widths.iter().sum::<usize>() + gap * (count - 1);
+
+        for row in &rows {
+            let cells: Vec<Vec<String>> = widths
+                .iter()
+                .enumerate()
+                .map(|(c, &w)| match row.cells.get(c) {
+                    Some(cell) => cell
+                        .lines
+                        .iter()
+                        .flat_map(|l| wrap_words(l, w))
+                        .map(|l| pad(&l, w, cell.align))
+                        .collect(),
+                    None => Vec::new(),
+                })
+                .collect();
+
+            let height = cells.iter().map(Vec::len).max().unwrap_or(0);
+            for i in 0..height {
+                let line: Vec<String> = cells
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, &w)| cell.get(i).cloned().unwrap_or_else(|| " ".repeat(w)))
+                    .collect();
+                self.lines.push(line.join(&" ".repeat(gap)));
+            }
+            if row.heading {
+                self.lines.push("-".repeat(total));
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+
+        let mut out = String::new();
+        let mut blank = true;
+        for line in &self.lines {
+            let line = line.trim_end();
+            if line.is_empty() {
+                if !blank {
+                    out.push('\n');
+                }
+                blank = true;
+            } else {
+                out.push_str(line);
+                out.push('\n');
+                blank = false;
+            }
+        }
+        while out.ends_with("\n\n") {
+            out.pop();
+        }
+        out
+    }
+}
+
+struct Cell {
+    lines: Vec<String>,
+    align: Align,
+}
+
+struct Row {
+    cells: Vec<Cell>,
+    /// The last row of `<thead>`, underlined when printed
+    heading: bool,
+}
+
+/// Rows of this table, leaving out those of nested tables
+fn table_rows(table: ElementRef) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for child in table.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "tr" => rows.push(table_row(child, false)),
+            "thead" | "tbody" | "tfoot" => {
+                let heading = child.value().name() == "thead";
+                let section: Vec<ElementRef> = child
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|e| e.value().name() == "tr")
+                    .collect();
+                let last = section.len().saturating_sub(1);
+                for (i, tr) in section.into_iter().enumerate() {
+                    rows.push(table_row(tr, heading && i == last));
+                }
+            }
+            _ => {}
+        }
+    }
+    rows
+}
+
+fn table_row(tr: ElementRef, heading: bool) -> Row {
+    let cells = tr
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| matches!(e.value().name(), "td" | "th"))
+        .map(|cell| {
+            let mut writer = Writer::new(None);
+            writer.children(cell);
+            writer.flush();
+            let mut lines = writer.lines;
+            while lines.last().is_some_and(|l| l.is_empty()) {
+                lines.pop();
+            }
+            Cell {
+                lines,
+                align: text_align(cell).unwrap_or_default(),
+            }
+        })
+        .collect();
+    Row { cells, heading }
+}
+
+/// Column widths and the gap between columns that fit the table in `width`.
+/// The widest column gives up a character at a time. Columns keep room for
+/// their longest word, first with the normal gap and then with a single
+/// space; only when that still does not fit are words split.
+fn fit_columns(natural: &[usize], longest_word: &[usize], width: usize) -> (Vec<usize>, usize) {
+    let floors: Vec<usize> = natural
+        .iter()
+        .zip(longest_word)
+        .map(|(&n, &w)| w.max(MIN_COLUMN).min(n))
+        .collect();
+
+    let mut widths = natural.to_vec();
+    for (gap, strict) in [(COLUMN_GAP, true), (1, true), (1, false)] {
+        widths = natural.to_vec();
+        let gaps = gap * widths.len().saturating_sub(1);
+        while widths.iter().sum::<usize>() + gaps > width {
+            let widest = widths
+                .iter()
+                .enumerate()
+                .filter(|&(c, &w)| w > if strict { floors[c] } else { 1 })
+                .max_by_key(|&(c, &w)| (w, std::cmp::Reverse(c)))
+                .map(|(c, _)| c);
+            match widest {
+                Some(c) => widths[c] -= 1,
+                None => break,
+            }
+        }
+        if widths.iter().sum::<usize>() + gaps <= width {
+            return (widths, gap);
+        }
+    }
+    (widths, 1)
+}
+
+/// The rupee sign is spelled out before layout; the printers' code pages
+/// cannot show it and replacing it later would push columns out of line
+fn push_char(out: &mut String, c: char) {
+    match c {
+        '₹' => out.push_str("Rs."),
+        '×' => out.push('x'),
+        '\u{00A0}' => out.push(' '),
+        c => out.push(c),
+    }
+}
+
+fn pad(text: &str, width: usize, align: Align) -> String {
+    let text = align_to(text, width, align);
+    let len = text.chars().count();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(len)))
+}
+
+/// Text of an inline element on one line
+fn inline_text(element: ElementRef) -> String {
+    let mut writer = Writer::new(None);
+    writer.children(element);
+    writer.flush();
+    writer.lines.join(" ")
+}
+
+fn has_class(element: ElementRef, class: &str) -> bool {
+    element
+        .value()
+        .has_class(class, CaseSensitivity::AsciiCaseInsensitive)
+}
+
+/// An empty `<div class="separator">` drawn with a CSS border
+fn is_separator(element: ElementRef) -> bool {
+    element
+        .value()
+        .classes()
+        .any(|c| c.to_lowercase().contains("separator"))
+        && element.text().all(|t| t.trim().is_empty())
+}
+
+/// `text-align` from the inline style
+fn text_align(element: ElementRef) -> Option<Align> {
+    let style = element.value().attr("style")?;
+    style.split(';').find_map(|decl| {
+        let (property, value) = decl.split_once(':')?;
+        if !property.trim().eq_ignore_ascii_case("text-align") {
+            return None;
+        }
+        match value.trim().to_lowercase().as_str() {
+            "center" => Some(Align::Center),
+            "right" | "end" => Some(Align::Right),
+            "left" | "start" => Some(Align::Left),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str, html: &str, width: usize, expected: &str) {
+        let actual = render(html, width);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let path = format!(
+                "{}/tests/fixtures/receipts/{}.txt",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            );
+            std::fs::write(path, &actual).unwrap();
+            return;
+        }
+        assert_eq!(actual, expected, "{} does not match its golden file", name);
+    }
+
+    #[test]
+    fn thermal_bill() {
+        golden(
+            "thermal",
+            include_str!("../../tests/fixtures/receipts/thermal.html"),
+            48,
+            include_str!("../../tests/fixtures/receipts/thermal.txt"),
+        );
+    }
+
+    #[test]
+    fn legal_bill() {
+        golden(
+            "legal",
+            include_str!("../../tests/fixtures/receipts/legal.html"),
+            80,
+            include_str!("../../tests/fixtures/receipts/legal.txt"),
+        );
+    }
+
+    #[test]
+    fn dot_matrix_bill() {
+        golden(
+            "dotmatrix",
+            include_str!("../../tests/fixtures/receipts/dotmatrix.html"),
+            80,
+            include_str!("../../tests/fixtures/receipts/dotmatrix.txt"),
+        );
+    }
+
+    #[test]
+    fn decodes_entities() {
+        let html =
+            "<p>Dolo&nbsp;650 &amp; Crocin &lt;10&gt; &#8377;5 &#x20B9;6 &times;2 &eacute;</p>\
+                    <pre>  a&amp;b   c</pre>";
+        assert_eq!(
+            render(html, 80),
+            "Dolo 650 & Crocin <10> Rs.5 Rs.6 x2 \u{e9}\n  a&b   c\n"
+        );
+    }
+}
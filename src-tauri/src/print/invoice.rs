@@ -0,0 +1,378 @@
+// =====================================================
+// A4 Tax Invoice
+// The legal-size bill as a PDF, laid out from the saved
+// bill so the file matches what the counter printed
+// =====================================================
+
+use super::bill::{self, Bill, Shop};
+use super::bitmap::Bitmap;
+use super::pdf::{self, Cell, Column, PageHeader, PdfDoc, Table, Weight, BODY_SIZE, SMALL_SIZE};
+use super::receipt::Align;
+use super::upi;
+
+/// Shop details for the page header
+pub fn shop_lines(shop: &Shop) -> Vec<String> {
+    let mut lines = vec![shop.name.clone()];
+    if !shop.address.is_empty() {
+        lines.push(shop.address.clone());
+    }
+    if !shop.phone.is_empty() {
+        lines.push(format!("Phone: {}", shop.phone));
+    }
+    if !shop.gstin.is_empty() {
+        lines.push(format!("GSTIN: {}", shop.gstin));
+    }
+    if !shop.drug_license.is_empty() {
+        lines.push(format!("D.L. No: {}", shop.drug_license));
+    }
+    lines
+}
+
+pub fn render(shop: &Shop, bill: &Bill) -> Result<PdfDoc, String> {
+    let mut right = vec![
+        format!("Invoice No: {}", bill.bill_number),
+        format!("Date: {}", bill::format_date_time(&bill.bill_date)),
+    ];
+    if !shop.state.is_empty() {
+        right.push(format!("Place of supply: {}", shop.state));
+    }
+    let mut doc = PdfDoc::new(PageHeader {
+        left: shop_lines(shop),
+        title: "TAX INVOICE".to_string(),
+        right,
+        footer: format!("Invoice {} - computer generated", bill.bill_number),
+    })?;
+
+    let customer = bill
+        .customer_name
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or("Walk-in Customer");
+    doc.paragraph(
+        &format!("Bill To: {}", customer),
+        BODY_SIZE,
+        Weight::Bold,
+        Align::Left,
+    );
+    if let Some(doctor) = bill.doctor_name.as_deref().filter(|d| !d.trim().is_empty()) {
+        doc.paragraph(
+            &format!("Prescribed by: Dr. {}", doctor),
+            BODY_SIZE,
+            Weight::Regular,
+            Align::Left,
+        );
+    }
+    if bill.status == "CANCELLED" {
+        doc.paragraph("*** CANCELLED ***", BODY_SIZE, Weight::Bold, Align::Center);
+    }
+    doc.space(3.0);
+
+    let mut items = Table::new(vec![
+        Column::right("#", 2.5),
+        Column::text("Product", 24.0),
+        Column::text("HSN", 6.0),
+        Column::right("Qty", 6.5),
+        Column::right("Rate", 9.0),
+        Column::right("GST%", 5.0),
+        Column::money("Disc", 8.0),
+        Column::money("Amount", 11.0),
+    ]);
+    for (i, item) in bill.items.iter().enumerate() {
+        let mut detail = vec![
+            format!("Batch {}", item.batch_number),
+            format!("Exp {}", bill::format_expiry(&item.expiry_date)),
+        ];
+        detail.extend(bill::location(item));
+        items.row(vec![
+            Cell::text((i + 1).to_string()),
+            Cell::Detail(item.medicine_name.clone(), detail.join(" · ")),
+            Cell::text(item.hsn_code.clone()),
+            Cell::text(bill::qty_display(item)),
+            Cell::Money(item.unit_price),
+            Cell::text(bill::rate(item.gst_rate)),
+            Cell::Money(item.discount_amount),
+            Cell::Money(item.total),
+        ]);
+    }
+    doc.table(&items);
+
+    doc.heading("GST Summary");
+    let mut gst = Table::new(vec![
+        Column::text("HSN", 4.0),
+        Column::right("GST%", 3.0),
+        Column::money("Taxable", 6.0),
+        Column::money("CGST", 5.0),
+        Column::money("SGST", 5.0),
+        Column::money("Total Tax", 6.0),
+    ]);
+    for line in bill::gst_breakup(&bill.items) {
+        gst.row(vec![
+            Cell::text(line.hsn_code.clone()),
+            Cell::text(bill::rate(line.gst_rate)),
+            Cell::Money(line.taxable),
+            Cell::Money(line.cgst),
+            Cell::Money(line.sgst),
+            Cell::Money(line.cgst + line.sgst),
+        ]);
+    }
+    doc.table(&gst);
+
+    let mut totals = vec![(
+        format!("Sub Total ({} items)", bill.items.len()),
+        pdf::rupees(bill.subtotal),
+    )];
+    if bill.discount_amount > 0.0 {
+        totals.push((
+            "Discount".to_string(),
+            format!("-{}", pdf::rupees(bill.discount_amount)),
+        ));
+    }
+    totals.push(("CGST".to_string(), pdf::rupees(bill.total_cgst)));
+    totals.push(("SGST".to_string(), pdf::rupees(bill.total_sgst)));
+    if bill.round_off != 0.0 {
+        totals.push(("Round Off".to_string(), pdf::rupees(bill.round_off)));
+    }
+    totals.push(("Grand Total".to_string(), pdf::rupees(bill.grand_total)));
+    doc.summary(&totals, true);
+    doc.space(2.0);
+
+    doc.paragraph(
+        &format!("Amount in words: {}", amount_in_words(bill.grand_total)),
+        BODY_SIZE,
+        Weight::Bold,
+        Align::Left,
+    );
+    doc.paragraph(
+        &format!("Payment: {}", bill::payment_line(bill)),
+        BODY_SIZE,
+        Weight::Regular,
+        Align::Left,
+    );
+    if let (Some(uri), Some(amount)) = (upi::for_bill(shop, bill), upi::amount_due(bill)) {
+        doc.space(2.0);
+        doc.paragraph(
+            &format!("Scan to pay {} by UPI", pdf::rupees(amount)),
+            BODY_SIZE,
+            Weight::Bold,
+            Align::Left,
+        );
+        doc.image(&Bitmap::qr(&uri)?, 30.0, Align::Left);
+        doc.paragraph(
+            &format!("UPI ID: {}", shop.upi_id),
+            SMALL_SIZE,
+            Weight::Regular,
+            Align::Left,
+        );
+    }
+    doc.space(10.0);
+    doc.paragraph(
+        "Authorized Signatory",
+        BODY_SIZE,
+        Weight::Bold,
+        Align::Right,
+    );
+    doc.paragraph(&shop.name, SMALL_SIZE, Weight::Regular, Align::Right);
+    doc.space(4.0);
+
+    doc.paragraph("Terms & Conditions:", SMALL_SIZE, Weight::Bold, Align::Left);
+    let state = if shop.state.is_empty() {
+        "local"
+    } else {
+        shop.state.as_str()
+    };
+    for term in [
+        "1. Goods once sold will not be taken back or exchanged.".to_string(),
+        "2. Please check the expiry date before use.".to_string(),
+        format!("3. Subject to {} jurisdiction only.", state),
+    ] {
+        doc.paragraph(&term, SMALL_SIZE, Weight::Regular, Align::Left);
+    }
+
+    Ok(doc)
+}
+
+const ONES: [&str; 20] = [
+    "",
+    "One",
+    "Two",
+    "Three",
+    "Four",
+    "Five",
+    "Six",
+    "Seven",
+    "Eight",
+    "Nine",
+    "Ten",
+    "Eleven",
+    "Twelve",
+    "Thirteen",
+    "Fourteen",
+    "Fifteen",
+    "Sixteen",
+    "Seventeen",
+    "Eighteen",
+    "Nineteen",
+];
+const TENS: [&str; 10] = [
+    "", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety",
+];
+
+/// Words for 0-999
+fn group_words(n: u64) -> String {
+    match n {
+        0..=19 => ONES[n as usize].to_string(),
+        20..=99 => {
+            let tens = TENS[(n / 10) as usize];
+            match n % 10 {
+                0 => tens.to_string(),
+                ones => format!("{} {}", tens, ONES[ones as usize]),
+            }
+        }
+        _ => match n % 100 {
+            0 => format!("{} Hundred", ONES[(n / 100) as usize]),
+            rest => format!("{} Hundred {}", ONES[(n / 100) as usize], group_words(rest)),
+        },
+    }
+}
+
+/// Words for a whole number in crores, lakhs and thousands
+fn indian_words(n: u64) -> String {
+    let mut parts = Vec::new();
+    if n >= 10_000_000 {
+        parts.push(format!("{} Crore", indian_words(n / 10_000_000)));
+    }
+    for (value, unit) in [(n / 100_000 % 100, "Lakh"), (n / 1000 % 100, "Thousand")] {
+        if value > 0 {
+            parts.push(format!("{} {}", group_words(value), unit));
+        }
+    }
+    if !n.is_multiple_of(1000) {
+        parts.push(group_words(n % 1000));
+    }
+    parts.join(" ")
+}
+
+/// `Nine Hundred Fifty Two Rupees Only`, in crores, lakhs and thousands
+/// like the printed invoice
+pub fn amount_in_words(amount: f64) -> String {
+    let paise_total = (amount.abs() * 100.0).round() as u64;
+    let rupees = paise_total / 100;
+    let paise = paise_total % 100;
+
+    let mut words = if rupees == 0 {
+        "Zero Rupees".to_string()
+    } else {
+        format!("{} Rupees", indian_words(rupees))
+    };
+    if paise > 0 {
+        words.push_str(&format!(" and {} Paise", group_words(paise)));
+    }
+    words.push_str(" Only");
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bill::BillItem;
+    use super::*;
+
+    #[test]
+    fn spells_amounts_in_lakhs_and_crores() {
+        assert_eq!(amount_in_words(0.0), "Zero Rupees Only");
+        assert_eq!(amount_in_words(952.0), "Nine Hundred Fifty Two Rupees Only");
+        assert_eq!(
+            amount_in_words(125_000.5),
+            "One Lakh Twenty Five Thousand Rupees and Fifty Paise Only"
+        );
+        assert_eq!(
+            amount_in_words(20_100_019.0),
+            "Two Crore One Lakh Nineteen Rupees Only"
+        );
+    }
+
+    #[test]
+    fn invoice_pdf_has_items_tax_and_totals() {
+        let shop = Shop {
+            name: "Test Medical Store".to_string(),
+            gstin: "33AABCU9603R1ZM".to_string(),
+            state: "Tamil Nadu".to_string(),
+            ..Shop::default()
+        };
+        let item = BillItem {
+            medicine_name: "Paracetamol 500mg".to_string(),
+            hsn_code: "3004".to_string(),
+            batch_number: "BT2024001".to_string(),
+            expiry_date: "2027-06-30".to_string(),
+            rack: Some("A1".to_string()),
+            quantity: 20,
+            tablets_per_strip: 10,
+            unit_price: 25.0,
+            discount_amount: 25.0,
+            taxable_value: 425.0,
+            gst_rate: 12.0,
+            cgst: 25.5,
+            sgst: 25.5,
+            total: 476.0,
+            ..BillItem::default()
+        };
+        let bill = Bill {
+            bill_number: "INV-2425-00001".to_string(),
+            bill_date: "2026-01-02 10:30:00".to_string(),
+            customer_name: Some("John Doe".to_string()),
+            subtotal: 1000.0,
+            discount_amount: 50.0,
+            total_cgst: 51.0,
+            total_sgst: 51.0,
+            grand_total: 952.0,
+            payment_mode: "CASH".to_string(),
+            cash_amount: 952.0,
+            status: "COMPLETED".to_string(),
+            items: vec![item.clone(), item],
+            ..Bill::default()
+        };
+
+        let doc = render(&shop, &bill).unwrap();
+        let bytes = doc.to_bytes("Invoice").unwrap();
+        let text = pdf_extract::extract_text_from_mem(&bytes).unwrap();
+
+        assert!(text.contains("TAX INVOICE"), "{}", text);
+        assert!(text.contains("Invoice No: INV-2425-00001"));
+        assert!(text.contains("GSTIN: 33AABCU9603R1ZM"));
+        assert!(text.contains("Bill To: John Doe"));
+        assert!(text.contains("Paracetamol 500mg"));
+        assert!(text.contains("Batch BT2024001"));
+        assert!(text.contains("2S"));
+        assert!(text.contains("₹952.00"));
+        assert!(text.contains("₹850.00"));
+        assert!(text.contains("Nine Hundred Fifty Two Rupees Only"));
+        assert!(text.contains("Subject to Tamil Nadu jurisdiction only."));
+        assert!(text.contains("Page 1 of 1"));
+        assert!(!text.contains("by UPI"));
+    }
+
+    #[test]
+    fn online_invoice_has_a_upi_qr_code() {
+        let shop = Shop {
+            name: "Test Medical Store".to_string(),
+            upi_id: "testmedical@okaxis".to_string(),
+            ..Shop::default()
+        };
+        let bill = Bill {
+            bill_number: "INV-2425-00002".to_string(),
+            bill_date: "2026-01-02 10:30:00".to_string(),
+            grand_total: 952.0,
+            payment_mode: "SPLIT".to_string(),
+            cash_amount: 452.0,
+            online_amount: 500.0,
+            status: "COMPLETED".to_string(),
+            ..Bill::default()
+        };
+
+        let bytes = render(&shop, &bill).unwrap().to_bytes("Invoice").unwrap();
+        let text = pdf_extract::extract_text_from_mem(&bytes).unwrap();
+
+        assert!(text.contains("Scan to pay ₹500.00 by UPI"), "{}", text);
+        assert!(text.contains("UPI ID: testmedical@okaxis"));
+        assert!(bytes.windows(14).any(|w| w == b"/Subtype/Image"));
+    }
+}
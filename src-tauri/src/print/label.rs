@@ -0,0 +1,592 @@
+// =====================================================
+// Shelf and Batch Labels
+// Stickers for racks and loose strips with a barcode of
+// the batch, encoded as ZPL, TSPL or EPL for thermal
+// label printers
+// =====================================================
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::bill::{self, format_expiry};
+use super::profiles::{PrintFormat, PrinterProfile};
+use super::receipt::transliterate;
+
+/// Label stock used when the label profile has no `WxHmm` size
+const DEFAULT_SIZE_MM: (f32, f32) = (50.0, 25.0);
+const MARGIN_MM: f32 = 1.5;
+const NAME_HEIGHT_MM: f32 = 3.2;
+const LINE_HEIGHT_MM: f32 = 2.6;
+const LINE_GAP_MM: f32 = 0.5;
+/// Largest DataMatrix symbol, so the text beside it keeps some room
+const MATRIX_MAX_MM: f32 = 12.0;
+
+/// Barcode printed on each label
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    /// A linear barcode under the text; any handheld scanner reads it
+    Code128,
+    /// A square 2D code beside the text; fits smaller stickers
+    DataMatrix,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LabelOptions {
+    pub width_mm: f32,
+    pub height_mm: f32,
+    /// Gap between labels on the liner
+    pub gap_mm: f32,
+    /// Print head resolution, usually 203 or 300
+    pub dpi: u32,
+    pub symbology: Symbology,
+}
+
+impl Default for LabelOptions {
+    fn default() -> Self {
+        LabelOptions {
+            width_mm: DEFAULT_SIZE_MM.0,
+            height_mm: DEFAULT_SIZE_MM.1,
+            gap_mm: 2.0,
+            dpi: 203,
+            symbology: Symbology::Code128,
+        }
+    }
+}
+
+impl LabelOptions {
+    /// Label size from the profile's paper size, the rest from the
+    /// `label_*` settings
+    pub fn from_settings(conn: &Connection, profile: &PrinterProfile) -> Self {
+        let (width_mm, height_mm) = profile.label_size_mm().unwrap_or(DEFAULT_SIZE_MM);
+        let dpi: u32 = crate::db::get_setting_or(conn, "label_dpi", 203);
+        LabelOptions {
+            width_mm,
+            height_mm,
+            gap_mm: crate::db::get_setting_or(conn, "label_gap_mm", 2.0),
+            dpi: if dpi > 0 { dpi } else { 203 },
+            symbology: match crate::db::get_setting(conn, "label_barcode").as_deref() {
+                Some("datamatrix") => Symbology::DataMatrix,
+                _ => Symbology::Code128,
+            },
+        }
+    }
+
+    fn dots(&self, mm: f32) -> u32 {
+        (mm * self.dpi as f32 / 25.4).round() as u32
+    }
+}
+
+/// What goes on one sticker
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LabelData {
+    pub medicine_name: String,
+    pub mrp: f64,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub rack: Option<String>,
+    pub box_label: Option<String>,
+    /// Internal item code carried by the barcode
+    pub item_code: String,
+    /// Stickers to print
+    pub copies: u32,
+}
+
+/// `MB` and the zero-padded batch id, so a scan finds the exact batch
+pub fn item_code(batch_id: i64) -> String {
+    format!("MB{:07}", batch_id)
+}
+
+/// Labels for the given batches, `copies` of each
+pub fn load_batches(
+    conn: &Connection,
+    batch_ids: &[i64],
+    copies: u32,
+) -> Result<Vec<LabelData>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.name, COALESCE(b.mrp, 0), b.batch_number, b.expiry_date, b.rack, b.box
+             FROM batches b
+             JOIN medicines m ON m.id = b.medicine_id
+             WHERE b.id = ?1",
+        )
+        .map_err(|e| format!("Failed to load batch: {}", e))?;
+
+    batch_ids
+        .iter()
+        .map(|&id| {
+            stmt.query_row(params![id], |row| {
+                Ok(LabelData {
+                    medicine_name: row.get(0)?,
+                    mrp: row.get(1)?,
+                    batch_number: row.get(2)?,
+                    expiry_date: row.get(3)?,
+                    rack: row.get(4)?,
+                    box_label: row.get(5)?,
+                    item_code: item_code(id),
+                    copies: copies.max(1),
+                })
+            })
+            .map_err(|e| format!("Failed to load batch {}: {}", id, e))
+        })
+        .collect()
+}
+
+/// Labels for every line of a purchase invoice. Unless `per_item` is
+/// given, each line gets one label per strip received, free ones included.
+pub fn load_purchase(
+    conn: &Connection,
+    purchase_id: i64,
+    per_item: Option<u32>,
+) -> Result<Vec<LabelData>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT pi.medicine_name, COALESCE(pi.mrp, 0), pi.batch_number, pi.expiry_date,
+                    b.rack, b.box, b.id,
+                    COALESCE(pi.quantity, 0) + COALESCE(pi.free_quantity, 0)
+             FROM purchase_items pi
+             LEFT JOIN batches b ON b.id = COALESCE(pi.batch_id,
+                 (SELECT id FROM batches
+                  WHERE medicine_id = pi.medicine_id AND batch_number = pi.batch_number))
+             WHERE pi.purchase_id = ?1
+             ORDER BY pi.id",
+        )
+        .map_err(|e| format!("Failed to load purchase items: {}", e))?;
+
+    let labels = stmt
+        .query_map(params![purchase_id], |row| {
+            let batch_number: String = row.get(2)?;
+            let batch_id: Option<i64> = row.get(6)?;
+            let received: i64 = row.get(7)?;
+            Ok(LabelData {
+                medicine_name: row.get(0)?,
+                mrp: row.get(1)?,
+                expiry_date: row.get(3)?,
+                rack: row.get(4)?,
+                box_label: row.get(5)?,
+                item_code: batch_id
+                    .map(item_code)
+                    .unwrap_or_else(|| batch_number.clone()),
+                batch_number,
+                copies: per_item.unwrap_or(received.max(1) as u32),
+            })
+        })
+        .map_err(|e| format!("Failed to load purchase items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read purchase item: {}", e))?;
+
+    if labels.is_empty() {
+        return Err(format!("Purchase {} has no items to label", purchase_id));
+    }
+    Ok(labels)
+}
+
+/// One element of a label, positioned in printer dots from the top left
+#[derive(Clone, Debug, PartialEq)]
+enum Field {
+    Text {
+        x: u32,
+        y: u32,
+        height: u32,
+        width: u32,
+        text: String,
+    },
+    Barcode {
+        x: u32,
+        y: u32,
+        height: u32,
+        module: u32,
+        symbology: Symbology,
+        data: String,
+    },
+}
+
+/// Lay out one label: name, MRP, batch and expiry, location and the
+/// barcode, either underneath (Code128) or to the right (DataMatrix)
+fn layout(label: &LabelData, options: &LabelOptions) -> Vec<Field> {
+    let margin = options.dots(MARGIN_MM);
+    let width = options.dots(options.width_mm);
+    let height = options.dots(options.height_mm);
+    let inner_height = height.saturating_sub(2 * margin);
+    // Narrowest bar: 2 dots at 203 dpi, 3 at 300
+    let module = (options.dpi as f32 / 100.0).round().max(1.0) as u32;
+
+    let matrix = match options.symbology {
+        Symbology::DataMatrix => options.dots(MATRIX_MAX_MM).min(inner_height),
+        Symbology::Code128 => 0,
+    };
+    let text_width =
+        width.saturating_sub(2 * margin + matrix + if matrix > 0 { margin } else { 0 });
+
+    let mut lines = vec![(NAME_HEIGHT_MM, label.medicine_name.trim().to_string())];
+    lines.push((LINE_HEIGHT_MM, format!("MRP ₹{:.2}", label.mrp)));
+    lines.push((
+        LINE_HEIGHT_MM,
+        format!(
+            "B:{}  EXP:{}",
+            label.batch_number.trim(),
+            format_expiry(&label.expiry_date)
+        ),
+    ));
+    if let Some(shelf) = bill::shelf(label.rack.as_deref(), label.box_label.as_deref()) {
+        lines.push((LINE_HEIGHT_MM, shelf));
+    }
+
+    let mut fields = Vec::new();
+    let mut y = margin;
+    for (size, text) in lines {
+        let line_height = options.dots(size);
+        fields.push(Field::Text {
+            x: margin,
+            y,
+            height: line_height,
+            width: text_width,
+            text,
+        });
+        y += line_height + options.dots(LINE_GAP_MM);
+    }
+
+    fields.push(match options.symbology {
+        Symbology::Code128 => Field::Barcode {
+            x: margin,
+            y,
+            height: height.saturating_sub(y + margin).max(module * 8),
+            module,
+            symbology: Symbology::Code128,
+            data: label.item_code.clone(),
+        },
+        // Item codes fit a 16x16 symbol
+        Symbology::DataMatrix => Field::Barcode {
+            x: width.saturating_sub(margin + matrix),
+            y: margin,
+            height: matrix,
+            module: (matrix / 16).max(module),
+            symbology: Symbology::DataMatrix,
+            data: label.item_code.clone(),
+        },
+    });
+    fields
+}
+
+/// Keep as many characters as fit in `width` dots
+fn fit(text: &str, width: u32, char_width: u32) -> String {
+    let max = (width / char_width.max(1)) as usize;
+    transliterate(text).chars().take(max).collect()
+}
+
+/// Encode labels in the command language of `format`
+pub fn encode(
+    labels: &[LabelData],
+    options: &LabelOptions,
+    format: PrintFormat,
+) -> Result<Vec<u8>, String> {
+    let text = match format {
+        PrintFormat::Zpl => zpl(labels, options),
+        PrintFormat::Tspl => tspl(labels, options),
+        PrintFormat::Epl => epl(labels, options),
+        other => {
+            return Err(format!(
+                "Labels need a ZPL, TSPL or EPL label printer, not {:?}. \
+                 Change the format of the Label printer profile.",
+                other
+            ))
+        }
+    };
+    Ok(text.into_bytes())
+}
+
+/// ZPL II: one ^XA..^XZ format per label, copies with ^PQ
+fn zpl(labels: &[LabelData], options: &LabelOptions) -> String {
+    // ^ and ~ start commands; there is nothing to escape them with
+    let clean = |text: &str| text.replace(['^', '~'], " ");
+    let mut out = String::new();
+    for label in labels {
+        out.push_str("^XA\n");
+        out.push_str(&format!(
+            "^PW{}\n^LL{}\n^LH0,0\n",
+            options.dots(options.width_mm),
+            options.dots(options.height_mm)
+        ));
+        for field in layout(label, options) {
+            match field {
+                Field::Text {
+                    x,
+                    y,
+                    height,
+                    width,
+                    text,
+                } => out.push_str(&format!(
+                    "^FO{},{}^A0N,{},{}^FD{}^FS\n",
+                    x,
+                    y,
+                    height,
+                    height,
+                    clean(&fit(&text, width, height * 3 / 5))
+                )),
+                Field::Barcode {
+                    x,
+                    y,
+                    height,
+                    module,
+                    symbology: Symbology::Code128,
+                    data,
+                } => out.push_str(&format!(
+                    "^FO{},{}^BY{}^BCN,{},N,N,N^FD{}^FS\n",
+                    x,
+                    y,
+                    module,
+                    height,
+                    clean(&data)
+                )),
+                Field::Barcode {
+                    x,
+                    y,
+                    module,
+                    symbology: Symbology::DataMatrix,
+                    data,
+                    ..
+                } => out.push_str(&format!(
+                    "^FO{},{}^BXN,{},200^FD{}^FS\n",
+                    x,
+                    y,
+                    module,
+                    clean(&data)
+                )),
+            }
+        }
+        out.push_str(&format!("^PQ{}\n^XZ\n", label.copies.max(1)));
+    }
+    out
+}
+
+/// TSPL: label size once, then CLS..PRINT per label
+fn tspl(labels: &[LabelData], options: &LabelOptions) -> String {
+    let quote = |text: &str| text.replace('"', "\\[\"]");
+    let mut out = format!(
+        "SIZE {} mm,{} mm\r\nGAP {} mm,0 mm\r\nDIRECTION 1\r\n",
+        options.width_mm, options.height_mm, options.gap_mm
+    );
+    for label in labels {
+        out.push_str("CLS\r\n");
+        for field in layout(label, options) {
+            match field {
+                Field::Text {
+                    x,
+                    y,
+                    height,
+                    width,
+                    text,
+                } => {
+                    // Font "0" is scalable; its size is given in points
+                    let points = (height as f32 * 72.0 / options.dpi as f32).round().max(1.0);
+                    out.push_str(&format!(
+                        "TEXT {},{},\"0\",0,{},{},\"{}\"\r\n",
+                        x,
+                        y,
+                        points,
+                        points,
+                        quote(&fit(&text, width, height * 3 / 5))
+                    ));
+                }
+                Field::Barcode {
+                    x,
+                    y,
+                    height,
+                    module,
+                    symbology: Symbology::Code128,
+                    data,
+                } => out.push_str(&format!(
+                    "BARCODE {},{},\"128\",{},0,0,{},{},\"{}\"\r\n",
+                    x,
+                    y,
+                    height,
+                    module,
+                    module,
+                    quote(&data)
+                )),
+                Field::Barcode {
+                    x,
+                    y,
+                    height,
+                    module,
+                    symbology: Symbology::DataMatrix,
+                    data,
+                } => out.push_str(&format!(
+                    "DMATRIX {},{},{},{},x{},\"{}\"\r\n",
+                    x,
+                    y,
+                    height,
+                    height,
+                    module,
+                    quote(&data)
+                )),
+            }
+        }
+        out.push_str(&format!("PRINT 1,{}\r\n", label.copies.max(1)));
+    }
+    out
+}
+
+/// EPL2 bitmap fonts 1-4 as (width, height) in dots, per print head
+fn epl_fonts(dpi: u32) -> [(u32, u32); 4] {
+    if dpi >= 300 {
+        [(12, 20), (16, 28), (20, 36), (24, 44)]
+    } else {
+        [(8, 12), (10, 16), (12, 20), (14, 24)]
+    }
+}
+
+/// EPL2: N..P per label. EPL2 has no DataMatrix, so those labels get a
+/// Code128 barcode in the same corner instead.
+fn epl(labels: &[LabelData], options: &LabelOptions) -> String {
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let fonts = epl_fonts(options.dpi);
+    let mut out = format!(
+        "\nq{}\nQ{},{}\n",
+        options.dots(options.width_mm),
+        options.dots(options.height_mm),
+        options.dots(options.gap_mm)
+    );
+    for label in labels {
+        out.push_str("N\n");
+        for field in layout(label, options) {
+            match field {
+                Field::Text {
+                    x,
+                    y,
+                    height,
+                    width,
+                    text,
+                } => {
+                    // Largest bitmap font that fits the line
+                    let (font, (char_width, _)) = fonts
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .find(|(_, (_, h))| *h <= height)
+                        .unwrap_or((0, &fonts[0]));
+                    out.push_str(&format!(
+                        "A{},{},0,{},1,1,N,\"{}\"\n",
+                        x,
+                        y,
+                        font + 1,
+                        quote(&fit(&text, width, char_width + 2))
+                    ));
+                }
+                Field::Barcode {
+                    x,
+                    y,
+                    height,
+                    module,
+                    symbology,
+                    data,
+                } => {
+                    if symbology == Symbology::DataMatrix {
+                        log::warn!("EPL has no DataMatrix; printing Code128 instead");
+                    }
+                    out.push_str(&format!(
+                        "B{},{},0,1,{},{},{},N,\"{}\"\n",
+                        x,
+                        y,
+                        module,
+                        module,
+                        height,
+                        quote(&data)
+                    ));
+                }
+            }
+        }
+        out.push_str(&format!("P{}\n", label.copies.max(1)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The shared fixture plus purchase 3: ten strips and two free of
+    /// batch 1, and a cough syrup line without its batch id
+    fn sample_db() -> Connection {
+        let conn = crate::print::fixtures::database();
+        crate::print::fixtures::seed(&conn);
+        conn.execute_batch(
+            "INSERT INTO purchases (id, invoice_number, invoice_date, user_id)
+                 VALUES (3, 'SUP-101', '2026-01-02', 1);
+             INSERT INTO purchase_items (id, purchase_id, batch_id, medicine_id, medicine_name,
+                     batch_number, expiry_date, quantity, free_quantity, purchase_price, mrp,
+                     gst_rate, total_amount)
+                 VALUES
+                 (1, 3, 1, 1, 'Paracetamol 500mg', 'BT2024001', '2027-06-30', 10, 2, 18, 30, 12,
+                     180),
+                 (2, 3, NULL, 3, 'Cough Syrup', 'CS01', '2027-01-31', 1, 0, 70, 110, 5, 70);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn loads_purchase_lines_with_a_label_per_strip() {
+        let conn = sample_db();
+        let labels = load_purchase(&conn, 3, None).unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].copies, 12);
+        assert_eq!(labels[0].item_code, "MB0000001");
+        assert_eq!(labels[0].rack.as_deref(), Some("A1"));
+        // Found through medicine and batch number when batch_id is missing
+        assert_eq!(labels[1].item_code, "MB0000003");
+        assert_eq!(load_purchase(&conn, 3, Some(1)).unwrap()[0].copies, 1);
+        assert!(load_purchase(&conn, 4, None).is_err());
+        assert!(load_batches(&conn, &[1, 99], 1).is_err());
+    }
+
+    #[test]
+    fn encodes_zpl_tspl_and_epl() {
+        let labels = load_batches(&sample_db(), &[1], 3).unwrap();
+        let options = LabelOptions::default();
+        let encode =
+            |format| String::from_utf8(encode(&labels, &options, format).unwrap()).unwrap();
+
+        let zpl = encode(PrintFormat::Zpl);
+        assert!(zpl.starts_with("^XA\n^PW400\n^LL200\n"), "{}", zpl);
+        assert!(zpl.contains("^FO12,12^A0N,26,26^FDParacetamol 500mg^FS"));
+        assert!(zpl.contains("^FDMRP Rs.30.00^FS"));
+        assert!(zpl.contains("^FDB:BT2024001  EXP:06/27^FS"));
+        assert!(zpl.contains("^FDRack A1/1^FS"));
+        assert!(zpl.contains("^BY2^BCN,"));
+        assert!(zpl.ends_with("^FDMB0000001^FS\n^PQ3\n^XZ\n"));
+
+        let tspl = encode(PrintFormat::Tspl);
+        assert!(
+            tspl.starts_with("SIZE 50 mm,25 mm\r\nGAP 2 mm,0 mm\r\n"),
+            "{}",
+            tspl
+        );
+        assert!(tspl.contains("TEXT 12,12,\"0\",0,9,9,\"Paracetamol 500mg\"\r\n"));
+        assert!(tspl.contains("\"128\","));
+        assert!(tspl.ends_with("\"MB0000001\"\r\nPRINT 1,3\r\n"));
+
+        let epl = encode(PrintFormat::Epl);
+        assert!(epl.starts_with("\nq400\nQ200,16\nN\n"), "{}", epl);
+        assert!(epl.contains("A12,12,0,4,1,1,N,\"Paracetamol 500mg\"\n"));
+        assert!(epl.ends_with(",N,\"MB0000001\"\nP3\n"));
+
+        assert!(super::encode(&labels, &options, PrintFormat::Escpos).is_err());
+    }
+
+    #[test]
+    fn datamatrix_sits_beside_the_text() {
+        let labels = load_batches(&sample_db(), &[1], 1).unwrap();
+        let options = LabelOptions {
+            symbology: Symbology::DataMatrix,
+            ..LabelOptions::default()
+        };
+        let zpl = String::from_utf8(encode(&labels, &options, PrintFormat::Zpl).unwrap()).unwrap();
+        // 12mm symbol against the right margin of a 400-dot label
+        assert!(
+            zpl.contains("^FO292,12^BXN,6,200^FDMB0000001^FS"),
+            "{}",
+            zpl
+        );
+    }
+}
@@ -0,0 +1,309 @@
+// =====================================================
+// Page Breaks for Pre-cut Stationery
+// Splits a long bill into pages of a fixed number of
+// lines: the header repeats on every page and the item
+// subtotal is carried forward across each perforation
+// =====================================================
+
+use rusqlite::Connection;
+
+use super::receipt::{is_item_heading, segments, Align, Block, Receipt, Style};
+
+/// Printable lines per page from `dot_matrix_page_lines`, or the dot matrix
+/// form length when that is not set. 0 prints one continuous page.
+pub fn page_lines(conn: &Connection) -> usize {
+    match crate::db::get_setting_or(conn, "dot_matrix_page_lines", 0usize) {
+        0 => crate::db::get_setting_or(conn, "dot_matrix_form_length", 0usize),
+        lines => lines,
+    }
+}
+
+/// Lines a block takes in plain text
+pub fn text_lines(block: &Block) -> usize {
+    match block {
+        Block::Text { .. } | Block::Rule(_) => 1,
+        Block::Feed(lines) => *lines as usize,
+        Block::Image(_) | Block::Qr(_) | Block::PageBreak => 0,
+    }
+}
+
+/// An item of the bill with its wrapped name and detail lines, kept together
+struct Row<'a> {
+    blocks: &'a [Block],
+    amount: Option<f64>,
+}
+
+/// A receipt cut into the part repeated on every page, the item rows and
+/// the totals that follow them
+struct Sections<'a> {
+    header: &'a [Block],
+    rows: Vec<Row<'a>>,
+    trailer: &'a [Block],
+}
+
+/// Find the item table: the header runs to the rule under the `Item ... Qty`
+/// heading and the table to the next rule. Without an item table every
+/// block is a row of its own and nothing is repeated.
+fn sections(receipt: &Receipt) -> Sections<'_> {
+    let blocks = receipt.blocks.as_slice();
+    let heading = blocks
+        .iter()
+        .position(|b| matches!(b, Block::Text { text, .. } if is_item_heading(text)));
+    let Some(heading) = heading else {
+        return Sections {
+            header: &[],
+            rows: blocks
+                .chunks(1)
+                .map(|blocks| Row {
+                    blocks,
+                    amount: None,
+                })
+                .collect(),
+            trailer: &[],
+        };
+    };
+
+    let mut start = heading + 1;
+    if matches!(blocks.get(start), Some(Block::Rule(_))) {
+        start += 1;
+    }
+    let end = blocks[start..]
+        .iter()
+        .position(|b| matches!(b, Block::Rule(_)))
+        .map_or(blocks.len(), |i| start + i);
+
+    let mut rows = Vec::new();
+    let mut first = start;
+    for i in start + 1..end {
+        if starts_item(&blocks[i]) {
+            rows.push(row(&blocks[first..i]));
+            first = i;
+        }
+    }
+    if first < end {
+        rows.push(row(&blocks[first..end]));
+    }
+
+    Sections {
+        header: &blocks[..start],
+        rows,
+        trailer: &blocks[end..],
+    }
+}
+
+/// Items are numbered `1. `, `2. ` ...
+fn starts_item(block: &Block) -> bool {
+    let Block::Text { text, .. } = block else {
+        return false;
+    };
+    let text = text.trim_start();
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && text[digits..].starts_with(". ")
+}
+
+/// The amount is the last column of the first line laid out in columns;
+/// the lines of a wrapped name have only the one
+fn row(blocks: &[Block]) -> Row<'_> {
+    let amount = blocks.iter().find_map(|block| {
+        let Block::Text { text, .. } = block else {
+            return None;
+        };
+        let columns = segments(text);
+        if columns.len() < 2 {
+            return None;
+        }
+        columns.last()?.1.replace(',', "").parse::<f64>().ok()
+    });
+    Row { blocks, amount }
+}
+
+/// Split `receipt` into pages of at most `lines` lines, measuring each block
+/// with `measure`. Pages end with the page total, the total carried forward
+/// and "continued...", followed by a page break; the next page starts with
+/// the header again and the total brought forward. The totals stay with the
+/// last items. A receipt that fits on one page is returned unchanged.
+pub fn paginate(receipt: &Receipt, lines: usize, measure: impl Fn(&Block) -> usize) -> Receipt {
+    let height = |blocks: &[Block]| blocks.iter().map(&measure).sum::<usize>();
+    if lines == 0 || height(&receipt.blocks) <= lines {
+        return receipt.clone();
+    }
+
+    let sections = sections(receipt);
+    let subtotals = sections.rows.iter().any(|r| r.amount.is_some());
+    let footer_lines = if subtotals { 4 } else { 2 };
+    let header_lines = height(sections.header);
+    let width = receipt.width;
+    let item_style = Style {
+        condensed: true,
+        ..Style::default()
+    };
+
+    let mut out = Receipt::new(width);
+    let mut used = header_lines;
+    let mut on_page = 0;
+    let mut page_total = 0.0;
+    let mut carried = 0.0;
+    out.blocks.extend_from_slice(sections.header);
+
+    let next_page = |out: &mut Receipt, page_total: f64, carried: f64| {
+        out.rule('-');
+        if subtotals {
+            out.text(spread("Page total:", &money(page_total), width), item_style);
+            out.text(
+                spread("Carried forward:", &money(carried), width),
+                item_style,
+            );
+        }
+        out.text(
+            "continued...",
+            Style {
+                align: Align::Center,
+                ..Style::default()
+            },
+        );
+        out.blocks.push(Block::PageBreak);
+        out.blocks.extend_from_slice(sections.header);
+        if subtotals {
+            out.text(
+                spread("Brought forward:", &money(carried), width),
+                item_style,
+            );
+            header_lines + 1
+        } else {
+            header_lines
+        }
+    };
+
+    for row in &sections.rows {
+        let row_lines = height(row.blocks);
+        if on_page > 0 && used + row_lines + footer_lines > lines {
+            used = next_page(&mut out, page_total, carried);
+            on_page = 0;
+            page_total = 0.0;
+        }
+        out.blocks.extend_from_slice(row.blocks);
+        used += row_lines;
+        on_page += 1;
+        page_total += row.amount.unwrap_or(0.0);
+        carried += row.amount.unwrap_or(0.0);
+    }
+    if on_page > 0 && used + height(sections.trailer) > lines {
+        next_page(&mut out, page_total, carried);
+    }
+    out.blocks.extend_from_slice(sections.trailer);
+    out
+}
+
+fn money(amount: f64) -> String {
+    format!("{:.2}", amount)
+}
+
+fn spread(left: &str, right: &str, width: usize) -> String {
+    let gap = width
+        .saturating_sub(left.chars().count() + right.chars().count())
+        .max(1);
+    format!("{}{}{}", left, " ".repeat(gap), right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bill with `items` numbered items, each with a details line
+    fn bill_text(items: usize) -> String {
+        let mut text = String::from(
+            "          CITY MEDICALS\n            Main Road\n\
+             ========================================\n\
+             Bill: INV-7                  02/01/26\n\
+             ----------------------------------------\n\
+             Item                         Qty     Amt\n\
+             ----------------------------------------\n",
+        );
+        for i in 1..=items {
+            text.push_str(&format!(
+                "{:<26}{:>6}{:>8}\n   Batch B{} Exp 12/27\n",
+                format!("{}. Medicine {}", i, i),
+                "1 S",
+                format!("{}.00", 10 * i),
+                i
+            ));
+        }
+        text.push_str(
+            "----------------------------------------\n\
+             TOTAL:                           Rs.550\n\
+             ========================================\n",
+        );
+        text
+    }
+
+    /// The last three lines of `page`, bottom first
+    fn footer(page: &str) -> Vec<&str> {
+        page.lines().rev().take(3).collect()
+    }
+
+    fn pages(receipt: &Receipt) -> Vec<String> {
+        receipt
+            .to_text()
+            .split('\x0C')
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn short_bills_print_unchanged() {
+        let receipt = Receipt::from_text(&bill_text(3));
+        assert_eq!(paginate(&receipt, 40, text_lines), receipt);
+        assert_eq!(paginate(&receipt, 0, text_lines), receipt);
+    }
+
+    #[test]
+    fn long_bills_break_with_header_and_carried_totals() {
+        let receipt = Receipt::from_text(&bill_text(10));
+        let paged = paginate(&receipt, 20, text_lines);
+        let pages = pages(&paged);
+        assert_eq!(pages.len(), 3);
+        for page in &pages {
+            assert!(page.lines().count() <= 20, "{}", page);
+            assert!(page.starts_with("             CITY MEDICALS\n"));
+            assert!(page.contains("Item                         Qty     Amt\n-----"));
+        }
+
+        // 7 header lines leave room for 4 items above the 4 footer lines
+        assert!(pages[0].contains("4. Medicine 4") && !pages[0].contains("5. Medicine"));
+        assert_eq!(
+            footer(&pages[0]),
+            [
+                "              continued...",
+                "Carried forward:                  100.00",
+                "Page total:                       100.00",
+            ]
+        );
+        assert!(pages[1].contains("-\nBrought forward:                  100.00\n5. Medicine 5"));
+        assert_eq!(
+            footer(&pages[1])[1..],
+            [
+                "Carried forward:                  360.00",
+                "Page total:                       260.00",
+            ]
+        );
+
+        // The last page has the remaining items and the totals, no footer
+        assert!(pages[2].contains("Brought forward:                  360.00"));
+        assert!(pages[2].contains("10. Medicine 10") && pages[2].contains("TOTAL:"));
+        assert!(!pages[2].contains("continued"));
+    }
+
+    #[test]
+    fn rows_are_never_split() {
+        let receipt = Receipt::from_text(&bill_text(10));
+        let paged = paginate(&receipt, 21, text_lines);
+        for page in pages(&paged) {
+            let lines: Vec<&str> = page.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                if line.contains(". Medicine") {
+                    assert!(lines[i + 1].starts_with("   Batch"), "{}", page);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,948 @@
+// =====================================================
+// PDF Documents
+// A4 pages with embedded DejaVu fonts (they carry the ₹
+// glyph), a header and footer on every page and tables
+// whose totals are carried forward across page breaks
+// =====================================================
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use printpdf::{
+    Color, ColorBits, ColorSpace, FontData, FontMetrics, GlyphMetrics, Greyscale, Image,
+    ImageTransform, ImageXObject, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point,
+    Px, Rect,
+};
+use ttf_parser::{Face, GlyphId};
+
+use super::bitmap::Bitmap;
+use super::receipt::Align;
+
+pub const REGULAR_FONT: &[u8] = include_bytes!("../../resources/fonts/DejaVuSansCondensed.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../../resources/fonts/DejaVuSansCondensed-Bold.ttf");
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 12.0;
+/// Room kept for the page header below the top margin
+const HEADER_HEIGHT: f32 = 32.0;
+/// Room kept for the page footer above the bottom margin
+const FOOTER_HEIGHT: f32 = 8.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const BODY_TOP: f32 = MARGIN + HEADER_HEIGHT;
+const BODY_BOTTOM: f32 = PAGE_HEIGHT - MARGIN - FOOTER_HEIGHT;
+
+const PT_TO_MM: f32 = 25.4 / 72.0;
+/// Line height as a multiple of the font size
+const LEADING: f32 = 1.3;
+/// Padding above and below the text of a table row, and between columns
+const CELL_PADDING: f32 = 1.2;
+
+pub const BODY_SIZE: f32 = 9.0;
+pub const SMALL_SIZE: f32 = 7.5;
+const HEADING_SIZE: f32 = 11.0;
+const TITLE_SIZE: f32 = 14.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weight {
+    Regular,
+    Bold,
+}
+
+/// Repeated at the top and bottom of every page
+#[derive(Clone, Debug, Default)]
+pub struct PageHeader {
+    /// Shop block on the left; the first line is printed large and bold
+    pub left: Vec<String>,
+    /// Document title on the right
+    pub title: String,
+    /// Lines under the title (number, date, period)
+    pub right: Vec<String>,
+    /// Footer note on the left; the page number goes on the right
+    pub footer: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell {
+    Text(String),
+    /// Text with a smaller second line under it (batch and expiry under a product)
+    Detail(String, String),
+    Money(f64),
+}
+
+impl Cell {
+    pub fn text(text: impl Into<String>) -> Cell {
+        Cell::Text(text.into())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub title: String,
+    /// Share of the page width relative to the other columns
+    pub weight: f32,
+    pub align: Align,
+    /// Money in this column is summed into the carried-forward and total rows
+    pub total: bool,
+}
+
+impl Column {
+    pub fn text(title: &str, weight: f32) -> Column {
+        Column {
+            title: title.to_string(),
+            weight,
+            align: Align::Left,
+            total: false,
+        }
+    }
+
+    pub fn right(title: &str, weight: f32) -> Column {
+        Column {
+            align: Align::Right,
+            ..Column::text(title, weight)
+        }
+    }
+
+    /// Right aligned and summed
+    pub fn money(title: &str, weight: f32) -> Column {
+        Column {
+            total: true,
+            ..Column::right(title, weight)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Table {
+        Table {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<Cell>) {
+        self.rows.push(cells);
+    }
+
+    fn has_totals(&self) -> bool {
+        self.columns.iter().any(|c| c.total)
+    }
+}
+
+/// What gets drawn; `y` is measured from the top of the page in mm
+#[derive(Clone, Debug)]
+enum Op {
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        weight: Weight,
+        text: String,
+    },
+    Rule {
+        x1: f32,
+        x2: f32,
+        y: f32,
+        thickness: f32,
+    },
+    Shade {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    /// A black and white graphic stretched to `width` mm, keeping its
+    /// aspect ratio
+    Image {
+        x: f32,
+        y: f32,
+        width: f32,
+        bitmap: Bitmap,
+    },
+}
+
+/// A font as handed to printpdf. Only the characters the document uses
+/// go into its ToUnicode map, which keeps the map small and leaves out
+/// the characters beyond U+FFFF that printpdf cannot encode there.
+#[derive(Clone, Debug)]
+struct EmbeddedFont {
+    face: Face<'static>,
+    chars: BTreeSet<char>,
+}
+
+impl FontData for EmbeddedFont {
+    fn font_metrics(&self) -> FontMetrics {
+        FontMetrics {
+            ascent: self.face.ascender(),
+            descent: self.face.descender(),
+            units_per_em: self.face.units_per_em(),
+        }
+    }
+
+    fn glyph_id(&self, c: char) -> Option<u16> {
+        self.face.glyph_index(c).map(|g| g.0)
+    }
+
+    fn glyph_ids(&self) -> HashMap<u16, char> {
+        self.chars
+            .iter()
+            .filter(|c| (**c as u32) <= 0xFFFF)
+            .filter_map(|c| self.glyph_id(*c).map(|g| (g, *c)))
+            .collect()
+    }
+
+    fn glyph_count(&self) -> u16 {
+        self.face.number_of_glyphs()
+    }
+
+    fn glyph_metrics(&self, glyph_id: u16) -> Option<GlyphMetrics> {
+        let glyph = GlyphId(glyph_id);
+        let width = self.face.glyph_hor_advance(glyph)? as u32;
+        let height = self
+            .face
+            .glyph_bounding_box(glyph)
+            .map(|b| b.y_max - b.y_min - self.face.descender())
+            .unwrap_or(1000) as u32;
+        Some(GlyphMetrics { width, height })
+    }
+}
+
+struct Fonts {
+    regular: Face<'static>,
+    bold: Face<'static>,
+}
+
+impl Fonts {
+    fn load() -> Result<Fonts, String> {
+        let parse =
+            |data| Face::parse(data, 0).map_err(|e| format!("Failed to load PDF font: {}", e));
+        Ok(Fonts {
+            regular: parse(REGULAR_FONT)?,
+            bold: parse(BOLD_FONT)?,
+        })
+    }
+
+    /// Width of `text` in mm
+    fn width(&self, text: &str, size: f32, weight: Weight) -> f32 {
+        let face = match weight {
+            Weight::Regular => &self.regular,
+            Weight::Bold => &self.bold,
+        };
+        let units: u32 = text
+            .chars()
+            .map(|c| {
+                face.glyph_index(c)
+                    .and_then(|g| face.glyph_hor_advance(g))
+                    .unwrap_or(face.units_per_em() / 2) as u32
+            })
+            .sum();
+        units as f32 / face.units_per_em() as f32 * size * PT_TO_MM
+    }
+
+    /// Break `text` into lines no wider than `max` mm. Words longer than
+    /// a line are split by character.
+    fn wrap(&self, text: &str, size: f32, weight: Weight, max: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                if self.width(&candidate, size, weight) <= max {
+                    line = candidate;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                for c in word.chars() {
+                    line.push(c);
+                    if self.width(&line, size, weight) > max && line.chars().count() > 1 {
+                        line.pop();
+                        lines.push(std::mem::replace(&mut line, c.to_string()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+fn line_height(size: f32) -> f32 {
+    size * PT_TO_MM * LEADING
+}
+
+/// Baseline of a line whose box starts at `top`
+fn baseline(top: f32, size: f32) -> f32 {
+    top + size * PT_TO_MM
+}
+
+/// `₹1,23,456.78`, grouped the Indian way
+pub fn rupees(amount: f64) -> String {
+    let paise = (amount.abs() * 100.0).round() as u64;
+    let digits = (paise / 100).to_string();
+    let mut grouped = String::new();
+    let len = digits.len();
+    for (i, c) in digits.chars().enumerate() {
+        let left = len - i;
+        if i > 0 && (left == 3 || (left > 3 && (left - 3).is_multiple_of(2))) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    let sign = if amount < 0.0 && paise > 0 { "-" } else { "" };
+    format!("{}₹{}.{:02}", sign, grouped, paise % 100)
+}
+
+/// Pages are laid out first and drawn on save, once the page count
+/// for "Page x of y" is known
+pub struct PdfDoc {
+    fonts: Fonts,
+    header: PageHeader,
+    pages: Vec<Vec<Op>>,
+    y: f32,
+}
+
+impl PdfDoc {
+    pub fn new(header: PageHeader) -> Result<PdfDoc, String> {
+        Ok(PdfDoc {
+            fonts: Fonts::load()?,
+            header,
+            pages: vec![Vec::new()],
+            y: BODY_TOP,
+        })
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn push(&mut self, op: Op) {
+        if let Some(page) = self.pages.last_mut() {
+            page.push(op);
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.y = BODY_TOP;
+    }
+
+    /// Start a new page unless `height` mm still fit on this one
+    fn ensure(&mut self, height: f32) -> bool {
+        if self.y + height > BODY_BOTTOM && self.y > BODY_TOP {
+            self.new_page();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn text_at(&mut self, x: f32, top: f32, size: f32, weight: Weight, text: &str, align: Align) {
+        let op = text_op(&self.fonts, x, top, size, weight, text, align);
+        self.push(op);
+    }
+
+    fn rule_at(&mut self, y: f32, thickness: f32) {
+        self.push(rule_op(y, thickness));
+    }
+
+    pub fn space(&mut self, mm: f32) {
+        self.y += mm;
+    }
+
+    /// Bold section title, kept on the same page as the lines after it
+    pub fn heading(&mut self, text: &str) {
+        self.ensure(line_height(HEADING_SIZE) + 4.0 * line_height(BODY_SIZE));
+        let top = self.y;
+        self.text_at(MARGIN, top, HEADING_SIZE, Weight::Bold, text, Align::Left);
+        self.y += line_height(HEADING_SIZE) + 1.0;
+    }
+
+    /// Text wrapped to the page width
+    pub fn paragraph(&mut self, text: &str, size: f32, weight: Weight, align: Align) {
+        let x = match align {
+            Align::Left => MARGIN,
+            Align::Center => PAGE_WIDTH / 2.0,
+            Align::Right => PAGE_WIDTH - MARGIN,
+        };
+        for line in self.fonts.wrap(text, size, weight, CONTENT_WIDTH) {
+            self.ensure(line_height(size));
+            let top = self.y;
+            self.text_at(x, top, size, weight, &line, align);
+            self.y += line_height(size);
+        }
+    }
+
+    /// A graphic `width` mm wide, such as a payment QR code
+    pub fn image(&mut self, bitmap: &Bitmap, width: f32, align: Align) {
+        let height = width * bitmap.height as f32 / bitmap.width.max(1) as f32;
+        self.ensure(height);
+        let x = match align {
+            Align::Left => MARGIN,
+            Align::Center => (PAGE_WIDTH - width) / 2.0,
+            Align::Right => PAGE_WIDTH - MARGIN - width,
+        };
+        let y = self.y;
+        self.push(Op::Image {
+            x,
+            y,
+            width,
+            bitmap: bitmap.clone(),
+        });
+        self.y += height;
+    }
+
+    /// Label and value pairs in a block at the right edge, as under an
+    /// invoice. The last pair is emphasised when `grand` is set.
+    pub fn summary(&mut self, rows: &[(String, String)], grand: bool) {
+        let label_x = PAGE_WIDTH - MARGIN - CONTENT_WIDTH * 0.42;
+        let value_x = PAGE_WIDTH - MARGIN;
+        for (i, (label, value)) in rows.iter().enumerate() {
+            let last = grand && i + 1 == rows.len();
+            let (size, weight) = if last {
+                (HEADING_SIZE, Weight::Bold)
+            } else {
+                (BODY_SIZE, Weight::Regular)
+            };
+            self.ensure(line_height(size) + 1.0);
+            if last {
+                let y = self.y;
+                self.push(Op::Rule {
+                    x1: label_x,
+                    x2: value_x,
+                    y,
+                    thickness: 0.8,
+                });
+                self.y += 0.8;
+            }
+            let top = self.y;
+            self.text_at(label_x, top, size, weight, label, Align::Left);
+            self.text_at(value_x, top, size, weight, value, Align::Right);
+            self.y += line_height(size);
+        }
+    }
+
+    /// Lay out `table`, repeating its header on each page. Tables with
+    /// money columns close every page with a "Carried forward" row, open
+    /// the next with "Brought forward" and end with a "Total" row.
+    pub fn table(&mut self, table: &Table) {
+        let weights: f32 = table.columns.iter().map(|c| c.weight).sum();
+        let widths: Vec<f32> = table
+            .columns
+            .iter()
+            .map(|c| c.weight / weights * CONTENT_WIDTH)
+            .collect();
+        let has_totals = table.has_totals();
+        let summary_height = line_height(BODY_SIZE) + 2.0 * CELL_PADDING;
+        let mut sums = vec![0.0; table.columns.len()];
+
+        self.ensure(2.0 * summary_height + line_height(BODY_SIZE) * 2.0);
+        self.table_header(table, &widths);
+
+        for row in &table.rows {
+            let cells = self.layout_row(table, &widths, row);
+            let height = cells
+                .iter()
+                .map(|lines| lines.iter().map(|l| line_height(l.size)).sum::<f32>())
+                .fold(line_height(BODY_SIZE), f32::max)
+                + 2.0 * CELL_PADDING;
+            let reserve = if has_totals { summary_height } else { 0.0 };
+
+            if self.y + height + reserve > BODY_BOTTOM && self.y > BODY_TOP {
+                if has_totals {
+                    self.summary_row(table, &widths, "Carried forward", &sums);
+                }
+                self.new_page();
+                self.table_header(table, &widths);
+                if has_totals {
+                    self.summary_row(table, &widths, "Brought forward", &sums);
+                }
+            }
+
+            let top = self.y;
+            for (i, lines) in cells.iter().enumerate() {
+                let mut line_top = top + CELL_PADDING;
+                for line in lines {
+                    let x = self.cell_x(table, &widths, i);
+                    self.text_at(
+                        x,
+                        line_top,
+                        line.size,
+                        line.weight,
+                        &line.text,
+                        table.columns[i].align,
+                    );
+                    line_top += line_height(line.size);
+                }
+            }
+            self.y += height;
+            let y = self.y;
+            self.push(Op::Rule {
+                x1: MARGIN,
+                x2: PAGE_WIDTH - MARGIN,
+                y,
+                thickness: 0.1,
+            });
+
+            for (i, cell) in row.iter().enumerate() {
+                if let (Cell::Money(amount), true) = (cell, table.columns[i].total) {
+                    sums[i] += amount;
+                }
+            }
+        }
+
+        if has_totals {
+            self.summary_row(table, &widths, "Total", &sums);
+        }
+        self.y += 3.0;
+    }
+
+    /// Anchor for column `i`'s text given its alignment
+    fn cell_x(&self, table: &Table, widths: &[f32], i: usize) -> f32 {
+        let left = MARGIN + widths[..i].iter().sum::<f32>();
+        match table.columns[i].align {
+            Align::Left => left + CELL_PADDING,
+            Align::Center => left + widths[i] / 2.0,
+            Align::Right => left + widths[i] - CELL_PADDING,
+        }
+    }
+
+    fn layout_row(&self, table: &Table, widths: &[f32], row: &[Cell]) -> Vec<Vec<CellLine>> {
+        table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                let max = widths[i] - 2.0 * CELL_PADDING;
+                let wrap = |text: &str, size, weight| {
+                    self.fonts
+                        .wrap(text, size, weight, max)
+                        .into_iter()
+                        .map(move |text| CellLine { text, size, weight })
+                };
+                match row.get(i) {
+                    Some(Cell::Text(text)) => wrap(text, BODY_SIZE, Weight::Regular).collect(),
+                    Some(Cell::Detail(text, detail)) => wrap(text, BODY_SIZE, Weight::Regular)
+                        .chain(wrap(detail, SMALL_SIZE, Weight::Regular))
+                        .collect(),
+                    Some(Cell::Money(amount)) => {
+                        wrap(&rupees(*amount), BODY_SIZE, Weight::Regular).collect()
+                    }
+                    None => Vec::new(),
+                }
+            })
+            .collect()
+    }
+
+    fn table_header(&mut self, table: &Table, widths: &[f32]) {
+        let lines: Vec<Vec<String>> = table
+            .columns
+            .iter()
+            .zip(widths)
+            .map(|(c, w)| {
+                self.fonts
+                    .wrap(&c.title, BODY_SIZE, Weight::Bold, w - 2.0 * CELL_PADDING)
+            })
+            .collect();
+        let rows = lines.iter().map(Vec::len).max().unwrap_or(1);
+        let height = rows as f32 * line_height(BODY_SIZE) + 2.0 * CELL_PADDING;
+
+        let top = self.y;
+        self.push(Op::Shade {
+            x: MARGIN,
+            y: top,
+            width: CONTENT_WIDTH,
+            height,
+        });
+        for (i, column_lines) in lines.iter().enumerate() {
+            let x = self.cell_x(table, widths, i);
+            for (n, line) in column_lines.iter().enumerate() {
+                let line_top = top + CELL_PADDING + n as f32 * line_height(BODY_SIZE);
+                self.text_at(
+                    x,
+                    line_top,
+                    BODY_SIZE,
+                    Weight::Bold,
+                    line,
+                    table.columns[i].align,
+                );
+            }
+        }
+        self.y += height;
+        self.rule_at(top + height, 0.4);
+    }
+
+    /// Bold row with `label` in the first column and the running sums
+    /// under the money columns
+    fn summary_row(&mut self, table: &Table, widths: &[f32], label: &str, sums: &[f64]) {
+        let top = self.y;
+        self.rule_at(top, 0.4);
+        let text_top = top + CELL_PADDING;
+        self.text_at(
+            MARGIN + CELL_PADDING,
+            text_top,
+            BODY_SIZE,
+            Weight::Bold,
+            label,
+            Align::Left,
+        );
+        for (i, column) in table.columns.iter().enumerate() {
+            if column.total {
+                let x = self.cell_x(table, widths, i);
+                self.text_at(
+                    x,
+                    text_top,
+                    BODY_SIZE,
+                    Weight::Bold,
+                    &rupees(sums[i]),
+                    column.align,
+                );
+            }
+        }
+        self.y += line_height(BODY_SIZE) + 2.0 * CELL_PADDING;
+        self.rule_at(self.y, 0.4);
+    }
+
+    /// Header and footer ops for page `number` of `count`
+    fn page_frame(&self, number: usize, count: usize) -> Vec<Op> {
+        let fonts = &self.fonts;
+        let mut frame = Vec::new();
+
+        let mut top = MARGIN;
+        for (i, line) in self.header.left.iter().enumerate() {
+            let (size, weight) = if i == 0 {
+                (TITLE_SIZE, Weight::Bold)
+            } else {
+                (SMALL_SIZE, Weight::Regular)
+            };
+            frame.push(text_op(fonts, MARGIN, top, size, weight, line, Align::Left));
+            top += line_height(size);
+        }
+
+        let right = PAGE_WIDTH - MARGIN;
+        let title = &self.header.title;
+        frame.push(text_op(
+            fonts,
+            right,
+            MARGIN,
+            TITLE_SIZE,
+            Weight::Bold,
+            title,
+            Align::Right,
+        ));
+        let mut top = MARGIN + line_height(TITLE_SIZE);
+        for line in &self.header.right {
+            frame.push(text_op(
+                fonts,
+                right,
+                top,
+                BODY_SIZE,
+                Weight::Regular,
+                line,
+                Align::Right,
+            ));
+            top += line_height(BODY_SIZE);
+        }
+        frame.push(rule_op(BODY_TOP - 3.0, 0.8));
+
+        let footer_top = PAGE_HEIGHT - MARGIN - line_height(SMALL_SIZE);
+        let page = format!("Page {} of {}", number, count);
+        frame.push(rule_op(footer_top - 1.5, 0.3));
+        frame.push(text_op(
+            fonts,
+            MARGIN,
+            footer_top,
+            SMALL_SIZE,
+            Weight::Regular,
+            &self.header.footer,
+            Align::Left,
+        ));
+        frame.push(text_op(
+            fonts,
+            right,
+            footer_top,
+            SMALL_SIZE,
+            Weight::Regular,
+            &page,
+            Align::Right,
+        ));
+        frame
+    }
+
+    pub fn to_bytes(&self, title: &str) -> Result<Vec<u8>, String> {
+        let count = self.pages.len();
+        let pages: Vec<Vec<Op>> = self
+            .pages
+            .iter()
+            .enumerate()
+            .map(|(i, ops)| {
+                let mut page = self.page_frame(i + 1, count);
+                page.extend(ops.iter().cloned());
+                // Shading goes first so text is drawn over it
+                page.sort_by_key(|op| !matches!(op, Op::Shade { .. }));
+                page
+            })
+            .collect();
+
+        let mut regular_chars = BTreeSet::new();
+        let mut bold_chars = BTreeSet::new();
+        for op in pages.iter().flatten() {
+            if let Op::Text { weight, text, .. } = op {
+                match weight {
+                    Weight::Regular => regular_chars.extend(text.chars()),
+                    Weight::Bold => bold_chars.extend(text.chars()),
+                }
+            }
+        }
+
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let embed = |data: &'static [u8], face: &Face<'static>, chars| {
+            doc.add_external_font_data(
+                data.to_vec(),
+                EmbeddedFont {
+                    face: face.clone(),
+                    chars,
+                },
+            )
+            .map_err(|e| format!("Failed to embed PDF font: {}", e))
+        };
+        let regular = embed(REGULAR_FONT, &self.fonts.regular, regular_chars)?;
+        let bold = embed(BOLD_FONT, &self.fonts.bold, bold_chars)?;
+
+        for (i, ops) in pages.iter().enumerate() {
+            let layer = if i == 0 {
+                doc.get_page(page).get_layer(layer)
+            } else {
+                let (page, layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+                doc.get_page(page).get_layer(layer)
+            };
+            for op in ops {
+                draw(&layer, op, &regular, &bold);
+            }
+        }
+
+        doc.save_to_bytes()
+            .map_err(|e| format!("Failed to write PDF: {}", e))
+    }
+
+    pub fn save(&self, title: &str, path: &Path) -> Result<(), String> {
+        let bytes = self.to_bytes(title)?;
+        std::fs::write(path, bytes)
+            .map_err(|e| format!("Failed to save PDF to {}: {}", path.display(), e))
+    }
+}
+
+/// Text placed by its anchor: the left edge, centre or right edge
+fn text_op(
+    fonts: &Fonts,
+    x: f32,
+    top: f32,
+    size: f32,
+    weight: Weight,
+    text: &str,
+    align: Align,
+) -> Op {
+    let width = fonts.width(text, size, weight);
+    let x = match align {
+        Align::Left => x,
+        Align::Center => x - width / 2.0,
+        Align::Right => x - width,
+    };
+    Op::Text {
+        x,
+        y: baseline(top, size),
+        size,
+        weight,
+        text: text.to_string(),
+    }
+}
+
+/// Full-width horizontal rule
+fn rule_op(y: f32, thickness: f32) -> Op {
+    Op::Rule {
+        x1: MARGIN,
+        x2: PAGE_WIDTH - MARGIN,
+        y,
+        thickness,
+    }
+}
+
+struct CellLine {
+    text: String,
+    size: f32,
+    weight: Weight,
+}
+
+fn draw(layer: &PdfLayerReference, op: &Op, regular: &IndirectFontRef, bold: &IndirectFontRef) {
+    match op {
+        Op::Text {
+            x,
+            y,
+            size,
+            weight,
+            text,
+        } => {
+            let font = match weight {
+                Weight::Regular => regular,
+                Weight::Bold => bold,
+            };
+            layer.use_text(text.as_str(), *size, Mm(*x), Mm(PAGE_HEIGHT - y), font);
+        }
+        Op::Rule {
+            x1,
+            x2,
+            y,
+            thickness,
+        } => {
+            layer.set_outline_thickness(*thickness);
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(*x1), Mm(PAGE_HEIGHT - y)), false),
+                    (Point::new(Mm(*x2), Mm(PAGE_HEIGHT - y)), false),
+                ],
+                is_closed: false,
+            });
+        }
+        Op::Shade {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            layer.set_fill_color(Color::Greyscale(Greyscale::new(0.9, None)));
+            layer.add_rect(Rect::new(
+                Mm(*x),
+                Mm(PAGE_HEIGHT - y - height),
+                Mm(x + width),
+                Mm(PAGE_HEIGHT - y),
+            ));
+            layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+        }
+        Op::Image {
+            x,
+            y,
+            width,
+            bitmap,
+        } => {
+            let height = width * bitmap.height as f32 / bitmap.width.max(1) as f32;
+            let mut pixels = Vec::with_capacity(bitmap.width * bitmap.height);
+            for row in 0..bitmap.height {
+                for col in 0..bitmap.width {
+                    pixels.push(if bitmap.get(col, row) { 0 } else { 255 });
+                }
+            }
+            let image = Image::from(ImageXObject {
+                width: Px(bitmap.width),
+                height: Px(bitmap.height),
+                color_space: ColorSpace::Greyscale,
+                bits_per_component: ColorBits::Bit8,
+                // Keep module edges sharp when viewers scale it up
+                interpolate: false,
+                image_data: pixels,
+                image_filter: None,
+                smask: None,
+                clipping_bbox: None,
+            });
+            image.add_to_layer(
+                layer.clone(),
+                ImageTransform {
+                    translate_x: Some(Mm(*x)),
+                    translate_y: Some(Mm(PAGE_HEIGHT - y - height)),
+                    dpi: Some(bitmap.width as f32 * 25.4 / width),
+                    ..ImageTransform::default()
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> PageHeader {
+        PageHeader {
+            left: vec!["Test Medical Store".to_string(), "Main Road".to_string()],
+            title: "SALES REPORT".to_string(),
+            right: vec!["01/01/2026 - 31/01/2026".to_string()],
+            footer: "Computer generated report".to_string(),
+        }
+    }
+
+    fn extract(doc: &PdfDoc) -> String {
+        let bytes = doc.to_bytes("Test").unwrap();
+        pdf_extract::extract_text_from_mem(&bytes).unwrap()
+    }
+
+    #[test]
+    fn groups_rupees_the_indian_way() {
+        assert_eq!(rupees(0.0), "₹0.00");
+        assert_eq!(rupees(952.0), "₹952.00");
+        assert_eq!(rupees(1234.5), "₹1,234.50");
+        assert_eq!(rupees(123456.78), "₹1,23,456.78");
+        assert_eq!(rupees(12345678.0), "₹1,23,45,678.00");
+        assert_eq!(rupees(-50.0), "-₹50.00");
+    }
+
+    #[test]
+    fn wraps_to_the_measured_width() {
+        let fonts = Fonts::load().unwrap();
+        let lines = fonts.wrap(
+            "Azithromycin 500mg Tablets IP with a long description",
+            BODY_SIZE,
+            Weight::Regular,
+            30.0,
+        );
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert!(
+                fonts.width(line, BODY_SIZE, Weight::Regular) <= 30.0,
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn carries_totals_across_pages() {
+        let mut doc = PdfDoc::new(header()).unwrap();
+        let mut table = Table::new(vec![
+            Column::right("#", 1.0),
+            Column::text("Bill No", 4.0),
+            Column::money("Amount", 3.0),
+        ]);
+        for i in 1..=80 {
+            table.row(vec![
+                Cell::Text(i.to_string()),
+                Cell::Text(format!("INV-{:05}", i)),
+                Cell::Money(100.0),
+            ]);
+        }
+        doc.table(&table);
+        assert!(doc.page_count() >= 2);
+
+        let text = extract(&doc);
+        let count = doc.page_count();
+        assert!(text.contains(&format!("Page 1 of {}", count)), "{}", text);
+        assert!(text.contains(&format!("Page {} of {}", count, count)));
+        assert_eq!(text.matches("SALES REPORT").count(), count);
+        assert!(text.contains("Carried forward"));
+        assert!(text.contains("Brought forward"));
+        assert!(text.contains("₹100.00"));
+        assert!(text.contains("Total"));
+        assert!(text.contains("₹8,000.00"));
+        assert!(text.contains("INV-00080"));
+    }
+}
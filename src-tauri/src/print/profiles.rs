@@ -0,0 +1,324 @@
+// =====================================================
+// Printer Profiles
+// Which printer, format, copies and paper each kind of
+// document goes to. Stored as JSON in the settings table
+// =====================================================
+
+use std::collections::BTreeMap;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+const SETTING_KEY: &str = "printer_profiles";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    Bill,
+    Duplicate,
+    Report,
+    Label,
+    PurchaseReturnNote,
+}
+
+impl DocumentKind {
+    pub const ALL: [DocumentKind; 5] = [
+        DocumentKind::Bill,
+        DocumentKind::Duplicate,
+        DocumentKind::Report,
+        DocumentKind::Label,
+        DocumentKind::PurchaseReturnNote,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintFormat {
+    /// Epson ESC/P for dot matrix printers
+    Escp,
+    /// Epson ESC/POS for thermal receipt printers
+    Escpos,
+    /// Plain text laid out by the printer driver
+    Text,
+    /// A file format, not a printer one: bills and reports are saved as PDF
+    /// from their screens. Profiles with it are rejected.
+    Pdf,
+    /// Zebra ZPL II label printers
+    Zpl,
+    /// TSC TSPL label printers (and most low-cost clones)
+    Tspl,
+    /// Eltron/Zebra EPL2 label printers
+    Epl,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrinterProfile {
+    /// Printer name; empty means the default printer
+    pub printer: String,
+    pub format: PrintFormat,
+    pub copies: u32,
+    /// `a4`, `legal`, `continuous`, a roll width such as `80mm` or a label
+    /// size such as `50x25mm`. Empty on a thermal profile means
+    /// `thermal_printer_width`.
+    pub paper_size: String,
+}
+
+impl Default for PrinterProfile {
+    fn default() -> Self {
+        PrinterProfile {
+            printer: String::new(),
+            format: PrintFormat::Text,
+            copies: 1,
+            paper_size: "a4".to_string(),
+        }
+    }
+}
+
+impl PrinterProfile {
+    fn new(format: PrintFormat, paper_size: &str) -> Self {
+        PrinterProfile {
+            format,
+            paper_size: paper_size.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The configured printer, if one is named
+    pub fn printer_name(&self) -> Option<&str> {
+        Some(self.printer.trim()).filter(|p| !p.is_empty())
+    }
+
+    /// Roll width for `NNmm` paper sizes
+    pub fn roll_width_mm(&self) -> Option<u32> {
+        self.paper_size
+            .trim()
+            .to_lowercase()
+            .strip_suffix("mm")
+            .and_then(|n| n.trim().parse().ok())
+    }
+
+    /// Width and height for `WxHmm` label sizes
+    pub fn label_size_mm(&self) -> Option<(f32, f32)> {
+        let size = self.paper_size.trim().to_lowercase();
+        let (width, height) = size.strip_suffix("mm").unwrap_or(&size).split_once('x')?;
+        let width: f32 = width.trim().parse().ok()?;
+        let height: f32 = height.trim().parse().ok()?;
+        (width > 0.0 && height > 0.0).then_some((width, height))
+    }
+}
+
+pub type PrinterProfiles = BTreeMap<DocumentKind, PrinterProfile>;
+
+impl PrintFormat {
+    fn is_label(self) -> bool {
+        matches!(
+            self,
+            PrintFormat::Zpl | PrintFormat::Tspl | PrintFormat::Epl
+        )
+    }
+}
+
+/// Whether `profile` can print documents of `kind`
+pub fn check(kind: DocumentKind, profile: &PrinterProfile) -> Result<(), String> {
+    let name = serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.replace('_', " ")))
+        .unwrap_or_default();
+    if profile.format == PrintFormat::Pdf {
+        return Err(format!(
+            "PDF cannot be sent to a printer ({} profile). Use ESC/P, ESC/POS or text, \
+             and save PDFs from the bill or report screen.",
+            name
+        ));
+    }
+    if kind == DocumentKind::Label && !profile.format.is_label() {
+        return Err(format!(
+            "{:?} cannot print labels. Use ZPL, TSPL or EPL for the label profile.",
+            profile.format
+        ));
+    }
+    if kind != DocumentKind::Label && profile.format.is_label() {
+        return Err(format!(
+            "{:?} is a label printer format. Use ESC/P, ESC/POS or text for the {} profile.",
+            profile.format, name
+        ));
+    }
+    if profile.copies == 0 {
+        return Err(format!("The {} profile must print at least one copy", name));
+    }
+    Ok(())
+}
+
+/// Profiles used before any are saved. Bills follow the `printer_type`
+/// setting; everything else goes to the default printer as text.
+pub fn defaults(printer_type: &str) -> PrinterProfiles {
+    let bill = match printer_type {
+        "dotmatrix" => PrinterProfile::new(PrintFormat::Escp, "continuous"),
+        "thermal" => PrinterProfile::new(PrintFormat::Escpos, ""),
+        "legal" => PrinterProfile::new(PrintFormat::Text, "legal"),
+        _ => PrinterProfile::new(PrintFormat::Text, "a4"),
+    };
+
+    DocumentKind::ALL
+        .iter()
+        .map(|kind| {
+            let profile = match kind {
+                DocumentKind::Bill | DocumentKind::Duplicate => bill.clone(),
+                DocumentKind::Label => PrinterProfile::new(PrintFormat::Zpl, "50x25mm"),
+                DocumentKind::Report | DocumentKind::PurchaseReturnNote => {
+                    PrinterProfile::new(PrintFormat::Text, "a4")
+                }
+            };
+            (*kind, profile)
+        })
+        .collect()
+}
+
+/// Saved profiles on top of the defaults, so every kind is always present
+pub fn load(conn: Option<&Connection>) -> PrinterProfiles {
+    let printer_type = conn
+        .and_then(|c| crate::db::get_setting(c, "printer_type"))
+        .unwrap_or_else(|| "thermal".to_string());
+    let mut profiles = defaults(&printer_type);
+
+    if let Some(json) = conn.and_then(|c| crate::db::get_setting(c, SETTING_KEY)) {
+        match serde_json::from_str::<PrinterProfiles>(&json) {
+            Ok(saved) => {
+                for (kind, profile) in saved {
+                    match check(kind, &profile) {
+                        Ok(()) => {
+                            profiles.insert(kind, profile);
+                        }
+                        Err(e) => log::warn!("Using the default printer profile: {}", e),
+                    }
+                }
+            }
+            Err(e) => log::warn!("Ignoring invalid printer_profiles setting: {}", e),
+        }
+    }
+    profiles
+}
+
+/// The profile for one kind of document
+pub fn resolve(conn: Option<&Connection>, kind: DocumentKind) -> PrinterProfile {
+    load(conn).remove(&kind).unwrap_or_default()
+}
+
+/// Save `profiles` once every one of them can print its documents
+pub fn save(conn: &Connection, profiles: &PrinterProfiles) -> Result<(), String> {
+    for (kind, profile) in profiles {
+        check(*kind, profile)?;
+    }
+    let json = serde_json::to_string(profiles)
+        .map_err(|e| format!("Failed to serialize printer profiles: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value, category, description, updated_at)
+         VALUES (?1, ?2, 'printing', 'Printer, format and copies per document kind', CURRENT_TIMESTAMP)",
+        rusqlite::params![SETTING_KEY, json],
+    )
+    .map_err(|e| format!("Failed to save printer profiles: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(format: PrintFormat, printer: &str) -> PrinterProfile {
+        PrinterProfile {
+            printer: printer.to_string(),
+            format,
+            ..PrinterProfile::default()
+        }
+    }
+
+    #[test]
+    fn defaults_follow_the_printer_type() {
+        let conn = crate::print::fixtures::database();
+        let profiles = load(Some(&conn));
+        assert_eq!(profiles.len(), DocumentKind::ALL.len());
+        assert_eq!(profiles[&DocumentKind::Bill].format, PrintFormat::Escpos);
+        assert_eq!(profiles[&DocumentKind::Label].format, PrintFormat::Zpl);
+        assert_eq!(profiles[&DocumentKind::Report].format, PrintFormat::Text);
+        assert_eq!(load(None), profiles);
+
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES ('printer_type', 'dotmatrix')",
+            [],
+        )
+        .unwrap();
+        let bill = resolve(Some(&conn), DocumentKind::Duplicate);
+        assert_eq!(
+            (bill.format, bill.paper_size.as_str()),
+            (PrintFormat::Escp, "continuous")
+        );
+    }
+
+    #[test]
+    fn saved_profiles_override_the_defaults() {
+        let conn = crate::print::fixtures::database();
+        let mut saved = PrinterProfiles::new();
+        saved.insert(
+            DocumentKind::Report,
+            profile(PrintFormat::Escp, "Office LQ"),
+        );
+        save(&conn, &saved).unwrap();
+
+        let profiles = load(Some(&conn));
+        assert_eq!(
+            profiles[&DocumentKind::Report].printer_name(),
+            Some("Office LQ")
+        );
+        assert_eq!(profiles[&DocumentKind::Report].format, PrintFormat::Escp);
+        // Kinds not saved keep their defaults
+        assert_eq!(profiles[&DocumentKind::Bill].format, PrintFormat::Escpos);
+        assert_eq!(profiles[&DocumentKind::Bill].printer_name(), None);
+    }
+
+    #[test]
+    fn profiles_a_printer_cannot_print_are_rejected() {
+        let conn = crate::print::fixtures::database();
+        for (kind, format) in [
+            (DocumentKind::Bill, PrintFormat::Pdf),
+            (DocumentKind::Report, PrintFormat::Pdf),
+            (DocumentKind::Bill, PrintFormat::Zpl),
+            (DocumentKind::Label, PrintFormat::Escpos),
+        ] {
+            let mut saved = PrinterProfiles::new();
+            saved.insert(kind, profile(format, ""));
+            assert!(save(&conn, &saved).is_err(), "{:?} {:?}", kind, format);
+        }
+        let mut none = profile(PrintFormat::Text, "");
+        none.copies = 0;
+        assert!(check(DocumentKind::Report, &none).is_err());
+        assert_eq!(crate::db::get_setting(&conn, SETTING_KEY), None);
+
+        // A PDF profile saved by an older build falls back to the default
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)",
+            rusqlite::params![
+                SETTING_KEY,
+                r#"{"bill":{"printer":"TVS","format":"pdf"},"report":{"format":"escp"}}"#
+            ],
+        )
+        .unwrap();
+        let profiles = load(Some(&conn));
+        assert_eq!(
+            profiles[&DocumentKind::Bill],
+            defaults("thermal")[&DocumentKind::Bill]
+        );
+        assert_eq!(profiles[&DocumentKind::Report].format, PrintFormat::Escp);
+    }
+
+    #[test]
+    fn paper_sizes() {
+        let mut roll = profile(PrintFormat::Escpos, "");
+        roll.paper_size = " 58MM ".to_string();
+        assert_eq!(roll.roll_width_mm(), Some(58));
+        roll.paper_size = "50 x 25mm".to_string();
+        assert_eq!(roll.label_size_mm(), Some((50.0, 25.0)));
+        roll.paper_size = "a4".to_string();
+        assert_eq!((roll.roll_width_mm(), roll.label_size_mm()), (None, None));
+    }
+}
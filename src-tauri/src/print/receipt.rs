@@ -0,0 +1,457 @@
+// =====================================================
+// Receipt Model
+// Printer-neutral description of a bill, shared by the
+// raw command encoders (ESC/P and ESC/POS)
+// =====================================================
+
+use super::bitmap::Bitmap;
+
+/// Horizontal alignment of a text line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Character attributes for a text line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub double_width: bool,
+    pub double_height: bool,
+    pub condensed: bool,
+    pub align: Align,
+}
+
+/// One printable element of a receipt
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    /// A single line of text (never contains '\n')
+    Text { text: String, style: Style },
+    /// A separator drawn across the full receipt width
+    Rule(char),
+    /// Blank lines
+    Feed(u8),
+    /// A monochrome graphic such as the shop logo, centered
+    Image(Bitmap),
+    /// A QR code for the given text (the UPI payment link), centered
+    Qr(String),
+    /// End of a page of pre-cut stationery (a form feed)
+    PageBreak,
+}
+
+/// A receipt laid out for a fixed number of columns
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Receipt {
+    pub width: usize,
+    pub blocks: Vec<Block>,
+}
+
+impl Receipt {
+    pub fn new(width: usize) -> Self {
+        Receipt {
+            width,
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn text(&mut self, text: impl Into<String>, style: Style) {
+        self.blocks.push(Block::Text {
+            text: text.into(),
+            style,
+        });
+    }
+
+    pub fn rule(&mut self, c: char) {
+        self.blocks.push(Block::Rule(c));
+    }
+
+    pub fn feed(&mut self, lines: u8) {
+        self.blocks.push(Block::Feed(lines));
+    }
+
+    pub fn qr(&mut self, data: impl Into<String>) {
+        self.blocks.push(Block::Qr(data.into()));
+    }
+
+    /// Plain text for printers driven by the system spooler, with a form
+    /// feed at each page break
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for block in &self.blocks {
+            match block {
+                Block::Text { text, style } => {
+                    out.push_str(align_to(text, self.width, style.align).trim_end());
+                    out.push('\n');
+                }
+                Block::Rule(c) => {
+                    out.push_str(&c.to_string().repeat(self.width));
+                    out.push('\n');
+                }
+                Block::Feed(lines) => out.push_str(&"\n".repeat(*lines as usize)),
+                Block::Image(_) | Block::Qr(_) => {}
+                Block::PageBreak => out.push('\x0C'),
+            }
+        }
+        out
+    }
+
+    /// Build a receipt from the plain text produced by the dot matrix bill
+    /// template (the `<pre>` block of `generateDotMatrixBillHTML`).
+    ///
+    /// The template is fixed-width text, so styling is recovered from its
+    /// structure: the first line is the shop name, everything above the
+    /// first `=` rule is the centered header, the item table sits between the
+    /// `Item ... Qty Amt` heading and the next `-` rule, and the grand total
+    /// line starts with `TOTAL`.
+    pub fn from_text(text: &str) -> Self {
+        // Separators span the template's full line width
+        let width = text
+            .lines()
+            .find(|l| rule_char(l).is_some())
+            .map(|l| l.trim().chars().count())
+            .or_else(|| text.lines().map(|l| l.trim_end().chars().count()).max())
+            .unwrap_or(0);
+        let mut receipt = Receipt::new(width);
+
+        let mut seen_shop_name = false;
+        let mut in_header = true;
+        let mut in_items = false;
+
+        for line in text.lines() {
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                receipt.feed(1);
+                continue;
+            }
+
+            if let Some(c) = rule_char(line) {
+                if c == '=' {
+                    in_header = false;
+                }
+                if in_items && c == '-' && !is_item_heading_rule(&receipt) {
+                    in_items = false;
+                }
+                receipt.rule(c);
+                continue;
+            }
+
+            if !seen_shop_name {
+                seen_shop_name = true;
+                receipt.text(
+                    line.trim(),
+                    Style {
+                        bold: true,
+                        double_width: true,
+                        align: Align::Center,
+                        ..Style::default()
+                    },
+                );
+                continue;
+            }
+
+            if in_header {
+                receipt.text(
+                    line.trim(),
+                    Style {
+                        align: Align::Center,
+                        ..Style::default()
+                    },
+                );
+                continue;
+            }
+
+            if is_item_heading(line) {
+                in_items = true;
+                receipt.text(
+                    line,
+                    Style {
+                        bold: true,
+                        condensed: true,
+                        ..Style::default()
+                    },
+                );
+                continue;
+            }
+
+            let trimmed = line.trim_start();
+            let style = if trimmed.starts_with("TOTAL") {
+                Style {
+                    bold: true,
+                    double_height: true,
+                    ..Style::default()
+                }
+            } else if in_items {
+                Style {
+                    condensed: true,
+                    ..Style::default()
+                }
+            } else if line.len() - trimmed.len() >= 2 {
+                // Lines indented by the template's center() helper
+                Style {
+                    align: Align::Center,
+                    ..Style::default()
+                }
+            } else {
+                Style::default()
+            };
+
+            let text = if style.align == Align::Center {
+                trimmed
+            } else {
+                line
+            };
+            receipt.text(text, style);
+        }
+
+        // Drop trailing blank lines; the encoders add their own feed
+        while matches!(receipt.blocks.last(), Some(Block::Feed(_))) {
+            receipt.blocks.pop();
+        }
+
+        receipt
+    }
+
+    /// Re-lay the receipt for a narrower or wider paper.
+    ///
+    /// Left-aligned lines are treated as columns separated by runs of two or
+    /// more spaces. Trailing columns (quantities, amounts) keep their distance
+    /// from the right edge so they stay lined up under their headings; the
+    /// first column (item or label) takes the remaining space and wraps onto
+    /// extra lines when it does not fit.
+    pub fn reflow(&self, width: usize) -> Receipt {
+        let mut out = Receipt::new(width);
+        for block in &self.blocks {
+            match block {
+                Block::Text { text, style } if style.align == Align::Center => {
+                    let mut style = *style;
+                    if style.double_width && text.chars().count() * 2 > width {
+                        style.double_width = false;
+                    }
+                    let columns = if style.double_width { width / 2 } else { width };
+                    for line in wrap_words(text, columns) {
+                        out.text(line, style);
+                    }
+                }
+                Block::Text { text, style } => {
+                    for line in reflow_columns(text, self.width, width) {
+                        out.text(line, *style);
+                    }
+                }
+                other => out.blocks.push(other.clone()),
+            }
+        }
+        out
+    }
+}
+
+/// Split a fixed-width line into `(start column, text)` segments separated
+/// by two or more spaces
+pub fn segments(line: &str) -> Vec<(usize, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        while i < chars.len() && chars[i] == ' ' {
+            i += 1;
+        }
+        if i >= chars.len() {
+            break;
+        }
+        let start = i;
+        while i < chars.len() {
+            if chars[i] == ' ' && (i + 1 >= chars.len() || chars[i + 1] == ' ') {
+                break;
+            }
+            i += 1;
+        }
+        result.push((start, chars[start..i].iter().collect()));
+    }
+    result
+}
+
+fn reflow_columns(line: &str, source_width: usize, width: usize) -> Vec<String> {
+    let segs = segments(line);
+    let indent = segs.first().map(|(start, _)| (*start).min(4)).unwrap_or(0);
+
+    if segs.len() < 2 {
+        let text = line.trim();
+        return wrap_words(text, width.saturating_sub(indent).max(1))
+            .into_iter()
+            .map(|l| format!("{}{}", " ".repeat(indent), l))
+            .collect();
+    }
+
+    // Place trailing columns at the same distance from the right edge
+    let source_width = source_width.max(line.chars().count());
+    let mut tail = String::new();
+    let mut cursor = width;
+    let mut placed: Vec<(usize, &str)> = Vec::new();
+    for (start, text) in segs[1..].iter().rev() {
+        let len = text.chars().count();
+        let from_right = source_width - (start + len);
+        let end = width.saturating_sub(from_right).min(cursor);
+        let begin = end.saturating_sub(len);
+        placed.push((begin, text));
+        cursor = begin.saturating_sub(1);
+    }
+    placed.reverse();
+
+    let tail_start = placed.first().map(|(b, _)| *b).unwrap_or(width);
+    let mut col = tail_start;
+    for (begin, text) in &placed {
+        let begin = (*begin).max(col);
+        tail.push_str(&" ".repeat(begin - col));
+        tail.push_str(text);
+        col = begin + text.chars().count();
+    }
+
+    let head = &segs[0].1;
+    let head_room = tail_start.saturating_sub(indent + 1);
+    let mut lines = Vec::new();
+
+    if head.chars().count() <= head_room {
+        let used = indent + head.chars().count();
+        lines.push(format!(
+            "{}{}{}{}",
+            " ".repeat(indent),
+            head,
+            " ".repeat(tail_start - used),
+            tail
+        ));
+        return lines;
+    }
+
+    let wrapped = wrap_words(head, width.saturating_sub(indent).max(1));
+    let last = wrapped.len() - 1;
+    for (i, part) in wrapped.iter().enumerate() {
+        let used = indent + part.chars().count();
+        if i == last && used < tail_start {
+            lines.push(format!(
+                "{}{}{}{}",
+                " ".repeat(indent),
+                part,
+                " ".repeat(tail_start - used),
+                tail
+            ));
+        } else {
+            lines.push(format!("{}{}", " ".repeat(indent), part));
+            if i == last {
+                lines.push(format!("{}{}", " ".repeat(tail_start), tail));
+            }
+        }
+    }
+    lines
+}
+
+/// Greedy word wrap; words longer than a line are split
+pub fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+        if word.is_empty() {
+            continue;
+        }
+        let word: String = word.into_iter().collect();
+        let needed = if current.is_empty() {
+            word.chars().count()
+        } else {
+            current.chars().count() + 1 + word.chars().count()
+        };
+        if needed > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Returns the repeated character if the line is a separator such as
+/// `-----` or `=====`
+fn rule_char(line: &str) -> Option<char> {
+    let line = line.trim();
+    let first = line.chars().next()?;
+    if !matches!(first, '-' | '=' | '*' | '_') || line.chars().count() < 8 {
+        return None;
+    }
+    line.chars().all(|c| c == first).then_some(first)
+}
+
+/// The item table heading of the receipt and invoice layouts
+pub fn is_item_heading(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next() == Some("Item")
+        && line.contains("Qty")
+        && (line.contains("Amt") || line.contains("Amount"))
+}
+
+/// The item heading is followed by its own underline; the table ends at the
+/// rule after that one.
+fn is_item_heading_rule(receipt: &Receipt) -> bool {
+    matches!(
+        receipt.blocks.last(),
+        Some(Block::Text { text, .. }) if is_item_heading(text)
+    )
+}
+
+/// Whether `c` prints in text mode, as itself or through `transliterate`
+pub fn printable(c: char) -> bool {
+    (c.is_ascii() && !c.is_ascii_control())
+        || matches!(
+            c,
+            '₹' | '×' | '–' | '—' | '‘' | '’' | '“' | '”' | '\u{00A0}' | '•' | '\t'
+        )
+}
+
+/// Replace characters the printer's built-in code page cannot show.
+/// Dot matrix and thermal printers start up in PC437, which covers ASCII
+/// but not the rupee sign or typographic punctuation.
+pub fn transliterate(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '₹' => out.push_str("Rs."),
+            '×' => out.push('x'),
+            '–' | '—' => out.push('-'),
+            '‘' | '’' => out.push('\''),
+            '“' | '”' => out.push('"'),
+            '\u{00A0}' => out.push(' '),
+            '•' => out.push('*'),
+            c if c.is_ascii() && !c.is_ascii_control() => out.push(c),
+            '\t' => out.push(' '),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+/// Pad `text` to `width` columns according to `align`
+pub fn align_to(text: &str, width: usize, align: Align) -> String {
+    let len = text.chars().count();
+    if len >= width {
+        return text.to_string();
+    }
+    let gap = width - len;
+    match align {
+        Align::Left => text.to_string(),
+        Align::Center => format!("{}{}", " ".repeat(gap / 2), text),
+        Align::Right => format!("{}{}", " ".repeat(gap), text),
+    }
+}
@@ -0,0 +1,509 @@
+// =====================================================
+// Receipt Templates
+// Shop-edited receipt layouts kept in medbill.db; the
+// active one replaces the built-in bill layout
+// =====================================================
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::bill::{self, Bill, BillItem, Shop};
+use super::receipt::Receipt;
+use super::template::Template;
+
+/// Narrowest and widest receipt a template can be laid out for
+const MIN_WIDTH: usize = 20;
+const MAX_WIDTH: usize = 136;
+
+/// Seeded by the migration creating the table (inactive) as a starting
+/// point for editing
+pub const STANDARD_NAME: &str = "Standard";
+pub const STANDARD: &str = r#"{# Standard 42 column receipt. See "Receipt Templates" in the user guide. #}
+{{ shop.name }}
+{% if shop.address %}
+{{ shop.address }}
+{% endif %}
+{% if shop.phone %}
+Ph: {{ shop.phone }}
+{% endif %}
+{% if shop.gstin %}
+GSTIN: {{ shop.gstin }}
+{% endif %}
+{% if shop.drug_license %}
+D.L: {{ shop.drug_license }}
+{% endif %}
+{{ "=" | repeat:width }}
+Bill: {{ bill.number | left:20 }}{{ bill.date | date:"%d/%m/%y %H:%M" | right:16 }}
+Customer: {{ bill.customer | default:"Walk-in Customer" }}
+{% if bill.doctor %}
+Doctor: {{ bill.doctor }}
+{% endif %}
+{% if bill.cancelled %}
+           *** CANCELLED ***
+{% endif %}
+{% if bill.reprint %}
+         *** DUPLICATE COPY ***
+              Reprint #{{ bill.reprint }}
+{% endif %}
+{{ "-" | repeat:width }}
+Item                         Qty      Amt
+{{ "-" | repeat:width }}
+{% for item in items %}
+{{ loop.index }}. {{ item.name | left:22 }}{{ item.qty | right:8 }}{{ item.total | money | right:9 }}
+   Batch {{ item.batch }}  Exp {{ item.expiry | date:"%m/%y" }}  @{{ item.rate | money }}
+{% if item.scheduled %}
+   Schedule H - sold on prescription
+{% endif %}
+{% endfor %}
+{{ "-" | repeat:width }}
+{{ "Subtotal:" | left:30 }}{{ bill.subtotal | money | right:12 }}
+{% if bill.discount > 0 %}
+{{ "Discount:" | left:30 }}{{ bill.discount | money | right:12 }}
+{% endif %}
+{{ "CGST:" | left:30 }}{{ bill.cgst | money | right:12 }}
+{{ "SGST:" | left:30 }}{{ bill.sgst | money | right:12 }}
+{% if bill.round_off != 0 %}
+{{ "Round off:" | left:30 }}{{ bill.round_off | money | right:12 }}
+{% endif %}
+{{ "TOTAL:" | left:30 }}{{ bill.total | money | right:12 }}
+{{ bill.total | words }}
+{{ bill.payment }}
+{% if patient %}
+{{ "-" | repeat:width }}
+Patient: {{ patient.name }}{% if patient.age %}, {{ patient.age }} yrs{% endif %}
+{% if patient.doctor %}
+Prescribed by: {{ patient.doctor }} {{ patient.doctor_registration }}
+{% endif %}
+{% endif %}
+
+               Thank you!
+          *** Get Well Soon ***
+"#;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReceiptTemplate {
+    pub id: i64,
+    pub name: String,
+    pub body: String,
+    pub width: usize,
+    pub is_active: bool,
+    pub updated_at: Option<String>,
+}
+
+/// Seed the standard template into an empty table
+pub fn seed(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO receipt_templates (name, body, width)
+         SELECT ?1, ?2, 42 WHERE NOT EXISTS (SELECT 1 FROM receipt_templates)",
+        params![STANDARD_NAME, STANDARD],
+    )
+    .map_err(|e| format!("Failed to seed receipt templates: {}", e))?;
+    Ok(())
+}
+
+fn from_row(row: &rusqlite::Row) -> rusqlite::Result<ReceiptTemplate> {
+    Ok(ReceiptTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        body: row.get(2)?,
+        width: row.get::<_, i64>(3)?.max(0) as usize,
+        is_active: row.get::<_, i64>(4)? != 0,
+        updated_at: row.get(5)?,
+    })
+}
+
+const COLUMNS: &str = "id, name, body, width, is_active, updated_at";
+
+pub fn list(conn: &Connection) -> Result<Vec<ReceiptTemplate>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM receipt_templates ORDER BY name",
+            COLUMNS
+        ))
+        .map_err(|e| format!("Failed to load receipt templates: {}", e))?;
+    stmt.query_map([], from_row)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load receipt templates: {}", e))
+}
+
+/// The template bills print with, if the shop has chosen one. A database
+/// without the table (not yet started once) has none.
+pub fn active(conn: &Connection) -> Option<ReceiptTemplate> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM receipt_templates WHERE is_active = 1 ORDER BY id LIMIT 1",
+            COLUMNS
+        ),
+        [],
+        from_row,
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Parse `body` and try it on the sample bill, so a template that would
+/// fail at the counter cannot be saved. Returns the sample rendering.
+pub fn validate(body: &str, width: usize) -> Result<String, String> {
+    if !(MIN_WIDTH..=MAX_WIDTH).contains(&width) {
+        return Err(format!(
+            "Width must be between {} and {} characters",
+            MIN_WIDTH, MAX_WIDTH
+        ));
+    }
+    let template = Template::parse(body)?;
+    let sample = sample_context(width);
+    template.check(&sample)?;
+    template.render(&sample)
+}
+
+/// Insert or update a template after validating it. Making a template
+/// active makes every other one inactive. Returns the template's id.
+pub fn save(
+    conn: &mut Connection,
+    id: Option<i64>,
+    name: &str,
+    body: &str,
+    width: usize,
+    is_active: bool,
+) -> Result<i64, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    validate(body, width)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to save receipt template: {}", e))?;
+    let taken: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM receipt_templates WHERE name = ?1 AND id IS NOT ?2",
+            params![name, id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to save receipt template: {}", e))?;
+    if taken.is_some() {
+        return Err(format!(
+            "A receipt template named '{}' already exists",
+            name
+        ));
+    }
+
+    let id = match id {
+        Some(id) => {
+            let updated = tx
+                .execute(
+                    "UPDATE receipt_templates
+                     SET name = ?2, body = ?3, width = ?4, is_active = ?5,
+                         updated_at = CURRENT_TIMESTAMP
+                     WHERE id = ?1",
+                    params![id, name, body, width as i64, is_active as i64],
+                )
+                .map_err(|e| format!("Failed to save receipt template: {}", e))?;
+            if updated == 0 {
+                return Err(format!("Receipt template {} not found", id));
+            }
+            id
+        }
+        None => {
+            tx.execute(
+                "INSERT INTO receipt_templates (name, body, width, is_active)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![name, body, width as i64, is_active as i64],
+            )
+            .map_err(|e| format!("Failed to save receipt template: {}", e))?;
+            tx.last_insert_rowid()
+        }
+    };
+    if is_active {
+        tx.execute(
+            "UPDATE receipt_templates SET is_active = 0 WHERE id != ?1",
+            params![id],
+        )
+        .map_err(|e| format!("Failed to save receipt template: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to save receipt template: {}", e))?;
+    log::info!("Saved receipt template '{}'", name);
+    Ok(id)
+}
+
+pub fn delete(conn: &Connection, id: i64) -> Result<(), String> {
+    conn.execute("DELETE FROM receipt_templates WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete receipt template: {}", e))?;
+    Ok(())
+}
+
+/// Patient and prescriber recorded for the bill's scheduled drugs
+#[derive(Clone, Debug, Default)]
+pub struct Patient {
+    pub name: String,
+    pub age: Option<i64>,
+    pub gender: Option<String>,
+    pub phone: Option<String>,
+    pub doctor: Option<String>,
+    pub doctor_registration: Option<String>,
+    pub prescription_number: Option<String>,
+    pub prescription_date: Option<String>,
+}
+
+impl Patient {
+    /// `None` when the bill has no scheduled drugs recorded
+    pub fn load(conn: &Connection, bill_id: i64) -> Option<Patient> {
+        conn.query_row(
+            "SELECT patient_name, patient_age, patient_gender, patient_phone, doctor_name,
+                    doctor_registration_number, prescription_number, prescription_date
+             FROM scheduled_medicine_records WHERE bill_id = ?1 ORDER BY id LIMIT 1",
+            params![bill_id],
+            |row| {
+                Ok(Patient {
+                    name: row.get(0)?,
+                    age: row.get(1)?,
+                    gender: row.get(2)?,
+                    phone: row.get(3)?,
+                    doctor: row.get(4)?,
+                    doctor_registration: row.get(5)?,
+                    prescription_number: row.get(6)?,
+                    prescription_date: row.get(7)?,
+                })
+            },
+        )
+        .optional()
+        .ok()
+        .flatten()
+    }
+}
+
+/// The names a template can use; documented in the user guide
+pub fn context(shop: &Shop, bill: &Bill, patient: Option<&Patient>, width: usize) -> Value {
+    let items: Vec<Value> = bill.items.iter().map(item).collect();
+    let gst: Vec<Value> = bill::gst_breakup(&bill.items)
+        .into_iter()
+        .map(|line| {
+            json!({
+                "hsn": line.hsn_code,
+                "rate": line.gst_rate,
+                "taxable": line.taxable,
+                "cgst": line.cgst,
+                "sgst": line.sgst,
+            })
+        })
+        .collect();
+    json!({
+        "width": width,
+        "shop": {
+            "name": shop.name,
+            "address": shop.address,
+            "phone": shop.phone,
+            "gstin": shop.gstin,
+            "drug_license": shop.drug_license,
+            "state": shop.state,
+            "upi_id": shop.upi_id,
+        },
+        "bill": {
+            "number": bill.bill_number,
+            "date": bill.bill_date,
+            "customer": bill.customer_name,
+            "doctor": bill.doctor_name,
+            "subtotal": bill.subtotal,
+            "discount": bill.discount_amount,
+            "cgst": bill.total_cgst,
+            "sgst": bill.total_sgst,
+            "gst": bill.total_cgst + bill.total_sgst,
+            "round_off": bill.round_off,
+            "total": bill.grand_total,
+            "mode": bill.payment_mode,
+            "cash": bill.cash_amount,
+            "online": bill.online_amount,
+            "credit": bill.credit_amount,
+            "payment": bill::payment_line(bill),
+            "cancelled": bill.status == "CANCELLED",
+            "reprint": bill.reprint,
+            "scheduled": bill.items.iter().any(|i| i.scheduled),
+        },
+        "items": items,
+        "gst": gst,
+        "patient": patient.map(|p| json!({
+            "name": p.name,
+            "age": p.age,
+            "gender": p.gender,
+            "phone": p.phone,
+            "doctor": p.doctor,
+            "doctor_registration": p.doctor_registration,
+            "prescription_number": p.prescription_number,
+            "prescription_date": p.prescription_date,
+        })),
+    })
+}
+
+fn item(item: &BillItem) -> Value {
+    json!({
+        "name": item.medicine_name,
+        "hsn": item.hsn_code,
+        "batch": item.batch_number,
+        "expiry": item.expiry_date,
+        "rack": item.rack,
+        "box": item.box_label,
+        "location": bill::location(item),
+        "qty": bill::qty_display(item),
+        "quantity": item.quantity,
+        "per_strip": item.tablets_per_strip,
+        "rate": item.unit_price,
+        "discount": item.discount_amount,
+        "taxable": item.taxable_value,
+        "gst_rate": item.gst_rate,
+        "cgst": item.cgst,
+        "sgst": item.sgst,
+        "total": item.total,
+        "scheduled": item.scheduled,
+    })
+}
+
+/// A made-up bill with a scheduled drug and its patient, for previews and
+/// for checking templates on save
+pub fn sample_context(width: usize) -> Value {
+    let shop = Shop {
+        name: "City Medicals".to_string(),
+        address: "12 Main Road, Madurai".to_string(),
+        phone: "9876543210".to_string(),
+        gstin: "33AABCU9603R1ZM".to_string(),
+        drug_license: "TN-MDU-20B-1234".to_string(),
+        state: "Tamil Nadu".to_string(),
+        upi_id: "citymedicals@upi".to_string(),
+    };
+    let item = |name: &str, batch: &str, quantity, price: f64, scheduled| {
+        let total = price * quantity as f64;
+        let taxable = total / 1.12;
+        BillItem {
+            medicine_name: name.to_string(),
+            hsn_code: "3004".to_string(),
+            batch_number: batch.to_string(),
+            expiry_date: "2027-06-30".to_string(),
+            rack: Some("A1".to_string()),
+            box_label: Some("3".to_string()),
+            quantity,
+            tablets_per_strip: 10,
+            unit_price: price,
+            taxable_value: taxable,
+            gst_rate: 12.0,
+            cgst: (total - taxable) / 2.0,
+            sgst: (total - taxable) / 2.0,
+            total,
+            scheduled,
+            ..BillItem::default()
+        }
+    };
+    let items = vec![
+        item("Paracetamol 500mg Tablets", "PCM2401", 20, 2.5, false),
+        item("Azithromycin 500mg Tablets IP", "AZ2405", 3, 35.0, true),
+        item("Cough Syrup 100ml", "CS0112", 1, 95.0, false),
+    ];
+    let subtotal: f64 = items.iter().map(|i| i.total).sum();
+    let tax: f64 = items.iter().map(|i| i.cgst).sum();
+    let bill = Bill {
+        bill_number: "INV-2526-00042".to_string(),
+        bill_date: "2026-01-02 10:30:00".to_string(),
+        customer_name: Some("Ravi Kumar".to_string()),
+        doctor_name: Some("Dr. Meena".to_string()),
+        subtotal,
+        total_cgst: tax,
+        total_sgst: tax,
+        grand_total: subtotal,
+        payment_mode: "CASH".to_string(),
+        cash_amount: subtotal,
+        status: "COMPLETED".to_string(),
+        items,
+        ..Bill::default()
+    };
+    let patient = Patient {
+        name: "Ravi Kumar".to_string(),
+        age: Some(46),
+        gender: Some("M".to_string()),
+        doctor: Some("Dr. Meena".to_string()),
+        doctor_registration: Some("TNMC 45678".to_string()),
+        prescription_number: Some("RX-118".to_string()),
+        prescription_date: Some("2026-01-02".to_string()),
+        ..Patient::default()
+    };
+    context(&shop, &bill, Some(&patient), width)
+}
+
+/// Lay out a saved bill with `template`
+pub fn render_bill(
+    conn: &Connection,
+    template: &ReceiptTemplate,
+    shop: &Shop,
+    bill_id: i64,
+    bill: &Bill,
+) -> Result<Receipt, String> {
+    let patient = Patient::load(conn, bill_id);
+    let context = context(shop, bill, patient.as_ref(), template.width);
+    let text = Template::parse(&template.body)
+        .and_then(|t| t.render(&context))
+        .map_err(|e| format!("Receipt template '{}': {}", template.name, e))?;
+    Ok(Receipt::from_text(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_template_lays_out_like_a_bill() {
+        let text = validate(STANDARD, 42).unwrap();
+        let receipt = Receipt::from_text(&text);
+        assert_eq!(receipt.width, 42);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "City Medicals");
+        assert!(text.contains(
+            "Bill: INV-2526-00042        02/01/26 10:30\n\
+             Customer: Ravi Kumar\nDoctor: Dr. Meena\n"
+        ));
+        assert!(text.contains(
+            "2. Azithromycin 500mg Tab      3P   105.00\n\
+             \x20  Batch AZ2405  Exp 06/27  @35.00\n\
+             \x20  Schedule H - sold on prescription\n\
+             3. Cough Syrup"
+        ));
+        assert!(text.contains("TOTAL:                              250.00\n"));
+        assert!(text.contains("Patient: Ravi Kumar, 46 yrs\n"));
+        assert!(lines.iter().all(|l| l.chars().count() <= 42), "{}", text);
+    }
+
+    #[test]
+    fn save_validates_and_keeps_one_active() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join("medbill-templates-unused");
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        seed(&conn).unwrap();
+        assert_eq!(list(&conn).unwrap().len(), 1);
+        assert!(active(&conn).is_none());
+
+        let err = save(&mut conn, None, "Short", "{{ bill.totl }}", 32, true).unwrap_err();
+        assert_eq!(err, "Line 1: unknown name 'bill.totl'");
+        let err = save(&mut conn, None, "Standard", STANDARD, 42, false).unwrap_err();
+        assert_eq!(err, "A receipt template named 'Standard' already exists");
+        assert!(save(&mut conn, None, "Tiny", "{{ bill.total }}", 8, false).is_err());
+
+        let short = save(
+            &mut conn,
+            None,
+            "Short",
+            "{{ bill.total | money }}",
+            32,
+            true,
+        )
+        .unwrap();
+        assert_eq!(active(&conn).unwrap().id, short);
+        let standard = list(&conn).unwrap()[1].id;
+        save(&mut conn, Some(standard), "Standard", STANDARD, 42, true).unwrap();
+        let templates = list(&conn).unwrap();
+        assert!(!templates[0].is_active && templates[1].is_active);
+
+        delete(&conn, standard).unwrap();
+        assert!(active(&conn).is_none());
+    }
+}
@@ -0,0 +1,509 @@
+// =====================================================
+// Report PDFs
+// Sales, GST, expiry and credit reports laid out as A4
+// documents, read straight from medbill.db
+// =====================================================
+
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+use super::bill::{self, Shop};
+use super::invoice::shop_lines;
+use super::pdf::{self, Cell, Column, PageHeader, PdfDoc, Table, Weight, BODY_SIZE};
+use super::receipt::Align;
+
+/// Expiry report window when `expiry_alert_days` is not set
+const DEFAULT_EXPIRY_DAYS: i64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    Sales,
+    Gst,
+    Expiry,
+    Credit,
+}
+
+impl ReportKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            ReportKind::Sales => "SALES REPORT",
+            ReportKind::Gst => "GST REPORT",
+            ReportKind::Expiry => "EXPIRY REPORT",
+            ReportKind::Credit => "CREDIT REPORT",
+        }
+    }
+}
+
+/// Lay out `kind` for bills dated `from` to `to` (`YYYY-MM-DD`, inclusive).
+/// The expiry and credit reports are as of `today` and ignore the range.
+pub fn render(
+    conn: &Connection,
+    shop: &Shop,
+    kind: ReportKind,
+    from: NaiveDate,
+    to: NaiveDate,
+    today: NaiveDate,
+) -> Result<PdfDoc, String> {
+    let period = match kind {
+        ReportKind::Sales | ReportKind::Gst => format!(
+            "Period: {} - {}",
+            from.format("%d/%m/%Y"),
+            to.format("%d/%m/%Y")
+        ),
+        ReportKind::Expiry | ReportKind::Credit => format!("As on {}", today.format("%d/%m/%Y")),
+    };
+    let mut doc = PdfDoc::new(PageHeader {
+        left: shop_lines(shop),
+        title: kind.title().to_string(),
+        right: vec![period],
+        footer: format!("{} - computer generated report", shop.name),
+    })?;
+
+    match kind {
+        ReportKind::Sales => sales(&mut doc, conn, from, to)?,
+        ReportKind::Gst => gst(&mut doc, conn, from, to)?,
+        ReportKind::Expiry => expiry(&mut doc, conn, today)?,
+        ReportKind::Credit => credit(&mut doc, conn)?,
+    }
+    Ok(doc)
+}
+
+fn query<T>(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+    map: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, String> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("Failed to load report: {}", e))?;
+    stmt.query_map(params, map)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load report: {}", e))
+}
+
+fn date_param(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn sales(
+    doc: &mut PdfDoc,
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(), String> {
+    let bills = query(
+        conn,
+        "SELECT bill_number, bill_date, COALESCE(customer_name, ''), payment_mode,
+                COALESCE(total_gst, 0), COALESCE(grand_total, 0)
+         FROM bills
+         WHERE date(bill_date) BETWEEN ?1 AND ?2 AND COALESCE(is_cancelled, 0) = 0
+         ORDER BY bill_date, id",
+        params![date_param(from), date_param(to)],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, f64>(5)?,
+            ))
+        },
+    )?;
+
+    let total: f64 = bills.iter().map(|b| b.5).sum();
+    let gst: f64 = bills.iter().map(|b| b.4).sum();
+    let average = if bills.is_empty() {
+        0.0
+    } else {
+        total / bills.len() as f64
+    };
+    doc.summary(
+        &[
+            ("Bills".to_string(), bills.len().to_string()),
+            ("GST collected".to_string(), pdf::rupees(gst)),
+            ("Average bill".to_string(), pdf::rupees(average)),
+            ("Total sales".to_string(), pdf::rupees(total)),
+        ],
+        true,
+    );
+    doc.space(3.0);
+
+    let payments = query(
+        conn,
+        "SELECT payment_mode, COUNT(*), COALESCE(SUM(grand_total), 0)
+         FROM bills
+         WHERE date(bill_date) BETWEEN ?1 AND ?2 AND COALESCE(is_cancelled, 0) = 0
+         GROUP BY payment_mode
+         ORDER BY payment_mode",
+        params![date_param(from), date_param(to)],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        },
+    )?;
+    doc.heading("Payment Modes");
+    let mut table = Table::new(vec![
+        Column::text("Mode", 4.0),
+        Column::right("Bills", 2.0),
+        Column::money("Amount", 4.0),
+    ]);
+    for (mode, count, amount) in payments {
+        table.row(vec![
+            Cell::text(mode),
+            Cell::text(count.to_string()),
+            Cell::Money(amount),
+        ]);
+    }
+    doc.table(&table);
+
+    doc.heading("Bills");
+    let mut table = Table::new(vec![
+        Column::right("#", 1.5),
+        Column::text("Bill No", 5.0),
+        Column::text("Date", 4.5),
+        Column::text("Customer", 8.0),
+        Column::text("Mode", 3.0),
+        Column::money("GST", 4.0),
+        Column::money("Amount", 5.0),
+    ]);
+    for (i, (number, date, customer, mode, gst, amount)) in bills.into_iter().enumerate() {
+        table.row(vec![
+            Cell::text((i + 1).to_string()),
+            Cell::text(number),
+            Cell::text(bill::format_date_time(&date)),
+            Cell::text(customer),
+            Cell::text(mode),
+            Cell::Money(gst),
+            Cell::Money(amount),
+        ]);
+    }
+    doc.table(&table);
+    Ok(())
+}
+
+fn gst(doc: &mut PdfDoc, conn: &Connection, from: NaiveDate, to: NaiveDate) -> Result<(), String> {
+    let lines = query(
+        conn,
+        "SELECT bi.hsn_code, bi.gst_rate, COALESCE(SUM(bi.taxable_amount), 0),
+                COALESCE(SUM(bi.cgst_amount), 0), COALESCE(SUM(bi.sgst_amount), 0)
+         FROM bill_items bi
+         JOIN bills b ON bi.bill_id = b.id
+         WHERE date(b.bill_date) BETWEEN ?1 AND ?2 AND COALESCE(b.is_cancelled, 0) = 0
+         GROUP BY bi.hsn_code, bi.gst_rate
+         ORDER BY bi.hsn_code, bi.gst_rate",
+        params![date_param(from), date_param(to)],
+        |row| {
+            Ok(bill::GstLine {
+                hsn_code: row.get(0)?,
+                gst_rate: row.get(1)?,
+                taxable: row.get(2)?,
+                cgst: row.get(3)?,
+                sgst: row.get(4)?,
+            })
+        },
+    )?;
+
+    let columns = |first: Column| {
+        vec![
+            first,
+            Column::money("Taxable", 5.0),
+            Column::money("CGST", 4.0),
+            Column::money("SGST", 4.0),
+            Column::money("Total Tax", 4.0),
+        ]
+    };
+    let cells = |line: &bill::GstLine| {
+        vec![
+            Cell::Money(line.taxable),
+            Cell::Money(line.cgst),
+            Cell::Money(line.sgst),
+            Cell::Money(line.cgst + line.sgst),
+        ]
+    };
+
+    doc.heading("Rate-wise Summary");
+    let mut by_rate: Vec<bill::GstLine> = Vec::new();
+    for line in &lines {
+        match by_rate.iter_mut().find(|r| r.gst_rate == line.gst_rate) {
+            Some(rate) => {
+                rate.taxable += line.taxable;
+                rate.cgst += line.cgst;
+                rate.sgst += line.sgst;
+            }
+            None => by_rate.push(bill::GstLine {
+                hsn_code: String::new(),
+                ..line.clone()
+            }),
+        }
+    }
+    by_rate.sort_by(|a, b| a.gst_rate.total_cmp(&b.gst_rate));
+    let mut table = Table::new(columns(Column::text("GST Rate", 4.0)));
+    for line in &by_rate {
+        let mut row = vec![Cell::text(format!("{}%", bill::rate(line.gst_rate)))];
+        row.extend(cells(line));
+        table.row(row);
+    }
+    doc.table(&table);
+
+    doc.heading("HSN-wise Summary");
+    let mut table = Table::new(columns(Column::text("HSN / Rate", 4.0)));
+    for line in &lines {
+        let mut row = vec![Cell::text(format!(
+            "{} @ {}%",
+            line.hsn_code,
+            bill::rate(line.gst_rate)
+        ))];
+        row.extend(cells(line));
+        table.row(row);
+    }
+    doc.table(&table);
+    Ok(())
+}
+
+fn expiry(doc: &mut PdfDoc, conn: &Connection, today: NaiveDate) -> Result<(), String> {
+    let days = crate::db::get_setting_or(conn, "expiry_alert_days", DEFAULT_EXPIRY_DAYS);
+    let horizon = today + Duration::days(days);
+    let batches = query(
+        conn,
+        "SELECT m.name, b.batch_number, b.expiry_date, b.rack, b.box, b.quantity,
+                COALESCE(b.tablets_per_strip, 10), b.selling_price
+         FROM batches b
+         JOIN medicines m ON b.medicine_id = m.id
+         WHERE b.is_active = 1 AND m.is_active = 1 AND b.quantity > 0
+           AND b.expiry_date <= ?1
+         ORDER BY b.expiry_date, m.name",
+        params![date_param(horizon)],
+        |row| {
+            Ok(bill::BillItem {
+                medicine_name: row.get(0)?,
+                batch_number: row.get(1)?,
+                expiry_date: row.get(2)?,
+                rack: row.get(3)?,
+                box_label: row.get(4)?,
+                quantity: row.get(5)?,
+                tablets_per_strip: row.get(6)?,
+                unit_price: row.get(7)?,
+                ..bill::BillItem::default()
+            })
+        },
+    )?;
+
+    let today_param = date_param(today);
+    let (expired, expiring): (Vec<_>, Vec<_>) = batches
+        .into_iter()
+        .partition(|b| b.expiry_date.get(..10).unwrap_or(&b.expiry_date) <= today_param.as_str());
+    let value = |items: &[bill::BillItem]| {
+        items
+            .iter()
+            .map(|b| b.unit_price * b.quantity as f64)
+            .sum::<f64>()
+    };
+    doc.summary(
+        &[
+            ("Expired batches".to_string(), expired.len().to_string()),
+            (
+                format!("Expiring within {} days", days),
+                expiring.len().to_string(),
+            ),
+            (
+                "Stock value at risk".to_string(),
+                pdf::rupees(value(&expired) + value(&expiring)),
+            ),
+        ],
+        true,
+    );
+    doc.space(3.0);
+
+    for (title, items) in [("Expired", expired), ("Expiring Soon", expiring)] {
+        doc.heading(title);
+        if items.is_empty() {
+            doc.paragraph("None", BODY_SIZE, Weight::Regular, Align::Left);
+            doc.space(3.0);
+            continue;
+        }
+        let mut table = Table::new(vec![
+            Column::right("#", 1.5),
+            Column::text("Medicine", 9.0),
+            Column::text("Batch", 4.5),
+            Column::text("Expiry", 3.0),
+            Column::text("Location", 4.0),
+            Column::right("Stock", 3.0),
+            Column::money("Value", 4.5),
+        ]);
+        for (i, item) in items.iter().enumerate() {
+            table.row(vec![
+                Cell::text((i + 1).to_string()),
+                Cell::text(item.medicine_name.clone()),
+                Cell::text(item.batch_number.clone()),
+                Cell::text(bill::format_expiry(&item.expiry_date)),
+                Cell::text(bill::location(item).unwrap_or_default()),
+                Cell::text(bill::qty_display(item)),
+                Cell::Money(item.unit_price * item.quantity as f64),
+            ]);
+        }
+        doc.table(&table);
+    }
+    Ok(())
+}
+
+fn credit(doc: &mut PdfDoc, conn: &Connection) -> Result<(), String> {
+    let customers = query(
+        conn,
+        "SELECT c.name, COALESCE(c.phone, ''), COALESCE(c.credit_limit, 0), c.current_balance,
+                (SELECT MAX(created_at) FROM credits WHERE customer_id = c.id)
+         FROM customers c
+         WHERE c.current_balance > 0
+         ORDER BY c.current_balance DESC, c.name",
+        [],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        },
+    )?;
+
+    let outstanding: f64 = customers.iter().map(|c| c.3).sum();
+    let over_limit = customers.iter().filter(|c| c.2 > 0.0 && c.3 > c.2).count();
+    doc.summary(
+        &[
+            (
+                "Customers with dues".to_string(),
+                customers.len().to_string(),
+            ),
+            ("Over credit limit".to_string(), over_limit.to_string()),
+            ("Total outstanding".to_string(), pdf::rupees(outstanding)),
+        ],
+        true,
+    );
+    doc.space(3.0);
+
+    let mut table = Table::new(vec![
+        Column::right("#", 1.5),
+        Column::text("Customer", 8.0),
+        Column::text("Phone", 4.5),
+        Column::right("Credit Limit", 4.5),
+        Column::text("Last Transaction", 4.5),
+        Column::money("Outstanding", 5.0),
+    ]);
+    for (i, (name, phone, limit, balance, last)) in customers.into_iter().enumerate() {
+        table.row(vec![
+            Cell::text((i + 1).to_string()),
+            Cell::text(name),
+            Cell::text(phone),
+            Cell::text(pdf::rupees(limit)),
+            Cell::text(
+                last.map(|d| bill::format_date_time(&d))
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Cell::Money(balance),
+        ]);
+    }
+    doc.table(&table);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The shared fixture plus an online bill, a cancelled bill, batches
+    /// either side of the expiry window and three credit customers
+    fn sample_db() -> Connection {
+        let conn = crate::print::fixtures::database();
+        crate::print::fixtures::seed(&conn);
+        conn.execute_batch(
+            "INSERT INTO batches (id, medicine_id, batch_number, expiry_date, purchase_price, mrp,
+                     selling_price, quantity, tablets_per_strip, rack, box)
+                 VALUES
+                 (4, 1, 'OLD1', '2026-01-10', 2, 3, 2.5, 40, 10, 'A1', '1'),
+                 (5, 3, 'CS02', '2026-02-05', 70, 110, 100, 3, 1, NULL, NULL),
+                 (6, 1, 'NEW1', '2028-01-01', 2, 3, 2.5, 100, 10, 'A1', '2');
+             INSERT INTO bills (id, bill_number, bill_date, user_id, payment_mode, total_gst,
+                     grand_total, is_cancelled)
+                 VALUES
+                 (2, 'INV-2', '2026-01-03 11:00:00', 1, 'ONLINE', 4.76, 100, 0),
+                 (3, 'INV-3', '2026-01-03 12:00:00', 1, 'CASH', 48, 500, 1);
+             INSERT INTO bill_items (id, bill_id, batch_id, medicine_id, medicine_name,
+                     batch_number, hsn_code, quantity, mrp, selling_price, taxable_amount,
+                     gst_rate, cgst_amount, sgst_amount, total_amount)
+                 VALUES (4, 3, 1, 1, 'Paracetamol 500mg', 'BT2024001', '3004', 200, 3, 2.5, 400,
+                     12, 24, 24, 448);
+             INSERT INTO customers (id, name, phone, credit_limit, current_balance) VALUES
+                 (1, 'Ravi', '98400', 1000, 1500), (2, 'Meena', NULL, 0, 250),
+                 (3, 'Paid Up', NULL, 0, 0);
+             INSERT INTO credits (id, customer_id, transaction_type, amount, balance_after,
+                     user_id, created_at)
+                 VALUES (1, 1, 'CREDIT', 1500, 1500, 1, '2026-01-05 09:00:00');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn text(kind: ReportKind) -> String {
+        let conn = sample_db();
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let doc = render(
+            &conn,
+            &Shop::load(&conn),
+            kind,
+            date("2026-01-01"),
+            date("2026-01-31"),
+            date("2026-01-20"),
+        )
+        .unwrap();
+        let bytes = doc.to_bytes(kind.title()).unwrap();
+        pdf_extract::extract_text_from_mem(&bytes).unwrap()
+    }
+
+    #[test]
+    fn sales_report_skips_cancelled_bills() {
+        let text = text(ReportKind::Sales);
+        assert!(text.contains("SALES REPORT"), "{}", text);
+        assert!(text.contains("Period: 01/01/2026 - 31/01/2026"));
+        assert!(text.contains("INV-2"));
+        assert!(!text.contains("INV-3"));
+        assert!(text.contains("₹1,052.00"));
+        assert!(text.contains("Page 1 of 1"));
+    }
+
+    #[test]
+    fn gst_report_groups_by_rate_and_hsn() {
+        let text = text(ReportKind::Gst);
+        assert!(text.contains("3004 @ 12%"), "{}", text);
+        assert!(text.contains("₹850.00"));
+        assert!(!text.contains("₹1,250.00"));
+        assert!(text.contains("₹945.24"));
+    }
+
+    #[test]
+    fn expiry_report_splits_expired_batches() {
+        let text = text(ReportKind::Expiry);
+        assert!(text.contains("As on 20/01/2026"), "{}", text);
+        assert!(text.contains("OLD1"));
+        assert!(text.contains("CS02"));
+        assert!(!text.contains("NEW1"));
+        assert!(text.contains("Rack A1/1"));
+        assert!(text.contains("₹400.00"));
+    }
+
+    #[test]
+    fn credit_report_lists_outstanding_customers() {
+        let text = text(ReportKind::Credit);
+        assert!(text.contains("Ravi"), "{}", text);
+        assert!(text.contains("Meena"));
+        assert!(!text.contains("Paid Up"));
+        assert!(text.contains("₹1,750.00"));
+        assert!(text.contains("05/01/26 09:00"));
+    }
+}
@@ -0,0 +1,189 @@
+// =====================================================
+// Bill Reprints
+// Every print of a saved bill goes in audit_log with the
+// user and printer, so later copies can be stamped as
+// duplicates and repeat reprints reported
+// =====================================================
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// `audit_log` action for the first print of a bill
+pub const PRINT: &str = "BILL_PRINT";
+/// `audit_log` action for every print after the first
+pub const REPRINT: &str = "BILL_REPRINT";
+
+/// How many times `bill_id` has been printed so far
+pub fn print_count(conn: &Connection, bill_id: i64) -> Result<u32, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM audit_log
+         WHERE entity_type = 'bill' AND entity_id = ?1 AND action IN (?2, ?3)",
+        params![bill_id, PRINT, REPRINT],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read print history: {}", e))
+}
+
+/// Record a print of `bill_id` on `printer`. `reprint` is 0 for the first
+/// print and the reprint number after that. Without a `user_id` the print
+/// is put down to the user who made the bill.
+pub fn log_print(
+    conn: &Connection,
+    user_id: Option<i64>,
+    bill_id: i64,
+    printer: &str,
+    reprint: u32,
+) -> Result<i64, String> {
+    let (action, description) = if reprint == 0 {
+        (PRINT, "Bill printed".to_string())
+    } else {
+        (REPRINT, format!("Reprint #{}", reprint))
+    };
+    conn.execute(
+        "INSERT INTO audit_log (user_id, action, entity_type, entity_id, new_value, description)
+         VALUES (COALESCE(?1, (SELECT user_id FROM bills WHERE id = ?2)), ?3, 'bill', ?2, ?4, ?5)",
+        params![user_id, bill_id, action, printer, description],
+    )
+    .map_err(|e| format!("Failed to write audit log: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// A row of the reprinted bills report
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReprintedBill {
+    pub bill_id: i64,
+    pub bill_number: String,
+    pub bill_date: String,
+    pub customer_name: Option<String>,
+    pub grand_total: f64,
+    pub reprints: u32,
+    pub last_reprinted_at: String,
+    /// Who asked for the latest reprint
+    pub last_reprinted_by: Option<String>,
+    pub last_printer: Option<String>,
+}
+
+/// Bills dated `from` to `to` (`YYYY-MM-DD`, inclusive) reprinted more than
+/// `more_than` times, most reprinted first
+pub fn reprinted_bills(
+    conn: &Connection,
+    more_than: u32,
+    from: &str,
+    to: &str,
+) -> Result<Vec<ReprintedBill>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT b.id, b.bill_number, b.bill_date, b.customer_name,
+                    COALESCE(b.grand_total, 0), COUNT(a.id), MAX(a.created_at),
+                    u.full_name, last.new_value
+             FROM bills b
+             JOIN audit_log a ON a.entity_type = 'bill' AND a.entity_id = b.id AND a.action = ?1
+             JOIN audit_log last ON last.id = (
+                 SELECT MAX(id) FROM audit_log
+                 WHERE entity_type = 'bill' AND entity_id = b.id AND action = ?1
+             )
+             LEFT JOIN users u ON u.id = last.user_id
+             WHERE date(b.bill_date) BETWEEN ?2 AND ?3
+             GROUP BY b.id
+             HAVING COUNT(a.id) > ?4
+             ORDER BY COUNT(a.id) DESC, b.bill_date",
+        )
+        .map_err(|e| format!("Failed to load reprinted bills: {}", e))?;
+    stmt.query_map(params![REPRINT, from, to, more_than], |row| {
+        Ok(ReprintedBill {
+            bill_id: row.get(0)?,
+            bill_number: row.get(1)?,
+            bill_date: row.get(2)?,
+            customer_name: row.get(3)?,
+            grand_total: row.get(4)?,
+            reprints: row.get(5)?,
+            last_reprinted_at: row.get(6)?,
+            last_reprinted_by: row.get(7)?,
+            last_printer: row.get(8)?,
+        })
+    })
+    .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+    .map_err(|e| format!("Failed to load reprinted bills: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bill 1 from the shared fixture, bill 2 a day later and bill 3 in
+    /// the month before
+    fn fixture() -> Connection {
+        let conn = crate::print::fixtures::database();
+        crate::print::fixtures::seed(&conn);
+        conn.execute_batch(
+            "INSERT INTO bills (id, bill_number, bill_date, user_id, grand_total) VALUES
+                 (2, 'INV-2', '2026-01-03 11:00:00', 1, 100),
+                 (3, 'INV-3', '2025-12-31 18:00:00', 1, 500);",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// Print `bill_id` `times` times as `print_bill` does
+    fn print(conn: &Connection, user_id: Option<i64>, bill_id: i64, times: u32) {
+        for _ in 0..times {
+            let reprint = print_count(conn, bill_id).unwrap();
+            log_print(conn, user_id, bill_id, "TVS MSP 250", reprint).unwrap();
+        }
+    }
+
+    #[test]
+    fn first_print_then_numbered_reprints() {
+        let conn = fixture();
+        assert_eq!(print_count(&conn, 1).unwrap(), 0);
+        print(&conn, None, 1, 1);
+        print(&conn, Some(2), 1, 2);
+
+        let rows: Vec<(i64, String, String, String)> = conn
+            .prepare("SELECT user_id, action, new_value, description FROM audit_log ORDER BY id")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rows[0],
+            (
+                1,
+                PRINT.to_string(),
+                "TVS MSP 250".to_string(),
+                "Bill printed".to_string()
+            )
+        );
+        assert_eq!(rows[2].0, 2);
+        assert_eq!(rows[2].1, REPRINT);
+        assert_eq!(rows[2].3, "Reprint #2");
+        assert_eq!(print_count(&conn, 1).unwrap(), 3);
+        assert_eq!(print_count(&conn, 2).unwrap(), 0);
+    }
+
+    #[test]
+    fn report_lists_bills_reprinted_more_than_n_times() {
+        let conn = fixture();
+        print(&conn, Some(1), 1, 4);
+        print(&conn, Some(1), 2, 2);
+        print(&conn, Some(2), 2, 1);
+        print(&conn, Some(1), 3, 5);
+
+        let bills = reprinted_bills(&conn, 1, "2026-01-01", "2026-01-31").unwrap();
+        let counts: Vec<(&str, u32)> = bills
+            .iter()
+            .map(|b| (b.bill_number.as_str(), b.reprints))
+            .collect();
+        assert_eq!(counts, [("INV-2425-00001", 3), ("INV-2", 2)]);
+        assert_eq!(bills[1].last_reprinted_by.as_deref(), Some("Counter Staff"));
+        assert_eq!(bills[1].last_printer.as_deref(), Some("TVS MSP 250"));
+
+        assert_eq!(
+            reprinted_bills(&conn, 2, "2026-01-01", "2026-01-31")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,171 @@
+// =====================================================
+// Raw TCP Printing (JetDirect / port 9100)
+// Ethernet receipt printers accept the same ESC/POS
+// bytes over a plain socket, no driver needed
+// =====================================================
+
+use std::io::Write;
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use super::backend::{PrintJob, PrinterBackend, PrinterStatus};
+
+pub const DEFAULT_PORT: u16 = 9100;
+/// Printer names starting with this are sent over a raw socket by any backend
+pub const SCHEME: &str = "tcp://";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// `host`, `host:port` or `tcp://host[:port]` to `host:port`. IPv6
+/// addresses are written bare (`fe80::1`) or in brackets (`[fe80::1]:9100`).
+pub fn parse_address(target: &str) -> String {
+    let address = target.trim();
+    let address = address.strip_prefix(SCHEME).unwrap_or(address);
+    let address = address.trim_end_matches('/');
+    if address.parse::<SocketAddr>().is_ok() {
+        return address.to_string();
+    }
+    let bare = address.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return SocketAddr::new(ip, DEFAULT_PORT).to_string();
+    }
+    match address.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => address.to_string(),
+        _ => format!("{}:{}", address, DEFAULT_PORT),
+    }
+}
+
+pub fn connect(address: &str, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve {}: {}", address, e))?;
+
+    let mut last_error = format!("No address found for {}", address);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = format!("Failed to connect to {}: {}", address, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// Send `data` to the printer at `address` and close the connection
+pub fn send(address: &str, data: &[u8]) -> Result<(), String> {
+    let mut stream = connect(address, CONNECT_TIMEOUT)?;
+    stream
+        .set_write_timeout(Some(WRITE_TIMEOUT))
+        .map_err(|e| format!("Failed to set write timeout: {}", e))?;
+    stream
+        .write_all(data)
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to send job to {}: {}", address, e))?;
+    // Half-close so the printer sees the end of the job
+    let _ = stream.shutdown(Shutdown::Write);
+    Ok(())
+}
+
+/// Whether the printer accepts connections
+pub fn probe(address: &str) -> bool {
+    match connect(address, PROBE_TIMEOUT) {
+        Ok(stream) => {
+            let _ = stream.shutdown(Shutdown::Both);
+            true
+        }
+        Err(e) => {
+            log::info!("Printer probe: {}", e);
+            false
+        }
+    }
+}
+
+/// Network printer reached directly at `host:port`
+pub struct SocketBackend {
+    address: String,
+}
+
+impl SocketBackend {
+    /// `address` is `host` or `host:port`; the port defaults to 9100
+    pub fn new(address: &str) -> Self {
+        SocketBackend {
+            address: parse_address(address),
+        }
+    }
+}
+
+impl PrinterBackend for SocketBackend {
+    fn name(&self) -> &'static str {
+        "socket"
+    }
+
+    fn list_printers(&self) -> Result<Vec<String>, String> {
+        Ok(vec![self.address.clone()])
+    }
+
+    fn default_printer(&self) -> Option<String> {
+        Some(self.address.clone())
+    }
+
+    fn status(&self, printer: &str) -> PrinterStatus {
+        if probe(&parse_address(printer)) {
+            PrinterStatus::Ready
+        } else {
+            PrinterStatus::Offline
+        }
+    }
+
+    fn submit(&self, printer: &str, job: &PrintJob) -> Result<String, String> {
+        let address = parse_address(printer);
+        send(&address, &job.data)?;
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn addresses_get_the_default_port() {
+        let cases = [
+            ("192.168.1.50", "192.168.1.50:9100"),
+            (" tcp://192.168.1.50:9101/ ", "192.168.1.50:9101"),
+            ("printer.local", "printer.local:9100"),
+            ("tcp://printer.local:515", "printer.local:515"),
+            ("fe80::1", "[fe80::1]:9100"),
+            ("tcp://2001:db8::20", "[2001:db8::20]:9100"),
+            ("[fe80::1]", "[fe80::1]:9100"),
+            ("[fe80::1]:9101", "[fe80::1]:9101"),
+        ];
+        for (target, expected) in cases {
+            assert_eq!(parse_address(target), expected, "{}", target);
+        }
+    }
+
+    #[test]
+    fn jobs_arrive_over_the_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let printer = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            // Ends when the sender half-closes the connection
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        let target = format!("tcp://127.0.0.1:{}", port);
+        let backend = SocketBackend::new(&target);
+        let job = PrintJob::raw("MedBill Receipt", b"\x1b@BILL\x1dVB\x03".to_vec());
+        let address = backend.submit(&target, &job).unwrap();
+        assert_eq!(address, format!("127.0.0.1:{}", port));
+        assert_eq!(printer.join().unwrap(), job.data);
+
+        // Nobody listens there any more
+        assert_eq!(backend.status(&target), PrinterStatus::Offline);
+    }
+}
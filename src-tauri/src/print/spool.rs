@@ -0,0 +1,615 @@
+// =====================================================
+// Print Spool
+// Jobs are stored in print_jobs and printed by a worker
+// thread, so a bill is not lost when the printer is
+// offline. Retries back off up to MAX_ATTEMPTS.
+// =====================================================
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::Emitter;
+
+use super::backend::{PrintJob, PrinterBackend};
+use super::drawer;
+use super::profiles::DocumentKind;
+use super::reprint;
+
+/// Tauri event emitted on every status change
+pub const EVENT: &str = "print-job-status";
+
+const MAX_ATTEMPTS: u32 = 8;
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+/// A rendered job waiting to be queued
+#[derive(Clone, Debug)]
+pub struct NewJob {
+    pub bill_id: Option<i64>,
+    pub document: DocumentKind,
+    pub printer: String,
+    pub job: PrintJob,
+    pub copies: u32,
+    /// Write the bill's print to `audit_log` once the job is done
+    pub audit: bool,
+    /// Who asked for the print; the bill's user when `None`
+    pub user_id: Option<i64>,
+}
+
+/// A print_jobs row without its data
+#[derive(Clone, Debug, Serialize)]
+pub struct SpoolJob {
+    pub id: i64,
+    pub bill_id: Option<i64>,
+    pub document: String,
+    pub printer: String,
+    pub title: String,
+    pub status: String,
+    pub copies: u32,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub reprint_of: Option<i64>,
+    pub created_at: String,
+    pub printed_at: Option<String>,
+}
+
+/// Payload of the `print-job-status` event
+#[derive(Clone, Debug, Serialize)]
+pub struct JobEvent {
+    pub id: i64,
+    pub bill_id: Option<i64>,
+    pub status: String,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+/// Put jobs interrupted by a shutdown back in the queue
+pub fn requeue_interrupted(conn: &Connection) -> Result<(), String> {
+    let requeued = conn
+        .execute(
+            "UPDATE print_jobs SET status = 'queued', next_attempt_at = CURRENT_TIMESTAMP
+             WHERE status = 'printing'",
+            [],
+        )
+        .map_err(|e| format!("Failed to requeue print jobs: {}", e))?;
+    if requeued > 0 {
+        log::info!("Requeued {} interrupted print jobs", requeued);
+    }
+    Ok(())
+}
+
+pub fn enqueue(conn: &Connection, new: &NewJob) -> Result<i64, String> {
+    let document = serde_json::to_value(new.document)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "bill".to_string());
+    conn.execute(
+        "INSERT INTO print_jobs (bill_id, document, printer, title, raw, data, copies, audit, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            new.bill_id,
+            document,
+            new.printer,
+            new.job.title,
+            new.job.raw,
+            new.job.data,
+            new.copies.max(1),
+            new.audit && new.bill_id.is_some(),
+            new.user_id
+        ],
+    )
+    .map_err(|e| format!("Failed to queue print job: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Jobs newest first, optionally filtered by status and bill
+pub fn list(
+    conn: &Connection,
+    status: Option<&str>,
+    bill_id: Option<i64>,
+    limit: u32,
+) -> Result<Vec<SpoolJob>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, bill_id, document, printer, title, status, copies, attempts,
+                    last_error, reprint_of, created_at, printed_at
+             FROM print_jobs
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR bill_id = ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )
+        .map_err(|e| format!("Failed to list print jobs: {}", e))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![status, bill_id, limit], |row| {
+            Ok(SpoolJob {
+                id: row.get(0)?,
+                bill_id: row.get(1)?,
+                document: row.get(2)?,
+                printer: row.get(3)?,
+                title: row.get(4)?,
+                status: row.get(5)?,
+                copies: row.get(6)?,
+                attempts: row.get(7)?,
+                last_error: row.get(8)?,
+                reprint_of: row.get(9)?,
+                created_at: row.get(10)?,
+                printed_at: row.get(11)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list print jobs: {}", e))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read print job: {}", e))
+}
+
+fn event(conn: &Connection, id: i64) -> Result<JobEvent, String> {
+    conn.query_row(
+        "SELECT bill_id, status, attempts, last_error FROM print_jobs WHERE id = ?1",
+        rusqlite::params![id],
+        |row| {
+            Ok(JobEvent {
+                id,
+                bill_id: row.get(0)?,
+                status: row.get(1)?,
+                attempts: row.get(2)?,
+                error: row.get(3)?,
+            })
+        },
+    )
+    .map_err(|e| format!("Print job {} not found: {}", id, e))
+}
+
+/// Cancel a job that has not printed yet
+pub fn cancel(conn: &Connection, id: i64) -> Result<JobEvent, String> {
+    let changed = conn
+        .execute(
+            "UPDATE print_jobs SET status = 'cancelled'
+             WHERE id = ?1 AND status IN ('queued', 'failed')",
+            rusqlite::params![id],
+        )
+        .map_err(|e| format!("Failed to cancel print job: {}", e))?;
+    if changed == 0 {
+        return Err(format!("Print job {} is printing or already finished", id));
+    }
+    event(conn, id)
+}
+
+/// Send a job again. Failed and cancelled jobs go back in the queue;
+/// a printed job is copied to a new job so the reprint shows in the history.
+pub fn resend(conn: &Connection, id: i64) -> Result<JobEvent, String> {
+    let status: Option<String> = conn
+        .query_row(
+            "SELECT status FROM print_jobs WHERE id = ?1",
+            rusqlite::params![id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read print job: {}", e))?;
+
+    match status.as_deref() {
+        None => Err(format!("Print job {} not found", id)),
+        Some("failed") | Some("cancelled") => {
+            conn.execute(
+                "UPDATE print_jobs SET status = 'queued', attempts = 0, last_error = NULL,
+                        next_attempt_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1",
+                rusqlite::params![id],
+            )
+            .map_err(|e| format!("Failed to requeue print job: {}", e))?;
+            event(conn, id)
+        }
+        Some("done") => {
+            conn.execute(
+                "INSERT INTO print_jobs (bill_id, document, printer, title, raw, data, copies, reprint_of)
+                 SELECT bill_id, document, printer, title, raw, data, copies, id
+                 FROM print_jobs WHERE id = ?1",
+                rusqlite::params![id],
+            )
+            .map_err(|e| format!("Failed to queue reprint: {}", e))?;
+            event(conn, conn.last_insert_rowid())
+        }
+        Some(_) => Err(format!("Print job {} is already queued", id)),
+    }
+}
+
+/// Bill and printer of a printed bill or duplicate job. Resending one lays
+/// the bill out again so the copy is stamped as a reprint.
+pub fn printed_bill(conn: &Connection, id: i64) -> Result<Option<(i64, String)>, String> {
+    conn.query_row(
+        "SELECT bill_id, printer FROM print_jobs
+         WHERE id = ?1 AND status = 'done' AND bill_id IS NOT NULL
+           AND document IN ('bill', 'duplicate')",
+        rusqlite::params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read print job: {}", e))
+}
+
+/// Queue `new` as a reprint of job `original`
+pub fn enqueue_reprint(conn: &Connection, new: &NewJob, original: i64) -> Result<JobEvent, String> {
+    let id = enqueue(conn, new)?;
+    conn.execute(
+        "UPDATE print_jobs SET reprint_of = ?1 WHERE id = ?2",
+        rusqlite::params![original, id],
+    )
+    .map_err(|e| format!("Failed to queue reprint: {}", e))?;
+    event(conn, id)
+}
+
+/// Delay before the next attempt: 5s, 10s, 20s ... capped at 5 minutes
+fn backoff(attempts: u32) -> Duration {
+    let secs = 5u64.saturating_mul(1 << attempts.saturating_sub(1).min(10));
+    Duration::from_secs(secs.min(300))
+}
+
+/// Print the oldest due job, if any. Returns whether a job was attempted.
+pub fn process_next(
+    conn: &Connection,
+    backend: &dyn PrinterBackend,
+    notify: &dyn Fn(JobEvent),
+) -> Result<bool, String> {
+    let due = conn
+        .query_row(
+            "SELECT id, printer, title, raw, data, copies, copies_printed FROM print_jobs
+             WHERE status = 'queued' AND next_attempt_at <= CURRENT_TIMESTAMP
+             ORDER BY id LIMIT 1",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    PrintJob {
+                        title: row.get(2)?,
+                        raw: row.get(3)?,
+                        data: row.get(4)?,
+                    },
+                    row.get::<_, u32>(5)?,
+                    row.get::<_, u32>(6)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read print queue: {}", e))?;
+
+    let Some((id, printer, job, copies, printed)) = due else {
+        return Ok(false);
+    };
+
+    conn.execute(
+        "UPDATE print_jobs SET status = 'printing', attempts = attempts + 1 WHERE id = ?1",
+        rusqlite::params![id],
+    )
+    .map_err(|e| format!("Failed to update print job: {}", e))?;
+    notify(event(conn, id)?);
+
+    // Each copy is counted as it goes out, so a retry skips the ones printed
+    let result = (printed..copies.max(1)).try_for_each(|_| {
+        let job_id = backend.submit(&printer, &job)?;
+        log::info!("{} job {} (spool {})", backend.name(), job_id, id);
+        conn.execute(
+            "UPDATE print_jobs SET copies_printed = copies_printed + 1 WHERE id = ?1",
+            rusqlite::params![id],
+        )
+        .map(|_| ())
+        .map_err(|e| format!("Failed to update print job: {}", e))
+    });
+
+    match result {
+        Ok(()) => {
+            conn.execute(
+                "UPDATE print_jobs SET status = 'done', last_error = NULL,
+                        printed_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1",
+                rusqlite::params![id],
+            )
+            .map_err(|e| format!("Failed to update print job: {}", e))?;
+            audit_print(conn, id, &printer)?;
+            drawer::after_print(conn, backend, id, &printer);
+        }
+        Err(error) => {
+            let attempts: u32 = conn
+                .query_row(
+                    "SELECT attempts FROM print_jobs WHERE id = ?1",
+                    rusqlite::params![id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Failed to read print job: {}", e))?;
+            log::warn!("Print job {} attempt {} failed: {}", id, attempts, error);
+
+            if attempts >= MAX_ATTEMPTS {
+                conn.execute(
+                    "UPDATE print_jobs SET status = 'failed', last_error = ?2 WHERE id = ?1",
+                    rusqlite::params![id, error],
+                )
+            } else {
+                conn.execute(
+                    "UPDATE print_jobs SET status = 'queued', last_error = ?2,
+                            next_attempt_at = datetime('now', ?3)
+                     WHERE id = ?1",
+                    rusqlite::params![
+                        id,
+                        error,
+                        format!("+{} seconds", backoff(attempts).as_secs())
+                    ],
+                )
+            }
+            .map_err(|e| format!("Failed to update print job: {}", e))?;
+        }
+    }
+
+    notify(event(conn, id)?);
+    Ok(true)
+}
+
+/// Write a printed bill to `audit_log`, numbered from the prints before it
+fn audit_print(conn: &Connection, id: i64, printer: &str) -> Result<(), String> {
+    let audited = conn
+        .query_row(
+            "SELECT bill_id, user_id FROM print_jobs
+             WHERE id = ?1 AND audit = 1 AND bill_id IS NOT NULL",
+            rusqlite::params![id],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read print job: {}", e))?;
+    if let Some((bill_id, user_id)) = audited {
+        let reprint = reprint::print_count(conn, bill_id)?;
+        reprint::log_print(conn, user_id, bill_id, printer, reprint)?;
+        if reprint > 0 {
+            log::info!("Reprint #{} of bill {}", reprint, bill_id);
+        }
+    }
+    Ok(())
+}
+
+/// Time until the earliest queued job is due
+fn next_due_in(conn: &Connection) -> Option<Duration> {
+    conn.query_row(
+        "SELECT CAST((julianday(MIN(next_attempt_at)) - julianday('now')) * 86400000 AS INTEGER)
+         FROM print_jobs WHERE status = 'queued'",
+        [],
+        |row| row.get::<_, Option<i64>>(0),
+    )
+    .ok()
+    .flatten()
+    .map(|ms| Duration::from_millis(ms.max(0) as u64))
+}
+
+/// Tauri managed state used to wake the worker when a job is queued
+pub struct Spool {
+    wake: Sender<()>,
+}
+
+impl Spool {
+    /// A spool with no worker, for when the database cannot be used
+    pub fn stopped() -> Self {
+        let (wake, _) = mpsc::channel();
+        Spool { wake }
+    }
+
+    pub fn wake(&self) {
+        let _ = self.wake.send(());
+    }
+}
+
+/// Requeue interrupted jobs and start the worker thread
+pub fn start(app: tauri::AppHandle, backend: Arc<dyn PrinterBackend>) -> Spool {
+    let (wake, rx) = mpsc::channel();
+
+    match crate::db::open(&app).and_then(|conn| requeue_interrupted(&conn).map(|_| conn)) {
+        Ok(conn) => {
+            std::thread::spawn(move || worker(app, conn, backend, rx));
+        }
+        Err(e) => log::warn!("Print spool disabled: {}", e),
+    }
+
+    Spool { wake }
+}
+
+fn worker(
+    app: tauri::AppHandle,
+    conn: Connection,
+    backend: Arc<dyn PrinterBackend>,
+    wake: Receiver<()>,
+) {
+    let notify = |event: JobEvent| {
+        let _ = app.emit(EVENT, event);
+    };
+
+    loop {
+        match process_next(&conn, backend.as_ref(), &notify) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => log::warn!("Print spool: {}", e),
+        }
+
+        let wait = next_due_in(&conn).map_or(IDLE_POLL, |d| d.min(IDLE_POLL));
+        if let Err(RecvTimeoutError::Disconnected) = wake.recv_timeout(wait) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::backend::MockBackend;
+
+    fn fixture() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir().join("medbill-spool-test-unused");
+        crate::migrations::migrate(&mut conn, crate::migrations::LATEST, &dir).unwrap();
+        conn.execute_batch(
+            "INSERT INTO users (id, username, password_hash, full_name, role)
+                 VALUES (1, 'admin', '-', 'Admin', 'admin');
+             INSERT INTO bills (id, bill_number, user_id, grand_total) VALUES (1, 'INV-1', 1, 952);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn bill_job(printer: &str) -> NewJob {
+        NewJob {
+            bill_id: Some(1),
+            document: DocumentKind::Bill,
+            printer: printer.to_string(),
+            job: PrintJob::raw("MedBill Receipt", b"receipt".to_vec()),
+            copies: 1,
+            audit: true,
+            user_id: None,
+        }
+    }
+
+    #[test]
+    fn bill_prints_are_audited_only_once_printed() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+
+        let failing = enqueue(&conn, &bill_job("Offline Printer")).unwrap();
+        assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+        assert_eq!(
+            list(&conn, Some("queued"), None, 10).unwrap()[0].id,
+            failing
+        );
+        assert_eq!(reprint::print_count(&conn, 1).unwrap(), 0);
+        cancel(&conn, failing).unwrap();
+
+        enqueue(&conn, &bill_job("Mock Printer")).unwrap();
+        assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+        assert_eq!(reprint::print_count(&conn, 1).unwrap(), 1);
+        let (user, action, printer): (i64, String, String) = conn
+            .query_row(
+                "SELECT user_id, action, new_value FROM audit_log WHERE entity_type = 'bill'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (user, action.as_str(), printer.as_str()),
+            (1, reprint::PRINT, "Mock Printer")
+        );
+
+        // Jobs not laid out from a saved bill are not audited
+        let mut html = bill_job("Mock Printer");
+        html.audit = false;
+        enqueue(&conn, &html).unwrap();
+        assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+        assert_eq!(reprint::print_count(&conn, 1).unwrap(), 1);
+    }
+
+    /// Let time pass until every queued job is due
+    fn make_due(conn: &Connection) {
+        conn.execute(
+            "UPDATE print_jobs SET next_attempt_at = datetime('now', '-1 second')",
+            [],
+        )
+        .unwrap();
+    }
+
+    fn job(conn: &Connection, id: i64) -> SpoolJob {
+        list(conn, None, None, 100)
+            .unwrap()
+            .into_iter()
+            .find(|job| job.id == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn retries_back_off_and_resume_after_the_printed_copies() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+        let mut new = bill_job("Mock Printer");
+        new.copies = 3;
+        let id = enqueue(&conn, &new).unwrap();
+
+        // Paper runs out after the first copy
+        backend.offline_after(Some(1));
+        assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+        let failed = job(&conn, id);
+        assert_eq!((failed.status.as_str(), failed.attempts), ("queued", 1));
+        assert_eq!(
+            failed.last_error.as_deref(),
+            Some("Mock Printer is offline")
+        );
+        let wait = next_due_in(&conn).unwrap();
+        assert!(
+            wait > Duration::from_secs(3) && wait <= backoff(1),
+            "{:?}",
+            wait
+        );
+        // Not due yet
+        assert!(!process_next(&conn, &backend, &|_| {}).unwrap());
+
+        backend.offline_after(None);
+        make_due(&conn);
+        assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+        let printed = job(&conn, id);
+        assert_eq!((printed.status.as_str(), printed.attempts), ("done", 2));
+        assert_eq!(backend.jobs().len(), 3);
+        assert_eq!(reprint::print_count(&conn, 1).unwrap(), 1);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_five_minutes() {
+        let secs: Vec<u64> = (1..=8).map(|n| backoff(n).as_secs()).collect();
+        assert_eq!(secs, [5, 10, 20, 40, 80, 160, 300, 300]);
+    }
+
+    #[test]
+    fn jobs_fail_after_max_attempts() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+        let id = enqueue(&conn, &bill_job("Offline Printer")).unwrap();
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            make_due(&conn);
+            assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+            let expected = if attempt < MAX_ATTEMPTS {
+                "queued"
+            } else {
+                "failed"
+            };
+            assert_eq!(job(&conn, id).status, expected);
+        }
+        make_due(&conn);
+        assert!(!process_next(&conn, &backend, &|_| {}).unwrap());
+        let failed = job(&conn, id);
+        assert_eq!(failed.attempts, MAX_ATTEMPTS);
+        assert_eq!(
+            failed.last_error.as_deref(),
+            Some("Unknown printer: Offline Printer")
+        );
+
+        // Resending starts the count again
+        resend(&conn, id).unwrap();
+        assert_eq!(job(&conn, id).attempts, 0);
+        assert_eq!(reprint::print_count(&conn, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn jobs_interrupted_by_a_restart_are_printed_once() {
+        let conn = fixture();
+        let backend = MockBackend::default();
+        let mut new = bill_job("Mock Printer");
+        new.copies = 2;
+        let id = enqueue(&conn, &new).unwrap();
+
+        // The app closed while the second copy was going out
+        conn.execute(
+            "UPDATE print_jobs SET status = 'printing', attempts = 1, copies_printed = 1
+             WHERE id = ?1",
+            rusqlite::params![id],
+        )
+        .unwrap();
+        assert!(!process_next(&conn, &backend, &|_| {}).unwrap());
+
+        requeue_interrupted(&conn).unwrap();
+        assert_eq!(job(&conn, id).status, "queued");
+        assert!(process_next(&conn, &backend, &|_| {}).unwrap());
+        assert_eq!(job(&conn, id).status, "done");
+        assert_eq!(backend.jobs().len(), 1);
+    }
+}
@@ -0,0 +1,346 @@
+// =====================================================
+// Printer Hardware Status
+// ESC/POS real-time status (DLE EOT) for printers we
+// reach directly, spooler state for the rest, and a
+// monitor that tells the UI when the printer changes
+// =====================================================
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::Emitter;
+
+use super::backend::{PrinterBackend, PrinterStatus};
+use super::device;
+use super::profiles::{self, DocumentKind};
+use super::socket;
+
+/// Tauri event emitted when the bill printer's status changes
+pub const EVENT: &str = "printer-status";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long to wait for each real-time status byte
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+/// Poll interval while monitoring is switched off, to notice it being enabled
+const DISABLED_POLL: Duration = Duration::from_secs(60);
+
+const DLE: u8 = 0x10;
+const EOT: u8 = 0x04;
+
+/// Where a status was read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusSource {
+    /// The printer answered DLE EOT
+    RealTime,
+    /// The OS spooler's view of the queue
+    Spooler,
+    /// Only whether the printer could be reached
+    Connection,
+}
+
+/// Payload of `get_printer_status` and the `printer-status` event
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DeviceStatus {
+    pub printer: String,
+    pub online: bool,
+    pub paper_low: bool,
+    pub paper_out: bool,
+    pub cover_open: bool,
+    /// Cutter jam, head overheat or another fault the printer reports
+    pub error: bool,
+    pub source: StatusSource,
+    /// Why the status is not ready, for the UI
+    pub message: Option<String>,
+}
+
+impl DeviceStatus {
+    pub fn new(printer: &str, source: StatusSource) -> Self {
+        DeviceStatus {
+            printer: printer.to_string(),
+            online: true,
+            paper_low: false,
+            paper_out: false,
+            cover_open: false,
+            error: false,
+            source,
+            message: None,
+        }
+    }
+
+    /// Status when all the backend knows is online / offline.
+    /// `Unknown` counts as online, as in `check_printer_available`.
+    pub fn from_status(printer: &str, status: PrinterStatus, source: StatusSource) -> Self {
+        let mut device = DeviceStatus::new(printer, source);
+        device.online = status != PrinterStatus::Offline;
+        device.describe()
+    }
+
+    /// Whether a bill sent now would print
+    pub fn ready(&self) -> bool {
+        self.online && !self.paper_out && !self.cover_open && !self.error
+    }
+
+    /// Fill `message` from the flags, worst condition first
+    pub fn describe(mut self) -> Self {
+        let message = if !self.online && self.source == StatusSource::Connection {
+            "Printer is not reachable"
+        } else if self.cover_open {
+            "Printer cover is open"
+        } else if self.paper_out {
+            "Printer is out of paper"
+        } else if self.error {
+            "Printer reports an error"
+        } else if !self.online {
+            "Printer is offline"
+        } else if self.paper_low {
+            "Printer paper is running low"
+        } else {
+            ""
+        };
+        self.message = Some(message.to_string()).filter(|m| !m.is_empty());
+        self
+    }
+}
+
+// =====================================================
+// ESC/POS real-time status (DLE EOT n)
+// =====================================================
+
+/// Status bytes always have bits 1 and 4 set and bits 0 and 7 clear
+fn is_status_byte(byte: u8) -> bool {
+    byte & 0x93 == 0x12
+}
+
+/// Send `DLE EOT n` and read the one-byte reply
+fn transmit<S: Read + Write>(stream: &mut S, n: u8) -> Result<u8, String> {
+    stream
+        .write_all(&[DLE, EOT, n])
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to send status request: {}", e))?;
+    let mut reply = [0u8];
+    stream
+        .read_exact(&mut reply)
+        .map_err(|e| format!("No status reply: {}", e))?;
+    if is_status_byte(reply[0]) {
+        Ok(reply[0])
+    } else {
+        Err(format!("Unexpected status byte {:#04x}", reply[0]))
+    }
+}
+
+/// Ask an ESC/POS printer for its printer (n=1), offline (2), error (3) and
+/// paper sensor (4) status. Older printers without n=4 still get the
+/// paper-end flag from n=2.
+pub fn query<S: Read + Write>(stream: &mut S, printer: &str) -> Result<DeviceStatus, String> {
+    let printer_byte = transmit(stream, 1)?;
+    let offline_byte = transmit(stream, 2)?;
+    let error_byte = transmit(stream, 3)?;
+    let paper_byte = transmit(stream, 4).ok();
+
+    let mut status = DeviceStatus::new(printer, StatusSource::RealTime);
+    status.online = printer_byte & 0x08 == 0;
+    status.cover_open = offline_byte & 0x04 != 0;
+    status.paper_out = offline_byte & 0x20 != 0;
+    status.error = offline_byte & 0x40 != 0 || error_byte & 0x68 != 0;
+    if let Some(paper) = paper_byte {
+        status.paper_low = paper & 0x0C != 0;
+        status.paper_out |= paper & 0x60 != 0;
+    }
+    Ok(status.describe())
+}
+
+/// Status of a `tcp://`, `serial:` or `lp:` printer.
+/// Printers that do not answer DLE EOT (dot matrix, label printers, or a
+/// one-way cable) are reported from whether the connection opened.
+/// `lp:` device files are not read back, since a read can block until the
+/// printer sends something.
+pub fn direct(printer: &str) -> DeviceStatus {
+    let opened = if printer.starts_with(socket::SCHEME) {
+        socket::connect(&socket::parse_address(printer), CONNECT_TIMEOUT).and_then(|mut stream| {
+            stream
+                .set_read_timeout(Some(REPLY_TIMEOUT))
+                .map_err(|e| format!("Failed to set read timeout: {}", e))?;
+            Ok(query(&mut stream, printer))
+        })
+    } else if printer.starts_with(device::SERIAL_SCHEME) {
+        device::SerialConfig::parse(printer)
+            .and_then(|config| config.open(REPLY_TIMEOUT))
+            .map(|mut port| query(&mut port, printer))
+    } else {
+        let status = device::status(printer);
+        return DeviceStatus::from_status(printer, status, StatusSource::Connection);
+    };
+
+    match opened {
+        Ok(Ok(status)) => status,
+        Ok(Err(e)) => {
+            log::info!("Printer status for {}: {}", printer, e);
+            DeviceStatus::new(printer, StatusSource::Connection)
+        }
+        Err(e) => {
+            log::info!("Printer status for {}: {}", printer, e);
+            DeviceStatus::from_status(printer, PrinterStatus::Offline, StatusSource::Connection)
+        }
+    }
+}
+
+// =====================================================
+// Monitor
+// =====================================================
+
+/// Status of the printer that bills go to, or `None` when there is none
+pub fn bill_printer(
+    conn: Option<&Connection>,
+    backend: &dyn PrinterBackend,
+) -> Option<DeviceStatus> {
+    let profile = profiles::resolve(conn, DocumentKind::Bill);
+    let printer = profile
+        .printer_name()
+        .map(str::to_string)
+        .or_else(|| backend.default_printer())?;
+    Some(backend.device_status(&printer))
+}
+
+/// Whether the spool is printing right now. Polling then could steal the
+/// serial port or the printer's only network connection from the job.
+fn printing(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM print_jobs WHERE status = 'printing')",
+        [],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Poll the bill printer every `printer_status_interval` seconds (0 turns
+/// it off) and emit `printer-status` whenever the status changes
+pub fn start(app: tauri::AppHandle, backend: Arc<dyn PrinterBackend>) {
+    let conn = match crate::db::open(&app) {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::warn!("Printer status monitor disabled: {}", e);
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        let mut last: Option<DeviceStatus> = None;
+        loop {
+            let interval: u64 = crate::db::get_setting_or(&conn, "printer_status_interval", 15);
+            if interval == 0 {
+                std::thread::sleep(DISABLED_POLL);
+                continue;
+            }
+
+            if !printing(&conn) {
+                if let Some(status) = bill_printer(Some(&conn), backend.as_ref()) {
+                    if last.as_ref() != Some(&status) {
+                        if let Some(message) = &status.message {
+                            log::warn!("{}: {}", status.printer, message);
+                        }
+                        let _ = app.emit(EVENT, &status);
+                        last = Some(status);
+                    }
+                }
+            }
+            std::thread::sleep(Duration::from_secs(interval));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Answers each DLE EOT n with the n-th reply, and nothing after that
+    struct FakePrinter {
+        replies: VecDeque<u8>,
+        pending: Option<u8>,
+        sent: Vec<u8>,
+    }
+
+    impl FakePrinter {
+        fn new(replies: &[u8]) -> Self {
+            FakePrinter {
+                replies: replies.iter().copied().collect(),
+                pending: None,
+                sent: Vec::new(),
+            }
+        }
+    }
+
+    impl Write for FakePrinter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.extend_from_slice(buf);
+            self.pending = self.replies.pop_front();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for FakePrinter {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.pending.take() {
+                Some(byte) => {
+                    buf[0] = byte;
+                    Ok(1)
+                }
+                None => Err(std::io::ErrorKind::TimedOut.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn ready_printer_reports_no_conditions() {
+        let mut printer = FakePrinter::new(&[0x16, 0x12, 0x12, 0x12]);
+        let status = query(&mut printer, "tcp://10.0.0.5").unwrap();
+        assert_eq!(
+            printer.sent,
+            [0x10, 0x04, 1, 0x10, 0x04, 2, 0x10, 0x04, 3, 0x10, 0x04, 4]
+        );
+        assert!(status.ready());
+        assert!(!status.paper_low);
+        assert_eq!(status.source, StatusSource::RealTime);
+        assert_eq!(status.message, None);
+    }
+
+    #[test]
+    fn decodes_cover_paper_and_offline_bits() {
+        // Offline, cover open, paper near end
+        let mut printer = FakePrinter::new(&[0x1E, 0x16, 0x12, 0x1E]);
+        let status = query(&mut printer, "serial:COM3").unwrap();
+        assert!(!status.online && status.cover_open && status.paper_low);
+        assert!(!status.paper_out && !status.error);
+        assert_eq!(status.message.as_deref(), Some("Printer cover is open"));
+
+        // Paper end from the paper sensor, autocutter error
+        let mut printer = FakePrinter::new(&[0x1E, 0x12, 0x1A, 0x72]);
+        let status = query(&mut printer, "serial:COM3").unwrap();
+        assert!(status.paper_out && status.error && !status.cover_open);
+        assert_eq!(status.message.as_deref(), Some("Printer is out of paper"));
+    }
+
+    #[test]
+    fn older_printers_without_paper_sensor_status_still_work() {
+        let mut printer = FakePrinter::new(&[0x16, 0x32, 0x12]);
+        let status = query(&mut printer, "tcp://10.0.0.5").unwrap();
+        assert!(status.paper_out && !status.paper_low);
+    }
+
+    #[test]
+    fn rejects_replies_that_are_not_status_bytes() {
+        // A dot matrix printer echoing or sending XOFF is not ESC/POS status
+        let mut printer = FakePrinter::new(&[0x13]);
+        assert!(query(&mut printer, "serial:COM1").is_err());
+        let mut printer = FakePrinter::new(&[]);
+        assert!(query(&mut printer, "serial:COM1").is_err());
+    }
+}
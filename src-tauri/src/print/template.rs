@@ -0,0 +1,958 @@
+// =====================================================
+// Receipt Template Language
+// Fixed-width receipt text from a template and a JSON
+// context; see "Receipt Templates" in docs/USER_GUIDE.md
+// =====================================================
+//
+//   {{ bill.number }}                 a value
+//   {{ item.total | money | right:10 }}
+//                                     a value through filters
+//   {% for item in items %} ... {% endfor %}
+//   {% if item.scheduled and not patient %} ... {% elif x %} ... {% else %} ... {% endif %}
+//   {# a comment #}
+//
+// A line holding nothing but `{% %}` tags and comments prints nothing, not
+// even a blank line. Tags end on the line they start on.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+use super::invoice;
+
+/// Filters and whether they take an argument
+const FILTERS: &[(&str, bool)] = &[
+    ("money", false),
+    ("number", true),
+    ("int", false),
+    ("words", false),
+    ("date", true),
+    ("left", true),
+    ("right", true),
+    ("center", true),
+    ("trunc", true),
+    ("repeat", true),
+    ("upper", false),
+    ("lower", false),
+    ("default", true),
+];
+
+/// A parsed template
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Value {
+        operand: Operand,
+        filters: Vec<Filter>,
+        line: usize,
+    },
+    If {
+        branches: Vec<(Cond, Vec<Node>)>,
+        otherwise: Vec<Node>,
+    },
+    For {
+        var: String,
+        list: Vec<String>,
+        body: Vec<Node>,
+        line: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Path(Vec<String>),
+    Str(String),
+    Num(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Filter {
+    name: String,
+    arg: Option<Operand>,
+}
+
+/// `or` of `and`s of optionally negated comparisons
+#[derive(Clone, Debug, PartialEq)]
+struct Cond {
+    any: Vec<Vec<Test>>,
+    line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Test {
+    negate: bool,
+    left: Operand,
+    compare: Option<(Cmp, Operand)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+/// Pieces of one source line
+#[derive(Debug)]
+enum Piece {
+    Text(String),
+    Value(String),
+    Tag(String),
+    Comment,
+}
+
+fn split_line(line: &str, number: usize) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    // A lone `{` is text; look past it for the next real opener
+    while let Some(start) = rest.match_indices('{').map(|(i, _)| i).find(|&i| {
+        matches!(
+            rest.as_bytes().get(i + 1),
+            Some(b'{') | Some(b'%') | Some(b'#')
+        )
+    }) {
+        if start > 0 {
+            pieces.push(Piece::Text(rest[..start].to_string()));
+        }
+        let close = match rest.as_bytes()[start + 1] {
+            b'{' => "}}",
+            b'%' => "%}",
+            _ => "#}",
+        };
+        let inner = &rest[start + 2..];
+        let end = inner.find(close).ok_or_else(|| {
+            format!(
+                "Line {}: '{}' is not closed with '{}' on the same line",
+                number,
+                &rest[start..start + 2],
+                close
+            )
+        })?;
+        let content = inner[..end].trim().to_string();
+        pieces.push(match close {
+            "}}" => Piece::Value(content),
+            "%}" => Piece::Tag(content),
+            _ => Piece::Comment,
+        });
+        rest = &inner[end + 2..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest.to_string()));
+    }
+    Ok(pieces)
+}
+
+/// Tokens inside `{{ }}` and `{% %}`
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Word(String),
+    Str(String),
+    Num(f64),
+    Sym(&'static str),
+}
+
+fn tokens(source: &str, line: usize) -> Result<Vec<Tok>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&q| q == c)
+                .ok_or_else(|| format!("Line {}: unclosed string", line))?;
+            out.push(Tok::Str(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let len = 1 + chars[i + 1..]
+                .iter()
+                .take_while(|d| d.is_ascii_digit() || **d == '.')
+                .count();
+            let text: String = chars[i..i + len].iter().collect();
+            let n = text
+                .parse()
+                .map_err(|_| format!("Line {}: bad number '{}'", line, text))?;
+            out.push(Tok::Num(n));
+            i += len;
+        } else if c.is_alphabetic() || c == '_' {
+            let len = chars[i..]
+                .iter()
+                .take_while(|d| d.is_alphanumeric() || **d == '_' || **d == '.')
+                .count();
+            out.push(Tok::Word(chars[i..i + len].iter().collect()));
+            i += len;
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let sym = ["==", "!=", "<=", ">="]
+                .into_iter()
+                .find(|s| *s == two)
+                .or_else(|| ["<", ">", "|", ":"].into_iter().find(|s| s.starts_with(c)))
+                .ok_or_else(|| format!("Line {}: unexpected '{}'", line, c))?;
+            out.push(Tok::Sym(sym));
+            i += sym.len();
+        }
+    }
+    Ok(out)
+}
+
+fn path(word: &str, line: usize) -> Result<Vec<String>, String> {
+    let parts: Vec<String> = word.split('.').map(str::to_string).collect();
+    if parts.iter().any(String::is_empty) {
+        return Err(format!("Line {}: bad name '{}'", line, word));
+    }
+    Ok(parts)
+}
+
+fn operand(tok: Option<Tok>, line: usize) -> Result<Operand, String> {
+    match tok {
+        Some(Tok::Word(w)) => Ok(Operand::Path(path(&w, line)?)),
+        Some(Tok::Str(s)) => Ok(Operand::Str(s)),
+        Some(Tok::Num(n)) => Ok(Operand::Num(n)),
+        Some(Tok::Sym(s)) => Err(format!("Line {}: expected a value, found '{}'", line, s)),
+        None => Err(format!("Line {}: expected a value", line)),
+    }
+}
+
+fn parse_value(source: &str, line: usize) -> Result<Node, String> {
+    let mut toks = tokens(source, line)?.into_iter().peekable();
+    let operand = operand(toks.next(), line)?;
+    let mut filters = Vec::new();
+    while let Some(tok) = toks.next() {
+        if tok != Tok::Sym("|") {
+            return Err(format!("Line {}: expected '|' before a filter", line));
+        }
+        let name = match toks.next() {
+            Some(Tok::Word(w)) => w,
+            _ => return Err(format!("Line {}: expected a filter name after '|'", line)),
+        };
+        let takes_arg = FILTERS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, arg)| *arg)
+            .ok_or_else(|| format!("Line {}: unknown filter '{}'", line, name))?;
+        let arg = if toks.peek() == Some(&Tok::Sym(":")) {
+            toks.next();
+            Some(self::operand(toks.next(), line)?)
+        } else {
+            None
+        };
+        if takes_arg != arg.is_some() {
+            return Err(if takes_arg {
+                format!(
+                    "Line {}: filter '{}' needs a value, e.g. {}:10",
+                    line, name, name
+                )
+            } else {
+                format!("Line {}: filter '{}' takes no value", line, name)
+            });
+        }
+        filters.push(Filter { name, arg });
+    }
+    if source.trim().is_empty() {
+        return Err(format!("Line {}: empty '{{{{ }}}}'", line));
+    }
+    Ok(Node::Value {
+        operand,
+        filters,
+        line,
+    })
+}
+
+fn parse_cond(toks: Vec<Tok>, line: usize) -> Result<Cond, String> {
+    let mut any = Vec::new();
+    let mut all = Vec::new();
+    let mut toks = toks.into_iter().peekable();
+    loop {
+        let mut negate = false;
+        while toks.peek() == Some(&Tok::Word("not".to_string())) {
+            toks.next();
+            negate = !negate;
+        }
+        let left = operand(toks.next(), line)?;
+        let compare = match toks.peek() {
+            Some(Tok::Sym(s)) => {
+                let cmp = match *s {
+                    "==" => Cmp::Eq,
+                    "!=" => Cmp::Ne,
+                    "<" => Cmp::Lt,
+                    "<=" => Cmp::Le,
+                    ">" => Cmp::Gt,
+                    ">=" => Cmp::Ge,
+                    other => return Err(format!("Line {}: unexpected '{}'", line, other)),
+                };
+                toks.next();
+                Some((cmp, operand(toks.next(), line)?))
+            }
+            _ => None,
+        };
+        all.push(Test {
+            negate,
+            left,
+            compare,
+        });
+        match toks.next() {
+            None => break,
+            Some(Tok::Word(w)) if w == "and" => {}
+            Some(Tok::Word(w)) if w == "or" => any.push(std::mem::take(&mut all)),
+            Some(_) => return Err(format!("Line {}: expected 'and' or 'or'", line)),
+        }
+    }
+    any.push(all);
+    Ok(Cond { any, line })
+}
+
+/// An open block while parsing
+enum Open {
+    If {
+        branches: Vec<(Cond, Vec<Node>)>,
+        otherwise: Option<Vec<Node>>,
+        line: usize,
+    },
+    For {
+        var: String,
+        list: Vec<String>,
+        line: usize,
+    },
+}
+
+impl Template {
+    /// Parse a template, reporting the first error with its line number
+    pub fn parse(source: &str) -> Result<Template, String> {
+        // Nodes of each open block; the bottom one is the template itself
+        let mut stack: Vec<(Option<Open>, Vec<Node>)> = vec![(None, Vec::new())];
+
+        let lines: Vec<&str> = source.split('\n').collect();
+        for (index, raw) in lines.iter().enumerate() {
+            let number = index + 1;
+            let raw = raw.strip_suffix('\r').unwrap_or(raw);
+            let pieces = split_line(raw, number)?;
+            let tags_only = pieces
+                .iter()
+                .any(|p| matches!(p, Piece::Tag(_) | Piece::Comment))
+                && pieces.iter().all(|p| match p {
+                    Piece::Text(t) => t.trim().is_empty(),
+                    Piece::Value(_) => false,
+                    Piece::Tag(_) | Piece::Comment => true,
+                });
+
+            for piece in pieces {
+                match piece {
+                    Piece::Text(_) if tags_only => {}
+                    Piece::Text(text) => push(&mut stack, Node::Text(text)),
+                    Piece::Value(source) => push(&mut stack, parse_value(&source, number)?),
+                    Piece::Comment => {}
+                    Piece::Tag(source) => tag(&mut stack, &source, number)?,
+                }
+            }
+            if !tags_only && index + 1 < lines.len() {
+                push(&mut stack, Node::Text("\n".to_string()));
+            }
+        }
+
+        if stack.len() > 1 {
+            let (open, _) = stack.pop().unwrap_or((None, Vec::new()));
+            return Err(match open {
+                Some(Open::If { line, .. }) => {
+                    format!("Line {}: 'if' is never closed with 'endif'", line)
+                }
+                Some(Open::For { line, .. }) => {
+                    format!("Line {}: 'for' is never closed with 'endfor'", line)
+                }
+                None => "Unbalanced blocks".to_string(),
+            });
+        }
+        let (_, nodes) = stack.pop().unwrap_or((None, Vec::new()));
+        Ok(Template { nodes })
+    }
+
+    /// Render with `context`. Names that are not in the context are errors,
+    /// so a misspelt name does not print as a blank.
+    pub fn render(&self, context: &Value) -> Result<String, String> {
+        let mut out = String::new();
+        let mut scope = Scope {
+            root: context,
+            vars: Vec::new(),
+        };
+        render(&self.nodes, &mut scope, &mut out)?;
+        Ok(out)
+    }
+
+    /// Check every name against `sample`, including branches the sample
+    /// would not take; loop variables are checked against the first element
+    pub fn check(&self, sample: &Value) -> Result<(), String> {
+        let mut scope = Scope {
+            root: sample,
+            vars: Vec::new(),
+        };
+        check(&self.nodes, &mut scope)
+    }
+}
+
+fn push(stack: &mut [(Option<Open>, Vec<Node>)], node: Node) {
+    if let Some((_, nodes)) = stack.last_mut() {
+        // Merge text so the tree stays small
+        if let (Node::Text(text), Some(Node::Text(last))) = (&node, nodes.last_mut()) {
+            last.push_str(text);
+            return;
+        }
+        nodes.push(node);
+    }
+}
+
+fn tag(
+    stack: &mut Vec<(Option<Open>, Vec<Node>)>,
+    source: &str,
+    line: usize,
+) -> Result<(), String> {
+    let mut toks = tokens(source, line)?;
+    let keyword = match toks.first() {
+        Some(Tok::Word(w)) => w.clone(),
+        _ => {
+            return Err(format!(
+                "Line {}: expected if, elif, else, endif, for or endfor",
+                line
+            ))
+        }
+    };
+    toks.remove(0);
+
+    match keyword.as_str() {
+        "if" => {
+            let cond = parse_cond(toks, line)?;
+            stack.push((
+                Some(Open::If {
+                    branches: vec![(cond, Vec::new())],
+                    otherwise: None,
+                    line,
+                }),
+                Vec::new(),
+            ));
+        }
+        "elif" | "else" => {
+            let Some((
+                Some(Open::If {
+                    branches,
+                    otherwise,
+                    ..
+                }),
+                nodes,
+            )) = stack.last_mut()
+            else {
+                return Err(format!("Line {}: '{}' without 'if'", line, keyword));
+            };
+            if otherwise.is_some() {
+                return Err(format!("Line {}: '{}' after 'else'", line, keyword));
+            }
+            let body = std::mem::take(nodes);
+            if let Some(last) = branches.last_mut() {
+                last.1 = body;
+            }
+            if keyword == "elif" {
+                branches.push((parse_cond(toks, line)?, Vec::new()));
+            } else {
+                if !toks.is_empty() {
+                    return Err(format!("Line {}: 'else' takes no condition", line));
+                }
+                *otherwise = Some(Vec::new());
+            }
+        }
+        "endif" => match stack.pop() {
+            Some((
+                Some(Open::If {
+                    mut branches,
+                    otherwise,
+                    ..
+                }),
+                body,
+            )) => {
+                let otherwise = match otherwise {
+                    Some(_) => body,
+                    None => {
+                        if let Some(last) = branches.last_mut() {
+                            last.1 = body;
+                        }
+                        Vec::new()
+                    }
+                };
+                push(
+                    stack,
+                    Node::If {
+                        branches,
+                        otherwise,
+                    },
+                );
+            }
+            other => return Err(mismatch(stack, other, "endif", line)),
+        },
+        "for" => {
+            let (var, list) = match toks.as_slice() {
+                [Tok::Word(var), Tok::Word(kw), Tok::Word(list)]
+                    if kw == "in" && !var.contains('.') =>
+                {
+                    (var.clone(), path(list, line)?)
+                }
+                _ => return Err(format!("Line {}: write 'for item in items'", line)),
+            };
+            stack.push((Some(Open::For { var, list, line }), Vec::new()));
+        }
+        "endfor" => match stack.pop() {
+            Some((Some(Open::For { var, list, line }), body)) => push(
+                stack,
+                Node::For {
+                    var,
+                    list,
+                    body,
+                    line,
+                },
+            ),
+            other => return Err(mismatch(stack, other, "endfor", line)),
+        },
+        other => return Err(format!("Line {}: unknown tag '{}'", line, other)),
+    }
+    Ok(())
+}
+
+/// Error for a closing tag that does not match the open block. `popped` is
+/// put back so the stack stays whole.
+fn mismatch(
+    stack: &mut Vec<(Option<Open>, Vec<Node>)>,
+    popped: Option<(Option<Open>, Vec<Node>)>,
+    closing: &str,
+    line: usize,
+) -> String {
+    let open = match popped.as_ref().and_then(|(open, _)| open.as_ref()) {
+        Some(Open::If { line: at, .. }) => format!(" ('if' on line {} is still open)", at),
+        Some(Open::For { line: at, .. }) => format!(" ('for' on line {} is still open)", at),
+        None => String::new(),
+    };
+    if let Some(popped) = popped {
+        stack.push(popped);
+    }
+    format!("Line {}: unexpected '{}'{}", line, closing, open)
+}
+
+// ---------------------------------------------------------------------------
+// Rendering
+// ---------------------------------------------------------------------------
+
+struct Scope<'a> {
+    root: &'a Value,
+    vars: Vec<(String, Value)>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, path: &[String], line: usize) -> Result<Value, String> {
+        let name = &path[0];
+        let mut value = match self.vars.iter().rev().find(|(n, _)| n == name) {
+            Some((_, v)) => v,
+            None => self
+                .root
+                .get(name)
+                .ok_or_else(|| format!("Line {}: unknown name '{}'", line, name))?,
+        };
+        for (i, key) in path.iter().enumerate().skip(1) {
+            value = match value {
+                // Optional sections such as `patient` are null when absent
+                Value::Null => return Ok(Value::Null),
+                _ => value.get(key).ok_or_else(|| {
+                    format!("Line {}: unknown name '{}'", line, path[..=i].join("."))
+                })?,
+            };
+        }
+        Ok(value.clone())
+    }
+
+    fn value(&self, operand: &Operand, line: usize) -> Result<Value, String> {
+        match operand {
+            Operand::Path(path) => self.lookup(path, line),
+            Operand::Str(s) => Ok(Value::String(s.clone())),
+            Operand::Num(n) => Ok(Value::from(*n)),
+        }
+    }
+}
+
+fn render(nodes: &[Node], scope: &mut Scope, out: &mut String) -> Result<(), String> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value {
+                operand,
+                filters,
+                line,
+            } => {
+                let mut value = scope.value(operand, *line)?;
+                for filter in filters {
+                    let arg = match &filter.arg {
+                        Some(arg) => Some(scope.value(arg, *line)?),
+                        None => None,
+                    };
+                    value = apply(&filter.name, &value, arg.as_ref())
+                        .map_err(|e| format!("Line {}: {}", line, e))?;
+                }
+                out.push_str(&text(&value));
+            }
+            Node::If {
+                branches,
+                otherwise,
+            } => {
+                let mut taken = None;
+                for (cond, body) in branches {
+                    if test(cond, scope)? {
+                        taken = Some(body);
+                        break;
+                    }
+                }
+                render(taken.unwrap_or(otherwise), scope, out)?;
+            }
+            Node::For {
+                var,
+                list,
+                body,
+                line,
+            } => {
+                let items = match scope.lookup(list, *line)? {
+                    Value::Array(items) => items,
+                    Value::Null => Vec::new(),
+                    _ => return Err(format!("Line {}: '{}' is not a list", line, list.join("."))),
+                };
+                let count = items.len();
+                for (i, item) in items.into_iter().enumerate() {
+                    scope.vars.push((var.clone(), item));
+                    scope.vars.push(("loop".to_string(), loop_value(i, count)));
+                    let result = render(body, scope, out);
+                    scope.vars.truncate(scope.vars.len() - 2);
+                    result?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn loop_value(index: usize, count: usize) -> Value {
+    serde_json::json!({
+        "index": index + 1,
+        "first": index == 0,
+        "last": index + 1 == count,
+    })
+}
+
+fn test(cond: &Cond, scope: &Scope) -> Result<bool, String> {
+    for all in &cond.any {
+        let mut pass = true;
+        for t in all {
+            let left = scope.value(&t.left, cond.line)?;
+            let result = match &t.compare {
+                None => truthy(&left),
+                Some((cmp, right)) => compare(&left, *cmp, &scope.value(right, cond.line)?),
+            };
+            if result == t.negate {
+                pass = false;
+                break;
+            }
+        }
+        if pass {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn check(nodes: &[Node], scope: &mut Scope) -> Result<(), String> {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Value {
+                operand,
+                filters,
+                line,
+            } => {
+                scope.value(operand, *line)?;
+                for filter in filters {
+                    if let Some(arg) = &filter.arg {
+                        scope.value(arg, *line)?;
+                    }
+                }
+            }
+            Node::If {
+                branches,
+                otherwise,
+            } => {
+                for (cond, body) in branches {
+                    for test in cond.any.iter().flatten() {
+                        scope.value(&test.left, cond.line)?;
+                        if let Some((_, right)) = &test.compare {
+                            scope.value(right, cond.line)?;
+                        }
+                    }
+                    check(body, scope)?;
+                }
+                check(otherwise, scope)?;
+            }
+            Node::For {
+                var,
+                list,
+                body,
+                line,
+            } => {
+                let first = match scope.lookup(list, *line)? {
+                    Value::Array(items) => items.into_iter().next().unwrap_or(Value::Null),
+                    _ => return Err(format!("Line {}: '{}' is not a list", line, list.join("."))),
+                };
+                scope.vars.push((var.clone(), first));
+                scope.vars.push(("loop".to_string(), loop_value(0, 1)));
+                let result = check(body, scope);
+                scope.vars.truncate(scope.vars.len() - 2);
+                result?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+fn compare(left: &Value, cmp: Cmp, right: &Value) -> bool {
+    let order = match (number(left), number(right)) {
+        (Some(l), Some(r)) if !left.is_string() || !right.is_string() => l.partial_cmp(&r),
+        _ => Some(text(left).cmp(&text(right))),
+    };
+    let Some(order) = order else {
+        return false;
+    };
+    match cmp {
+        Cmp::Eq => order.is_eq(),
+        Cmp::Ne => order.is_ne(),
+        Cmp::Lt => order.is_lt(),
+        Cmp::Le => order.is_le(),
+        Cmp::Gt => order.is_gt(),
+        Cmp::Ge => order.is_ge(),
+    }
+}
+
+/// How a value prints: whole numbers without decimals, null as nothing
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => if *b { "yes" } else { "no" }.to_string(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{}", f as i64),
+            _ => n.to_string(),
+        },
+        Value::Array(_) | Value::Object(_) => String::new(),
+    }
+}
+
+fn count_arg(name: &str, arg: Option<&Value>) -> Result<usize, String> {
+    arg.and_then(number)
+        .filter(|n| *n >= 0.0)
+        .map(|n| n as usize)
+        .ok_or_else(|| format!("'{}' needs a whole number", name))
+}
+
+fn apply(name: &str, value: &Value, arg: Option<&Value>) -> Result<Value, String> {
+    let s = || text(value);
+    let amount = || number(value).unwrap_or(0.0);
+    let blank = value.is_null() || s().is_empty();
+    Ok(Value::String(match name {
+        "money" if blank => String::new(),
+        "money" => format!("{:.2}", amount()),
+        "number" if blank => String::new(),
+        "number" => format!("{:.*}", count_arg(name, arg)?.min(6), amount()),
+        "int" if blank => String::new(),
+        "int" => format!("{}", amount().round() as i64),
+        "words" => invoice::amount_in_words(amount()),
+        "date" => format_date(&s(), &text(arg.unwrap_or(&Value::Null)))?,
+        "left" => fit(&s(), count_arg(name, arg)?, Align::Left),
+        "right" => fit(&s(), count_arg(name, arg)?, Align::Right),
+        "center" => fit(&s(), count_arg(name, arg)?, Align::Center),
+        "trunc" => s().chars().take(count_arg(name, arg)?).collect(),
+        "repeat" => s().repeat(count_arg(name, arg)?.min(500)),
+        "upper" => s().to_uppercase(),
+        "lower" => s().to_lowercase(),
+        "default" if blank => text(arg.unwrap_or(&Value::Null)),
+        "default" => return Ok(value.clone()),
+        other => return Err(format!("unknown filter '{}'", other)),
+    }))
+}
+
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// Pad or cut `text` to exactly `width` columns
+fn fit(text: &str, width: usize, align: Align) -> String {
+    let text: String = text.chars().take(width).collect();
+    let gap = width - text.chars().count();
+    match align {
+        Align::Left => format!("{}{}", text, " ".repeat(gap)),
+        Align::Right => format!("{}{}", " ".repeat(gap), text),
+        Align::Center => format!(
+            "{}{}{}",
+            " ".repeat(gap / 2),
+            text,
+            " ".repeat(gap - gap / 2)
+        ),
+    }
+}
+
+/// Dates from the database (`2026-01-02`, `2026-01-02 10:30:00`) in a
+/// strftime `format` such as `%d/%m/%Y`
+fn format_date(value: &str, format: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    if format.contains('%')
+        && chrono::format::StrftimeItems::new(format)
+            .any(|i| matches!(i, chrono::format::Item::Error))
+    {
+        return Err(format!("bad date format '{}'", format));
+    }
+    let formatted = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .map(|dt| dt.format(format).to_string())
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.format(format).to_string())
+        })
+        .or_else(|_| {
+            NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+                .map(|d| d.format(format).to_string())
+        });
+    // Time fields on a plain date fail to format; show the value as stored
+    Ok(formatted.unwrap_or_else(|_| value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(source: &str, context: Value) -> Result<String, String> {
+        Template::parse(source)?.render(&context)
+    }
+
+    #[test]
+    fn loops_conditions_and_columns() {
+        let context = json!({
+            "width": 24,
+            "items": [
+                {"name": "Paracetamol 500mg Tablets", "qty": 2, "total": 50, "scheduled": false},
+                {"name": "Alprazolam", "qty": 1, "total": 32.5, "scheduled": true},
+            ],
+            "patient": {"name": "Ravi"},
+        });
+        let source = "\
+{{ \"-\" | repeat:width }}
+{% for item in items %}
+{{ loop.index }}. {{ item.name | left:12 }}{{ item.qty | right:3 }}{{ item.total | money | right:7 }}
+{% if item.scheduled and patient %}
+  Rx for {{ patient.name | upper }}
+{% endif %}
+{% endfor %}
+{# end of items #}
+{% if items %}Thank you{% else %}No items{% endif %}";
+        assert_eq!(
+            render(source, context).unwrap(),
+            "------------------------\n\
+             1. Paracetamol   2  50.00\n\
+             2. Alprazolam    1  32.50\n  Rx for RAVI\n\
+             Thank you"
+        );
+    }
+
+    #[test]
+    fn comparisons_and_filters() {
+        let context = json!({
+            "bill": {"mode": "CASH", "total": 952, "date": "2026-01-02 10:30:00", "doctor": null},
+        });
+        let source = "{% if bill.mode == \"CASH\" and bill.total >= 500 %}big cash{% elif not bill.doctor %}x{% endif %}|\
+{{ bill.date | date:\"%d/%m/%y %H:%M\" }}|{{ bill.doctor | default:\"-\" }}|{{ bill.total | words }}|\
+{{ \"TOTAL\" | center:9 }}|{{ 2.5 | number:1 }}|{{ 2.5 | int }}";
+        assert_eq!(
+            render(source, context).unwrap(),
+            "big cash|02/01/26 10:30|-|Nine Hundred Fifty Two Rupees Only|  TOTAL  |2.5|3"
+        );
+    }
+
+    #[test]
+    fn lone_braces_are_text() {
+        let context = json!({"mrp": 32.5});
+        assert_eq!(
+            render("Rate {per strip}: {{ mrp | money }} {", context.clone()).unwrap(),
+            "Rate {per strip}: 32.50 {"
+        );
+        assert_eq!(
+            render("{a} {b} {# note #}{% if mrp %}{x}{% endif %}", context).unwrap(),
+            "{a} {b} {x}"
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let err = |source: &str| Template::parse(source).unwrap_err();
+        assert_eq!(
+            err("a\n{% for item in items %}\nb"),
+            "Line 2: 'for' is never closed with 'endfor'"
+        );
+        assert_eq!(err("{{ x | pad:3 }}"), "Line 1: unknown filter 'pad'");
+        assert_eq!(
+            err("{{ x | left }}"),
+            "Line 1: filter 'left' needs a value, e.g. left:10"
+        );
+        assert_eq!(
+            err("{% if x %}\n{% endfor %}"),
+            "Line 2: unexpected 'endfor' ('if' on line 1 is still open)"
+        );
+        assert_eq!(
+            err("x\n{{ total"),
+            "Line 2: '{{' is not closed with '}}' on the same line"
+        );
+
+        // Names are checked in every branch, not only the one taken
+        let template = Template::parse("{% if ok %}{% else %}{{ bil.total }}{% endif %}").unwrap();
+        let sample = json!({"ok": true, "bill": {"total": 1}});
+        assert_eq!(template.render(&sample).unwrap(), "");
+        assert_eq!(
+            template.check(&sample).unwrap_err(),
+            "Line 1: unknown name 'bil'"
+        );
+    }
+}
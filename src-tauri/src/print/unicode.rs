@@ -0,0 +1,690 @@
+// =====================================================
+// Unicode Text as Graphics
+// Receipt printers only know their code page, so words
+// in Tamil (or any other script) are shaped with a
+// TrueType font and printed as bit images
+// =====================================================
+
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use ab_glyph::{point, Font, FontRef, GlyphId, PxScale, ScaleFont};
+use rusqlite::Connection;
+
+use super::bitmap::Bitmap;
+use super::pdf::REGULAR_FONT;
+use super::receipt::{printable, segments, transliterate, Align};
+
+/// Fonts with Tamil glyphs that come with the OS or common font packages,
+/// tried in order when no `unicode_font_path` is set
+const SYSTEM_FONTS: &[&str] = &[
+    "C:\\Windows\\Fonts\\Nirmala.ttc",
+    "C:\\Windows\\Fonts\\Nirmala.ttf",
+    "C:\\Windows\\Fonts\\latha.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansTamil-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansTamil-Regular.ttf",
+    "/usr/share/fonts/truetype/lohit-tamil/Lohit-Tamil.ttf",
+    "/System/Library/Fonts/Supplemental/Tamil Sangam MN.ttc",
+];
+
+/// Noto Sans Tamil, shipped with the app for machines without a Tamil font
+pub const TAMIL_FONT: &str = "resources/fonts/NotoSansTamil-Regular.ttf";
+
+/// The app's resource directory, set once at startup
+static RESOURCE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_resource_dir(dir: PathBuf) {
+    let _ = RESOURCE_DIR.set(dir);
+}
+
+/// Ink coverage above which a dot is printed
+const THRESHOLD: f32 = 0.45;
+
+/// Whether a line has characters the printer cannot show in text mode
+pub fn needs_graphics(text: &str) -> bool {
+    !text.chars().all(printable)
+}
+
+/// Part of a line printed one way, `column` characters in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run<'a> {
+    pub column: usize,
+    pub text: &'a str,
+    /// Drawn as a bit image rather than sent as text
+    pub graphics: bool,
+}
+
+/// Split a line at spaces into runs of words the printer can show as text
+/// and runs of words it cannot
+pub fn runs(line: &str) -> Vec<Run<'_>> {
+    // Column, byte range and kind of each run
+    let mut spans: Vec<(usize, usize, usize, bool)> = Vec::new();
+    let (mut column, mut start) = (0, 0);
+    for word in line.split(' ') {
+        if !word.is_empty() {
+            let graphics = needs_graphics(word);
+            let end = start + word.len();
+            match spans.last_mut() {
+                Some(span) if span.3 == graphics => span.2 = end,
+                _ => spans.push((column, start, end, graphics)),
+            }
+        }
+        column += word.chars().count() + 1;
+        start += word.len() + 1;
+    }
+    spans
+        .into_iter()
+        .map(|(column, start, end, graphics)| Run {
+            column,
+            text: &line[start..end],
+            graphics,
+        })
+        .collect()
+}
+
+/// A piece of a line that mixes text with words outside the code page
+#[derive(Debug)]
+pub enum Piece {
+    /// Sent as it is; already in the printer's code page
+    Text(String),
+    /// Drawn to fill its columns up to the next piece
+    Image(Bitmap),
+}
+
+/// Dot grid of a text line on the printer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    /// Horizontal dots per character column
+    pub width: usize,
+    /// Vertical dots per line
+    pub height: usize,
+    /// Horizontal dots per vertical dot's length (1.0 for square dots)
+    pub aspect: f32,
+}
+
+impl Cell {
+    /// Columns `width` dots wide, glyphs stretched or narrowed to match
+    pub fn with_width(self, width: usize) -> Cell {
+        Cell {
+            width,
+            height: self.height,
+            aspect: self.aspect * width as f32 / self.width as f32,
+        }
+    }
+
+    /// Lines `height` dots tall, glyphs as wide as before
+    pub fn with_height(self, height: usize) -> Cell {
+        Cell {
+            width: self.width,
+            height,
+            aspect: self.aspect * self.height as f32 / height as f32,
+        }
+    }
+}
+
+/// The `unicode_font_path` setting
+pub fn font_setting(conn: &Connection) -> Option<PathBuf> {
+    crate::db::get_setting(conn, "unicode_font_path")
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Fonts tried in order for each character: the configured or system
+/// font first, then the bundled Noto Sans Tamil, then the bundled DejaVu
+/// (Latin, the rupee sign, Greek and Cyrillic). Each face is parsed once,
+/// when the stack is loaded for a job.
+pub struct FontStack {
+    faces: Vec<Face<'static>>,
+}
+
+impl FontStack {
+    /// Only the bundled fonts
+    pub fn bundled() -> Self {
+        FontStack::bundled_in(RESOURCE_DIR.get().map(PathBuf::as_path))
+    }
+
+    /// The bundled fonts, with the Tamil one read from `resources`
+    fn bundled_in(resources: Option<&Path>) -> Self {
+        let mut faces = Vec::new();
+        if let Some(dir) = resources {
+            match read_font(&dir.join(TAMIL_FONT)) {
+                Ok(face) => faces.push(face),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        faces.extend(Face::parse(REGULAR_FONT));
+        FontStack { faces }
+    }
+
+    /// `path` if it loads, else the first system font found, with the
+    /// bundled font behind it
+    pub fn load(path: Option<&Path>) -> Self {
+        let mut stack = FontStack::bundled();
+        let configured = path.and_then(|path| match read_font(path) {
+            Ok(font) => Some(font),
+            Err(e) => {
+                log::warn!("{}", e);
+                None
+            }
+        });
+        let font = configured.or_else(|| {
+            SYSTEM_FONTS
+                .iter()
+                .map(Path::new)
+                .filter(|path| path.exists())
+                .find_map(|path| read_font(path).ok())
+        });
+        if let Some(font) = font {
+            stack.faces.insert(0, font);
+        }
+        stack
+    }
+
+    /// Draw `text` on a `columns`-wide grid of `cell`s.
+    ///
+    /// Left-aligned text keeps its columns: each group of words separated
+    /// from the next by two or more spaces starts at its own column, plain
+    /// ASCII sits one character per cell as in text mode, and other runs are
+    /// shaped and squeezed if they would run into the next group. Centered
+    /// and right-aligned text is placed by its drawn width.
+    ///
+    /// Fails on a character no font has a glyph for, rather than printing
+    /// it as a box.
+    pub fn render_line(
+        &self,
+        text: &str,
+        columns: usize,
+        cell: Cell,
+        align: Align,
+        bold: bool,
+    ) -> Result<Bitmap, String> {
+        let faces = &self.faces;
+        let width = columns * cell.width;
+        let mut glyphs = Vec::new();
+
+        let groups = segments(text);
+        for (i, (start, group)) in groups.iter().enumerate() {
+            let x = (start * cell.width) as f32;
+            let group: Vec<char> = group.chars().collect();
+            if group.iter().all(char::is_ascii) {
+                for (column, &c) in (*start..).zip(&group) {
+                    if let Some(glyph) = monospaced(faces, c, (column * cell.width) as f32, cell)? {
+                        glyphs.push(glyph);
+                    }
+                }
+                continue;
+            }
+
+            let limit = match (align, groups.get(i + 1)) {
+                (Align::Left, Some((next, _))) => (next - 1) * cell.width,
+                _ => width,
+            };
+            let mut run = shape(faces, &group, cell)?;
+            let advance: f32 = run.iter().map(|g| g.advance).sum();
+            let room = limit as f32 - x;
+            let squeeze = if advance > room && room > 0.0 {
+                room / advance
+            } else {
+                1.0
+            };
+            let mut pen = x;
+            for glyph in &mut run {
+                glyph.x = pen + glyph.x * squeeze;
+                glyph.squeeze = squeeze;
+                pen += glyph.advance * squeeze;
+            }
+            glyphs.extend(run);
+        }
+
+        let left = glyphs.iter().map(|g| g.x).reduce(f32::min).unwrap_or(0.0);
+        let right = glyphs
+            .iter()
+            .map(|g| g.x + g.advance * g.squeeze)
+            .reduce(f32::max)
+            .unwrap_or(0.0);
+        let gap = (width as f32 - (right - left)).max(0.0);
+        let offset = match align {
+            Align::Left => 0.0,
+            Align::Center => gap / 2.0 - left,
+            Align::Right => gap - left,
+        };
+
+        let mut bitmap = Bitmap::new(width, cell.height);
+        for glyph in &glyphs {
+            faces[glyph.face].draw(glyph, offset, cell, &mut bitmap);
+        }
+        if bold {
+            embolden(&mut bitmap);
+        }
+        Ok(bitmap)
+    }
+
+    /// The pieces of a `columns`-wide line with both text and words outside
+    /// the code page, each with the column it starts at, so only those words
+    /// are drawn. None when the whole line has to be drawn.
+    pub fn mixed_line(
+        &self,
+        line: &str,
+        columns: usize,
+        cell: Cell,
+        bold: bool,
+    ) -> Result<Option<Vec<(usize, Piece)>>, String> {
+        let runs = runs(line);
+        if !runs.iter().any(|run| !run.graphics) {
+            return Ok(None);
+        }
+        let mut pieces = Vec::new();
+        for (i, run) in runs.iter().enumerate() {
+            let piece = if run.graphics {
+                // Up to the space before the next run, or the end of the line
+                let end = runs
+                    .get(i + 1)
+                    .map_or(columns, |next| next.column.saturating_sub(1));
+                let span = end.saturating_sub(run.column).max(1);
+                Piece::Image(self.render_line(run.text, span, cell, Align::Left, bold)?)
+            } else {
+                Piece::Text(transliterate(run.text))
+            };
+            pieces.push((run.column, piece));
+        }
+        Ok(Some(pieces))
+    }
+}
+
+/// Font files read so far. Faces borrow the file data, so each file is
+/// read once and kept for the life of the app.
+static FONT_FILES: Mutex<Vec<(PathBuf, &'static [u8])>> = Mutex::new(Vec::new());
+
+/// The face of a font file; collections (.ttc) use their first font
+fn read_font(path: &Path) -> Result<Face<'static>, String> {
+    let mut files = FONT_FILES
+        .lock()
+        .map_err(|_| "Font cache lock poisoned".to_string())?;
+    let data = match files.iter().find(|(read, _)| read == path) {
+        Some((_, data)) => *data,
+        None => {
+            let data =
+                std::fs::read(path).map_err(|e| format!("Cannot read font {:?}: {}", path, e))?;
+            FontRef::try_from_slice_and_index(&data, 0)
+                .map_err(|e| format!("Cannot load font {:?}: {}", path, e))?;
+            log::info!("Unicode receipt font: {:?}", path);
+            let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+            files.push((path.to_path_buf(), data));
+            data
+        }
+    };
+    Face::parse(data).ok_or_else(|| format!("Cannot load font {:?}", path))
+}
+
+/// Why a character cannot be printed
+fn missing_glyph(c: char) -> String {
+    let hint = if ('\u{0B80}'..='\u{0BFF}').contains(&c) {
+        format!(
+            "Install Noto Sans Tamil, add {} to the app, or set unicode_font_path to a Tamil font",
+            TAMIL_FONT
+        )
+    } else {
+        "Set unicode_font_path to a font that has it".to_string()
+    };
+    format!(
+        "No receipt font can print '{}' (U+{:04X}). {}",
+        c, c as u32, hint
+    )
+}
+
+/// Loads the font stack the first time a line needs it, so bills in
+/// plain ASCII never read a font file
+pub struct LazyFonts {
+    path: Option<PathBuf>,
+    fonts: OnceCell<FontStack>,
+}
+
+impl LazyFonts {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        LazyFonts {
+            path,
+            fonts: OnceCell::new(),
+        }
+    }
+
+    pub fn get(&self) -> &FontStack {
+        self.fonts
+            .get_or_init(|| FontStack::load(self.path.as_deref()))
+    }
+}
+
+/// Thicken strokes by one dot to the right
+fn embolden(bitmap: &mut Bitmap) {
+    for y in 0..bitmap.height {
+        for x in (1..bitmap.width).rev() {
+            if bitmap.get(x - 1, y) {
+                bitmap.set(x, y, true);
+            }
+        }
+    }
+}
+
+// =====================================================
+// Shaping and drawing
+// =====================================================
+
+/// A glyph on the line. `x` is in dots from the left edge once placed
+/// (from the pen position straight out of `shape`), `y` is the shift up
+/// from the baseline.
+struct Placed {
+    face: usize,
+    id: u16,
+    x: f32,
+    y: f32,
+    advance: f32,
+    squeeze: f32,
+}
+
+struct Face<'a> {
+    shaper: rustybuzz::Face<'a>,
+    font: FontRef<'a>,
+}
+
+impl<'a> Face<'a> {
+    fn parse(data: &'a [u8]) -> Option<Face<'a>> {
+        Some(Face {
+            shaper: rustybuzz::Face::from_slice(data, 0)?,
+            font: FontRef::try_from_slice_and_index(data, 0).ok()?,
+        })
+    }
+
+    /// Scale that fits the font's ascent and descent in the cell height
+    fn scale(&self, cell: Cell) -> PxScale {
+        let height = cell.height as f32;
+        PxScale {
+            x: height * cell.aspect,
+            y: height,
+        }
+    }
+
+    fn has(&self, c: char) -> bool {
+        self.shaper.glyph_index(c).is_some()
+    }
+
+    fn draw(&self, glyph: &Placed, offset: f32, cell: Cell, bitmap: &mut Bitmap) {
+        // Glyph 0 is only kept for blank characters the font lacks
+        if glyph.id == 0 {
+            return;
+        }
+        let scale = self.scale(cell);
+        let scaled = self.font.as_scaled(scale);
+        let baseline = scaled.ascent();
+        let scale = PxScale {
+            x: scale.x * glyph.squeeze,
+            y: scale.y,
+        };
+        let positioned = GlyphId(glyph.id)
+            .with_scale_and_position(scale, point(glyph.x + offset, baseline - glyph.y));
+        if let Some(outline) = self.font.outline_glyph(positioned) {
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, coverage| {
+                let x = bounds.min.x as i32 + x as i32;
+                let y = bounds.min.y as i32 + y as i32;
+                if coverage > THRESHOLD && x >= 0 && y >= 0 {
+                    bitmap.set(x as usize, y as usize, true);
+                }
+            });
+        }
+    }
+}
+
+/// First face with a glyph for `c`
+fn face_for(faces: &[Face], c: char) -> Result<usize, String> {
+    faces
+        .iter()
+        .position(|face| face.has(c))
+        .ok_or_else(|| missing_glyph(c))
+}
+
+/// An ASCII character centered in its cell
+fn monospaced(faces: &[Face], c: char, left: f32, cell: Cell) -> Result<Option<Placed>, String> {
+    if c == ' ' {
+        return Ok(None);
+    }
+    let face = face_for(faces, c)?;
+    let scaled = faces[face].font.as_scaled(faces[face].scale(cell));
+    let id = scaled.glyph_id(c);
+    let advance = scaled.h_advance(id);
+    let squeeze = (cell.width as f32 / advance).min(1.0);
+    Ok(Some(Placed {
+        face,
+        id: id.0,
+        x: left + (cell.width as f32 - advance * squeeze) / 2.0,
+        y: 0.0,
+        advance,
+        squeeze,
+    }))
+}
+
+/// Shape a run of text, switching faces where the first one has no glyph;
+/// combining marks stay with the face of their base letter
+fn shape(faces: &[Face], chars: &[char], cell: Cell) -> Result<Vec<Placed>, String> {
+    let mut placed = Vec::new();
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    for &c in chars {
+        let face = match runs.last() {
+            Some((face, _)) if is_mark(c) || invisible(c) => *face,
+            _ if invisible(c) => face_for(faces, c).unwrap_or(0),
+            _ => face_for(faces, c)?,
+        };
+        match runs.last_mut() {
+            Some((current, text)) if *current == face => text.push(c),
+            _ => runs.push((face, c.to_string())),
+        }
+    }
+
+    for (face, text) in runs {
+        let scaled = faces[face].font.as_scaled(faces[face].scale(cell));
+        let (sx, sy) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(&text);
+        let shaped = rustybuzz::shape(&faces[face].shaper, &[], buffer);
+        for (info, pos) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+            let c = text[info.cluster as usize..].chars().next().unwrap_or(' ');
+            if info.glyph_id == 0 && !invisible(c) {
+                return Err(missing_glyph(c));
+            }
+            placed.push(Placed {
+                face,
+                id: info.glyph_id as u16,
+                x: pos.x_offset as f32 * sx,
+                y: pos.y_offset as f32 * sy,
+                advance: pos.x_advance as f32 * sx,
+                squeeze: 1.0,
+            });
+        }
+    }
+    Ok(placed)
+}
+
+/// Spaces and format characters (joiners, variation selectors) that the
+/// shaper removes or draws as blank whatever the font
+fn invisible(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '\u{200B}'..='\u{200F}' | '\u{FE00}'..='\u{FE0F}')
+}
+
+/// Combining marks (vowel signs, virama) in the Indic blocks and the
+/// general combining diacritics
+fn is_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{0900}'..='\u{0903}'
+        | '\u{093A}'..='\u{094F}'
+        | '\u{0B82}'
+        | '\u{0BBE}'..='\u{0BCD}'
+        | '\u{0BD7}'
+        | '\u{200C}'..='\u{200D}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::escp::{self, EscpOptions};
+    use crate::print::escpos::{self, EscposOptions};
+    use crate::print::receipt::{Receipt, Style};
+
+    const CELL: Cell = Cell {
+        width: 12,
+        height: 24,
+        aspect: 1.0,
+    };
+
+    /// Columns of the line with any ink in them
+    fn inked_columns(bitmap: &Bitmap) -> Vec<usize> {
+        (0..bitmap.width / CELL.width)
+            .filter(|column| {
+                (column * CELL.width..(column + 1) * CELL.width)
+                    .any(|x| (0..bitmap.height).any(|y| bitmap.get(x, y)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_characters_outside_the_code_page_need_graphics() {
+        assert!(!needs_graphics("Paracetamol 500mg  2  ₹20.00 – 10% × 2"));
+        assert!(needs_graphics("பாராசிட்டமால்  2  20.00"));
+        assert!(needs_graphics("Ωmega 3"));
+    }
+
+    #[test]
+    fn ascii_columns_line_up_with_text_mode() {
+        let fonts = FontStack::bundled();
+        let bitmap = fonts
+            .render_line("ΩΨ  ab  7", 20, CELL, Align::Left, false)
+            .unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (240, 24));
+        // The Greek run is proportional; the ASCII after it is on the grid
+        let inked = inked_columns(&bitmap);
+        assert_eq!(inked[0], 0);
+        assert!(
+            inked.ends_with(&[4, 5, 8]) && !inked.contains(&3),
+            "{:?}",
+            inked
+        );
+    }
+
+    #[test]
+    fn long_runs_are_squeezed_before_the_next_column() {
+        let fonts = FontStack::bundled();
+        let bitmap = fonts
+            .render_line("ΩΩΩΩΩΩΩΩ  9", 12, CELL, Align::Left, false)
+            .unwrap();
+        let inked = inked_columns(&bitmap);
+        assert!(!inked.contains(&9), "{:?}", inked);
+        assert_eq!(inked.last(), Some(&10));
+    }
+
+    #[test]
+    #[ignore = "needs resources/fonts/NotoSansTamil-Regular.ttf"]
+    fn tamil_is_shaped_with_the_bundled_font() {
+        let fonts = FontStack::bundled_in(Some(Path::new(env!("CARGO_MANIFEST_DIR"))));
+        assert_eq!(fonts.faces.len(), 2, "Noto Sans Tamil did not load");
+
+        let name: Vec<char> = "பாராசிட்டமால்".chars().collect();
+        let run = shape(&fonts.faces, &name, CELL).unwrap();
+        assert!(!run.is_empty());
+        // Every glyph comes from the Tamil face and none is the missing box
+        assert!(run.iter().all(|g| g.face == 0 && g.id != 0));
+
+        let bitmap = fonts
+            .render_line("பாராசிட்டமால்  2  20.00", 40, CELL, Align::Left, false)
+            .unwrap();
+        let inked = inked_columns(&bitmap);
+        assert_eq!(inked[0], 0);
+        assert!(inked.len() >= 6, "{:?}", inked);
+        // The quantity and amount stay on the text-mode grid
+        assert!(inked.ends_with(&[15, 18, 19, 20, 21, 22]), "{:?}", inked);
+    }
+
+    #[test]
+    fn missing_glyphs_fail_instead_of_printing_boxes() {
+        // DejaVu alone has no Tamil
+        let fonts = FontStack::bundled_in(None);
+        let err = fonts
+            .render_line("பாராசிட்டமால்  2  20.00", 40, CELL, Align::Left, false)
+            .unwrap_err();
+        assert!(
+            err.contains("U+0BAA") && err.contains("Noto Sans Tamil"),
+            "{}",
+            err
+        );
+        assert!(fonts
+            .render_line("Ωmega\u{200D} 3", 20, CELL, Align::Left, false)
+            .is_ok());
+    }
+
+    #[test]
+    fn lines_split_into_text_and_graphics_runs() {
+        let run = |column, text, graphics| Run {
+            column,
+            text,
+            graphics,
+        };
+        assert_eq!(
+            runs("Customer: ரவி  2  ₹20.00"),
+            [
+                run(0, "Customer:", false),
+                run(10, "ரவி", true),
+                run(15, "2  ₹20.00", false)
+            ]
+        );
+        assert_eq!(runs("  Ωmega Ψ"), [run(2, "Ωmega Ψ", true)]);
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn escp_draws_only_the_words_outside_the_code_page() {
+        let mut receipt = Receipt::new(40);
+        receipt.text("Paracetamol  2  20.00", Style::default());
+        receipt.text("Ωmega  1  150.00", Style::default());
+        receipt.text("ΩΨ", Style::default());
+        let bytes = escp::encode(&receipt, &EscpOptions::default()).unwrap();
+
+        assert!(contains(&bytes, b"Paracetamol  2  20.00\r\n"));
+        // Ωmega fills columns 0-5 (72 dots) in both bands, the text follows
+        // it in the top band and blanks keep the bottom band in step
+        assert!(contains(&bytes, b"\x1b3\x18\x1b*\x01\x48\x00"));
+        let top = bytes.windows(3).position(|w| w == b"\x1b*\x01").unwrap() + 5 + 72;
+        assert!(bytes[top..].starts_with(b" 1  150.00\r\n\x1b*\x01\x48\x00"));
+        assert!(contains(
+            &bytes,
+            &[b"\x00          \r\n".as_slice()].concat()
+        ));
+        // A line with no text words is drawn whole, 40 columns wide
+        assert!(contains(&bytes, b"\x1b*\x01\xe0\x01"));
+        assert_eq!(bytes.windows(2).filter(|w| w == b"\x1B*").count(), 4);
+    }
+
+    #[test]
+    fn escpos_draws_only_the_words_outside_the_code_page() {
+        let mut receipt = Receipt::new(48);
+        receipt.text("Ωmega  1  150.00", Style::default());
+        receipt.text(
+            "Ωmega  1",
+            Style {
+                double_height: true,
+                ..Style::default()
+            },
+        );
+        let bytes = escpos::encode(&receipt, &EscposOptions::for_width_mm(80)).unwrap();
+
+        // 72 dots of 24-dot image (3 bytes a column), then the text
+        let image = bytes
+            .windows(5)
+            .position(|w| w == b"\x1b*\x21\x48\x00")
+            .unwrap();
+        assert!(bytes[image + 5 + 72 * 3..].starts_with(b" 1  150.00\n"));
+        // Double height text is taller than a band, so that line is a raster
+        assert!(contains(&bytes, b"\x1dv0\x00\x48\x00\x30\x00"));
+    }
+}
@@ -0,0 +1,123 @@
+// =====================================================
+// UPI Payment QR
+// A upi://pay link for bills paid online, printed as a
+// QR code so customers scan instead of typing our VPA
+// =====================================================
+
+use super::bill::{Bill, Shop};
+
+/// `upi://pay` link for `amount` rupees to `vpa`, with `note` shown in the
+/// customer's app as the transaction remark
+pub fn pay_uri(vpa: &str, payee: &str, amount: f64, note: &str) -> String {
+    format!(
+        "upi://pay?pa={}&pn={}&am={:.2}&cu=INR&tn={}",
+        encode(vpa),
+        encode(payee),
+        amount,
+        encode(note)
+    )
+}
+
+/// The part of the bill to be paid through UPI: all of an ONLINE bill,
+/// the online share of a SPLIT one
+pub fn amount_due(bill: &Bill) -> Option<f64> {
+    let amount = match bill.payment_mode.to_uppercase().as_str() {
+        "ONLINE" if bill.online_amount > 0.0 => bill.online_amount,
+        "ONLINE" => bill.grand_total,
+        "SPLIT" => bill.online_amount,
+        _ => return None,
+    };
+    (amount > 0.0).then_some(amount)
+}
+
+/// Payment link for a bill, when the shop has a UPI ID and the bill has
+/// an online amount to collect
+pub fn for_bill(shop: &Shop, bill: &Bill) -> Option<String> {
+    if shop.upi_id.is_empty() || bill.status == "CANCELLED" {
+        return None;
+    }
+    let amount = amount_due(bill)?;
+    Some(pay_uri(
+        &shop.upi_id,
+        &shop.name,
+        amount,
+        &format!("Bill {}", bill.bill_number),
+    ))
+}
+
+/// Percent-encode a query value; `@` is left as is since every UPI app
+/// expects it verbatim in the VPA
+fn encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::bitmap::{Bitmap, QUIET_ZONE};
+
+    fn shop() -> Shop {
+        Shop {
+            name: "Sri Ram Medicals & Co".to_string(),
+            upi_id: "sriram@okaxis".to_string(),
+            ..Shop::default()
+        }
+    }
+
+    fn bill(mode: &str, grand_total: f64, online_amount: f64) -> Bill {
+        Bill {
+            bill_number: "INV-2425-00001".to_string(),
+            payment_mode: mode.to_string(),
+            grand_total,
+            online_amount,
+            status: "COMPLETED".to_string(),
+            ..Bill::default()
+        }
+    }
+
+    #[test]
+    fn builds_an_encoded_pay_link() {
+        assert_eq!(
+            for_bill(&shop(), &bill("ONLINE", 952.0, 0.0)).unwrap(),
+            "upi://pay?pa=sriram@okaxis&pn=Sri%20Ram%20Medicals%20%26%20Co&am=952.00\
+             &cu=INR&tn=Bill%20INV-2425-00001"
+        );
+        let split = for_bill(&shop(), &bill("SPLIT", 952.0, 452.5)).unwrap();
+        assert!(split.contains("&am=452.50&"), "{}", split);
+    }
+
+    #[test]
+    fn only_bills_with_an_online_amount_get_a_link() {
+        assert!(for_bill(&shop(), &bill("CASH", 952.0, 0.0)).is_none());
+        assert!(for_bill(&shop(), &bill("SPLIT", 952.0, 0.0)).is_none());
+        assert!(for_bill(&Shop::default(), &bill("ONLINE", 952.0, 0.0)).is_none());
+
+        let mut cancelled = bill("ONLINE", 952.0, 0.0);
+        cancelled.status = "CANCELLED".to_string();
+        assert!(for_bill(&shop(), &cancelled).is_none());
+    }
+
+    #[test]
+    fn pay_link_fits_a_small_qr_symbol() {
+        let uri = for_bill(&shop(), &bill("ONLINE", 952.0, 0.0)).unwrap();
+        let qr = Bitmap::qr(&uri).unwrap();
+        assert_eq!(qr.width, qr.height);
+        // Version 6 or lower, so modules stay large on a 58mm roll
+        assert!(qr.width <= 41 + 2 * QUIET_ZONE, "{}", qr.width);
+        assert_eq!((qr.width - 2 * QUIET_ZONE - 17) % 4, 0);
+        assert!((0..qr.width).all(|x| !qr.get(x, 0) && !qr.get(x, QUIET_ZONE - 1)));
+        // Top-left finder: dark ring, light ring, dark centre
+        assert!(qr.get(QUIET_ZONE, QUIET_ZONE));
+        assert!(!qr.get(QUIET_ZONE + 1, QUIET_ZONE + 1));
+        assert!(qr.get(QUIET_ZONE + 3, QUIET_ZONE + 3));
+    }
+}
@@ -0,0 +1,141 @@
+// =====================================================
+// Windows Backend
+// Printer discovery via PowerShell/CIM, RAW jobs via
+// the spooler API and text jobs via Out-Printer
+// =====================================================
+
+use std::process::Command;
+
+use super::backend::{PrintJob, PrinterBackend, PrinterStatus};
+use super::status::{DeviceStatus, StatusSource};
+use super::winspool;
+
+pub struct WindowsBackend;
+
+/// Run a PowerShell snippet without a console window and return stdout
+fn powershell(script: &str) -> Result<String, String> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-WindowStyle",
+            "Hidden",
+            "-Command",
+            script,
+        ])
+        .output()
+        .map_err(|e| format!("Failed: {}", e))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+impl PrinterBackend for WindowsBackend {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    fn list_printers(&self) -> Result<Vec<String>, String> {
+        let stdout = powershell(
+            "Get-CimInstance -Class Win32_Printer | Select-Object -ExpandProperty Name",
+        )?;
+        Ok(stdout
+            .lines()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect())
+    }
+
+    fn default_printer(&self) -> Option<String> {
+        let stdout = powershell(
+            "(Get-CimInstance -Class Win32_Printer | Where-Object {$_.Default -eq $true}).Name",
+        )
+        .ok()?;
+        let name = stdout.trim().to_string();
+        (!name.is_empty()).then_some(name)
+    }
+
+    fn status(&self, printer: &str) -> PrinterStatus {
+        let script = format!(
+            "(Get-CimInstance -Class Win32_Printer | Where-Object {{$_.Name -eq '{}'}}).WorkOffline",
+            printer.replace("'", "''")
+        );
+        match powershell(&script).as_deref().map(str::trim) {
+            Ok("False") => PrinterStatus::Ready,
+            Ok("True") | Ok("") => PrinterStatus::Offline,
+            _ => PrinterStatus::Unknown,
+        }
+    }
+
+    fn device_status(&self, printer: &str) -> DeviceStatus {
+        let script = format!(
+            "$p = Get-CimInstance -Class Win32_Printer | Where-Object {{$_.Name -eq '{}'}}; \
+             \"$($p.WorkOffline) $($p.DetectedErrorState)\"",
+            printer.replace("'", "''")
+        );
+        match powershell(&script) {
+            Ok(stdout) => parse_device_status(printer, &stdout),
+            Err(_) => {
+                DeviceStatus::from_status(printer, PrinterStatus::Unknown, StatusSource::Spooler)
+            }
+        }
+    }
+
+    fn submit(&self, printer: &str, job: &PrintJob) -> Result<String, String> {
+        if job.raw {
+            winspool::send_raw(printer, &job.title, &job.data)?;
+            return Ok(printer.to_string());
+        }
+
+        // Text jobs go through Out-Printer so the driver handles layout
+        let text = String::from_utf8_lossy(&job.data);
+        let escaped = text.replace("'", "''").replace("`", "``");
+        let script = format!(
+            r#"
+$content = @'
+{}
+'@
+$content | Out-Printer -Name '{}'
+"#,
+            escaped,
+            printer.replace("'", "''")
+        );
+
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-NonInteractive",
+                "-WindowStyle",
+                "Hidden",
+                "-ExecutionPolicy",
+                "Bypass",
+                "-Command",
+                &script,
+            ])
+            .output()
+            .map_err(|e| format!("Print failed: {}", e))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.trim().is_empty() || !stderr.to_lowercase().contains("error") {
+            Ok(printer.to_string())
+        } else {
+            log::warn!("Print stderr: {}", stderr.trim());
+            Err(stderr.trim().to_string())
+        }
+    }
+}
+
+/// "False 4": WorkOffline and Win32_Printer.DetectedErrorState
+/// (3 low paper, 4 no paper, 7 door open, 8 jammed, 9 offline)
+fn parse_device_status(printer: &str, stdout: &str) -> DeviceStatus {
+    let mut fields = stdout.split_whitespace();
+    let mut status = DeviceStatus::new(printer, StatusSource::Spooler);
+    status.online = fields.next() == Some("False");
+    match fields.next().and_then(|s| s.parse::<u32>().ok()) {
+        Some(3) => status.paper_low = true,
+        Some(4) => status.paper_out = true,
+        Some(7) => status.cover_open = true,
+        Some(8) | Some(10) => status.error = true,
+        Some(9) => status.online = false,
+        _ => {}
+    }
+    status.describe()
+}
@@ -0,0 +1,70 @@
+// =====================================================
+// Windows Spooler - RAW Datatype Jobs
+// Sends printer command bytes straight to the device,
+// bypassing the driver's page layout and margins
+// =====================================================
+
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::Graphics::Printing::{
+    ClosePrinter, EndDocPrinter, EndPagePrinter, OpenPrinterW, StartDocPrinterW, StartPagePrinter,
+    WritePrinter, DOC_INFO_1W,
+};
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Submit `data` to `printer_name` as a single RAW job
+pub fn send_raw(printer_name: &str, doc_name: &str, data: &[u8]) -> Result<(), String> {
+    let printer = wide(printer_name);
+    let mut doc = wide(doc_name);
+    let mut datatype = wide("RAW");
+
+    unsafe {
+        let mut handle = HANDLE::default();
+        OpenPrinterW(PCWSTR(printer.as_ptr()), &mut handle, None)
+            .map_err(|e| format!("Cannot open printer {}: {}", printer_name, e))?;
+
+        let doc_info = DOC_INFO_1W {
+            pDocName: PWSTR(doc.as_mut_ptr()),
+            pOutputFile: PWSTR::null(),
+            pDatatype: PWSTR(datatype.as_mut_ptr()),
+        };
+
+        let result = (|| {
+            if StartDocPrinterW(handle, 1, &doc_info) == 0 {
+                return Err(format!("StartDocPrinter failed for {}", printer_name));
+            }
+            if !StartPagePrinter(handle).as_bool() {
+                let _ = EndDocPrinter(handle);
+                return Err(format!("StartPagePrinter failed for {}", printer_name));
+            }
+
+            let mut written: u32 = 0;
+            let ok = WritePrinter(
+                handle,
+                data.as_ptr() as *const core::ffi::c_void,
+                data.len() as u32,
+                &mut written,
+            )
+            .as_bool();
+
+            let _ = EndPagePrinter(handle);
+            let _ = EndDocPrinter(handle);
+
+            if !ok || written as usize != data.len() {
+                return Err(format!(
+                    "WritePrinter sent {} of {} bytes to {}",
+                    written,
+                    data.len(),
+                    printer_name
+                ));
+            }
+            Ok(())
+        })();
+
+        let _ = ClosePrinter(handle);
+        result
+    }
+}
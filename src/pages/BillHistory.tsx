@@ -3,13 +3,14 @@
 // View and manage past bills
 // =====================================================
 
-import { Calendar, Eye, Pill, Printer, Search, X } from 'lucide-react';
+import { Calendar, Eye, FileText, Pill, Printer, Search, X } from 'lucide-react';
 import { useCallback, useEffect, useState } from 'react';
 import { Pagination } from '../components/common/Pagination';
+import { useToast } from '../components/common/Toast';
 import { query } from '../services/database';
 import { formatCurrency } from '../services/gst.service';
-import { printBill } from '../services/print.service';
-import { useSettingsStore } from '../stores';
+import { printSavedBill, saveBillPdf } from '../services/print.service';
+import { useAuthStore } from '../stores';
 import type { Bill, BillItem, ScheduledMedicineRecord } from '../types';
 import { formatDate } from '../utils';
 
@@ -20,8 +21,8 @@ interface BillWithItems extends Bill {
 type ViewMode = 'all' | 'schedule';
 
 export function BillHistory() {
-    const { settings } = useSettingsStore();
-    const printerType = (settings.printer_type as 'thermal' | 'dotmatrix' | 'a4' | 'legal') || 'thermal';
+    const { user } = useAuthStore();
+    const { showToast } = useToast();
     const [viewMode, setViewMode] = useState<ViewMode>('all');
     const [bills, setBills] = useState<Bill[]>([]);
     const [scheduleRecords, setScheduleRecords] = useState<ScheduledMedicineRecord[]>([]);
@@ -164,6 +165,18 @@ export function BillHistory() {
         }
     };
 
+    // Printed by the backend, which stamps copies after the first as
+    // duplicates and records who reprinted the bill
+    const handleReprint = async (bill: Bill) => {
+        try {
+            await printSavedBill(bill.id, undefined, undefined, user?.id);
+            showToast('success', `Bill ${bill.bill_number} sent to printer`);
+        } catch (error) {
+            console.error('Reprint failed:', error);
+            showToast('error', error instanceof Error ? error.message : 'Failed to print bill');
+        }
+    };
+
     return (
         <>
             <header className="page-header">
@@ -611,13 +624,24 @@ export function BillHistory() {
                         <div className="modal-footer">
                             <button
                                 className="btn btn-secondary"
-                                onClick={() => {
-                                    if (selectedBill?.items) {
-                                        printBill(selectedBill, selectedBill.items, { paperSize: printerType });
+                                onClick={() => handleReprint(selectedBill)}
+                            >
+                                <Printer size={16} /> Print Receipt
+                            </button>
+                            <button
+                                className="btn btn-secondary"
+                                onClick={async () => {
+                                    try {
+                                        const path = await saveBillPdf(selectedBill.id, selectedBill.bill_number);
+                                        if (path) {
+                                            showToast('success', 'Invoice saved as PDF');
+                                        }
+                                    } catch (error) {
+                                        showToast('error', error instanceof Error ? error.message : 'Failed to save PDF');
                                     }
                                 }}
                             >
-                                <Printer size={16} /> Print Receipt
+                                <FileText size={16} /> Save PDF
                             </button>
                             <button className="btn btn-primary" onClick={() => {
                                 setSelectedBill(null);
@@ -757,11 +781,7 @@ export function BillHistory() {
                             {selectedBill && (
                                 <button
                                     className="btn btn-secondary"
-                                    onClick={() => {
-                                        if (selectedBill?.items) {
-                                            printBill(selectedBill, selectedBill.items, { paperSize: printerType });
-                                        }
-                                    }}
+                                    onClick={() => handleReprint(selectedBill)}
                                 >
                                     <Printer size={16} /> Print Bill
                                 </button>
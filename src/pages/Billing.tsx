@@ -9,6 +9,7 @@ import {
     Banknote,
     CreditCard,
     Percent,
+    Printer,
     Search,
     Smartphone,
     Trash2,
@@ -19,9 +20,11 @@ import { useCallback, useEffect, useMemo, useRef, useState } from 'react';
 import { useToast } from '../components/common/Toast';
 import { createBill } from '../services/billing.service';
 import { query } from '../services/database';
+import { displayItem, displayThankYou, displayTotal, displayWelcome } from '../services/display.service';
 import { calculateBill, formatCurrency } from '../services/gst.service';
 import { searchMedicinesForBilling } from '../services/inventory.service';
-import { silentPrintBill } from '../services/print.service';
+import { getPrinterStatus, onPrinterStatus, onPrintJobStatus, openCashDrawer, silentPrintBill } from '../services/print.service';
+import type { PrinterDeviceStatus } from '../services/print.service';
 import { useAuthStore, useBillingStore, useSettingsStore } from '../stores';
 import type { Customer, ScheduledMedicineInput, StockItem } from '../types';
 import { debounce, formatDate } from '../utils';
@@ -61,6 +64,8 @@ export function Billing() {
     const [customers, setCustomers] = useState<Customer[]>([]);
     const [isSubmitting, setIsSubmitting] = useState(false);
     const [error, setError] = useState<string | null>(null);
+    const [printerStatus, setPrinterStatus] = useState<PrinterDeviceStatus | null>(null);
+    const [cashReceived, setCashReceived] = useState(0); // Cash handed over, for the change due
     const [showPatientModal, setShowPatientModal] = useState(false);
     const [doctorName, setDoctorName] = useState(''); // Optional doctor name for all bills
     const [tempPatientInfo, setTempPatientInfo] = useState<ScheduledMedicineInput>({
@@ -81,6 +86,7 @@ export function Billing() {
     const hasScheduled = hasScheduledMedicines();
     const searchInputRef = useRef<HTMLInputElement>(null);
     const resultsRef = useRef<HTMLDivElement>(null);
+    const scannedBatchRef = useRef<number | null>(null); // Item to show on the customer display
 
     // Calculate bill totals (per-piece pricing)
     const billCalc = calculateBill(
@@ -103,6 +109,26 @@ export function Billing() {
         discountValue
     );
 
+    // Customer display: shop greeting until the first item is added
+    useEffect(() => {
+        displayWelcome();
+    }, []);
+
+    // Customer display: the item just added, or the running total after a change.
+    // An empty bill leaves the thank-you screen up until the next item.
+    const displayAmount = billCalc.finalAmount;
+    useEffect(() => {
+        if (items.length === 0) return;
+        const index = items.findIndex(i => i.batch.batch_id === scannedBatchRef.current);
+        scannedBatchRef.current = null;
+        if (index >= 0) {
+            displayItem(items[index].batch.medicine_name, billCalc.items[index]?.total ?? 0, displayAmount);
+        } else {
+            displayTotal(displayAmount, items.length);
+        }
+        // eslint-disable-next-line react-hooks/exhaustive-deps
+    }, [items, displayAmount]);
+
     useEffect(() => {
         let mounted = true;
         async function loadCustomers() {
@@ -122,6 +148,49 @@ export function Billing() {
         return () => { mounted = false; };
     }, []);
 
+    // Printing happens in the background spool; tell staff when a job gives up
+    useEffect(() => {
+        let unlisten: (() => void) | undefined;
+        let mounted = true;
+        onPrintJobStatus((job) => {
+            if (job.status === 'failed') {
+                showToast('error', `Printing failed after ${job.attempts} attempts: ${job.error ?? 'printer unavailable'}. Re-send it from the print queue.`);
+            }
+        })
+            .then((fn) => {
+                if (mounted) unlisten = fn;
+                else fn();
+            })
+            .catch((err) => console.warn('[Billing] Print status listener unavailable:', err));
+        return () => {
+            mounted = false;
+            unlisten?.();
+        };
+    }, [showToast]);
+
+    // Paper low still prints; anything else means the bill will not come out
+    const printerReady = !!printerStatus && printerStatus.online && !printerStatus.paper_out
+        && !printerStatus.cover_open && !printerStatus.error;
+
+    // Warn before billing when the bill printer is out of paper, open or offline
+    useEffect(() => {
+        let unlisten: (() => void) | undefined;
+        let mounted = true;
+        getPrinterStatus().then((status) => {
+            if (mounted && status) setPrinterStatus(status);
+        });
+        onPrinterStatus((status) => setPrinterStatus(status))
+            .then((fn) => {
+                if (mounted) unlisten = fn;
+                else fn();
+            })
+            .catch((err) => console.warn('[Billing] Printer status listener unavailable:', err));
+        return () => {
+            mounted = false;
+            unlisten?.();
+        };
+    }, []);
+
     // Sync tempPatientInfo with patientInfo when modal opens
     useEffect(() => {
         if (showPatientModal && patientInfo) {
@@ -171,6 +240,7 @@ export function Billing() {
     }, [searchQuery, performSearch]);
 
     const handleAddItem = useCallback((item: StockItem) => {
+        scannedBatchRef.current = item.batch_id;
         addItem(item, 1);
         setSearchQuery('');
         setShowSearchDropdown(false);
@@ -178,6 +248,19 @@ export function Billing() {
         searchInputRef.current?.focus();
     }, [addItem]);
 
+    // Opening the drawer without a bill needs a reason for the audit log
+    const handleNoSale = async () => {
+        if (!user) return;
+        const reason = window.prompt('Reason for opening the cash drawer (e.g. change, cash pickup):');
+        if (reason === null) return;
+        try {
+            await openCashDrawer(user.id, reason);
+            showToast('success', 'Cash drawer opened');
+        } catch (err) {
+            showToast('error', err instanceof Error ? err.message : 'Cash drawer did not open');
+        }
+    };
+
     // Keyboard Navigation
     useEffect(() => {
         const handleKeyDown = (e: KeyboardEvent) => {
@@ -302,20 +385,24 @@ export function Billing() {
             // 2. Auto-print (non-blocking - doesn't rollback bill on failure)
             let printSuccess = true;
             try {
-                await silentPrintBill(bill, bill.items ?? [], printerType);
+                await silentPrintBill(bill, bill.items ?? [], printerType, undefined, user?.id);
             } catch (printErr) {
                 printSuccess = false;
                 console.warn('[Billing] Print failed:', printErr);
-                showToast('warning', 'Bill saved! Printing failed - please print manually from Bill History.');
+                showToast('warning', 'Bill saved! Could not queue printing - please print manually from Bill History.');
             }
 
             // 3. Success feedback
             if (printSuccess) {
-                showToast('success', `Bill ${bill.bill_number} saved & printed!`);
+                showToast('success', `Bill ${bill.bill_number} saved & sent to printer!`);
             }
 
-            // 4. Clear bill and reset form
+            // 4. Thank the customer with the change due
+            displayThankYou(paymentMode === 'CASH' ? Math.max(0, cashReceived - billCalc.finalAmount) : 0);
+
+            // 5. Clear bill and reset form
             clearBill();
+            setCashReceived(0);
             setDoctorName('');
             setTempPatientInfo({
                 patient_name: '',
@@ -667,6 +754,16 @@ export function Billing() {
             <div className="billing-container">
                 {/* LEFT COLUMN: Search & Items */}
                 <div className="main-section">
+                    {printerStatus?.message && (
+                        <div className={`alert ${printerReady ? 'alert-warning' : 'alert-danger'}`} role="status">
+                            <Printer size={18} />
+                            <span style={{ flex: 1 }}>
+                                {printerStatus.message} ({printerStatus.printer})
+                                {!printerReady && '. Bills wait in the print queue until it is fixed.'}
+                            </span>
+                        </div>
+                    )}
+
                     {/* Search Bar */}
                     <div className="search-container">
                         <div className="search-input-wrapper">
@@ -934,6 +1031,27 @@ export function Billing() {
                             </button>
                         </div>
 
+                        {paymentMode === 'CASH' && (
+                            <div style={{ marginTop: 12, display: 'grid', gridTemplateColumns: '1fr 1fr', gap: 8 }}>
+                                <div>
+                                    <label className="form-label" style={{ fontSize: 11 }}>Cash Received</label>
+                                    <input
+                                        type="number"
+                                        className="form-input"
+                                        value={cashReceived || ''}
+                                        onChange={(e) => setCashReceived(parseFloat(e.target.value) || 0)}
+                                        min={0}
+                                    />
+                                </div>
+                                <div>
+                                    <label className="form-label" style={{ fontSize: 11 }}>Change</label>
+                                    <div className="form-input" style={{ background: 'transparent' }}>
+                                        {formatCurrency(Math.max(0, cashReceived - billCalc.finalAmount))}
+                                    </div>
+                                </div>
+                            </div>
+                        )}
+
                         {paymentMode === 'SPLIT' && (
                             <div style={{ marginTop: 12, display: 'grid', gridTemplateColumns: '1fr 1fr', gap: 8 }}>
                                 <div>
@@ -1025,6 +1143,16 @@ export function Billing() {
                         <div style={{ marginTop: 8, fontSize: 10, color: 'rgba(255,255,255,0.5)', textAlign: 'center' }}>
                             Amounts rounded to nearest ₹ (paise &lt;50 = floor, ≥50 = ceil)
                         </div>
+
+                        {settings.cash_drawer_enabled === 'true' && (
+                            <button
+                                className="btn btn-ghost btn-sm"
+                                style={{ width: '100%', marginTop: 8, color: 'rgba(255,255,255,0.7)' }}
+                                onClick={handleNoSale}
+                            >
+                                <Banknote size={14} /> Open Drawer (No Sale)
+                            </button>
+                        )}
                     </div>
                 </div>
             </div>
@@ -12,6 +12,7 @@ import {
     Pill,
     Plus,
     Search,
+    Tag,
     Trash2,
     X
 } from 'lucide-react';
@@ -31,6 +32,7 @@ import {
     getScheduledMedicines,
     updateMedicine
 } from '../services/inventory.service';
+import { printBatchLabels } from '../services/print.service';
 import { useAuthStore } from '../stores';
 import type { CreateBatchInput, CreateMedicineInput, GstRate, Medicine, StockItem, Supplier } from '../types';
 import { formatCurrency, formatDate, getExpiryStatusInfo, getStockStatusInfo } from '../utils';
@@ -640,6 +642,20 @@ export function Inventory() {
                                         </span>
                                     </span>
                                     <div className="action-btns">
+                                        <button
+                                            className="action-btn"
+                                            onClick={async () => {
+                                                try {
+                                                    await printBatchLabels([item.batch_id]);
+                                                    showToast('success', `Label for ${item.medicine_name} sent to the label printer`);
+                                                } catch (error) {
+                                                    showToast('error', error instanceof Error ? error.message : 'Failed to print label');
+                                                }
+                                            }}
+                                            title="Print Shelf Label"
+                                        >
+                                            <Tag size={16} />
+                                        </button>
                                         <button
                                             className="action-btn"
                                             onClick={async () => {
@@ -10,6 +10,7 @@ import {
     Pencil,
     Plus,
     Search,
+    Tag,
     Trash2,
     Truck,
     X
@@ -18,6 +19,8 @@ import { useEffect, useId, useState } from 'react';
 import { Pagination } from '../components/common/Pagination';
 import { useToast } from '../components/common/Toast';
 import { execute, query } from '../services/database';
+import { searchMedicinesMaster } from '../services/inventory.service';
+import { printPurchaseLabels } from '../services/print.service';
 import { useAuthStore } from '../stores';
 import type { CreateSupplierInput, GstRate, Medicine, Purchase, Supplier } from '../types';
 import { formatCurrency, formatDate } from '../utils';
@@ -67,6 +70,7 @@ export function Purchases() {
     const [showAddSupplierModal, setShowAddSupplierModal] = useState(false);
     const [showNewPurchaseModal, setShowNewPurchaseModal] = useState(false);
     const [showEditPurchaseModal, setShowEditPurchaseModal] = useState(false);
+    const [printLabelsOnSave, setPrintLabelsOnSave] = useState(false);
     const [showQuickAddMedicineModal, setShowQuickAddMedicineModal] = useState(false);
     const [editingSupplier, setEditingSupplier] = useState<Supplier | null>(null);
     const [editingPurchase, setEditingPurchase] = useState<Purchase | null>(null);
@@ -158,15 +162,9 @@ export function Purchases() {
 
         const searchMedicines = async () => {
             try {
-                const term = `%${medicineSearch}%`;
-                const results = await query<Medicine>(
-                    `SELECT * FROM medicines 
-                     WHERE is_active = 1 
-                       AND (name LIKE ? OR generic_name LIKE ? OR manufacturer LIKE ?)
-                     ORDER BY name ASC
-                     LIMIT 15`,
-                    [term, term, term]
-                );
+                // Falls back to similar names, so a misspelt name finds the
+                // existing medicine instead of adding a duplicate
+                const results = await searchMedicinesMaster(medicineSearch, 15);
                 setFilteredMedicines(results);
                 setShowMedicineDropdown(results.length > 0);
             } catch (error) {
@@ -214,6 +212,16 @@ export function Purchases() {
     };
 
     // Delete supplier
+    const handlePrintLabels = async (purchaseId: number, invoiceNumber: string) => {
+        try {
+            await printPurchaseLabels(purchaseId);
+            showToast('success', `Labels for ${invoiceNumber} sent to the label printer`);
+        } catch (error) {
+            console.error('Failed to print labels:', error);
+            showToast('error', error instanceof Error ? error.message : 'Failed to print labels');
+        }
+    };
+
     const handleDeleteSupplier = async (supplierId: number, supplierName: string) => {
         if (!confirm(`Are you sure you want to delete supplier "${supplierName}"?`)) {
             return;
@@ -720,6 +728,9 @@ export function Purchases() {
             }
 
             showToast('success', `Purchase ${purchaseForm.invoice_number} saved successfully!`);
+            if (printLabelsOnSave) {
+                await handlePrintLabels(purchaseId, purchaseForm.invoice_number);
+            }
             setShowNewPurchaseModal(false);
             resetPurchaseForm();
             loadData();
@@ -962,6 +973,16 @@ export function Purchases() {
                                                 GST: {formatCurrency(purchase.total_gst)}
                                             </div>
                                         </div>
+                                        <button
+                                            className="btn btn-ghost btn-icon"
+                                            onClick={(e) => {
+                                                e.stopPropagation();
+                                                handlePrintLabels(purchase.id, purchase.invoice_number);
+                                            }}
+                                            title="Print shelf labels"
+                                        >
+                                            <Tag size={16} />
+                                        </button>
                                         <button
                                             className="btn btn-ghost btn-icon"
                                             onClick={(e) => {
@@ -1535,6 +1556,14 @@ export function Purchases() {
                                 )}
                             </div>
                             <div className="modal-footer">
+                                <label className="form-label" style={{ display: 'flex', alignItems: 'center', gap: 'var(--space-2)', marginRight: 'auto', marginBottom: 0 }}>
+                                    <input
+                                        type="checkbox"
+                                        checked={printLabelsOnSave}
+                                        onChange={(e) => setPrintLabelsOnSave(e.target.checked)}
+                                    />
+                                    Print shelf labels
+                                </label>
                                 <button type="button" className="btn btn-secondary" onClick={() => { setShowNewPurchaseModal(false); resetPurchaseForm(); }}>
                                     Cancel
                                 </button>
@@ -13,7 +13,9 @@ import {
     Calendar,
     ChevronDown,
     ChevronRight,
+    Copy,
     FileSpreadsheet,
+    FileText,
     Filter,
     IndianRupee,
     Package,
@@ -35,10 +37,11 @@ import { useToast } from '../components/common/Toast';
 import { getBills, getPaymentModeBreakdown, getSalesTrend, getTopSellingMedicines } from '../services/billing.service';
 import { query } from '../services/database';
 import { getExpiringItems, getStockValue } from '../services/inventory.service';
+import { getReprintedBills, saveReportPdf, type PdfReport, type ReprintedBill } from '../services/print.service';
 import type { Bill, ScheduledMedicineRecord, StockItem } from '../types';
 import { formatCurrency, formatDate, toISODate } from '../utils';
 
-type ReportType = 'sales' | 'gst' | 'inventory' | 'expiry' | 'credit' | 'scheduled';
+type ReportType = 'sales' | 'gst' | 'inventory' | 'expiry' | 'credit' | 'scheduled' | 'reprints';
 
 interface SalesReportData {
     bills: Bill[];
@@ -76,6 +79,11 @@ interface ScheduledReportData {
     totalQuantity: number;
 }
 
+interface ReprintReportData {
+    bills: ReprintedBill[];
+    totalReprints: number;
+}
+
 interface ReportData {
     sales: SalesReportData | undefined;
     gst: GstReportData | undefined;
@@ -83,6 +91,7 @@ interface ReportData {
     expiry: ExpiryReportData | undefined;
     credit: CreditReportData | undefined;
     scheduled: ScheduledReportData | undefined;
+    reprints: ReprintReportData | undefined;
 }
 
 export function Reports() {
@@ -95,6 +104,8 @@ export function Reports() {
     const [isLoading, setIsLoading] = useState(false);
     const [reportData, setReportData] = useState<Partial<ReportData>>({});
     const [expandedRows, setExpandedRows] = useState<number[]>([]);
+    // Reprinted bills report: bills reprinted more than this many times
+    const [reprintThreshold, setReprintThreshold] = useState(1);
 
     const loadReport = useCallback(async () => {
         setIsLoading(true);
@@ -245,13 +256,24 @@ c.id,
                     });
                     break;
                 }
+                case 'reprints': {
+                    const reprinted = await getReprintedBills(reprintThreshold, dateRange.start, dateRange.end);
+                    setReportData({
+                        ...reportData,
+                        reprints: {
+                            bills: reprinted,
+                            totalReprints: reprinted.reduce((sum, b) => sum + b.reprints, 0)
+                        }
+                    });
+                    break;
+                }
             }
         } catch (error) {
             console.error('Failed to load report:', error);
         }
         setIsLoading(false);
         // eslint-disable-next-line react-hooks/exhaustive-deps
-    }, [activeReport, dateRange.start, dateRange.end]);
+    }, [activeReport, dateRange.start, dateRange.end, reprintThreshold]);
 
     useEffect(() => {
         loadReport();
@@ -261,6 +283,25 @@ c.id,
         window.print();
     };
 
+    // Reports the backend can lay out as a paginated A4 PDF
+    const pdfReport: PdfReport | null =
+        activeReport === 'sales' || activeReport === 'gst' || activeReport === 'expiry' || activeReport === 'credit'
+            ? activeReport
+            : null;
+
+    const handleSavePdf = async () => {
+        if (!pdfReport) return;
+        try {
+            const path = await saveReportPdf(pdfReport, dateRange.start, dateRange.end);
+            if (path) {
+                showToast('success', 'Report saved as PDF');
+            }
+        } catch (error) {
+            console.error('PDF export failed:', error);
+            showToast('error', error instanceof Error ? error.message : 'Failed to save PDF');
+        }
+    };
+
     const handleExportExcel = async () => {
         try {
             const workbook = new ExcelJS.Workbook();
@@ -441,6 +482,37 @@ c.id,
                         ];
                     }
                     break;
+
+                case 'reprints':
+                    sheetName = 'Reprinted_Bills';
+                    reportTitle = `Bills Reprinted More Than ${reprintThreshold} Times`;
+                    columns = [
+                        { header: 'Date', key: 'date', width: 12 },
+                        { header: 'Bill No', key: 'billNo', width: 16 },
+                        { header: 'Customer', key: 'customer', width: 20 },
+                        { header: 'Total', key: 'total', width: 12 },
+                        { header: 'Reprints', key: 'reprints', width: 10 },
+                        { header: 'Last Reprint', key: 'lastReprint', width: 18 },
+                        { header: 'Reprinted By', key: 'user', width: 18 },
+                        { header: 'Printer', key: 'printer', width: 20 },
+                    ];
+                    if (reportData.reprints) {
+                        data = reportData.reprints.bills.map((b) => ({
+                            date: formatDate(b.bill_date),
+                            billNo: b.bill_number,
+                            customer: b.customer_name || 'Walk-in',
+                            total: b.grand_total,
+                            reprints: b.reprints,
+                            lastReprint: formatDate(b.last_reprinted_at, 'dd/MM/yyyy HH:mm'),
+                            user: b.last_reprinted_by || '-',
+                            printer: b.last_printer || '-'
+                        }));
+                        summary = [
+                            { label: 'Bills', value: reportData.reprints.bills.length },
+                            { label: 'Total Reprints', value: reportData.reprints.totalReprints }
+                        ];
+                    }
+                    break;
             }
 
             const sheet = workbook.addWorksheet(sheetName);
@@ -552,6 +624,7 @@ c.id,
         { id: 'expiry', label: 'Expiry Report', icon: Calendar },
         { id: 'credit', label: 'Credit Report', icon: Users },
         { id: 'scheduled', label: 'Scheduled Drugs', icon: AlertCircle },
+        { id: 'reprints', label: 'Reprinted Bills', icon: Copy },
     ] as const;
 
     return (
@@ -563,6 +636,12 @@ c.id,
                         <Printer size={18} />
                         Print
                     </button>
+                    {pdfReport && (
+                        <button className="btn btn-secondary" onClick={handleSavePdf}>
+                            <FileText size={18} />
+                            Save PDF
+                        </button>
+                    )}
                     <button className="btn btn-primary" onClick={handleExportExcel}>
                         <FileSpreadsheet size={18} />
                         Export Excel
@@ -758,6 +837,18 @@ c.id,
                                     onChange={(e) => setDateRange({ ...dateRange, end: e.target.value })}
                                 />
                             </div>
+                            {activeReport === 'reprints' && (
+                                <div className="form-group">
+                                    <label className="form-label">Reprinted more than</label>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="form-input"
+                                        value={reprintThreshold}
+                                        onChange={(e) => setReprintThreshold(Math.max(0, parseInt(e.target.value) || 0))}
+                                    />
+                                </div>
+                            )}
                         </div>
                     </div>
 
@@ -1135,6 +1226,67 @@ c.id,
                                         )}
                                     </>
                                 )}
+
+                                {/* Reprinted Bills Report */}
+                                {activeReport === 'reprints' && reportData.reprints && (
+                                    <>
+                                        <div className="report-header">
+                                            <div>
+                                                <h2 className="report-title">Reprinted Bills</h2>
+                                                <p className="report-period">
+                                                    Bills dated {formatDate(dateRange.start)} - {formatDate(dateRange.end)} reprinted more than {reprintThreshold} {reprintThreshold === 1 ? 'time' : 'times'}
+                                                </p>
+                                            </div>
+                                        </div>
+
+                                        <div className="summary-cards" style={{ gridTemplateColumns: 'repeat(2, 1fr)' }}>
+                                            <div className="summary-card">
+                                                <div className="summary-value">{reportData.reprints.bills.length}</div>
+                                                <div className="summary-label">Bills</div>
+                                            </div>
+                                            <div className="summary-card">
+                                                <div className="summary-value">{reportData.reprints.totalReprints}</div>
+                                                <div className="summary-label">Total Reprints</div>
+                                            </div>
+                                        </div>
+
+                                        {reportData.reprints.bills.length > 0 ? (
+                                            <table className="report-table">
+                                                <thead>
+                                                    <tr>
+                                                        <th>Date</th>
+                                                        <th>Bill #</th>
+                                                        <th>Customer</th>
+                                                        <th className="numeric">Total</th>
+                                                        <th className="numeric">Reprints</th>
+                                                        <th>Last Reprint</th>
+                                                        <th>Reprinted By</th>
+                                                        <th>Printer</th>
+                                                    </tr>
+                                                </thead>
+                                                <tbody>
+                                                    {reportData.reprints.bills.map((b) => (
+                                                        <tr key={b.bill_id}>
+                                                            <td>{formatDate(b.bill_date)}</td>
+                                                            <td style={{ fontFamily: 'var(--font-mono)', fontSize: 12 }}>{b.bill_number}</td>
+                                                            <td>{b.customer_name || 'Walk-in'}</td>
+                                                            <td className="numeric">{formatCurrency(b.grand_total)}</td>
+                                                            <td className="numeric font-semibold text-danger">{b.reprints}</td>
+                                                            <td>{formatDate(b.last_reprinted_at, 'dd/MM/yyyy HH:mm')}</td>
+                                                            <td>{b.last_reprinted_by || '-'}</td>
+                                                            <td>{b.last_printer || '-'}</td>
+                                                        </tr>
+                                                    ))}
+                                                </tbody>
+                                            </table>
+                                        ) : (
+                                            <div style={{ textAlign: 'center', padding: 40, color: 'var(--text-tertiary)' }}>
+                                                <Copy size={48} strokeWidth={1} />
+                                                <p style={{ marginTop: 16 }}>No bills reprinted more than {reprintThreshold} {reprintThreshold === 1 ? 'time' : 'times'} in this period</p>
+                                            </div>
+                                        )}
+                                    </>
+                                )}
                             </>
                         )}
                     </div>
@@ -34,7 +34,25 @@ import {
     listBackups
 } from '../services/backup.service';
 import { execute, exportDatabase, importDatabase, query } from '../services/database';
+import type { DocumentKind, PrinterProfile, PrinterProfiles, ReceiptTemplate } from '../services/print.service';
+import {
+    DOCUMENT_KIND_LABELS,
+    deleteReceiptTemplate,
+    getPrinterProfiles,
+    listPrinters,
+    listReceiptTemplates,
+    previewReceiptTemplate,
+    savePrinterProfiles,
+    saveReceiptTemplate
+} from '../services/print.service';
 import { useAuthStore, useSettingsStore } from '../stores';
+import type { MedicineCsvImportSummary, MedicineImportProgress, MedicineMergeSummary } from '../services/inventory.service';
+import {
+    cancelMedicineImport,
+    importMedicinesCsv,
+    mergeBundledMedicines,
+    onMedicineImportProgress
+} from '../services/inventory.service';
 import type { User, UserRole } from '../types';
 
 type SettingsTab = 'shop' | 'billing' | 'users' | 'backup' | 'about';
@@ -47,6 +65,21 @@ interface UserFormData {
     role: UserRole;
 }
 
+interface TemplateFormData {
+    id?: number;
+    name: string;
+    body: string;
+    width: string;
+    is_active: boolean;
+}
+
+const initialTemplateForm: TemplateFormData = {
+    name: '',
+    body: '',
+    width: '42',
+    is_active: false
+};
+
 const initialUserForm: UserFormData = {
     username: '',
     password: '',
@@ -77,6 +110,24 @@ export function Settings() {
     const [backupFolder, setBackupFolder] = useState<string>('');
     const [deletingBackup, setDeletingBackup] = useState<string | null>(null);
 
+    // Medicine list update (a dry run is shown before anything is applied)
+    const [medicineMerge, setMedicineMerge] = useState<MedicineMergeSummary | null>(null);
+    const [isMergingMedicines, setIsMergingMedicines] = useState(false);
+    const [medicineMergeError, setMedicineMergeError] = useState('');
+    const [medicineImport, setMedicineImport] = useState<MedicineCsvImportSummary | null>(null);
+    const [importProgress, setImportProgress] = useState<MedicineImportProgress | null>(null);
+
+    // Printer profiles (saved only once edited, so bills keep following Printer Type until then)
+    const [printerProfiles, setPrinterProfiles] = useState<PrinterProfiles | null>(null);
+    const [profilesEdited, setProfilesEdited] = useState(false);
+    const [printers, setPrinters] = useState<string[]>([]);
+
+    // Receipt templates (saved on their own, each checked against a sample bill)
+    const [receiptTemplates, setReceiptTemplates] = useState<ReceiptTemplate[]>([]);
+    const [templateForm, setTemplateForm] = useState<TemplateFormData>(initialTemplateForm);
+    const [templateError, setTemplateError] = useState<string>('');
+    const [templatePreview, setTemplatePreview] = useState<string>('');
+
     // Shop settings form
     const [shopForm, setShopForm] = useState({
         shop_name: settings.shop_name || '',
@@ -84,6 +135,7 @@ export function Settings() {
         shop_phone: settings.shop_phone || '',
         shop_email: settings.shop_email || '',
         shop_gstin: settings.shop_gstin || '',
+        shop_upi_id: settings.shop_upi_id || '',
         drug_license: settings.drug_license || ''
     });
 
@@ -97,6 +149,10 @@ export function Settings() {
         require_customer: boolean;
         staff_discount_limit: string;
         printer_type: 'thermal' | 'dotmatrix' | 'a4' | 'legal';
+        cash_drawer_enabled: boolean;
+        cash_drawer_pin: '2' | '5';
+        customer_display_port: string;
+        customer_display_protocol: 'epson' | 'cd5220';
     }>({
         bill_prefix: settings.bill_prefix || 'INV',
         default_gst_rate: settings.default_gst_rate || '12',
@@ -105,7 +161,11 @@ export function Settings() {
         enable_discounts: settings.enable_discounts !== 'false',
         require_customer: settings.require_customer === 'true',
         staff_discount_limit: settings.staff_discount_limit || '10',
-        printer_type: (settings.printer_type as 'thermal' | 'dotmatrix' | 'a4' | 'legal') || 'thermal'
+        printer_type: (settings.printer_type as 'thermal' | 'dotmatrix' | 'a4' | 'legal') || 'thermal',
+        cash_drawer_enabled: settings.cash_drawer_enabled === 'true',
+        cash_drawer_pin: settings.cash_drawer_pin === '5' ? '5' : '2',
+        customer_display_port: settings.customer_display_port || '',
+        customer_display_protocol: settings.customer_display_protocol === 'cd5220' ? 'cd5220' : 'epson'
     });
 
 
@@ -135,6 +195,84 @@ export function Settings() {
         setIsLoadingBackups(false);
     }, []);
 
+    const loadPrinterProfiles = useCallback(async () => {
+        setPrinterProfiles(await getPrinterProfiles());
+        setPrinters(await listPrinters());
+        setProfilesEdited(false);
+    }, []);
+
+    const loadReceiptTemplates = useCallback(async () => {
+        try {
+            setReceiptTemplates(await listReceiptTemplates());
+        } catch (error) {
+            console.error('Failed to load receipt templates:', error);
+        }
+    }, []);
+
+    const editReceiptTemplate = (template?: ReceiptTemplate) => {
+        setTemplateForm(
+            template
+                ? {
+                      id: template.id,
+                      name: template.name,
+                      body: template.body,
+                      width: String(template.width),
+                      is_active: template.is_active
+                  }
+                : initialTemplateForm
+        );
+        setTemplateError('');
+        setTemplatePreview('');
+    };
+
+    const handlePreviewTemplate = async () => {
+        setTemplateError('');
+        try {
+            setTemplatePreview(await previewReceiptTemplate(templateForm.body, Number(templateForm.width) || 0));
+        } catch (error) {
+            setTemplatePreview('');
+            setTemplateError(error instanceof Error ? error.message : String(error));
+        }
+    };
+
+    const handleSaveTemplate = async () => {
+        setTemplateError('');
+        try {
+            const id = await saveReceiptTemplate({
+                id: templateForm.id,
+                name: templateForm.name,
+                body: templateForm.body,
+                width: Number(templateForm.width) || 0,
+                isActive: templateForm.is_active
+            });
+            setTemplateForm({ ...templateForm, id });
+            await loadReceiptTemplates();
+            setSaveSuccess(true);
+            setTimeout(() => setSaveSuccess(false), 3000);
+        } catch (error) {
+            setTemplateError(error instanceof Error ? error.message : String(error));
+        }
+    };
+
+    const handleDeleteTemplate = async () => {
+        if (!templateForm.id || !confirm(`Delete the receipt template "${templateForm.name}"?`)) {
+            return;
+        }
+        try {
+            await deleteReceiptTemplate(templateForm.id);
+            editReceiptTemplate();
+            await loadReceiptTemplates();
+        } catch (error) {
+            setTemplateError(error instanceof Error ? error.message : String(error));
+        }
+    };
+
+    const updatePrinterProfile = (kind: DocumentKind, changes: Partial<PrinterProfile>) => {
+        if (!printerProfiles) return;
+        setPrinterProfiles({ ...printerProfiles, [kind]: { ...printerProfiles[kind], ...changes } });
+        setProfilesEdited(true);
+    };
+
     useEffect(() => {
         if (activeTab === 'users') {
             loadUsers();
@@ -142,7 +280,11 @@ export function Settings() {
         if (activeTab === 'backup') {
             loadBackups();
         }
-    }, [activeTab, loadUsers, loadBackups]);
+        if (activeTab === 'billing') {
+            loadPrinterProfiles();
+            loadReceiptTemplates();
+        }
+    }, [activeTab, loadUsers, loadBackups, loadPrinterProfiles, loadReceiptTemplates]);
 
     const handleSaveShopSettings = async () => {
         setIsSaving(true);
@@ -168,7 +310,8 @@ export function Settings() {
             const settingsToSave = {
                 ...billingForm,
                 enable_discounts: String(billingForm.enable_discounts),
-                require_customer: String(billingForm.require_customer)
+                require_customer: String(billingForm.require_customer),
+                cash_drawer_enabled: String(billingForm.cash_drawer_enabled)
             };
 
             for (const [key, value] of Object.entries(settingsToSave)) {
@@ -178,6 +321,10 @@ export function Settings() {
                 );
                 updateSetting(key, String(value));
             }
+            if (printerProfiles && profilesEdited) {
+                await savePrinterProfiles(printerProfiles);
+            }
+            await loadPrinterProfiles();
             setSaveSuccess(true);
             setTimeout(() => setSaveSuccess(false), 3000);
         } catch (error) {
@@ -261,6 +408,44 @@ export function Settings() {
         setDeletingBackup(null);
     };
 
+    const handleMergeMedicines = async (dryRun: boolean) => {
+        setIsMergingMedicines(true);
+        setMedicineMergeError('');
+        setMedicineImport(null);
+        try {
+            setMedicineMerge(await mergeBundledMedicines(dryRun));
+        } catch (error) {
+            setMedicineMergeError(error instanceof Error ? error.message : String(error));
+        }
+        setIsMergingMedicines(false);
+    };
+
+    const handleImportMedicinesCsv = async () => {
+        const filePath = await open({
+            title: 'Select Medicine List',
+            filters: [{ name: 'CSV File', extensions: ['csv'] }],
+            multiple: false
+        });
+        if (!filePath || typeof filePath !== 'string') return;
+
+        setIsMergingMedicines(true);
+        setMedicineMergeError('');
+        setMedicineMerge(null);
+        setMedicineImport(null);
+        setImportProgress({ stage: 'reading', done: 0, total: 0, rejected: 0 });
+        const unlisten = await onMedicineImportProgress(setImportProgress);
+        try {
+            const summary = await importMedicinesCsv(filePath);
+            setMedicineImport(summary);
+            setMedicineMerge(summary.merge);
+        } catch (error) {
+            setMedicineMergeError(error instanceof Error ? error.message : String(error));
+        }
+        unlisten();
+        setImportProgress(null);
+        setIsMergingMedicines(false);
+    };
+
 
 
     // User management functions
@@ -720,6 +905,16 @@ export function Settings() {
                                                 maxLength={15}
                                             />
                                         </div>
+                                        <div className="form-group">
+                                            <label className="form-label">UPI ID</label>
+                                            <input
+                                                type="text"
+                                                className="form-input"
+                                                value={shopForm.shop_upi_id}
+                                                onChange={(e) => setShopForm({ ...shopForm, shop_upi_id: e.target.value.trim() })}
+                                                placeholder="medicalstore@okaxis"
+                                            />
+                                        </div>
                                         <div className="form-group">
                                             <label className="form-label">Drug License Number</label>
                                             <input
@@ -802,6 +997,244 @@ export function Settings() {
                                             </span>
                                         </div>
                                     </div>
+                                    <div className="settings-row">
+                                        <div>
+                                            <div className="settings-label">Cash Drawer</div>
+                                            <div className="settings-description">Open the drawer on the receipt printer's RJ11 port after cash and split bills print</div>
+                                        </div>
+                                        <div
+                                            className={`toggle-switch ${billingForm.cash_drawer_enabled ? 'active' : ''}`}
+                                            onClick={() => setBillingForm({ ...billingForm, cash_drawer_enabled: !billingForm.cash_drawer_enabled })}
+                                        />
+                                    </div>
+                                    {billingForm.cash_drawer_enabled && (
+                                        <div className="settings-grid">
+                                            <div className="form-group">
+                                                <label className="form-label">Drawer Kick Pin</label>
+                                                <select
+                                                    className="form-select"
+                                                    value={billingForm.cash_drawer_pin}
+                                                    onChange={(e) => setBillingForm({ ...billingForm, cash_drawer_pin: e.target.value as '2' | '5' })}
+                                                >
+                                                    <option value="2">Pin 2 (most drawers)</option>
+                                                    <option value="5">Pin 5</option>
+                                                </select>
+                                                <span className="form-hint">Try pin 5 if the drawer does not open</span>
+                                            </div>
+                                        </div>
+                                    )}
+                                    <div className="settings-grid">
+                                        <div className="form-group">
+                                            <label className="form-label">Customer Display Port</label>
+                                            <input
+                                                type="text"
+                                                className="form-input"
+                                                placeholder="serial:COM3?baud=9600"
+                                                value={billingForm.customer_display_port}
+                                                onChange={(e) => setBillingForm({ ...billingForm, customer_display_port: e.target.value })}
+                                            />
+                                            <span className="form-hint">2x20 pole display showing items, total and change. Leave empty if there is none</span>
+                                        </div>
+                                        <div className="form-group">
+                                            <label className="form-label">Display Commands</label>
+                                            <select
+                                                className="form-select"
+                                                value={billingForm.customer_display_protocol}
+                                                onChange={(e) => setBillingForm({ ...billingForm, customer_display_protocol: e.target.value as 'epson' | 'cd5220' })}
+                                            >
+                                                <option value="epson">Epson (ESC/POS)</option>
+                                                <option value="cd5220">CD5220</option>
+                                            </select>
+                                        </div>
+                                    </div>
+                                </div>
+
+                                {printerProfiles && (
+                                    <div className="settings-section">
+                                        <h2 className="settings-section-title">Printer Profiles</h2>
+                                        <datalist id="printer-names">
+                                            {printers.map((name) => (
+                                                <option key={name} value={name} />
+                                            ))}
+                                        </datalist>
+                                        <table className="table">
+                                            <thead>
+                                                <tr>
+                                                    <th>Document</th>
+                                                    <th>Printer</th>
+                                                    <th>Format</th>
+                                                    <th>Copies</th>
+                                                    <th>Paper</th>
+                                                </tr>
+                                            </thead>
+                                            <tbody>
+                                                {(Object.keys(DOCUMENT_KIND_LABELS) as DocumentKind[]).map((kind) => {
+                                                    const profile = printerProfiles[kind];
+                                                    return (
+                                                        <tr key={kind}>
+                                                            <td>{DOCUMENT_KIND_LABELS[kind]}</td>
+                                                            <td>
+                                                                <input
+                                                                    type="text"
+                                                                    className="form-input"
+                                                                    list="printer-names"
+                                                                    value={profile.printer}
+                                                                    onChange={(e) => updatePrinterProfile(kind, { printer: e.target.value })}
+                                                                    placeholder="Default printer"
+                                                                />
+                                                            </td>
+                                                            <td>
+                                                                <select
+                                                                    className="form-select"
+                                                                    value={profile.format}
+                                                                    onChange={(e) => updatePrinterProfile(kind, { format: e.target.value as PrinterProfile['format'] })}
+                                                                >
+                                                                    <option value="escp">ESC/P (Dot Matrix)</option>
+                                                                    <option value="escpos">ESC/POS (Thermal)</option>
+                                                                    <option value="text">Text</option>
+                                                                    <option value="zpl">ZPL (Zebra Label)</option>
+                                                                    <option value="tspl">TSPL (TSC Label)</option>
+                                                                    <option value="epl">EPL (Eltron Label)</option>
+                                                                </select>
+                                                            </td>
+                                                            <td>
+                                                                <input
+                                                                    type="number"
+                                                                    className="form-input"
+                                                                    value={profile.copies}
+                                                                    onChange={(e) => updatePrinterProfile(kind, { copies: Math.max(1, Number(e.target.value) || 1) })}
+                                                                    min={1}
+                                                                    max={5}
+                                                                    style={{ width: 70 }}
+                                                                />
+                                                            </td>
+                                                            <td>
+                                                                <input
+                                                                    type="text"
+                                                                    className="form-input"
+                                                                    value={profile.paper_size}
+                                                                    onChange={(e) => updatePrinterProfile(kind, { paper_size: e.target.value })}
+                                                                    placeholder="a4, 80mm, 50x25mm"
+                                                                    style={{ width: 110 }}
+                                                                />
+                                                            </td>
+                                                        </tr>
+                                                    );
+                                                })}
+                                            </tbody>
+                                        </table>
+                                        <span className="form-hint">
+                                            Choose where each kind of document is printed. Leave the printer empty for the default printer, or enter tcp://address:9100 for a network printer, serial:COM3?baud=9600 for a serial printer or lp:/dev/usb/lp0 for a USB line printer. Labels use a ZPL, TSPL or EPL label printer with the label size as paper (e.g. 50x25mm). Until a profile is changed, bills follow the Printer Type above.
+                                        </span>
+                                    </div>
+                                )}
+
+                                <div className="settings-section">
+                                    <h2 className="settings-section-title">Receipt Templates</h2>
+                                    <div className="settings-grid">
+                                        <div className="form-group">
+                                            <label className="form-label">Template</label>
+                                            <select
+                                                className="form-select"
+                                                value={templateForm.id ?? ''}
+                                                onChange={(e) =>
+                                                    editReceiptTemplate(receiptTemplates.find((t) => t.id === Number(e.target.value)))
+                                                }
+                                            >
+                                                <option value="">New template</option>
+                                                {receiptTemplates.map((template) => (
+                                                    <option key={template.id} value={template.id}>
+                                                        {template.name}
+                                                        {template.is_active ? ' (active)' : ''}
+                                                    </option>
+                                                ))}
+                                            </select>
+                                        </div>
+                                        <div className="form-group">
+                                            <label className="form-label">Name</label>
+                                            <input
+                                                type="text"
+                                                className="form-input"
+                                                value={templateForm.name}
+                                                onChange={(e) => setTemplateForm({ ...templateForm, name: e.target.value })}
+                                                placeholder="e.g. 58mm Roll"
+                                            />
+                                        </div>
+                                        <div className="form-group">
+                                            <label className="form-label">Width (characters)</label>
+                                            <input
+                                                type="number"
+                                                className="form-input"
+                                                value={templateForm.width}
+                                                onChange={(e) => setTemplateForm({ ...templateForm, width: e.target.value })}
+                                                min={20}
+                                                max={136}
+                                            />
+                                            <span className="form-hint">32 for a 58mm roll, 42 or 48 for 80mm, 80 for dot matrix</span>
+                                        </div>
+                                    </div>
+                                    <div className="form-group">
+                                        <label className="form-label">Template</label>
+                                        <textarea
+                                            className="form-textarea"
+                                            value={templateForm.body}
+                                            onChange={(e) => setTemplateForm({ ...templateForm, body: e.target.value })}
+                                            rows={16}
+                                            spellCheck={false}
+                                            style={{ fontFamily: 'monospace', whiteSpace: 'pre' }}
+                                        />
+                                        <span className="form-hint">
+                                            {'{{ bill.total | money | right:10 }}'} prints a value, {'{% for item in items %}'} and {'{% if item.scheduled %}'} repeat or hide lines. See Receipt Templates in the user guide for every name and filter.
+                                        </span>
+                                    </div>
+                                    <div className="settings-row">
+                                        <div>
+                                            <div className="settings-label">Use for Bills</div>
+                                            <div className="settings-description">
+                                                Print every bill with this template instead of the built-in layout
+                                            </div>
+                                        </div>
+                                        <div
+                                            className={`toggle-switch ${templateForm.is_active ? 'active' : ''}`}
+                                            onClick={() => setTemplateForm({ ...templateForm, is_active: !templateForm.is_active })}
+                                        />
+                                    </div>
+                                    {templateError && (
+                                        <div className="alert alert-danger mb-4">
+                                            <AlertCircle size={18} />
+                                            {templateError}
+                                        </div>
+                                    )}
+                                    <div className="flex gap-2">
+                                        <button className="btn btn-secondary" onClick={handlePreviewTemplate}>
+                                            <Printer size={18} />
+                                            Preview
+                                        </button>
+                                        <button className="btn btn-primary" onClick={handleSaveTemplate}>
+                                            <Save size={18} />
+                                            Save Template
+                                        </button>
+                                        {templateForm.id && (
+                                            <button className="btn btn-danger" onClick={handleDeleteTemplate}>
+                                                <Trash2 size={18} />
+                                                Delete
+                                            </button>
+                                        )}
+                                    </div>
+                                    {templatePreview && (
+                                        <pre
+                                            style={{
+                                                background: 'var(--bg-tertiary)',
+                                                padding: 16,
+                                                borderRadius: 8,
+                                                fontSize: 12,
+                                                marginTop: 16,
+                                                overflowX: 'auto'
+                                            }}
+                                        >
+                                            {templatePreview}
+                                        </pre>
+                                    )}
                                 </div>
 
                                 <div className="settings-section">
@@ -1057,6 +1490,107 @@ export function Settings() {
                                         </div>
                                     </div>
                                 </div>
+
+                                <div className="settings-section">
+                                    <div style={{ display: 'flex', justifyContent: 'space-between', alignItems: 'center', marginBottom: 'var(--space-4)' }}>
+                                        <h2 className="settings-section-title" style={{ marginBottom: 0, borderBottom: 'none', paddingBottom: 0 }}>Medicine List Update</h2>
+                                        <div style={{ display: 'flex', gap: 'var(--space-2)' }}>
+                                            <button className="btn btn-secondary" onClick={() => handleMergeMedicines(true)} disabled={isMergingMedicines}>
+                                                <RefreshCw size={18} className={isMergingMedicines ? 'animate-spin' : ''} />
+                                                Check for Changes
+                                            </button>
+                                            <button className="btn btn-secondary" onClick={handleImportMedicinesCsv} disabled={isMergingMedicines}>
+                                                <Upload size={18} />
+                                                Import CSV
+                                            </button>
+                                            {medicineMerge?.dry_run && (
+                                                <button className="btn btn-primary" onClick={() => handleMergeMedicines(false)} disabled={isMergingMedicines}>
+                                                    <Check size={18} />
+                                                    Apply Changes
+                                                </button>
+                                            )}
+                                        </div>
+                                    </div>
+
+                                    <p className="text-sm text-secondary mb-4">
+                                        Adds new products from the medicine list shipped with this version and updates details you have
+                                        never edited. Anything you changed yourself is kept. A medicine list in CSV format, such as
+                                        indian_medicine_data.csv, can be imported the same way.
+                                    </p>
+
+                                    {importProgress && (
+                                        <div className="alert alert-info mb-4">
+                                            <RefreshCw size={18} className="animate-spin" />
+                                            <div style={{ flex: 1 }}>
+                                                <div style={{ display: 'flex', justifyContent: 'space-between', alignItems: 'center' }}>
+                                                    <span>
+                                                        {importProgress.stage === 'reading' ? 'Reading file' : 'Updating medicines'}
+                                                        {importProgress.total > 0 && ` ${Math.floor((importProgress.done / importProgress.total) * 100)}%`}
+                                                        {importProgress.rejected > 0 && ` (${importProgress.rejected.toLocaleString()} rows rejected)`}
+                                                    </span>
+                                                    <button className="btn btn-ghost btn-sm" onClick={() => cancelMedicineImport()}>
+                                                        <X size={16} />
+                                                        Cancel
+                                                    </button>
+                                                </div>
+                                            </div>
+                                        </div>
+                                    )}
+
+                                    {medicineMergeError && (
+                                        <div className="alert alert-danger mb-4">
+                                            <AlertCircle size={18} />
+                                            {medicineMergeError}
+                                        </div>
+                                    )}
+
+                                    {medicineMerge && (
+                                        <div className={`alert ${medicineMerge.dry_run ? 'alert-info' : 'alert-success'} mb-4`}>
+                                            <Database size={18} />
+                                            <div>
+                                                <strong>{medicineMerge.dry_run ? 'Would change:' : 'Updated:'}</strong>{' '}
+                                                {medicineMerge.added.toLocaleString()} added, {medicineMerge.updated.toLocaleString()} updated,{' '}
+                                                {medicineMerge.skipped.toLocaleString()} unchanged, {medicineMerge.conflicting.toLocaleString()} need review
+                                                {medicineImport && medicineImport.rejected > 0 && (
+                                                    <div className="text-sm" style={{ marginTop: 'var(--space-1)' }}>
+                                                        {medicineImport.rejected.toLocaleString()} of {medicineImport.rows.toLocaleString()} rows were rejected; see{' '}
+                                                        <span style={{ fontFamily: 'monospace' }}>{medicineImport.report}</span>
+                                                    </div>
+                                                )}
+                                            </div>
+                                        </div>
+                                    )}
+
+                                    {medicineMerge && medicineMerge.conflicts.length > 0 && (
+                                        <div className="table-container">
+                                            <table className="table">
+                                                <thead>
+                                                    <tr>
+                                                        <th>Medicine</th>
+                                                        <th>Manufacturer</th>
+                                                        <th>Pack</th>
+                                                        <th>Needs review</th>
+                                                    </tr>
+                                                </thead>
+                                                <tbody>
+                                                    {medicineMerge.conflicts.map((conflict, index) => (
+                                                        <tr key={index}>
+                                                            <td>{conflict.name}</td>
+                                                            <td>{conflict.manufacturer ?? '-'}</td>
+                                                            <td>{conflict.pack_size ?? '-'}</td>
+                                                            <td className="text-sm text-secondary">{conflict.reason}</td>
+                                                        </tr>
+                                                    ))}
+                                                </tbody>
+                                            </table>
+                                            {medicineMerge.conflicting > medicineMerge.conflicts.length && (
+                                                <p className="text-sm text-secondary" style={{ marginTop: 'var(--space-2)' }}>
+                                                    Showing the first {medicineMerge.conflicts.length.toLocaleString()} of {medicineMerge.conflicting.toLocaleString()}.
+                                                </p>
+                                            )}
+                                        </div>
+                                    )}
+                                </div>
                             </>
                         )}
 
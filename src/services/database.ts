@@ -7,389 +7,6 @@ import Database from '@tauri-apps/plugin-sql';
 
 let db: Database | null = null;
 
-// Individual table creation statements
-const TABLE_STATEMENTS = [
-    // Users Table
-    `CREATE TABLE IF NOT EXISTS users (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        username TEXT NOT NULL UNIQUE,
-        password_hash TEXT NOT NULL,
-        full_name TEXT NOT NULL,
-        role TEXT NOT NULL CHECK (role IN ('admin', 'staff')),
-        is_active INTEGER DEFAULT 1,
-        last_login DATETIME,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Medicines Table - Master data only (GST/schedule set per batch)
-    `CREATE TABLE IF NOT EXISTS medicines (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        name TEXT NOT NULL,
-        generic_name TEXT,
-        manufacturer TEXT,
-        hsn_code TEXT NOT NULL DEFAULT '3004',
-        category TEXT,
-        drug_type TEXT,
-        pack_size TEXT,
-        unit TEXT DEFAULT 'PCS',
-        reorder_level INTEGER DEFAULT 10,
-        is_active INTEGER DEFAULT 1,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Batches Table - GST rate and schedule stored per batch
-    `CREATE TABLE IF NOT EXISTS batches (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        medicine_id INTEGER NOT NULL REFERENCES medicines(id),
-        batch_number TEXT NOT NULL,
-        expiry_date DATE NOT NULL,
-        purchase_price DECIMAL(10,2) NOT NULL,
-        mrp DECIMAL(10,2) NOT NULL,
-        selling_price DECIMAL(10,2) NOT NULL,
-        price_type TEXT NOT NULL DEFAULT 'INCLUSIVE' CHECK (price_type IN ('INCLUSIVE', 'EXCLUSIVE')),
-        gst_rate DECIMAL(5,2) NOT NULL DEFAULT 12 CHECK (gst_rate IN (0, 5, 12, 18)),
-        is_schedule INTEGER DEFAULT 0,
-        quantity INTEGER NOT NULL DEFAULT 0,
-        tablets_per_strip INTEGER DEFAULT 10,
-        rack TEXT,
-        box TEXT,
-        last_sold_date DATE,
-        purchase_id INTEGER,
-        supplier_id INTEGER REFERENCES suppliers(id),
-        is_active INTEGER DEFAULT 1,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        UNIQUE(medicine_id, batch_number)
-    )`,
-
-    // Suppliers Table
-    `CREATE TABLE IF NOT EXISTS suppliers (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        name TEXT NOT NULL,
-        contact_person TEXT,
-        phone TEXT,
-        email TEXT,
-        gstin TEXT,
-        address TEXT,
-        city TEXT,
-        state TEXT DEFAULT 'Tamil Nadu',
-        pincode TEXT,
-        payment_terms INTEGER DEFAULT 30,
-        is_active INTEGER DEFAULT 1,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Customers Table
-    `CREATE TABLE IF NOT EXISTS customers (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        name TEXT NOT NULL,
-        phone TEXT,
-        email TEXT,
-        gstin TEXT,
-        address TEXT,
-        credit_limit DECIMAL(12,2) DEFAULT 0,
-        current_balance DECIMAL(12,2) DEFAULT 0,
-        is_active INTEGER DEFAULT 1,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Bills Table
-    `CREATE TABLE IF NOT EXISTS bills (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        bill_number TEXT NOT NULL UNIQUE,
-        bill_date DATETIME DEFAULT CURRENT_TIMESTAMP,
-        customer_id INTEGER REFERENCES customers(id),
-        customer_name TEXT,
-        doctor_name TEXT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        subtotal DECIMAL(12,2) NOT NULL DEFAULT 0,
-        discount_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
-        discount_percent DECIMAL(5,2) DEFAULT 0,
-        taxable_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
-        cgst_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
-        sgst_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
-        total_gst DECIMAL(12,2) NOT NULL DEFAULT 0,
-        round_off DECIMAL(5,2) DEFAULT 0,
-        grand_total DECIMAL(12,2) NOT NULL DEFAULT 0,
-        payment_mode TEXT NOT NULL DEFAULT 'CASH' CHECK (payment_mode IN ('CASH', 'ONLINE', 'CREDIT', 'SPLIT')),
-        payment_status TEXT NOT NULL DEFAULT 'PAID' CHECK (payment_status IN ('PAID', 'PARTIAL', 'PENDING')),
-        cash_amount DECIMAL(12,2) DEFAULT 0,
-        online_amount DECIMAL(12,2) DEFAULT 0,
-        credit_amount DECIMAL(12,2) DEFAULT 0,
-        notes TEXT,
-        total_items INTEGER DEFAULT 0,
-        is_cancelled INTEGER DEFAULT 0,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Bill Items Table
-    `CREATE TABLE IF NOT EXISTS bill_items (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        bill_id INTEGER NOT NULL REFERENCES bills(id),
-        batch_id INTEGER NOT NULL REFERENCES batches(id),
-        medicine_id INTEGER NOT NULL REFERENCES medicines(id),
-        medicine_name TEXT NOT NULL,
-        batch_number TEXT NOT NULL,
-        hsn_code TEXT NOT NULL,
-        quantity INTEGER NOT NULL,
-        quantity_strips INTEGER DEFAULT 0,
-        quantity_pieces INTEGER DEFAULT 0,
-        tablets_per_strip INTEGER DEFAULT 10,
-        unit TEXT NOT NULL DEFAULT 'PCS',
-        mrp DECIMAL(10,2) NOT NULL,
-        selling_price DECIMAL(10,2) NOT NULL,
-        discount_percent DECIMAL(5,2) DEFAULT 0,
-        discount_amount DECIMAL(10,2) DEFAULT 0,
-        taxable_amount DECIMAL(12,2) NOT NULL,
-        gst_rate DECIMAL(5,2) NOT NULL,
-        cgst_amount DECIMAL(10,2) NOT NULL,
-        sgst_amount DECIMAL(10,2) NOT NULL,
-        total_amount DECIMAL(12,2) NOT NULL,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Purchases Table
-    `CREATE TABLE IF NOT EXISTS purchases (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        supplier_id INTEGER REFERENCES suppliers(id),
-        invoice_number TEXT NOT NULL,
-        invoice_date DATE NOT NULL,
-        subtotal DECIMAL(12,2) NOT NULL DEFAULT 0,
-        discount_amount DECIMAL(12,2) DEFAULT 0,
-        taxable_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
-        cgst_amount DECIMAL(12,2) DEFAULT 0,
-        sgst_amount DECIMAL(12,2) DEFAULT 0,
-        igst_amount DECIMAL(12,2) DEFAULT 0,
-        total_gst DECIMAL(12,2) DEFAULT 0,
-        grand_total DECIMAL(12,2) NOT NULL DEFAULT 0,
-        payment_status TEXT DEFAULT 'PENDING' CHECK (payment_status IN ('PAID', 'PARTIAL', 'PENDING')),
-        paid_amount DECIMAL(12,2) DEFAULT 0,
-        notes TEXT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Purchase Items Table
-    `CREATE TABLE IF NOT EXISTS purchase_items (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        purchase_id INTEGER NOT NULL REFERENCES purchases(id),
-        batch_id INTEGER REFERENCES batches(id),
-        medicine_id INTEGER NOT NULL REFERENCES medicines(id),
-        medicine_name TEXT NOT NULL,
-        batch_number TEXT NOT NULL,
-        expiry_date DATE NOT NULL,
-        quantity INTEGER NOT NULL,
-        free_quantity INTEGER DEFAULT 0,
-        pack_size INTEGER DEFAULT 1,
-        purchase_price DECIMAL(10,2) NOT NULL,
-        mrp DECIMAL(10,2) NOT NULL,
-        discount_percent DECIMAL(5,2) DEFAULT 0,
-        gst_rate DECIMAL(5,2) NOT NULL,
-        cgst_amount DECIMAL(10,2) DEFAULT 0,
-        sgst_amount DECIMAL(10,2) DEFAULT 0,
-        total_amount DECIMAL(12,2) NOT NULL,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Credits Table (Udhar)
-    `CREATE TABLE IF NOT EXISTS credits (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        customer_id INTEGER NOT NULL REFERENCES customers(id),
-        bill_id INTEGER REFERENCES bills(id),
-        transaction_type TEXT NOT NULL CHECK (transaction_type IN ('CREDIT', 'PAYMENT', 'SALE', 'ADJUSTMENT')),
-        amount DECIMAL(12,2) NOT NULL,
-        balance_after DECIMAL(12,2) NOT NULL,
-        payment_mode TEXT CHECK (payment_mode IN ('CASH', 'ONLINE', 'ADJUSTMENT')),
-        reference_number TEXT,
-        notes TEXT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Audit Log Table
-    `CREATE TABLE IF NOT EXISTS audit_log (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        action TEXT NOT NULL,
-        entity_type TEXT NOT NULL,
-        entity_id INTEGER,
-        old_value TEXT,
-        new_value TEXT,
-        description TEXT,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Settings Table
-    `CREATE TABLE IF NOT EXISTS settings (
-        key TEXT PRIMARY KEY,
-        value TEXT NOT NULL,
-        category TEXT DEFAULT 'general',
-        description TEXT,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Bill Sequence Table
-    `CREATE TABLE IF NOT EXISTS bill_sequence (
-        id INTEGER PRIMARY KEY CHECK (id = 1),
-        prefix TEXT NOT NULL DEFAULT 'INV',
-        current_number INTEGER NOT NULL DEFAULT 0,
-        financial_year TEXT NOT NULL,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Scheduled Medicine Records Table - Patient details for scheduled drug sales
-    `CREATE TABLE IF NOT EXISTS scheduled_medicine_records (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        bill_id INTEGER NOT NULL REFERENCES bills(id),
-        bill_item_id INTEGER NOT NULL REFERENCES bill_items(id),
-        medicine_id INTEGER NOT NULL REFERENCES medicines(id),
-        batch_id INTEGER NOT NULL REFERENCES batches(id),
-        patient_name TEXT NOT NULL,
-        patient_age INTEGER,
-        patient_gender TEXT CHECK (patient_gender IN ('M', 'F', 'O')),
-        patient_phone TEXT,
-        patient_address TEXT,
-        doctor_name TEXT,
-        doctor_registration_number TEXT,
-        clinic_hospital_name TEXT,
-        prescription_number TEXT,
-        prescription_date TEXT,
-        doctor_prescription TEXT,
-        quantity INTEGER NOT NULL,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Running Bills Table - For medicines sold without stock (to be reconciled later)
-    // Creates an actual bill for the customer, but tracks the pending stock reconciliation
-    `CREATE TABLE IF NOT EXISTS running_bills (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        bill_id INTEGER NOT NULL REFERENCES bills(id),
-        bill_item_id INTEGER REFERENCES bill_items(id),
-        medicine_name TEXT NOT NULL,
-        quantity INTEGER NOT NULL,
-        unit_price DECIMAL(10,2) NOT NULL,
-        total_amount DECIMAL(12,2) NOT NULL,
-        gst_rate DECIMAL(5,2) DEFAULT 0,
-        hsn_code TEXT DEFAULT '3004',
-        notes TEXT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        status TEXT NOT NULL DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'STOCKED', 'CANCELLED')),
-        linked_batch_id INTEGER REFERENCES batches(id),
-        linked_medicine_id INTEGER REFERENCES medicines(id),
-        stocked_at DATETIME,
-        stocked_by INTEGER REFERENCES users(id),
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Sales Returns Table - Customer returns to pharmacy
-    `CREATE TABLE IF NOT EXISTS sales_returns (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        return_number TEXT NOT NULL UNIQUE,
-        return_date DATETIME DEFAULT CURRENT_TIMESTAMP,
-        bill_id INTEGER NOT NULL REFERENCES bills(id),
-        customer_id INTEGER REFERENCES customers(id),
-        reason TEXT,
-        refund_mode TEXT CHECK (refund_mode IN ('CASH', 'CREDIT_NOTE', 'ADJUSTMENT')),
-        total_amount DECIMAL(12,2) NOT NULL,
-        total_gst DECIMAL(12,2) DEFAULT 0,
-        status TEXT DEFAULT 'COMPLETED',
-        notes TEXT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Sales Return Items Table
-    `CREATE TABLE IF NOT EXISTS sales_return_items (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        return_id INTEGER NOT NULL REFERENCES sales_returns(id),
-        bill_item_id INTEGER NOT NULL REFERENCES bill_items(id),
-        batch_id INTEGER NOT NULL REFERENCES batches(id),
-        quantity INTEGER NOT NULL,
-        unit_price DECIMAL(10,2) NOT NULL,
-        gst_rate DECIMAL(5,2) NOT NULL,
-        cgst DECIMAL(10,2) DEFAULT 0,
-        sgst DECIMAL(10,2) DEFAULT 0,
-        total DECIMAL(12,2) NOT NULL,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Purchase Returns Table - Pharmacy returns to supplier
-    `CREATE TABLE IF NOT EXISTS purchase_returns (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        return_number TEXT NOT NULL UNIQUE,
-        return_date DATETIME DEFAULT CURRENT_TIMESTAMP,
-        purchase_id INTEGER REFERENCES purchases(id),
-        supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
-        reason TEXT CHECK (reason IN ('EXPIRY', 'DAMAGE', 'OVERSTOCK', 'OTHER')),
-        total_amount DECIMAL(12,2) NOT NULL,
-        total_gst DECIMAL(12,2) DEFAULT 0,
-        status TEXT DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'COMPLETED')),
-        notes TEXT,
-        user_id INTEGER NOT NULL REFERENCES users(id),
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`,
-
-    // Purchase Return Items Table
-    `CREATE TABLE IF NOT EXISTS purchase_return_items (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        return_id INTEGER NOT NULL REFERENCES purchase_returns(id),
-        batch_id INTEGER NOT NULL REFERENCES batches(id),
-        medicine_id INTEGER NOT NULL REFERENCES medicines(id),
-        quantity INTEGER NOT NULL,
-        unit_price DECIMAL(10,2) NOT NULL,
-        gst_rate DECIMAL(5,2) NOT NULL,
-        cgst DECIMAL(10,2) DEFAULT 0,
-        sgst DECIMAL(10,2) DEFAULT 0,
-        total DECIMAL(12,2) NOT NULL,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-    )`
-];
-
-// Index creation statements
-const INDEX_STATEMENTS = [
-    `CREATE INDEX IF NOT EXISTS idx_users_username ON users(username)`,
-    `CREATE INDEX IF NOT EXISTS idx_medicines_name ON medicines(name)`,
-    `CREATE INDEX IF NOT EXISTS idx_medicines_hsn ON medicines(hsn_code)`,
-    `CREATE INDEX IF NOT EXISTS idx_medicines_manufacturer ON medicines(manufacturer)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_medicine ON batches(medicine_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_expiry ON batches(expiry_date)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_location ON batches(rack, box)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_quantity ON batches(quantity)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_gst ON batches(gst_rate)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_schedule ON batches(is_schedule)`,
-    `CREATE INDEX IF NOT EXISTS idx_bills_number ON bills(bill_number)`,
-    `CREATE INDEX IF NOT EXISTS idx_bills_date ON bills(bill_date)`,
-    `CREATE INDEX IF NOT EXISTS idx_bills_customer ON bills(customer_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_bills_cancelled ON bills(is_cancelled)`,
-    `CREATE INDEX IF NOT EXISTS idx_bill_items_bill ON bill_items(bill_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_bill_items_batch ON bill_items(batch_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_bill_items_medicine ON bill_items(medicine_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_purchases_supplier ON purchases(supplier_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_purchases_date ON purchases(invoice_date)`,
-    `CREATE INDEX IF NOT EXISTS idx_credits_customer ON credits(customer_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_credits_bill ON credits(bill_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_audit_entity ON audit_log(entity_type, entity_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_scheduled_medicine_bill ON scheduled_medicine_records(bill_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_scheduled_medicine_medicine ON scheduled_medicine_records(medicine_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_running_bills_status ON running_bills(status)`,
-    `CREATE INDEX IF NOT EXISTS idx_running_bills_bill ON running_bills(bill_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_batches_supplier ON batches(supplier_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_sales_returns_bill ON sales_returns(bill_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_sales_returns_customer ON sales_returns(customer_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_sales_return_items_return ON sales_return_items(return_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_purchase_returns_supplier ON purchase_returns(supplier_id)`,
-    `CREATE INDEX IF NOT EXISTS idx_purchase_return_items_return ON purchase_return_items(return_id)`
-];
-
-
 // Default data statements
 const DEFAULT_DATA_STATEMENTS = [
     // Default Admin User - IMPORTANT: Change password on first login!
@@ -404,10 +21,33 @@ const DEFAULT_DATA_STATEMENTS = [
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_address', '', 'shop', 'Shop address')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_phone', '', 'shop', 'Shop phone number')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_gstin', '', 'shop', 'GST Number')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_upi_id', '', 'shop', 'UPI ID (VPA) printed as a payment QR code')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_drug_license', '', 'shop', 'Drug License Number')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_state', 'Tamil Nadu', 'shop', 'State for CGST/SGST')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('bill_prefix', 'INV', 'billing', 'Bill number prefix')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('thermal_printer_width', '80', 'printing', 'Thermal printer width in mm')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('dot_matrix_form_length', '0', 'printing', 'Dot matrix form length in lines (0 = continuous paper)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('dot_matrix_page_lines', '0', 'printing', 'Lines printed per page before a bill continues on the next form (0 = form length)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('dot_matrix_tear_off_lines', '6', 'printing', 'Lines fed after a bill on continuous paper')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('dot_matrix_lines_per_inch', '6', 'printing', 'Dot matrix line spacing (6 or 8 lines per inch)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('thermal_printer_columns', '0', 'printing', 'Thermal characters per line (0 = from paper width)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('thermal_cut_mode', 'partial', 'printing', 'Thermal paper cut: partial, full or none')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('thermal_qr_mode', 'native', 'printing', 'Thermal QR codes: native (printer-drawn) or raster')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('label_dpi', '203', 'printing', 'Label printer resolution in dots per inch (203 or 300)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('label_gap_mm', '2', 'printing', 'Gap between labels on the roll in mm')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('label_barcode', 'code128', 'printing', 'Label barcode: code128 or datamatrix')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('printer_backend', 'system', 'printing', 'Printer connection: system, socket, file or mock')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('printer_address', '', 'printing', 'Network printer host:port for the socket backend')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('printer_output_dir', '', 'printing', 'Folder for print files when using the file backend')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('printer_status_interval', '15', 'printing', 'Seconds between bill printer status checks (0 = off)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('cash_drawer_enabled', 'false', 'printing', 'Open the cash drawer after cash bills print')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('cash_drawer_pin', '2', 'printing', 'Cash drawer kick pin on the receipt printer (2 or 5)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('cash_drawer_on_ms', '100', 'printing', 'Cash drawer pulse on time in ms')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('cash_drawer_off_ms', '200', 'printing', 'Cash drawer pulse off time in ms')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('unicode_font_path', '', 'printing', 'TrueType font for Tamil and other Unicode text on receipts (empty = system font)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('customer_display_port', '', 'printing', 'Serial port of the customer pole display, e.g. serial:COM3?baud=9600 (empty = none)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('customer_display_protocol', 'epson', 'printing', 'Customer display command set (epson or cd5220)')`,
+    `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('shop_logo_path', '', 'shop', 'Logo image printed on thermal receipts')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('backup_path', './backups', 'system', 'Backup directory path')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('last_backup_date', '', 'system', 'Last backup timestamp')`,
     `INSERT OR IGNORE INTO settings (key, value, category, description) VALUES ('expiry_alert_days', '30', 'alerts', 'Days before expiry to alert')`,
@@ -427,6 +67,12 @@ export async function initDatabase(): Promise<Database> {
     try {
         console.log('Connecting to database...');
 
+        // Bring the schema up to date first; a database from a newer
+        // version of MedBill is refused here and never opened
+        const { invoke } = await import('@tauri-apps/api/core');
+        const schemaVersion = await invoke<number>('migrate_database');
+        console.log(`Database schema version ${schemaVersion}`);
+
         // Connect to SQLite database
         db = await Database.load('sqlite:medbill.db');
         console.log('Database connected successfully');
@@ -443,30 +89,6 @@ export async function initDatabase(): Promise<Database> {
         await db.execute('PRAGMA foreign_keys = ON');
         console.log('Foreign keys enabled');
 
-        // Create tables
-        console.log('Creating tables...');
-        for (const statement of TABLE_STATEMENTS) {
-            try {
-                await db.execute(statement);
-            } catch (tableError) {
-                console.error('Error creating table:', tableError);
-                throw tableError;
-            }
-        }
-        console.log('Tables created successfully');
-
-        // Create indexes
-        console.log('Creating indexes...');
-        for (const statement of INDEX_STATEMENTS) {
-            try {
-                await db.execute(statement);
-            } catch (indexError) {
-                console.warn('Index creation warning:', indexError);
-                // Continue even if index creation fails
-            }
-        }
-        console.log('Indexes created');
-
         // Insert default data
         console.log('Inserting default data...');
         for (const statement of DEFAULT_DATA_STATEMENTS) {
@@ -479,67 +101,8 @@ export async function initDatabase(): Promise<Database> {
         }
         console.log('Default data inserted');
 
-        // Run migrations for existing databases
-        console.log('Running migrations...');
-        const migrations = [
-            // Add tablets_per_strip to batches if not exists
-            `ALTER TABLE batches ADD COLUMN tablets_per_strip INTEGER DEFAULT 10`,
-            // Add new columns to bill_items if not exists
-            `ALTER TABLE bill_items ADD COLUMN quantity_strips INTEGER DEFAULT 0`,
-            `ALTER TABLE bill_items ADD COLUMN quantity_pieces INTEGER DEFAULT 0`,
-            `ALTER TABLE bill_items ADD COLUMN tablets_per_strip INTEGER DEFAULT 10`,
-            // Add total_items to bills if not exists
-            `ALTER TABLE bills ADD COLUMN total_items INTEGER DEFAULT 0`,
-            // Add doctor_name to bills for prescription tracking
-            `ALTER TABLE bills ADD COLUMN doctor_name TEXT`,
-            // Add missing columns to scheduled_medicine_records for older databases
-            `ALTER TABLE scheduled_medicine_records ADD COLUMN doctor_registration_number TEXT`,
-            `ALTER TABLE scheduled_medicine_records ADD COLUMN clinic_hospital_name TEXT`,
-            `ALTER TABLE scheduled_medicine_records ADD COLUMN prescription_date TEXT`,
-            `ALTER TABLE scheduled_medicine_records ADD COLUMN doctor_prescription TEXT`,
-            // Add supplier_id to batches for direct supplier tracking
-            `ALTER TABLE batches ADD COLUMN supplier_id INTEGER REFERENCES suppliers(id)`,
-            // Add gst_rate and is_schedule to batches (moved from medicines)
-            `ALTER TABLE batches ADD COLUMN gst_rate DECIMAL(5,2) DEFAULT 12`,
-            `ALTER TABLE batches ADD COLUMN is_schedule INTEGER DEFAULT 0`,
-            // Add pack_size to medicines for dataset import
-            `ALTER TABLE medicines ADD COLUMN pack_size TEXT`,
-            // Add free_quantity to batches for supplier free units
-            `ALTER TABLE batches ADD COLUMN free_quantity INTEGER DEFAULT 0`
-        ];
-        for (const migration of migrations) {
-            try {
-                await db.execute(migration);
-            } catch {
-                // Column probably already exists, ignore
-            }
-        }
-
-
-        // Migrate existing batch quantities from strips to tablets (one-time)
-        try {
-            const migrationFlag = await db.select<{ value: string }[]>(
-                `SELECT value FROM settings WHERE key = 'tablets_migration_done'`
-            );
-            if (migrationFlag.length === 0) {
-                console.log('Running one-time quantity migration...');
-                await db.execute(
-                    `UPDATE batches SET quantity = quantity * COALESCE(tablets_per_strip, 10) WHERE quantity > 0`
-                );
-                await db.execute(
-                    `INSERT INTO settings (key, value, category, description) VALUES ('tablets_migration_done', 'true', 'system', 'Quantity converted from strips to tablets')`
-                );
-                console.log('Migrated batch quantities from strips to tablets');
-            }
-        } catch (migrationErr) {
-            console.warn('Batch quantity migration skipped:', migrationErr);
-        }
-
-        console.log('Migrations complete');
-
         // Import bundled medicines if table is empty
         try {
-            const { invoke } = await import('@tauri-apps/api/core');
             console.log('Checking for bundled medicines...');
             const importCount = await invoke<number>('import_bundled_medicines');
             if (importCount > 0) {
@@ -0,0 +1,42 @@
+// =====================================================
+// Customer Display Service
+// Drives the 2x20 pole display at the billing counter
+// Failures are logged only, billing never waits on the display
+// =====================================================
+
+async function show(command: string, args?: Record<string, unknown>): Promise<void> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        await invoke(command, args);
+    } catch (error) {
+        console.warn('[Display] Customer display update failed:', error);
+    }
+}
+
+/**
+ * Show the item just added with its price, and the running total
+ */
+export function displayItem(name: string, price: number, total: number): Promise<void> {
+    return show('customer_display_item', { name, price, total });
+}
+
+/**
+ * Show the running total after items are changed or removed
+ */
+export function displayTotal(total: number, items: number): Promise<void> {
+    return show('customer_display_total', { total, items });
+}
+
+/**
+ * Show "THANK YOU" and the change due once the bill is saved
+ */
+export function displayThankYou(change: number): Promise<void> {
+    return show('customer_display_thank_you', { change });
+}
+
+/**
+ * Show the shop name and a greeting between bills
+ */
+export function displayWelcome(): Promise<void> {
+    return show('customer_display_welcome');
+}
@@ -44,21 +44,73 @@ export async function getMedicineById(id: number): Promise<Medicine | null> {
   );
 }
 
+/** A medicine found by search_medicines, with its sellable stock */
+export interface MedicineSearchResult extends Medicine {
+  /** Tablets or units in active, unexpired batches */
+  stock: number;
+  last_sold_date: string | null;
+}
+
+/**
+ * Search the medicine master by the start of any word of the name,
+ * composition or manufacturer. Medicines in stock and recent sellers rank first.
+ */
+export async function searchMedicines(
+  searchTerm: string,
+  limit: number = 20,
+  inStockOnly: boolean = false
+): Promise<MedicineSearchResult[]> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  try {
+    return await invoke<MedicineSearchResult[]>('search_medicines', {
+      query: searchTerm,
+      limit,
+      inStockOnly
+    });
+  } catch (error) {
+    throw new Error(typeof error === 'string' ? error : 'Could not search medicines');
+  }
+}
+
 /**
  * Search medicines from master list (for adding stock)
  * Unlike searchMedicinesForBilling, this returns medicines without requiring batches
  */
 export async function searchMedicinesMaster(searchTerm: string, limit: number = 20): Promise<Medicine[]> {
-  const sql = `
-    SELECT * FROM medicines 
-    WHERE is_active = 1 
-      AND (name LIKE ? OR generic_name LIKE ? OR manufacturer LIKE ?)
-    ORDER BY name ASC
-    LIMIT ?
-  `;
+  const found = await searchMedicines(searchTerm, limit);
+  if (found.length > 0) {
+    return found;
+  }
 
-  const term = `%${searchTerm}%`;
-  return await query<Medicine>(sql, [term, term, term, limit]);
+  // Nothing starts with what was typed; offer names that look or sound like it
+  const ids = (await matchMedicineNames(searchTerm, limit)).map((match) => match.id);
+  const rank = new Map(ids.map((id, index) => [id, index]));
+  const medicines = await query<Medicine>(
+    `SELECT * FROM medicines WHERE id IN (SELECT value FROM json_each(?))`,
+    [JSON.stringify(ids)]
+  );
+  return medicines.sort((a, b) => (rank.get(a.id) ?? 0) - (rank.get(b.id) ?? 0));
+}
+
+/** A medicine whose name looks or sounds like what was typed */
+export interface MedicineNameMatch {
+  id: number;
+  name: string;
+  /** 1 for an exact match, down to 0.5 */
+  score: number;
+}
+
+/**
+ * Find medicines by a misspelt or misheard name ("paracitamol", "amoxyclav",
+ * "dolo 65"), best first. For when searchMedicines finds nothing.
+ */
+export async function matchMedicineNames(searchTerm: string, limit: number = 10): Promise<MedicineNameMatch[]> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  try {
+    return await invoke<MedicineNameMatch[]>('match_medicine_names', { query: searchTerm, limit });
+  } catch (error) {
+    throw new Error(typeof error === 'string' ? error : 'Could not search medicines');
+  }
 }
 
 /**
@@ -73,7 +125,8 @@ export async function getMedicineCount(): Promise<number> {
 }
 
 /**
- * Search medicines with stock info for billing
+ * Search medicines with stock info for billing, in search_medicines order
+ * (or by similar names when nothing matches), plus batches whose number matches
  */
 export async function searchMedicinesForBilling(searchTerm: string): Promise<StockItem[]> {
   const sql = `
@@ -119,14 +172,20 @@ export async function searchMedicinesForBilling(searchTerm: string): Promise<Sto
       AND m.is_active = 1
       AND b.quantity > 0
       AND b.expiry_date > date('now')
-      AND (m.name LIKE ? OR m.generic_name LIKE ? OR b.batch_number LIKE ?)
-    ORDER BY m.name ASC, b.expiry_date ASC
-    LIMIT 50
+      AND (m.id IN (SELECT value FROM json_each(?)) OR b.batch_number LIKE ?)
+    ORDER BY b.expiry_date ASC
   `;
 
-
-  const term = `%${searchTerm}%`;
-  return await query<StockItem>(sql, [term, term, term]);
+  let ids = (await searchMedicines(searchTerm, 50, true)).map((medicine) => medicine.id);
+  if (ids.length === 0) {
+    ids = (await matchMedicineNames(searchTerm, 50)).map((match) => match.id);
+  }
+  const rank = new Map(ids.map((id, index) => [id, index]));
+  const items = await query<StockItem>(sql, [JSON.stringify(ids), `%${searchTerm}%`]);
+  // Stable sort keeps each medicine's batches earliest expiry first
+  return items
+    .sort((a, b) => (rank.get(a.medicine_id) ?? ids.length) - (rank.get(b.medicine_id) ?? ids.length))
+    .slice(0, 50);
 }
 
 /**
@@ -765,6 +824,106 @@ export async function getScheduledMedicines(): Promise<StockItem[]> {
   return await query<StockItem>(sql, []);
 }
 
+// =====================================================
+// MEDICINE MASTER UPDATES
+// =====================================================
+
+/** A bundle row that could not be merged without losing a local edit */
+export interface MedicineMergeConflict {
+  name: string;
+  manufacturer: string | null;
+  pack_size: string | null;
+  reason: string;
+}
+
+/** Outcome of merging the bundled medicine master */
+export interface MedicineMergeSummary {
+  dry_run: boolean;
+  added: number;
+  updated: number;
+  skipped: number;
+  conflicting: number;
+  /** The first conflicting rows; `conflicting` counts all of them */
+  conflicts: MedicineMergeConflict[];
+}
+
+/**
+ * Merge the medicine master shipped with this version into the shop's.
+ * New products are added and fields never edited here follow the bundle;
+ * local edits are kept.
+ *
+ * @param dryRun - Only report what would change
+ */
+export async function mergeBundledMedicines(dryRun: boolean): Promise<MedicineMergeSummary> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  try {
+    return await invoke<MedicineMergeSummary>('merge_bundled_medicines', { dryRun });
+  } catch (error) {
+    throw new Error(typeof error === 'string' ? error : 'Could not update the medicine list');
+  }
+}
+
+/** CSV column feeding each medicine field; omitted fields use the dataset's headers */
+export interface MedicineCsvMapping {
+  name?: string;
+  manufacturer?: string | null;
+  drug_type?: string | null;
+  pack_size?: string | null;
+  generic_name?: string[];
+  discontinued?: string | null;
+  price?: string | null;
+}
+
+/** Progress of a CSV import: bytes while reading, rows while merging */
+export interface MedicineImportProgress {
+  stage: 'reading' | 'merging';
+  done: number;
+  total: number;
+  rejected: number;
+}
+
+export interface MedicineCsvImportSummary {
+  rows: number;
+  rejected: number;
+  /** CSV listing each rejected row and why, when any were rejected */
+  report: string | null;
+  merge: MedicineMergeSummary;
+}
+
+/**
+ * Import a medicine catalog CSV such as dataset/indian_medicine_data.csv.
+ * Rows are merged like a bundle update; invalid rows are written to a report.
+ */
+export async function importMedicinesCsv(
+  path: string,
+  mapping?: MedicineCsvMapping
+): Promise<MedicineCsvImportSummary> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  try {
+    return await invoke<MedicineCsvImportSummary>('import_medicines_csv', { path, mapping });
+  } catch (error) {
+    throw new Error(typeof error === 'string' ? error : 'Could not import the medicine list');
+  }
+}
+
+/**
+ * Stop the running CSV import; nothing from it is saved
+ */
+export async function cancelMedicineImport(): Promise<void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  await invoke('cancel_medicine_import');
+}
+
+/**
+ * Subscribe to CSV import progress. Returns an unsubscribe function.
+ */
+export async function onMedicineImportProgress(
+  handler: (progress: MedicineImportProgress) => void
+): Promise<() => void> {
+  const { listen } = await import('@tauri-apps/api/event');
+  return await listen<MedicineImportProgress>('medicine-import-progress', (event) => handler(event.payload));
+}
+
 export default {
   // Medicine operations
   getMedicines,
@@ -773,8 +932,13 @@ export default {
   createMedicine,
   updateMedicine,
   deleteMedicine,
+  searchMedicines,
   searchMedicinesMaster,
+  matchMedicineNames,
   getMedicineCount,
+  mergeBundledMedicines,
+  importMedicinesCsv,
+  cancelMedicineImport,
 
   // Batch operations
   getBatchesByMedicine,
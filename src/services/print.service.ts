@@ -21,6 +21,81 @@ interface ShopInfo {
     shop_state: string;
 }
 
+export type DocumentKind = 'bill' | 'duplicate' | 'report' | 'label' | 'purchase_return_note';
+
+export type PrintFormat = 'escp' | 'escpos' | 'text' | 'zpl' | 'tspl' | 'epl';
+
+/** Item columns of a bill laid out by the backend (mirrors the Rust BillTemplate) */
+export type BillTemplate = 'receipt' | 'invoice';
+
+/** Where one kind of document is printed (mirrors the Rust PrinterProfile) */
+export interface PrinterProfile {
+    printer: string; // empty = default printer
+    format: PrintFormat;
+    copies: number;
+    paper_size: string;
+}
+
+export type PrinterProfiles = Record<DocumentKind, PrinterProfile>;
+
+export type PrintJobStatus = 'queued' | 'printing' | 'done' | 'failed' | 'cancelled';
+
+/** A job in the backend print spool */
+export interface PrintJob {
+    id: number;
+    bill_id: number | null;
+    document: DocumentKind;
+    printer: string;
+    title: string;
+    status: PrintJobStatus;
+    copies: number;
+    attempts: number;
+    last_error: string | null;
+    reprint_of: number | null;
+    created_at: string;
+    printed_at: string | null;
+}
+
+/** Payload of the `print-job-status` event */
+export interface PrintJobEvent {
+    id: number;
+    bill_id: number | null;
+    status: PrintJobStatus;
+    attempts: number;
+    error: string | null;
+}
+
+/** Payload of `get_printer_status` and the `printer-status` event */
+export interface PrinterDeviceStatus {
+    printer: string;
+    online: boolean;
+    paper_low: boolean;
+    paper_out: boolean;
+    cover_open: boolean;
+    error: boolean;
+    /** real_time: the printer answered; spooler: the OS queue; connection: reachability only */
+    source: 'real_time' | 'spooler' | 'connection';
+    message: string | null;
+}
+
+/** A saved receipt layout; the active one is used for every bill */
+export interface ReceiptTemplate {
+    id: number;
+    name: string;
+    body: string;
+    width: number;
+    is_active: boolean;
+    updated_at: string | null;
+}
+
+export const DOCUMENT_KIND_LABELS: Record<DocumentKind, string> = {
+    bill: 'Bill',
+    duplicate: 'Duplicate Bill',
+    report: 'Reports',
+    label: 'Labels',
+    purchase_return_note: 'Purchase Return Note'
+};
+
 interface PrintOptions {
     paperSize: 'thermal' | 'a4' | 'legal' | 'dotmatrix';
     showGstBreakdown?: boolean;
@@ -1137,37 +1212,55 @@ export function downloadBillHTML(html: string, billNumber: string): void {
 
 /**
  * Print a bill silently using the Tauri backend.
- * This prints directly to the default printer without any dialogs.
+ * This prints directly to the printer in the bill's printer profile without any dialogs.
+ * The job is queued in the backend print spool, which retries while the printer
+ * is offline; watch progress with onPrintJobStatus. Saved bills are laid out by
+ * the backend from the database (see printSavedBill).
  * 
  * @param bill - The bill to print
  * @param items - Bill items
  * @param paperSize - Paper size (default: thermal for receipt printing)
+ * @param documentKind - Printer profile to use (original bill or duplicate copy);
+ *                       saved bills default to duplicate once printed
+ * @param userId - User printing the bill, for the audit log
  */
 export async function silentPrintBill(
     bill: Bill,
     items: BillItem[],
-    paperSize: 'thermal' | 'a4' | 'legal' | 'dotmatrix' = 'thermal'
+    paperSize: 'thermal' | 'a4' | 'legal' | 'dotmatrix' = 'thermal',
+    documentKind?: 'bill' | 'duplicate',
+    userId?: number
 ): Promise<void> {
-    // Generate HTML based on paper size
+    // Saved bills are laid out by the backend straight from the database, so
+    // every reprint of a bill gives the same receipt
+    if (bill.id) {
+        await printSavedBill(bill.id, documentKind, undefined, userId);
+        return;
+    }
+
+    // The printer profile decides the printer, format and copies.
+    // Receipt printers get the fixed-width text bill, which the backend encodes
+    // as ESC/P (dot matrix) or ESC/POS (thermal, wrapped to the roll width).
+    const profiles = await getPrinterProfiles();
+    const format = profiles?.[documentKind ?? 'bill']?.format;
+
     let html: string;
-    switch (paperSize) {
-        case 'legal':
-        case 'a4':
-            html = await generateLegalBillHTML(bill, items);
-            break;
-        case 'dotmatrix':
-            html = await generateDotMatrixBillHTML(bill, items);
-            break;
-        case 'thermal':
-        default:
-            html = await generateThermalBillHTML(bill, items);
-            break;
+    if (format === 'escp' || format === 'escpos' || paperSize === 'dotmatrix') {
+        html = await generateDotMatrixBillHTML(bill, items);
+    } else if (paperSize === 'legal' || paperSize === 'a4') {
+        html = await generateLegalBillHTML(bill, items);
+    } else {
+        html = await generateThermalBillHTML(bill, items);
     }
 
     // Send to Tauri backend for silent printing
     try {
         const { invoke } = await import('@tauri-apps/api/core');
-        const result = await invoke<string>('silent_print', { htmlContent: html });
+        const result = await invoke<string>('silent_print', {
+            htmlContent: html,
+            document: documentKind ?? 'bill',
+            billId: bill.id
+        });
         console.log('[Print] Silent print result:', result);
     } catch (error) {
         console.error('[Print] Silent print failed:', error);
@@ -1179,6 +1272,213 @@ export async function silentPrintBill(
     }
 }
 
+/**
+ * Print a saved bill laid out by the backend from the database, with batch,
+ * expiry, rack/box and the HSN-wise GST breakup. A bill printed before is
+ * stamped "DUPLICATE COPY" with its reprint number, and every print is
+ * written to the audit log.
+ *
+ * @param billId - ID of the saved bill
+ * @param documentKind - Printer profile to use; by default the bill profile for
+ *                       the first print and the duplicate profile for reprints
+ * @param template - Item columns; defaults to the receipt layout on narrow rolls
+ *                   and the invoice layout on wider paper
+ * @param userId - User printing the bill (defaults to the user who made it)
+ */
+export async function printSavedBill(
+    billId: number,
+    documentKind?: 'bill' | 'duplicate',
+    template?: BillTemplate,
+    userId?: number
+): Promise<void> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        const result = await invoke<string>('print_bill', {
+            billId,
+            template,
+            document: documentKind,
+            userId
+        });
+        console.log('[Print] Bill print result:', result);
+    } catch (error) {
+        console.error('[Print] Bill print failed:', error);
+        throw new Error(
+            error instanceof Error
+                ? error.message
+                : typeof error === 'string'
+                    ? error
+                    : 'Bill print failed. Please check the printer settings.'
+        );
+    }
+}
+
+// =====================================================
+// PDF EXPORT (via Tauri Backend)
+// =====================================================
+
+/** Reports the backend can lay out as PDF */
+export type PdfReport = 'sales' | 'gst' | 'expiry' | 'credit';
+
+function invokeError(error: unknown, fallback: string): Error {
+    return new Error(
+        error instanceof Error ? error.message : typeof error === 'string' ? error : fallback
+    );
+}
+
+/**
+ * Plain text the next print of a saved bill gives on a text or dot matrix
+ * printer, split into pages with form feeds ('\f') between them
+ *
+ * @param billId - ID of the saved bill
+ * @param pageLines - Lines per page; defaults to the dot matrix settings
+ */
+export async function previewBillText(
+    billId: number,
+    template?: BillTemplate,
+    pageLines?: number
+): Promise<string> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        return await invoke<string>('preview_bill_text', { billId, template, pageLines });
+    } catch (error) {
+        throw invokeError(error, 'Could not lay out the bill');
+    }
+}
+
+/**
+ * Plain text document HTML prints as, split into pages like previewBillText
+ */
+export async function previewText(htmlContent: string, pageLines?: number): Promise<string> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        return await invoke<string>('preview_text', { htmlContent, pageLines });
+    } catch (error) {
+        throw invokeError(error, 'Could not lay out the document');
+    }
+}
+
+/**
+ * Save a saved bill as an A4 tax invoice PDF at a path the user picks.
+ * Returns the saved path, or null when the save dialog is cancelled.
+ *
+ * @param billId - ID of the saved bill
+ * @param billNumber - Used for the suggested file name
+ */
+export async function saveBillPdf(billId: number, billNumber: string): Promise<string | null> {
+    const { save } = await import('@tauri-apps/plugin-dialog');
+    const path = await save({
+        defaultPath: `Invoice_${billNumber.replace(/[/\\]/g, '_')}.pdf`,
+        filters: [{ name: 'PDF Document', extensions: ['pdf'] }]
+    });
+    if (!path) return null;
+
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<string>('save_bill_pdf', { billId, path });
+    } catch (error) {
+        console.error('[Print] Invoice PDF failed:', error);
+        throw invokeError(error, 'Could not save the invoice PDF.');
+    }
+}
+
+/**
+ * Save a report as an A4 PDF at a path the user picks. Sales and GST
+ * reports cover startDate to endDate (YYYY-MM-DD); the expiry and credit
+ * reports are as of today.
+ * Returns the saved path, or null when the save dialog is cancelled.
+ */
+export async function saveReportPdf(
+    report: PdfReport,
+    startDate?: string,
+    endDate?: string
+): Promise<string | null> {
+    const { save } = await import('@tauri-apps/plugin-dialog');
+    const path = await save({
+        defaultPath: `${report}_report_${endDate ?? new Date().toISOString().slice(0, 10)}.pdf`,
+        filters: [{ name: 'PDF Document', extensions: ['pdf'] }]
+    });
+    if (!path) return null;
+
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<string>('save_report_pdf', { report, startDate, endDate, path });
+    } catch (error) {
+        console.error('[Print] Report PDF failed:', error);
+        throw invokeError(error, 'Could not save the report PDF.');
+    }
+}
+
+/** A bill in the reprinted bills report */
+export interface ReprintedBill {
+    bill_id: number;
+    bill_number: string;
+    bill_date: string;
+    customer_name: string | null;
+    grand_total: number;
+    reprints: number;
+    last_reprinted_at: string;
+    last_reprinted_by: string | null;
+    last_printer: string | null;
+}
+
+/**
+ * Bills dated startDate to endDate (YYYY-MM-DD) reprinted more than
+ * moreThan times, most reprinted first.
+ */
+export async function getReprintedBills(
+    moreThan: number,
+    startDate?: string,
+    endDate?: string
+): Promise<ReprintedBill[]> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<ReprintedBill[]>('get_reprinted_bills', { moreThan, startDate, endDate });
+    } catch (error) {
+        console.error('[Print] Reprinted bills failed:', error);
+        throw invokeError(error, 'Could not load reprinted bills.');
+    }
+}
+
+// =====================================================
+// LABEL PRINTING (via Tauri Backend)
+// =====================================================
+
+/**
+ * Print shelf or strip labels for batches on the Label printer profile
+ * (ZPL, TSPL or EPL), with name, MRP, batch, expiry, rack/box and a barcode.
+ *
+ * @param batchIds - Batches to label
+ * @param copies - Labels per batch (default 1)
+ */
+export async function printBatchLabels(batchIds: number[], copies?: number): Promise<string> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<string>('print_batch_labels', { batchIds, copies });
+    } catch (error) {
+        console.error('[Print] Label print failed:', error);
+        throw invokeError(error, 'Label print failed. Please check the Label printer profile.');
+    }
+}
+
+/**
+ * Print labels for every item of a purchase invoice.
+ *
+ * @param purchaseId - ID of the saved purchase
+ * @param labelsPerItem - Labels per item; by default one per strip received
+ */
+export async function printPurchaseLabels(
+    purchaseId: number,
+    labelsPerItem?: number
+): Promise<string> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<string>('print_purchase_labels', { purchaseId, labelsPerItem });
+    } catch (error) {
+        console.error('[Print] Purchase label print failed:', error);
+        throw invokeError(error, 'Label print failed. Please check the Label printer profile.');
+    }
+}
+
 /**
  * Check if a printer is available (via Tauri backend)
  */
@@ -1191,3 +1491,159 @@ export async function checkPrinterAvailable(): Promise<boolean> {
     }
 }
 
+/**
+ * Paper, cover and error state of the bill printer (or the given one)
+ */
+export async function getPrinterStatus(printerName?: string): Promise<PrinterDeviceStatus | null> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<PrinterDeviceStatus>('get_printer_status', { printerName });
+    } catch (error) {
+        console.warn('[Print] Printer status unavailable:', error);
+        return null;
+    }
+}
+
+/**
+ * Subscribe to bill printer status changes from the background monitor.
+ * Returns an unsubscribe function.
+ */
+export async function onPrinterStatus(handler: (status: PrinterDeviceStatus) => void): Promise<() => void> {
+    const { listen } = await import('@tauri-apps/api/event');
+    return await listen<PrinterDeviceStatus>('printer-status', (event) => handler(event.payload));
+}
+
+/**
+ * Open the cash drawer without a sale. Logged in the audit log against the user.
+ */
+export async function openCashDrawer(userId: number, reason?: string): Promise<string> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        return await invoke<string>('open_cash_drawer', { userId, reason });
+    } catch (error) {
+        throw invokeError(error, 'Cash drawer did not open');
+    }
+}
+
+/**
+ * List printers known to the active printer backend
+ */
+export async function listPrinters(): Promise<string[]> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<string[]>('list_printers');
+    } catch {
+        return [];
+    }
+}
+
+/**
+ * Printer profiles for every document kind (saved values over defaults)
+ */
+export async function getPrinterProfiles(): Promise<PrinterProfiles | null> {
+    try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return await invoke<PrinterProfiles>('get_printer_profiles');
+    } catch (error) {
+        console.error('[Print] Failed to load printer profiles:', error);
+        return null;
+    }
+}
+
+export async function savePrinterProfiles(profiles: PrinterProfiles): Promise<void> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('save_printer_profiles', { profiles });
+}
+
+// =====================================================
+// RECEIPT TEMPLATES
+// =====================================================
+
+export async function listReceiptTemplates(): Promise<ReceiptTemplate[]> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        return await invoke<ReceiptTemplate[]>('list_receipt_templates');
+    } catch (error) {
+        throw invokeError(error, 'Could not load receipt templates');
+    }
+}
+
+/**
+ * Save a receipt template (a new one without an id). The template is checked
+ * against a sample bill first; the error names the line at fault.
+ */
+export async function saveReceiptTemplate(template: {
+    id?: number;
+    name: string;
+    body: string;
+    width: number;
+    isActive: boolean;
+}): Promise<number> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        return await invoke<number>('save_receipt_template', template);
+    } catch (error) {
+        throw invokeError(error, 'Could not save the receipt template');
+    }
+}
+
+export async function deleteReceiptTemplate(id: number): Promise<void> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        await invoke('delete_receipt_template', { id });
+    } catch (error) {
+        throw invokeError(error, 'Could not delete the receipt template');
+    }
+}
+
+/**
+ * A template laid out with a sample bill, as plain text
+ */
+export async function previewReceiptTemplate(body: string, width: number): Promise<string> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    try {
+        return await invoke<string>('preview_receipt_template', { body, width });
+    } catch (error) {
+        throw invokeError(error, 'Could not lay out the receipt template');
+    }
+}
+
+// =====================================================
+// PRINT QUEUE
+// =====================================================
+
+/**
+ * Spooled print jobs, newest first. Pass billId for a bill's print/reprint history.
+ */
+export async function listPrintJobs(
+    filter: { status?: PrintJobStatus; billId?: number; limit?: number } = {}
+): Promise<PrintJob[]> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    return await invoke<PrintJob[]>('list_print_jobs', {
+        status: filter.status,
+        billId: filter.billId,
+        limit: filter.limit
+    });
+}
+
+export async function cancelPrintJob(id: number): Promise<void> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('cancel_print_job', { id });
+}
+
+/**
+ * Retry a failed/cancelled job or reprint a finished one. Returns the queued job id.
+ * A printed bill is laid out again and stamped as a duplicate copy.
+ */
+export async function resendPrintJob(id: number, userId?: number): Promise<number> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    return await invoke<number>('resend_print_job', { id, userId });
+}
+
+/**
+ * Subscribe to print job status changes. Returns an unsubscribe function.
+ */
+export async function onPrintJobStatus(handler: (event: PrintJobEvent) => void): Promise<() => void> {
+    const { listen } = await import('@tauri-apps/api/event');
+    return await listen<PrintJobEvent>('print-job-status', (event) => handler(event.payload));
+}